@@ -9,10 +9,23 @@ pub struct Model {
     pub id: i32,
     pub balance: Decimal,
     pub user_id: i32,
+    pub created_at: DateTimeWithTimeZone,
+    pub updated_at: DateTimeWithTimeZone,
+    #[sea_orm(unique)]
+    pub alias: Option<String>,
+    pub held_balance: Decimal,
+    pub parent_account_id: Option<i32>,
+    pub name: Option<String>,
+    pub organization_id: Option<i32>,
+    pub approval_threshold: Option<Decimal>,
+    pub frozen_at: Option<DateTimeWithTimeZone>,
+    pub frozen_reason: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::account_members::Entity")]
+    AccountMembers,
     #[sea_orm(
         belongs_to = "super::user::Entity",
         from = "Column::UserId",
@@ -23,6 +36,12 @@ pub enum Relation {
     User,
 }
 
+impl Related<super::account_members::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::AccountMembers.def()
+    }
+}
+
 impl Related<super::user::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::User.def()
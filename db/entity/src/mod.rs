@@ -2,6 +2,35 @@
 
 pub mod prelude;
 
+pub mod account_members;
 pub mod accounts;
+pub mod api_keys;
+pub mod audit_log;
+pub mod balance_adjustments;
+pub mod dispute_notes;
+pub mod disputes;
+pub mod email_deliveries;
+pub mod invoice_line_items;
+pub mod invoice_payments;
+pub mod invoices;
+pub mod notification_preferences;
+pub mod organization_members;
+pub mod organizations;
+pub mod outbox;
+pub mod outbox_offsets;
+pub mod payment_intents;
+pub mod payment_links;
+pub mod refresh_tokens;
+pub mod sea_orm_active_enums;
+pub mod split_payment_legs;
+pub mod split_payments;
+pub mod subscription_charges;
+pub mod subscription_plans;
+pub mod subscriptions;
 pub mod transactions;
+pub mod transfer_approval_events;
+pub mod transfer_approvals;
 pub mod user;
+pub mod webhook_attempts;
+pub mod webhook_deliveries;
+pub mod webhook_endpoints;
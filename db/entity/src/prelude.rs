@@ -1,5 +1,33 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.0
 
+pub use super::account_members::Entity as AccountMembers;
 pub use super::accounts::Entity as Accounts;
+pub use super::api_keys::Entity as ApiKeys;
+pub use super::audit_log::Entity as AuditLog;
+pub use super::balance_adjustments::Entity as BalanceAdjustments;
+pub use super::dispute_notes::Entity as DisputeNotes;
+pub use super::disputes::Entity as Disputes;
+pub use super::email_deliveries::Entity as EmailDeliveries;
+pub use super::invoice_line_items::Entity as InvoiceLineItems;
+pub use super::invoice_payments::Entity as InvoicePayments;
+pub use super::invoices::Entity as Invoices;
+pub use super::notification_preferences::Entity as NotificationPreferences;
+pub use super::organization_members::Entity as OrganizationMembers;
+pub use super::organizations::Entity as Organizations;
+pub use super::outbox::Entity as Outbox;
+pub use super::outbox_offsets::Entity as OutboxOffsets;
+pub use super::payment_intents::Entity as PaymentIntents;
+pub use super::payment_links::Entity as PaymentLinks;
+pub use super::refresh_tokens::Entity as RefreshTokens;
+pub use super::split_payment_legs::Entity as SplitPaymentLegs;
+pub use super::split_payments::Entity as SplitPayments;
+pub use super::subscription_charges::Entity as SubscriptionCharges;
+pub use super::subscription_plans::Entity as SubscriptionPlans;
+pub use super::subscriptions::Entity as Subscriptions;
 pub use super::transactions::Entity as Transactions;
+pub use super::transfer_approval_events::Entity as TransferApprovalEvents;
+pub use super::transfer_approvals::Entity as TransferApprovals;
 pub use super::user::Entity as User;
+pub use super::webhook_attempts::Entity as WebhookAttempts;
+pub use super::webhook_deliveries::Entity as WebhookDeliveries;
+pub use super::webhook_endpoints::Entity as WebhookEndpoints;
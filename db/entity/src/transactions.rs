@@ -1,5 +1,6 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.0
 
+use super::sea_orm_active_enums::TransactionStatus;
 use sea_orm::entity::prelude::*;
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
@@ -10,7 +11,8 @@ pub struct Model {
     pub from_account_id: i32,
     pub to_account_id: i32,
     pub amount: Decimal,
-    pub created_at: Date,
+    pub created_at: DateTimeWithTimeZone,
+    pub status: TransactionStatus,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
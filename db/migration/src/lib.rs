@@ -3,6 +3,29 @@ pub use sea_orm_migration::prelude::*;
 mod m20241221_185614_create_user_table;
 mod m20241221_190742_create_accounts_table;
 mod m20241221_191426_create_transactions_table;
+mod m20261019_090000_use_timestamptz_columns;
+mod m20261019_100000_add_transaction_status;
+mod m20261019_110000_add_recipient_aliases;
+mod m20261019_120000_create_disputes;
+mod m20261019_130000_create_account_members;
+mod m20261019_140000_add_account_pockets;
+mod m20261019_150000_create_organizations;
+mod m20261019_160000_create_transfer_approvals;
+mod m20261019_170000_create_payment_intents;
+mod m20261019_180000_create_payment_links;
+mod m20261019_190000_create_invoices;
+mod m20261019_200000_create_subscriptions;
+mod m20261019_210000_create_split_payments;
+mod m20261019_220000_create_webhooks;
+mod m20261019_230000_create_outbox;
+mod m20261019_233000_notify_outbox_events;
+mod m20261019_235000_create_email_notifications;
+mod m20261020_000000_create_audit_log;
+mod m20261020_010000_add_account_freezes_and_adjustments;
+mod m20261020_020000_create_api_keys;
+mod m20261020_030000_create_refresh_tokens;
+mod m20261020_040000_add_refresh_token_organizations;
+mod m20261020_050000_create_email_deliveries;
 
 pub struct Migrator;
 
@@ -13,6 +36,29 @@ impl MigratorTrait for Migrator {
             Box::new(m20241221_185614_create_user_table::Migration),
             Box::new(m20241221_190742_create_accounts_table::Migration),
             Box::new(m20241221_191426_create_transactions_table::Migration),
+            Box::new(m20261019_090000_use_timestamptz_columns::Migration),
+            Box::new(m20261019_100000_add_transaction_status::Migration),
+            Box::new(m20261019_110000_add_recipient_aliases::Migration),
+            Box::new(m20261019_120000_create_disputes::Migration),
+            Box::new(m20261019_130000_create_account_members::Migration),
+            Box::new(m20261019_140000_add_account_pockets::Migration),
+            Box::new(m20261019_150000_create_organizations::Migration),
+            Box::new(m20261019_160000_create_transfer_approvals::Migration),
+            Box::new(m20261019_170000_create_payment_intents::Migration),
+            Box::new(m20261019_180000_create_payment_links::Migration),
+            Box::new(m20261019_190000_create_invoices::Migration),
+            Box::new(m20261019_200000_create_subscriptions::Migration),
+            Box::new(m20261019_210000_create_split_payments::Migration),
+            Box::new(m20261019_220000_create_webhooks::Migration),
+            Box::new(m20261019_230000_create_outbox::Migration),
+            Box::new(m20261019_233000_notify_outbox_events::Migration),
+            Box::new(m20261019_235000_create_email_notifications::Migration),
+            Box::new(m20261020_000000_create_audit_log::Migration),
+            Box::new(m20261020_010000_add_account_freezes_and_adjustments::Migration),
+            Box::new(m20261020_020000_create_api_keys::Migration),
+            Box::new(m20261020_030000_create_refresh_tokens::Migration),
+            Box::new(m20261020_040000_add_refresh_token_organizations::Migration),
+            Box::new(m20261020_050000_create_email_deliveries::Migration),
         ]
     }
 }
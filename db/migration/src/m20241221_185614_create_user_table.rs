@@ -37,4 +37,8 @@ pub enum User {
     Username,
     Password,
     CreatedAt,
+    DisplayName,
+    DefaultAccountId,
+    Role,
+    Email,
 }
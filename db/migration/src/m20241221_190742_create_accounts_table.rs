@@ -43,4 +43,14 @@ pub enum Accounts {
     Id,
     UserId,
     Balance,
+    CreatedAt,
+    UpdatedAt,
+    Alias,
+    HeldBalance,
+    ParentAccountId,
+    Name,
+    OrganizationId,
+    ApprovalThreshold,
+    FrozenAt,
+    FrozenReason,
 }
@@ -40,4 +40,5 @@ pub enum Transactions {
     ToAccountId,
     Amount,
     CreatedAt,
+    Status,
 }
@@ -0,0 +1,68 @@
+use crate::m20241221_190742_create_accounts_table::Accounts;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Existing `date` values become midnight UTC of the same day, whatever the time
+        // zone of the session running the migration.
+        for table in ["user", "transactions"] {
+            manager
+                .get_connection()
+                .execute_unprepared(&format!(
+                    r#"
+                    ALTER TABLE "{table}"
+                        ALTER COLUMN created_at TYPE timestamp with time zone
+                            USING date_trunc('day', created_at::timestamp) AT TIME ZONE 'UTC',
+                        ALTER COLUMN created_at SET NOT NULL;
+                    "#
+                ))
+                .await?;
+        }
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Accounts::Table)
+                    .add_column(
+                        timestamp_with_time_zone(Accounts::CreatedAt)
+                            .default(Expr::current_timestamp()),
+                    )
+                    .add_column(
+                        timestamp_with_time_zone(Accounts::UpdatedAt)
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Accounts::Table)
+                    .drop_column(Accounts::CreatedAt)
+                    .drop_column(Accounts::UpdatedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        for table in ["transactions", "user"] {
+            manager
+                .get_connection()
+                .execute_unprepared(&format!(
+                    r#"
+                    ALTER TABLE "{table}"
+                        ALTER COLUMN created_at TYPE date
+                            USING (created_at AT TIME ZONE 'UTC')::date;
+                    "#
+                ))
+                .await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,77 @@
+use crate::m20241221_191426_create_transactions_table::Transactions;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Transactions::Table)
+                    .add_column(string_len(Transactions::Status, 16).default("completed"))
+                    .to_owned(),
+            )
+            .await?;
+
+        // Transaction listings filter by either side of the transfer and order by time.
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_transactions_from_account_id")
+                    .table(Transactions::Table)
+                    .col(Transactions::FromAccountId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_transactions_to_account_id")
+                    .table(Transactions::Table)
+                    .col(Transactions::ToAccountId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_transactions_created_at_id")
+                    .table(Transactions::Table)
+                    .col(Transactions::CreatedAt)
+                    .col(Transactions::Id)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for name in [
+            "idx_transactions_created_at_id",
+            "idx_transactions_to_account_id",
+            "idx_transactions_from_account_id",
+        ] {
+            manager
+                .drop_index(
+                    Index::drop()
+                        .name(name)
+                        .table(Transactions::Table)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Transactions::Table)
+                    .drop_column(Transactions::Status)
+                    .to_owned(),
+            )
+            .await
+    }
+}
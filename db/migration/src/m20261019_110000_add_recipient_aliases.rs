@@ -0,0 +1,93 @@
+use crate::m20241221_185614_create_user_table::User;
+use crate::m20241221_190742_create_accounts_table::Accounts;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Recipients are looked up by username, so it has to identify a single user.
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_user_username")
+                    .table(User::Table)
+                    .col(User::Username)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Accounts::Table)
+                    .add_column(string_len_null(Accounts::Alias, 32))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_accounts_alias")
+                    .table(Accounts::Table)
+                    .col(Accounts::Alias)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .add_column(string_null(User::DisplayName))
+                    .add_column(integer_null(User::DefaultAccountId))
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name("fk_user_default_account_id")
+                            .from_tbl(User::Table)
+                            .from_col(User::DefaultAccountId)
+                            .to_tbl(Accounts::Table)
+                            .to_col(Accounts::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .drop_foreign_key(Alias::new("fk_user_default_account_id"))
+                    .drop_column(User::DisplayName)
+                    .drop_column(User::DefaultAccountId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Accounts::Table)
+                    .drop_column(Accounts::Alias)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_user_username")
+                    .table(User::Table)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,164 @@
+use crate::m20241221_185614_create_user_table::User;
+use crate::m20241221_190742_create_accounts_table::Accounts;
+use crate::m20241221_191426_create_transactions_table::Transactions;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .add_column(string_len(User::Role, 16).default("user"))
+                    .to_owned(),
+            )
+            .await?;
+
+        // Funds under dispute stay on the account but cannot be spent.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Accounts::Table)
+                    .add_column(decimal(Accounts::HeldBalance).default(0))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(Disputes::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Disputes::Id))
+                    .col(integer_uniq(Disputes::TransactionId))
+                    .col(integer(Disputes::OpenedBy))
+                    .col(integer(Disputes::HeldAccountId))
+                    .col(decimal(Disputes::Amount))
+                    .col(text(Disputes::Reason))
+                    .col(string_len(Disputes::Status, 16))
+                    .col(integer_null(Disputes::ResolvedBy))
+                    .col(integer_null(Disputes::ReversalTransactionId))
+                    .col(timestamp_with_time_zone(Disputes::CreatedAt))
+                    .col(timestamp_with_time_zone(Disputes::UpdatedAt))
+                    .col(timestamp_with_time_zone_null(Disputes::ResolvedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_disputes_transaction_id")
+                            .from(Disputes::Table, Disputes::TransactionId)
+                            .to(Transactions::Table, Transactions::Id),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_disputes_opened_by")
+                            .from(Disputes::Table, Disputes::OpenedBy)
+                            .to(User::Table, User::Id),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_disputes_held_account_id")
+                            .from(Disputes::Table, Disputes::HeldAccountId)
+                            .to(Accounts::Table, Accounts::Id),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_disputes_resolved_by")
+                            .from(Disputes::Table, Disputes::ResolvedBy)
+                            .to(User::Table, User::Id),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_disputes_reversal_transaction_id")
+                            .from(Disputes::Table, Disputes::ReversalTransactionId)
+                            .to(Transactions::Table, Transactions::Id),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(DisputeNotes::Table)
+                    .if_not_exists()
+                    .col(pk_auto(DisputeNotes::Id))
+                    .col(integer(DisputeNotes::DisputeId))
+                    .col(integer(DisputeNotes::AuthorId))
+                    .col(text(DisputeNotes::Note))
+                    .col(timestamp_with_time_zone(DisputeNotes::CreatedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_dispute_notes_dispute_id")
+                            .from(DisputeNotes::Table, DisputeNotes::DisputeId)
+                            .to(Disputes::Table, Disputes::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_dispute_notes_author_id")
+                            .from(DisputeNotes::Table, DisputeNotes::AuthorId)
+                            .to(User::Table, User::Id),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(DisputeNotes::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(Disputes::Table).to_owned())
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Accounts::Table)
+                    .drop_column(Accounts::HeldBalance)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .drop_column(User::Role)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum Disputes {
+    Table,
+    Id,
+    TransactionId,
+    OpenedBy,
+    HeldAccountId,
+    Amount,
+    Reason,
+    Status,
+    ResolvedBy,
+    ReversalTransactionId,
+    CreatedAt,
+    UpdatedAt,
+    ResolvedAt,
+}
+
+#[derive(DeriveIden)]
+pub enum DisputeNotes {
+    Table,
+    Id,
+    DisputeId,
+    AuthorId,
+    Note,
+    CreatedAt,
+}
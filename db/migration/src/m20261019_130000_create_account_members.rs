@@ -0,0 +1,99 @@
+use crate::m20241221_185614_create_user_table::User;
+use crate::m20241221_190742_create_accounts_table::Accounts;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(AccountMembers::Table)
+                    .if_not_exists()
+                    .col(pk_auto(AccountMembers::Id))
+                    .col(integer(AccountMembers::AccountId))
+                    .col(integer(AccountMembers::UserId))
+                    .col(string_len(AccountMembers::Role, 16))
+                    .col(timestamp_with_time_zone(AccountMembers::CreatedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_account_members_account_id")
+                            .from(AccountMembers::Table, AccountMembers::AccountId)
+                            .to(Accounts::Table, Accounts::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_account_members_user_id")
+                            .from(AccountMembers::Table, AccountMembers::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_account_members_account_id_user_id")
+                    .table(AccountMembers::Table)
+                    .col(AccountMembers::AccountId)
+                    .col(AccountMembers::UserId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_account_members_user_id")
+                    .table(AccountMembers::Table)
+                    .col(AccountMembers::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Every existing account keeps its creator as its first owner.
+        let backfill = Query::insert()
+            .into_table(AccountMembers::Table)
+            .columns([
+                AccountMembers::AccountId,
+                AccountMembers::UserId,
+                AccountMembers::Role,
+                AccountMembers::CreatedAt,
+            ])
+            .select_from(
+                Query::select()
+                    .column(Accounts::Id)
+                    .column(Accounts::UserId)
+                    .expr(Expr::val("owner"))
+                    .column(Accounts::CreatedAt)
+                    .from(Accounts::Table)
+                    .to_owned(),
+            )
+            .map_err(|err| DbErr::Migration(err.to_string()))?
+            .to_owned();
+        manager.exec_stmt(backfill).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(AccountMembers::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum AccountMembers {
+    Table,
+    Id,
+    AccountId,
+    UserId,
+    Role,
+    CreatedAt,
+}
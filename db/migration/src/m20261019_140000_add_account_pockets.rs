@@ -0,0 +1,52 @@
+use crate::m20241221_190742_create_accounts_table::Accounts;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Pockets are accounts that hang off a main account.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Accounts::Table)
+                    .add_column(integer_null(Accounts::ParentAccountId))
+                    .add_column(string_len_null(Accounts::Name, 64))
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name("fk_accounts_parent_account_id")
+                            .from_tbl(Accounts::Table)
+                            .from_col(Accounts::ParentAccountId)
+                            .to_tbl(Accounts::Table)
+                            .to_col(Accounts::Id),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_accounts_parent_account_id")
+                    .table(Accounts::Table)
+                    .col(Accounts::ParentAccountId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Accounts::Table)
+                    .drop_foreign_key(Alias::new("fk_accounts_parent_account_id"))
+                    .drop_column(Accounts::ParentAccountId)
+                    .drop_column(Accounts::Name)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,141 @@
+use crate::m20241221_185614_create_user_table::User;
+use crate::m20241221_190742_create_accounts_table::Accounts;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Organizations::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Organizations::Id))
+                    .col(string_len(Organizations::Name, 64))
+                    .col(integer(Organizations::CreatedBy))
+                    .col(timestamp_with_time_zone(Organizations::CreatedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_organizations_created_by")
+                            .from(Organizations::Table, Organizations::CreatedBy)
+                            .to(User::Table, User::Id),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(OrganizationMembers::Table)
+                    .if_not_exists()
+                    .col(pk_auto(OrganizationMembers::Id))
+                    .col(integer(OrganizationMembers::OrganizationId))
+                    .col(integer(OrganizationMembers::UserId))
+                    .col(string_len(OrganizationMembers::Role, 16))
+                    .col(timestamp_with_time_zone(OrganizationMembers::CreatedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_organization_members_organization_id")
+                            .from(
+                                OrganizationMembers::Table,
+                                OrganizationMembers::OrganizationId,
+                            )
+                            .to(Organizations::Table, Organizations::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_organization_members_user_id")
+                            .from(OrganizationMembers::Table, OrganizationMembers::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_organization_members_organization_id_user_id")
+                    .table(OrganizationMembers::Table)
+                    .col(OrganizationMembers::OrganizationId)
+                    .col(OrganizationMembers::UserId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        // Organization accounts are reached through organization membership
+        // instead of `account_members`.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Accounts::Table)
+                    .add_column(integer_null(Accounts::OrganizationId))
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name("fk_accounts_organization_id")
+                            .from_tbl(Accounts::Table)
+                            .from_col(Accounts::OrganizationId)
+                            .to_tbl(Organizations::Table)
+                            .to_col(Organizations::Id),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_accounts_organization_id")
+                    .table(Accounts::Table)
+                    .col(Accounts::OrganizationId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Accounts::Table)
+                    .drop_foreign_key(Alias::new("fk_accounts_organization_id"))
+                    .drop_column(Accounts::OrganizationId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(OrganizationMembers::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(Organizations::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum Organizations {
+    Table,
+    Id,
+    Name,
+    CreatedBy,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+pub enum OrganizationMembers {
+    Table,
+    Id,
+    OrganizationId,
+    UserId,
+    Role,
+    CreatedAt,
+}
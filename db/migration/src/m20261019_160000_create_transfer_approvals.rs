@@ -0,0 +1,165 @@
+use crate::m20241221_185614_create_user_table::User;
+use crate::m20241221_190742_create_accounts_table::Accounts;
+use crate::m20241221_191426_create_transactions_table::Transactions;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Transfers above the threshold from shared or organization accounts need a
+        // second user's approval. `NULL` disables approvals for the account.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Accounts::Table)
+                    .add_column(decimal_null(Accounts::ApprovalThreshold))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(TransferApprovals::Table)
+                    .if_not_exists()
+                    .col(pk_auto(TransferApprovals::Id))
+                    .col(integer(TransferApprovals::FromAccountId))
+                    .col(integer(TransferApprovals::ToAccountId))
+                    .col(decimal(TransferApprovals::Amount))
+                    .col(string_len(TransferApprovals::Status, 16))
+                    .col(integer(TransferApprovals::RequestedBy))
+                    .col(integer_null(TransferApprovals::DecidedBy))
+                    .col(integer_null(TransferApprovals::TransactionId))
+                    .col(timestamp_with_time_zone(TransferApprovals::CreatedAt))
+                    .col(timestamp_with_time_zone_null(TransferApprovals::DecidedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_transfer_approvals_from_account_id")
+                            .from(TransferApprovals::Table, TransferApprovals::FromAccountId)
+                            .to(Accounts::Table, Accounts::Id),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_transfer_approvals_to_account_id")
+                            .from(TransferApprovals::Table, TransferApprovals::ToAccountId)
+                            .to(Accounts::Table, Accounts::Id),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_transfer_approvals_requested_by")
+                            .from(TransferApprovals::Table, TransferApprovals::RequestedBy)
+                            .to(User::Table, User::Id),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_transfer_approvals_decided_by")
+                            .from(TransferApprovals::Table, TransferApprovals::DecidedBy)
+                            .to(User::Table, User::Id),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_transfer_approvals_transaction_id")
+                            .from(TransferApprovals::Table, TransferApprovals::TransactionId)
+                            .to(Transactions::Table, Transactions::Id),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_transfer_approvals_from_account_id")
+                    .table(TransferApprovals::Table)
+                    .col(TransferApprovals::FromAccountId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(TransferApprovalEvents::Table)
+                    .if_not_exists()
+                    .col(pk_auto(TransferApprovalEvents::Id))
+                    .col(integer(TransferApprovalEvents::ApprovalId))
+                    .col(integer(TransferApprovalEvents::ActorId))
+                    .col(string_len(TransferApprovalEvents::Action, 16))
+                    .col(text_null(TransferApprovalEvents::Note))
+                    .col(timestamp_with_time_zone(TransferApprovalEvents::CreatedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_transfer_approval_events_approval_id")
+                            .from(
+                                TransferApprovalEvents::Table,
+                                TransferApprovalEvents::ApprovalId,
+                            )
+                            .to(TransferApprovals::Table, TransferApprovals::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_transfer_approval_events_actor_id")
+                            .from(
+                                TransferApprovalEvents::Table,
+                                TransferApprovalEvents::ActorId,
+                            )
+                            .to(User::Table, User::Id),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(TransferApprovalEvents::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(TransferApprovals::Table).to_owned())
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Accounts::Table)
+                    .drop_column(Accounts::ApprovalThreshold)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum TransferApprovals {
+    Table,
+    Id,
+    FromAccountId,
+    ToAccountId,
+    Amount,
+    Status,
+    RequestedBy,
+    DecidedBy,
+    TransactionId,
+    CreatedAt,
+    DecidedAt,
+}
+
+#[derive(DeriveIden)]
+pub enum TransferApprovalEvents {
+    Table,
+    Id,
+    ApprovalId,
+    ActorId,
+    Action,
+    Note,
+    CreatedAt,
+}
@@ -0,0 +1,90 @@
+use crate::m20241221_185614_create_user_table::User;
+use crate::m20241221_190742_create_accounts_table::Accounts;
+use crate::m20241221_191426_create_transactions_table::Transactions;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // A merchant's request for a payment. The customer account and the settling
+        // transaction are only known once a customer confirms it.
+        manager
+            .create_table(
+                Table::create()
+                    .table(PaymentIntents::Table)
+                    .if_not_exists()
+                    .col(pk_auto(PaymentIntents::Id))
+                    .col(integer(PaymentIntents::MerchantAccountId))
+                    .col(decimal(PaymentIntents::Amount))
+                    .col(string_len(PaymentIntents::Currency, 3))
+                    .col(json_binary_null(PaymentIntents::Metadata))
+                    .col(string_len(PaymentIntents::Status, 16))
+                    .col(integer(PaymentIntents::CreatedBy))
+                    .col(integer_null(PaymentIntents::CustomerAccountId))
+                    .col(integer_null(PaymentIntents::TransactionId))
+                    .col(timestamp_with_time_zone(PaymentIntents::CreatedAt))
+                    .col(timestamp_with_time_zone(PaymentIntents::UpdatedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_payment_intents_merchant_account_id")
+                            .from(PaymentIntents::Table, PaymentIntents::MerchantAccountId)
+                            .to(Accounts::Table, Accounts::Id),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_payment_intents_created_by")
+                            .from(PaymentIntents::Table, PaymentIntents::CreatedBy)
+                            .to(User::Table, User::Id),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_payment_intents_customer_account_id")
+                            .from(PaymentIntents::Table, PaymentIntents::CustomerAccountId)
+                            .to(Accounts::Table, Accounts::Id),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_payment_intents_transaction_id")
+                            .from(PaymentIntents::Table, PaymentIntents::TransactionId)
+                            .to(Transactions::Table, Transactions::Id),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_payment_intents_merchant_account_id")
+                    .table(PaymentIntents::Table)
+                    .col(PaymentIntents::MerchantAccountId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(PaymentIntents::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum PaymentIntents {
+    Table,
+    Id,
+    MerchantAccountId,
+    Amount,
+    Currency,
+    Metadata,
+    Status,
+    CreatedBy,
+    CustomerAccountId,
+    TransactionId,
+    CreatedAt,
+    UpdatedAt,
+}
@@ -0,0 +1,91 @@
+use crate::m20241221_185614_create_user_table::User;
+use crate::m20241221_190742_create_accounts_table::Accounts;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // A `NULL` amount lets the payer choose how much to pay
+        manager
+            .create_table(
+                Table::create()
+                    .table(PaymentLinks::Table)
+                    .if_not_exists()
+                    .col(pk_auto(PaymentLinks::Id))
+                    .col(string_len(PaymentLinks::Slug, 32))
+                    .col(integer(PaymentLinks::AccountId))
+                    .col(integer(PaymentLinks::CreatedBy))
+                    .col(decimal_null(PaymentLinks::Amount))
+                    .col(string_len_null(PaymentLinks::Description, 200))
+                    .col(boolean(PaymentLinks::SingleUse))
+                    .col(boolean(PaymentLinks::Active))
+                    .col(timestamp_with_time_zone_null(PaymentLinks::ExpiresAt))
+                    .col(integer(PaymentLinks::PaymentCount).default(0))
+                    .col(decimal(PaymentLinks::CollectedAmount).default(0))
+                    .col(timestamp_with_time_zone(PaymentLinks::CreatedAt))
+                    .col(timestamp_with_time_zone(PaymentLinks::UpdatedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_payment_links_account_id")
+                            .from(PaymentLinks::Table, PaymentLinks::AccountId)
+                            .to(Accounts::Table, Accounts::Id),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_payment_links_created_by")
+                            .from(PaymentLinks::Table, PaymentLinks::CreatedBy)
+                            .to(User::Table, User::Id),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_payment_links_slug")
+                    .table(PaymentLinks::Table)
+                    .col(PaymentLinks::Slug)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_payment_links_account_id")
+                    .table(PaymentLinks::Table)
+                    .col(PaymentLinks::AccountId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(PaymentLinks::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum PaymentLinks {
+    Table,
+    Id,
+    Slug,
+    AccountId,
+    CreatedBy,
+    Amount,
+    Description,
+    SingleUse,
+    Active,
+    ExpiresAt,
+    PaymentCount,
+    CollectedAmount,
+    CreatedAt,
+    UpdatedAt,
+}
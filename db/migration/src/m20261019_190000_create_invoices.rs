@@ -0,0 +1,196 @@
+use crate::m20241221_185614_create_user_table::User;
+use crate::m20241221_190742_create_accounts_table::Accounts;
+use crate::m20241221_191426_create_transactions_table::Transactions;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Totals are stored next to the line items so listings and payment matching
+        // don't have to add them up again
+        manager
+            .create_table(
+                Table::create()
+                    .table(Invoices::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Invoices::Id))
+                    .col(integer(Invoices::IssuerAccountId))
+                    .col(integer(Invoices::IssuedBy))
+                    .col(integer(Invoices::RecipientUserId))
+                    .col(string_len(Invoices::Number, 32))
+                    .col(text_null(Invoices::Memo))
+                    .col(decimal(Invoices::Subtotal))
+                    .col(decimal(Invoices::TaxRate))
+                    .col(decimal(Invoices::TaxAmount))
+                    .col(decimal(Invoices::Total))
+                    .col(decimal(Invoices::AmountPaid))
+                    .col(string_len(Invoices::Status, 16))
+                    .col(date(Invoices::DueDate))
+                    .col(timestamp_with_time_zone(Invoices::CreatedAt))
+                    .col(timestamp_with_time_zone(Invoices::UpdatedAt))
+                    .col(timestamp_with_time_zone_null(Invoices::PaidAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_invoices_issuer_account_id")
+                            .from(Invoices::Table, Invoices::IssuerAccountId)
+                            .to(Accounts::Table, Accounts::Id),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_invoices_issued_by")
+                            .from(Invoices::Table, Invoices::IssuedBy)
+                            .to(User::Table, User::Id),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_invoices_recipient_user_id")
+                            .from(Invoices::Table, Invoices::RecipientUserId)
+                            .to(User::Table, User::Id),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_invoices_issuer_account_id_number")
+                    .table(Invoices::Table)
+                    .col(Invoices::IssuerAccountId)
+                    .col(Invoices::Number)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_invoices_recipient_user_id")
+                    .table(Invoices::Table)
+                    .col(Invoices::RecipientUserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(InvoiceLineItems::Table)
+                    .if_not_exists()
+                    .col(pk_auto(InvoiceLineItems::Id))
+                    .col(integer(InvoiceLineItems::InvoiceId))
+                    .col(string_len(InvoiceLineItems::Description, 200))
+                    .col(integer(InvoiceLineItems::Quantity))
+                    .col(decimal(InvoiceLineItems::UnitPrice))
+                    .col(decimal(InvoiceLineItems::Amount))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_invoice_line_items_invoice_id")
+                            .from(InvoiceLineItems::Table, InvoiceLineItems::InvoiceId)
+                            .to(Invoices::Table, Invoices::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(InvoicePayments::Table)
+                    .if_not_exists()
+                    .col(pk_auto(InvoicePayments::Id))
+                    .col(integer(InvoicePayments::InvoiceId))
+                    .col(integer(InvoicePayments::TransactionId))
+                    .col(decimal(InvoicePayments::Amount))
+                    .col(timestamp_with_time_zone(InvoicePayments::CreatedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_invoice_payments_invoice_id")
+                            .from(InvoicePayments::Table, InvoicePayments::InvoiceId)
+                            .to(Invoices::Table, Invoices::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_invoice_payments_transaction_id")
+                            .from(InvoicePayments::Table, InvoicePayments::TransactionId)
+                            .to(Transactions::Table, Transactions::Id),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // A transaction pays at most one invoice
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_invoice_payments_transaction_id")
+                    .table(InvoicePayments::Table)
+                    .col(InvoicePayments::TransactionId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(InvoicePayments::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(InvoiceLineItems::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(Invoices::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum Invoices {
+    Table,
+    Id,
+    IssuerAccountId,
+    IssuedBy,
+    RecipientUserId,
+    Number,
+    Memo,
+    Subtotal,
+    TaxRate,
+    TaxAmount,
+    Total,
+    AmountPaid,
+    Status,
+    DueDate,
+    CreatedAt,
+    UpdatedAt,
+    PaidAt,
+}
+
+#[derive(DeriveIden)]
+pub enum InvoiceLineItems {
+    Table,
+    Id,
+    InvoiceId,
+    Description,
+    Quantity,
+    UnitPrice,
+    Amount,
+}
+
+#[derive(DeriveIden)]
+pub enum InvoicePayments {
+    Table,
+    Id,
+    InvoiceId,
+    TransactionId,
+    Amount,
+    CreatedAt,
+}
@@ -0,0 +1,219 @@
+use crate::m20241221_185614_create_user_table::User;
+use crate::m20241221_190742_create_accounts_table::Accounts;
+use crate::m20241221_191426_create_transactions_table::Transactions;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(SubscriptionPlans::Table)
+                    .if_not_exists()
+                    .col(pk_auto(SubscriptionPlans::Id))
+                    .col(integer(SubscriptionPlans::MerchantAccountId))
+                    .col(string_len(SubscriptionPlans::Name, 100))
+                    .col(decimal(SubscriptionPlans::Amount))
+                    .col(string_len(SubscriptionPlans::Interval, 16))
+                    .col(integer(SubscriptionPlans::IntervalCount))
+                    .col(integer(SubscriptionPlans::TrialDays))
+                    .col(boolean(SubscriptionPlans::Active))
+                    .col(integer(SubscriptionPlans::CreatedBy))
+                    .col(timestamp_with_time_zone(SubscriptionPlans::CreatedAt))
+                    .col(timestamp_with_time_zone(SubscriptionPlans::UpdatedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_subscription_plans_merchant_account_id")
+                            .from(
+                                SubscriptionPlans::Table,
+                                SubscriptionPlans::MerchantAccountId,
+                            )
+                            .to(Accounts::Table, Accounts::Id),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_subscription_plans_created_by")
+                            .from(SubscriptionPlans::Table, SubscriptionPlans::CreatedBy)
+                            .to(User::Table, User::Id),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // `next_charge_at` is what the scheduler polls: the end of the trial or the
+        // current period, or the next dunning retry. It is `NULL` once canceled.
+        // `credit` is left over from plan downgrades and reduces the next charge.
+        manager
+            .create_table(
+                Table::create()
+                    .table(Subscriptions::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Subscriptions::Id))
+                    .col(integer(Subscriptions::PlanId))
+                    .col(integer(Subscriptions::SubscriberUserId))
+                    .col(integer(Subscriptions::PayerAccountId))
+                    .col(string_len(Subscriptions::Status, 16))
+                    .col(timestamp_with_time_zone(Subscriptions::CurrentPeriodStart))
+                    .col(timestamp_with_time_zone(Subscriptions::CurrentPeriodEnd))
+                    .col(timestamp_with_time_zone_null(Subscriptions::TrialEnd))
+                    .col(timestamp_with_time_zone_null(Subscriptions::NextChargeAt))
+                    .col(integer(Subscriptions::FailedAttempts).default(0))
+                    .col(decimal(Subscriptions::Credit).default(0))
+                    .col(boolean(Subscriptions::CancelAtPeriodEnd).default(false))
+                    .col(timestamp_with_time_zone_null(Subscriptions::CanceledAt))
+                    .col(timestamp_with_time_zone(Subscriptions::CreatedAt))
+                    .col(timestamp_with_time_zone(Subscriptions::UpdatedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_subscriptions_plan_id")
+                            .from(Subscriptions::Table, Subscriptions::PlanId)
+                            .to(SubscriptionPlans::Table, SubscriptionPlans::Id),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_subscriptions_subscriber_user_id")
+                            .from(Subscriptions::Table, Subscriptions::SubscriberUserId)
+                            .to(User::Table, User::Id),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_subscriptions_payer_account_id")
+                            .from(Subscriptions::Table, Subscriptions::PayerAccountId)
+                            .to(Accounts::Table, Accounts::Id),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_subscriptions_next_charge_at")
+                    .table(Subscriptions::Table)
+                    .col(Subscriptions::NextChargeAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_subscriptions_subscriber_user_id")
+                    .table(Subscriptions::Table)
+                    .col(Subscriptions::SubscriberUserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(SubscriptionCharges::Table)
+                    .if_not_exists()
+                    .col(pk_auto(SubscriptionCharges::Id))
+                    .col(integer(SubscriptionCharges::SubscriptionId))
+                    .col(integer(SubscriptionCharges::PlanId))
+                    .col(string_len(SubscriptionCharges::Kind, 16))
+                    .col(string_len(SubscriptionCharges::Status, 16))
+                    .col(decimal(SubscriptionCharges::Amount))
+                    .col(integer_null(SubscriptionCharges::TransactionId))
+                    .col(string_len_null(SubscriptionCharges::FailureReason, 200))
+                    .col(timestamp_with_time_zone(SubscriptionCharges::CreatedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_subscription_charges_subscription_id")
+                            .from(
+                                SubscriptionCharges::Table,
+                                SubscriptionCharges::SubscriptionId,
+                            )
+                            .to(Subscriptions::Table, Subscriptions::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_subscription_charges_plan_id")
+                            .from(SubscriptionCharges::Table, SubscriptionCharges::PlanId)
+                            .to(SubscriptionPlans::Table, SubscriptionPlans::Id),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_subscription_charges_transaction_id")
+                            .from(
+                                SubscriptionCharges::Table,
+                                SubscriptionCharges::TransactionId,
+                            )
+                            .to(Transactions::Table, Transactions::Id),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SubscriptionCharges::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(Subscriptions::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(SubscriptionPlans::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum SubscriptionPlans {
+    Table,
+    Id,
+    MerchantAccountId,
+    Name,
+    Amount,
+    Interval,
+    IntervalCount,
+    TrialDays,
+    Active,
+    CreatedBy,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+pub enum Subscriptions {
+    Table,
+    Id,
+    PlanId,
+    SubscriberUserId,
+    PayerAccountId,
+    Status,
+    CurrentPeriodStart,
+    CurrentPeriodEnd,
+    TrialEnd,
+    NextChargeAt,
+    FailedAttempts,
+    Credit,
+    CancelAtPeriodEnd,
+    CanceledAt,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+pub enum SubscriptionCharges {
+    Table,
+    Id,
+    SubscriptionId,
+    PlanId,
+    Kind,
+    Status,
+    Amount,
+    TransactionId,
+    FailureReason,
+    CreatedAt,
+}
@@ -0,0 +1,145 @@
+use crate::m20241221_185614_create_user_table::User;
+use crate::m20241221_190742_create_accounts_table::Accounts;
+use crate::m20241221_191426_create_transactions_table::Transactions;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(SplitPayments::Table)
+                    .if_not_exists()
+                    .col(pk_auto(SplitPayments::Id))
+                    .col(integer(SplitPayments::PayerAccountId))
+                    .col(integer(SplitPayments::CreatedBy))
+                    .col(decimal(SplitPayments::Amount))
+                    .col(decimal(SplitPayments::PlatformFee))
+                    .col(string_len_null(SplitPayments::Description, 200))
+                    .col(timestamp_with_time_zone(SplitPayments::CreatedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_split_payments_payer_account_id")
+                            .from(SplitPayments::Table, SplitPayments::PayerAccountId)
+                            .to(Accounts::Table, Accounts::Id),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_split_payments_created_by")
+                            .from(SplitPayments::Table, SplitPayments::CreatedBy)
+                            .to(User::Table, User::Id),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_split_payments_payer_account_id")
+                    .table(SplitPayments::Table)
+                    .col(SplitPayments::PayerAccountId)
+                    .to_owned(),
+            )
+            .await?;
+
+        // One leg per recipient and one for the platform fee, each settled by its own
+        // transaction. The rule is kept next to the amount it produced.
+        manager
+            .create_table(
+                Table::create()
+                    .table(SplitPaymentLegs::Table)
+                    .if_not_exists()
+                    .col(pk_auto(SplitPaymentLegs::Id))
+                    .col(integer(SplitPaymentLegs::SplitPaymentId))
+                    .col(integer(SplitPaymentLegs::RecipientAccountId))
+                    .col(string_len(SplitPaymentLegs::Kind, 16))
+                    .col(decimal(SplitPaymentLegs::RuleValue))
+                    .col(decimal(SplitPaymentLegs::Amount))
+                    .col(integer(SplitPaymentLegs::TransactionId))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_split_payment_legs_split_payment_id")
+                            .from(SplitPaymentLegs::Table, SplitPaymentLegs::SplitPaymentId)
+                            .to(SplitPayments::Table, SplitPayments::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_split_payment_legs_recipient_account_id")
+                            .from(
+                                SplitPaymentLegs::Table,
+                                SplitPaymentLegs::RecipientAccountId,
+                            )
+                            .to(Accounts::Table, Accounts::Id),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_split_payment_legs_transaction_id")
+                            .from(SplitPaymentLegs::Table, SplitPaymentLegs::TransactionId)
+                            .to(Transactions::Table, Transactions::Id),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_split_payment_legs_transaction_id")
+                    .table(SplitPaymentLegs::Table)
+                    .col(SplitPaymentLegs::TransactionId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_split_payment_legs_recipient_account_id")
+                    .table(SplitPaymentLegs::Table)
+                    .col(SplitPaymentLegs::RecipientAccountId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SplitPaymentLegs::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(SplitPayments::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum SplitPayments {
+    Table,
+    Id,
+    PayerAccountId,
+    CreatedBy,
+    Amount,
+    PlatformFee,
+    Description,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+pub enum SplitPaymentLegs {
+    Table,
+    Id,
+    SplitPaymentId,
+    RecipientAccountId,
+    Kind,
+    RuleValue,
+    Amount,
+    TransactionId,
+}
@@ -0,0 +1,176 @@
+use crate::m20241221_185614_create_user_table::User;
+use crate::m20241221_190742_create_accounts_table::Accounts;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // The secret signs every delivery, so it is kept in plain text
+        manager
+            .create_table(
+                Table::create()
+                    .table(WebhookEndpoints::Table)
+                    .if_not_exists()
+                    .col(pk_auto(WebhookEndpoints::Id))
+                    .col(integer(WebhookEndpoints::AccountId))
+                    .col(integer(WebhookEndpoints::CreatedBy))
+                    .col(string_len(WebhookEndpoints::Url, 2048))
+                    .col(string_len(WebhookEndpoints::Secret, 64))
+                    .col(json_binary(WebhookEndpoints::EventTypes))
+                    .col(boolean(WebhookEndpoints::Active))
+                    .col(timestamp_with_time_zone(WebhookEndpoints::CreatedAt))
+                    .col(timestamp_with_time_zone(WebhookEndpoints::UpdatedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_webhook_endpoints_account_id")
+                            .from(WebhookEndpoints::Table, WebhookEndpoints::AccountId)
+                            .to(Accounts::Table, Accounts::Id),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_webhook_endpoints_created_by")
+                            .from(WebhookEndpoints::Table, WebhookEndpoints::CreatedBy)
+                            .to(User::Table, User::Id),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_webhook_endpoints_account_id")
+                    .table(WebhookEndpoints::Table)
+                    .col(WebhookEndpoints::AccountId)
+                    .to_owned(),
+            )
+            .await?;
+
+        // One event sent to one endpoint. `next_attempt_at` is cleared once the delivery
+        // succeeded or ran out of attempts.
+        manager
+            .create_table(
+                Table::create()
+                    .table(WebhookDeliveries::Table)
+                    .if_not_exists()
+                    .col(pk_auto(WebhookDeliveries::Id))
+                    .col(integer(WebhookDeliveries::EndpointId))
+                    .col(string_len(WebhookDeliveries::EventType, 32))
+                    .col(json_binary(WebhookDeliveries::Payload))
+                    .col(string_len(WebhookDeliveries::Status, 16))
+                    .col(integer(WebhookDeliveries::AttemptCount).default(0))
+                    .col(timestamp_with_time_zone_null(
+                        WebhookDeliveries::NextAttemptAt,
+                    ))
+                    .col(timestamp_with_time_zone(WebhookDeliveries::CreatedAt))
+                    .col(timestamp_with_time_zone(WebhookDeliveries::UpdatedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_webhook_deliveries_endpoint_id")
+                            .from(WebhookDeliveries::Table, WebhookDeliveries::EndpointId)
+                            .to(WebhookEndpoints::Table, WebhookEndpoints::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_webhook_deliveries_next_attempt_at")
+                    .table(WebhookDeliveries::Table)
+                    .col(WebhookDeliveries::NextAttemptAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_webhook_deliveries_endpoint_id")
+                    .table(WebhookDeliveries::Table)
+                    .col(WebhookDeliveries::EndpointId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(WebhookAttempts::Table)
+                    .if_not_exists()
+                    .col(pk_auto(WebhookAttempts::Id))
+                    .col(integer(WebhookAttempts::DeliveryId))
+                    .col(integer_null(WebhookAttempts::ResponseStatus))
+                    .col(string_len_null(WebhookAttempts::Error, 500))
+                    .col(integer(WebhookAttempts::DurationMs))
+                    .col(timestamp_with_time_zone(WebhookAttempts::AttemptedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_webhook_attempts_delivery_id")
+                            .from(WebhookAttempts::Table, WebhookAttempts::DeliveryId)
+                            .to(WebhookDeliveries::Table, WebhookDeliveries::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(WebhookAttempts::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(WebhookDeliveries::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(WebhookEndpoints::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum WebhookEndpoints {
+    Table,
+    Id,
+    AccountId,
+    CreatedBy,
+    Url,
+    Secret,
+    EventTypes,
+    Active,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+pub enum WebhookDeliveries {
+    Table,
+    Id,
+    EndpointId,
+    EventType,
+    Payload,
+    Status,
+    AttemptCount,
+    NextAttemptAt,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+pub enum WebhookAttempts {
+    Table,
+    Id,
+    DeliveryId,
+    ResponseStatus,
+    Error,
+    DurationMs,
+    AttemptedAt,
+}
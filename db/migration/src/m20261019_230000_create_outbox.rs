@@ -0,0 +1,91 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // `transaction_id` is the id of the database transaction that wrote the event.
+        // Postgres assigns it at the transaction's first write, in increasing order, so
+        // a transaction that has not written yet gets a higher id than any seen so far.
+        // The relay reads events in `(transaction_id, id)` order and only up to the
+        // oldest transaction still running; an event committed late can then never
+        // land behind a sink's offset.
+        manager
+            .create_table(
+                Table::create()
+                    .table(Outbox::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Outbox::Id))
+                    .col(
+                        big_integer(Outbox::TransactionId)
+                            .default(Expr::cust("pg_current_xact_id()::text::bigint")),
+                    )
+                    .col(string_len(Outbox::EventType, 32))
+                    .col(integer(Outbox::AggregateId))
+                    .col(json_binary(Outbox::Payload))
+                    .col(timestamp_with_time_zone(Outbox::CreatedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_outbox_transaction_id_id")
+                    .table(Outbox::Table)
+                    .col(Outbox::TransactionId)
+                    .col(Outbox::Id)
+                    .to_owned(),
+            )
+            .await?;
+
+        // How far each sink has read the outbox. `locked_until` is a lease that keeps
+        // other relays off the sink while one of them is publishing.
+        manager
+            .create_table(
+                Table::create()
+                    .table(OutboxOffsets::Table)
+                    .if_not_exists()
+                    .col(string_len(OutboxOffsets::Sink, 32).primary_key())
+                    .col(big_integer(OutboxOffsets::LastTransactionId).default(0))
+                    .col(integer(OutboxOffsets::LastEventId).default(0))
+                    .col(timestamp_with_time_zone_null(OutboxOffsets::LockedUntil))
+                    .col(timestamp_with_time_zone(OutboxOffsets::UpdatedAt))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(OutboxOffsets::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(Outbox::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum Outbox {
+    Table,
+    Id,
+    TransactionId,
+    EventType,
+    AggregateId,
+    Payload,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+pub enum OutboxOffsets {
+    Table,
+    Sink,
+    LastTransactionId,
+    LastEventId,
+    LockedUntil,
+    UpdatedAt,
+}
@@ -0,0 +1,43 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Postgres sends notifications when the inserting transaction commits, so
+        // listeners on every server instance learn about an event once it is visible.
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"
+                CREATE FUNCTION notify_outbox_event() RETURNS trigger AS $$
+                BEGIN
+                    PERFORM pg_notify('outbox_events', NEW.id::text);
+                    RETURN NEW;
+                END;
+                $$ LANGUAGE plpgsql;
+
+                CREATE TRIGGER outbox_notify
+                    AFTER INSERT ON outbox
+                    FOR EACH ROW EXECUTE FUNCTION notify_outbox_event();
+                "#,
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"
+                DROP TRIGGER IF EXISTS outbox_notify ON outbox;
+                DROP FUNCTION IF EXISTS notify_outbox_event();
+                "#,
+            )
+            .await?;
+        Ok(())
+    }
+}
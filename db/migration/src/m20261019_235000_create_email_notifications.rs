@@ -0,0 +1,91 @@
+use crate::m20241221_185614_create_user_table::User;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Stored in lower case, so the unique index is case-insensitive
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .add_column(string_len_null(User::Email, 254))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_user_email")
+                    .table(User::Table)
+                    .col(User::Email)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        // Users without a row get every email and the default large transfer threshold
+        manager
+            .create_table(
+                Table::create()
+                    .table(NotificationPreferences::Table)
+                    .if_not_exists()
+                    .col(integer(NotificationPreferences::UserId).primary_key())
+                    .col(boolean(NotificationPreferences::MoneyReceived))
+                    .col(boolean(NotificationPreferences::MoneySent))
+                    .col(boolean(NotificationPreferences::LargeTransfers))
+                    .col(decimal(NotificationPreferences::LargeTransferThreshold))
+                    .col(boolean(NotificationPreferences::NewLogins))
+                    .col(boolean(NotificationPreferences::PasswordChanges))
+                    .col(timestamp_with_time_zone(NotificationPreferences::UpdatedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_notification_preferences_user_id")
+                            .from(
+                                NotificationPreferences::Table,
+                                NotificationPreferences::UserId,
+                            )
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(NotificationPreferences::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .drop_column(User::Email)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum NotificationPreferences {
+    Table,
+    UserId,
+    MoneyReceived,
+    MoneySent,
+    LargeTransfers,
+    LargeTransferThreshold,
+    NewLogins,
+    PasswordChanges,
+    UpdatedAt,
+}
@@ -0,0 +1,107 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // `hash` covers the row's contents and `prev_hash`, the hash of the row before
+        // it, so editing or removing a row breaks the chain from that row on. The actor
+        // is not a foreign key, so entries outlive the users they name.
+        manager
+            .create_table(
+                Table::create()
+                    .table(AuditLog::Table)
+                    .if_not_exists()
+                    .col(pk_auto(AuditLog::Id))
+                    .col(integer_null(AuditLog::ActorId))
+                    .col(string_len(AuditLog::Action, 128))
+                    .col(string_len(AuditLog::Target, 256))
+                    .col(small_integer(AuditLog::Status))
+                    .col(json_binary_null(AuditLog::Before))
+                    .col(json_binary_null(AuditLog::After))
+                    .col(string_len_null(AuditLog::Ip, 64))
+                    .col(string_len_null(AuditLog::RequestId, 64))
+                    .col(timestamp_with_time_zone(AuditLog::CreatedAt))
+                    .col(string_len(AuditLog::PrevHash, 64))
+                    .col(string_len(AuditLog::Hash, 64).unique_key())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_audit_log_actor_id")
+                    .table(AuditLog::Table)
+                    .col(AuditLog::ActorId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_audit_log_created_at_id")
+                    .table(AuditLog::Table)
+                    .col(AuditLog::CreatedAt)
+                    .col(AuditLog::Id)
+                    .to_owned(),
+            )
+            .await?;
+
+        // The application only ever inserts; the hash chain catches changes made by
+        // anyone who gets around these triggers.
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"
+                CREATE FUNCTION reject_audit_log_change() RETURNS trigger AS $$
+                BEGIN
+                    RAISE EXCEPTION 'audit_log is append-only';
+                END;
+                $$ LANGUAGE plpgsql;
+
+                CREATE TRIGGER audit_log_append_only
+                    BEFORE UPDATE OR DELETE ON audit_log
+                    FOR EACH ROW EXECUTE FUNCTION reject_audit_log_change();
+
+                CREATE TRIGGER audit_log_no_truncate
+                    BEFORE TRUNCATE ON audit_log
+                    FOR EACH STATEMENT EXECUTE FUNCTION reject_audit_log_change();
+                "#,
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(AuditLog::Table).to_owned())
+            .await?;
+
+        manager
+            .get_connection()
+            .execute_unprepared("DROP FUNCTION IF EXISTS reject_audit_log_change();")
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum AuditLog {
+    Table,
+    Id,
+    ActorId,
+    Action,
+    Target,
+    Status,
+    Before,
+    After,
+    Ip,
+    RequestId,
+    CreatedAt,
+    PrevHash,
+    Hash,
+}
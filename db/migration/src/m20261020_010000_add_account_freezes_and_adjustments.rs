@@ -0,0 +1,92 @@
+use crate::m20241221_185614_create_user_table::User;
+use crate::m20241221_190742_create_accounts_table::Accounts;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Frozen accounts can still receive money but cannot send any
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Accounts::Table)
+                    .add_column(timestamp_with_time_zone_null(Accounts::FrozenAt))
+                    .add_column(string_len_null(Accounts::FrozenReason, 500))
+                    .to_owned(),
+            )
+            .await?;
+
+        // Manual balance changes made by admins. `amount` is signed: credits are
+        // positive, debits negative.
+        manager
+            .create_table(
+                Table::create()
+                    .table(BalanceAdjustments::Table)
+                    .if_not_exists()
+                    .col(pk_auto(BalanceAdjustments::Id))
+                    .col(integer(BalanceAdjustments::AccountId))
+                    .col(integer(BalanceAdjustments::AdjustedBy))
+                    .col(decimal(BalanceAdjustments::Amount))
+                    .col(decimal(BalanceAdjustments::BalanceAfter))
+                    .col(string_len(BalanceAdjustments::Reason, 32))
+                    .col(string_len_null(BalanceAdjustments::Note, 500))
+                    .col(timestamp_with_time_zone(BalanceAdjustments::CreatedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_balance_adjustments_account_id")
+                            .from(BalanceAdjustments::Table, BalanceAdjustments::AccountId)
+                            .to(Accounts::Table, Accounts::Id),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_balance_adjustments_adjusted_by")
+                            .from(BalanceAdjustments::Table, BalanceAdjustments::AdjustedBy)
+                            .to(User::Table, User::Id),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_balance_adjustments_account_id")
+                    .table(BalanceAdjustments::Table)
+                    .col(BalanceAdjustments::AccountId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(BalanceAdjustments::Table).to_owned())
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Accounts::Table)
+                    .drop_column(Accounts::FrozenAt)
+                    .drop_column(Accounts::FrozenReason)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum BalanceAdjustments {
+    Table,
+    Id,
+    AccountId,
+    AdjustedBy,
+    Amount,
+    BalanceAfter,
+    Reason,
+    Note,
+    CreatedAt,
+}
@@ -0,0 +1,68 @@
+use crate::m20241221_185614_create_user_table::User;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Only the SHA-256 of a key is kept; `prefix` is its start, to tell keys apart
+        manager
+            .create_table(
+                Table::create()
+                    .table(ApiKeys::Table)
+                    .if_not_exists()
+                    .col(pk_auto(ApiKeys::Id))
+                    .col(integer(ApiKeys::UserId))
+                    .col(string_len(ApiKeys::Name, 100))
+                    .col(string_len(ApiKeys::Prefix, 16))
+                    .col(string_len(ApiKeys::KeyHash, 64).unique_key())
+                    .col(json_binary(ApiKeys::Scopes))
+                    .col(timestamp_with_time_zone_null(ApiKeys::ExpiresAt))
+                    .col(timestamp_with_time_zone_null(ApiKeys::LastUsedAt))
+                    .col(timestamp_with_time_zone_null(ApiKeys::RevokedAt))
+                    .col(timestamp_with_time_zone(ApiKeys::CreatedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_api_keys_user_id")
+                            .from(ApiKeys::Table, ApiKeys::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_api_keys_user_id")
+                    .table(ApiKeys::Table)
+                    .col(ApiKeys::UserId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ApiKeys::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum ApiKeys {
+    Table,
+    Id,
+    UserId,
+    Name,
+    Prefix,
+    KeyHash,
+    Scopes,
+    ExpiresAt,
+    LastUsedAt,
+    RevokedAt,
+    CreatedAt,
+}
@@ -0,0 +1,43 @@
+use crate::m20261019_150000_create_organizations::Organizations;
+use crate::m20261020_030000_create_refresh_tokens::RefreshTokens;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // The organization a token family acts for, so that refreshed access tokens keep
+        // it. Deleting the organization drops its tokens back to personal accounts.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(RefreshTokens::Table)
+                    .add_column(integer_null(RefreshTokens::OrganizationId))
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name("fk_refresh_tokens_organization_id")
+                            .from_tbl(RefreshTokens::Table)
+                            .from_col(RefreshTokens::OrganizationId)
+                            .to_tbl(Organizations::Table)
+                            .to_col(Organizations::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(RefreshTokens::Table)
+                    .drop_foreign_key(Alias::new("fk_refresh_tokens_organization_id"))
+                    .drop_column(RefreshTokens::OrganizationId)
+                    .to_owned(),
+            )
+            .await
+    }
+}
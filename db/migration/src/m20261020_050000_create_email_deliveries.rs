@@ -0,0 +1,67 @@
+use crate::m20241221_185614_create_user_table::User;
+use crate::m20261019_230000_create_outbox::Outbox;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // One row per email sent about an outbox event, so that retrying the event after
+        // a failed send skips the recipients who already got theirs
+        manager
+            .create_table(
+                Table::create()
+                    .table(EmailDeliveries::Table)
+                    .if_not_exists()
+                    .col(pk_auto(EmailDeliveries::Id))
+                    .col(integer(EmailDeliveries::EventId))
+                    .col(integer(EmailDeliveries::UserId))
+                    .col(timestamp_with_time_zone(EmailDeliveries::SentAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_email_deliveries_event_id")
+                            .from(EmailDeliveries::Table, EmailDeliveries::EventId)
+                            .to(Outbox::Table, Outbox::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_email_deliveries_user_id")
+                            .from(EmailDeliveries::Table, EmailDeliveries::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_email_deliveries_event_id_user_id")
+                    .table(EmailDeliveries::Table)
+                    .col(EmailDeliveries::EventId)
+                    .col(EmailDeliveries::UserId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(EmailDeliveries::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum EmailDeliveries {
+    Table,
+    Id,
+    EventId,
+    UserId,
+    SentAt,
+}
@@ -0,0 +1,142 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+use entity::account_members::{ActiveModel, Column, Model};
+use entity::prelude::{AccountMembers, Accounts, User};
+use sea_orm::sea_query::{Expr, Query};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, Condition, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder,
+    Set, TransactionTrait,
+};
+
+pub use entity::sea_orm_active_enums::AccountRole;
+
+use crate::accounts::lock_account;
+use crate::db_conn::DB;
+use crate::util::DBError;
+
+pub struct AccountMembersImpl {
+    db: Arc<DB>,
+}
+
+impl AccountMembersImpl {
+    pub fn new(db: Arc<DB>) -> Self {
+        Self { db }
+    }
+
+    /// The user's membership of an account, `None` if the user has no access to it
+    pub async fn find_membership(
+        &self,
+        account_id: i32,
+        user_id: i32,
+    ) -> Result<Option<Model>, DBError> {
+        let db = self.db.get()?;
+        let member = AccountMembers::find()
+            .filter(Column::AccountId.eq(account_id))
+            .filter(Column::UserId.eq(user_id))
+            .one(db)
+            .await?;
+        Ok(member)
+    }
+
+    /// Members of an account in the order they joined
+    pub async fn list_members(&self, account_id: i32) -> Result<Vec<Model>, DBError> {
+        let db = self.db.get()?;
+        let members = AccountMembers::find()
+            .filter(Column::AccountId.eq(account_id))
+            .order_by_asc(Column::CreatedAt)
+            .order_by_asc(Column::Id)
+            .all(db)
+            .await?;
+        Ok(members)
+    }
+
+    pub async fn add_member(
+        &self,
+        account_id: i32,
+        user_id: i32,
+        role: AccountRole,
+    ) -> Result<Model, DBError> {
+        let db = self.db.get()?;
+        let txn = db.begin().await?;
+
+        lock_account(&txn, account_id).await?;
+        let existing = AccountMembers::find()
+            .filter(Column::AccountId.eq(account_id))
+            .filter(Column::UserId.eq(user_id))
+            .one(&txn)
+            .await?;
+        if existing.is_some() {
+            return Err(DBError::Conflict(
+                "This user is already a member of the account".to_string(),
+            ));
+        }
+
+        let member = ActiveModel {
+            account_id: Set(account_id),
+            user_id: Set(user_id),
+            role: Set(role),
+            created_at: Set(Utc::now().fixed_offset()),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+
+        txn.commit().await?;
+        Ok(member)
+    }
+
+    /// Removes a member from an account. The last owner cannot be removed, and the
+    /// account stops being the removed user's default receiving account.
+    pub async fn remove_member(&self, account_id: i32, user_id: i32) -> Result<(), DBError> {
+        let db = self.db.get()?;
+        let txn = db.begin().await?;
+
+        // The account row lock serializes concurrent removals of the last owners.
+        lock_account(&txn, account_id).await?;
+        let member = AccountMembers::find()
+            .filter(Column::AccountId.eq(account_id))
+            .filter(Column::UserId.eq(user_id))
+            .one(&txn)
+            .await?
+            .ok_or(DBError::NotFound("Account member"))?;
+
+        if member.role == AccountRole::Owner {
+            let owners = AccountMembers::find()
+                .filter(Column::AccountId.eq(account_id))
+                .filter(Column::Role.eq(AccountRole::Owner))
+                .count(&txn)
+                .await?;
+            if owners <= 1 {
+                return Err(DBError::Conflict(
+                    "An account must keep at least one owner".to_string(),
+                ));
+            }
+        }
+
+        AccountMembers::delete_by_id(member.id).exec(&txn).await?;
+        // The removed member loses the account's pockets too, so a default pointing at
+        // one of them is cleared as well.
+        let pockets = Query::select()
+            .column(entity::accounts::Column::Id)
+            .from(Accounts)
+            .and_where(entity::accounts::Column::ParentAccountId.eq(account_id))
+            .to_owned();
+        User::update_many()
+            .col_expr(
+                entity::user::Column::DefaultAccountId,
+                Expr::value(Option::<i32>::None),
+            )
+            .filter(entity::user::Column::Id.eq(user_id))
+            .filter(
+                Condition::any()
+                    .add(entity::user::Column::DefaultAccountId.eq(account_id))
+                    .add(entity::user::Column::DefaultAccountId.in_subquery(pockets)),
+            )
+            .exec(&txn)
+            .await?;
+
+        txn.commit().await?;
+        Ok(())
+    }
+}
@@ -1,15 +1,44 @@
 use crate::db_conn::DB;
-use crate::util::DBError;
+use crate::outbox::{account_data, record_event, OutboxEventType};
+use crate::pagination::{Keyset, Page, PageRequest};
+use crate::util::{to_decimal, DBError};
+use crate::webhooks::{enqueue_event, WebhookEventType};
+use chrono::Utc;
+use entity::account_members;
 use entity::accounts::{ActiveModel, Model};
-use entity::prelude::Accounts;
-use sea_orm::{ColumnTrait, QueryFilter, QueryOrder};
+use entity::prelude::{AccountMembers, Accounts};
+use entity::sea_orm_active_enums::AccountRole;
+use sea_orm::sea_query::Query;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, Condition, ConnectionTrait, DatabaseConnection, QueryFilter,
+    QueryOrder, QuerySelect, TransactionTrait,
+};
 use sea_orm::{EntityTrait, Set};
 
 use std::sync::Arc;
 
-use num_traits::cast::ToPrimitive;
 use sea_orm::entity::prelude::Decimal;
 
+/// Whose accounts a listing covers
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccountScope {
+    /// Personal and joint accounts the user is a member of
+    Member(i32),
+    /// Accounts owned by an organization
+    Organization(i32),
+}
+
+impl AccountScope {
+    fn condition(self) -> Condition {
+        match self {
+            AccountScope::Member(user_id) => member_of(user_id),
+            AccountScope::Organization(organization_id) => {
+                Condition::all().add(entity::accounts::Column::OrganizationId.eq(organization_id))
+            }
+        }
+    }
+}
+
 pub struct AccountsImpl {
     db: Arc<DB>,
 }
@@ -19,7 +48,15 @@ impl AccountsImpl {
         Self { db }
     }
 
-    pub async fn create_account(&self, balance: Option<f64>, user_id: i32) -> Result<i32, DBError> {
+    /// Creates an account for `user_id`, or on behalf of an organization when
+    /// `organization_id` is set. Personal accounts start with their creator as owner;
+    /// organization accounts are shared through organization membership instead.
+    pub async fn create_account(
+        &self,
+        balance: Option<f64>,
+        user_id: i32,
+        organization_id: Option<i32>,
+    ) -> Result<i32, DBError> {
         let db = self.db.get()?;
         let bal_to_be_updated: f64;
         if let Some(bal) = balance {
@@ -27,13 +64,38 @@ impl AccountsImpl {
         } else {
             bal_to_be_updated = 0f64;
         }
+        let now = Utc::now().fixed_offset();
+        let txn = db.begin().await?;
         let account = ActiveModel {
             user_id: Set(user_id),
-            balance: Set(Decimal::from_f64_retain(bal_to_be_updated).unwrap()),
+            balance: Set(to_decimal(bal_to_be_updated)?),
+            held_balance: Set(Decimal::ZERO),
+            organization_id: Set(organization_id),
+            created_at: Set(now),
+            updated_at: Set(now),
             ..Default::default()
-        };
-        let result = Accounts::insert(account).exec(db).await?;
-        Ok(result.last_insert_id)
+        }
+        .insert(&txn)
+        .await?;
+        record_event(
+            &txn,
+            OutboxEventType::AccountCreated,
+            account.id,
+            account_data(&account),
+        )
+        .await?;
+        if organization_id.is_none() {
+            let owner = account_members::ActiveModel {
+                account_id: Set(account.id),
+                user_id: Set(user_id),
+                role: Set(AccountRole::Owner),
+                created_at: Set(now),
+                ..Default::default()
+            };
+            AccountMembers::insert(owner).exec(&txn).await?;
+        }
+        txn.commit().await?;
+        Ok(account.id)
     }
 
     pub async fn find_account(&self, id: i32) -> Result<Option<Model>, DBError> {
@@ -42,11 +104,167 @@ impl AccountsImpl {
         Ok(acc)
     }
 
-    pub async fn list_user_accounts(&self, user_id: i32) -> Result<Vec<Model>, DBError> {
+    pub async fn find_account_by_alias(&self, alias: &str) -> Result<Option<Model>, DBError> {
+        let db = self.db.get()?;
+        let acc = Accounts::find()
+            .filter(entity::accounts::Column::Alias.eq(alias))
+            .one(db)
+            .await?;
+        Ok(acc)
+    }
+
+    pub async fn set_alias(&self, id: i32, alias: Option<String>) -> Result<(), DBError> {
+        let db = self.db.get()?;
+        let account = ActiveModel {
+            id: Set(id),
+            alias: Set(alias),
+            updated_at: Set(Utc::now().fixed_offset()),
+            ..Default::default()
+        };
+        update_account(db, account).await
+    }
+
+    /// Sets the amount above which transfers from a shared or organization account
+    /// need a second user's approval; `None` turns approvals off
+    pub async fn set_approval_threshold(
+        &self,
+        id: i32,
+        threshold: Option<f64>,
+    ) -> Result<(), DBError> {
+        let db = self.db.get()?;
+        let account = ActiveModel {
+            id: Set(id),
+            approval_threshold: Set(threshold.map(to_decimal).transpose()?),
+            updated_at: Set(Utc::now().fixed_offset()),
+            ..Default::default()
+        };
+        update_account(db, account).await
+    }
+
+    /// Opens an empty pocket under a main account. Pockets share the parent's members
+    /// and cannot have pockets of their own.
+    pub async fn create_pocket(&self, parent_id: i32, name: String) -> Result<Model, DBError> {
+        let db = self.db.get()?;
+        let parent = Accounts::find_by_id(parent_id)
+            .one(db)
+            .await?
+            .ok_or(DBError::AccountNotFound)?;
+        if parent.parent_account_id.is_some() {
+            return Err(DBError::Conflict(
+                "Pockets cannot be opened inside another pocket".to_string(),
+            ));
+        }
+
+        let now = Utc::now().fixed_offset();
+        let txn = db.begin().await?;
+        let pocket = ActiveModel {
+            user_id: Set(parent.user_id),
+            balance: Set(Decimal::ZERO),
+            held_balance: Set(Decimal::ZERO),
+            parent_account_id: Set(Some(parent.id)),
+            organization_id: Set(parent.organization_id),
+            name: Set(Some(name)),
+            frozen_at: Set(parent.frozen_at),
+            frozen_reason: Set(parent.frozen_reason.clone()),
+            created_at: Set(now),
+            updated_at: Set(now),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+        record_event(
+            &txn,
+            OutboxEventType::AccountCreated,
+            pocket.id,
+            account_data(&pocket),
+        )
+        .await?;
+        txn.commit().await?;
+        Ok(pocket)
+    }
+
+    /// Freezes a main account and its pockets. Frozen accounts keep receiving money, but
+    /// nothing can be sent from them until they are unfrozen.
+    pub async fn freeze(&self, id: i32, reason: String) -> Result<Model, DBError> {
+        self.set_frozen(id, Some(reason)).await
+    }
+
+    pub async fn unfreeze(&self, id: i32) -> Result<Model, DBError> {
+        self.set_frozen(id, None).await
+    }
+
+    /// Freezes the account and its pockets with `reason`, or unfreezes them for `None`,
+    /// and queues the `account.frozen` or `account.unfrozen` webhook event
+    async fn set_frozen(&self, id: i32, reason: Option<String>) -> Result<Model, DBError> {
+        let db = self.db.get()?;
+        let txn = db.begin().await?;
+
+        let account = lock_account(&txn, id).await?;
+        if account.parent_account_id.is_some() {
+            return Err(DBError::Conflict(
+                "Pockets are frozen and unfrozen with their main account".to_string(),
+            ));
+        }
+        match (&account.frozen_at, &reason) {
+            (Some(_), Some(_)) => {
+                return Err(DBError::Conflict(
+                    "The account is already frozen".to_string(),
+                ))
+            }
+            (None, None) => return Err(DBError::Conflict("The account is not frozen".to_string())),
+            _ => {}
+        }
+
+        let now = Utc::now().fixed_offset();
+        let pockets = Accounts::find()
+            .filter(entity::accounts::Column::ParentAccountId.eq(id))
+            .all(&txn)
+            .await?;
+        let mut frozen = Vec::with_capacity(pockets.len() + 1);
+        for account in std::iter::once(account).chain(pockets) {
+            let mut active_model: ActiveModel = account.into();
+            active_model.frozen_at = Set(reason.as_ref().map(|_| now));
+            active_model.frozen_reason = Set(reason.clone());
+            active_model.updated_at = Set(now);
+            let account = Accounts::update(active_model).exec(&txn).await?;
+            record_event(
+                &txn,
+                OutboxEventType::AccountUpdated,
+                account.id,
+                account_data(&account),
+            )
+            .await?;
+            frozen.push(account);
+        }
+
+        let account = frozen.swap_remove(0);
+        let event_type = match reason {
+            Some(_) => WebhookEventType::AccountFrozen,
+            None => WebhookEventType::AccountUnfrozen,
+        };
+        enqueue_event(&txn, event_type, &[account.id], account_data(&account)).await?;
+        txn.commit().await?;
+        Ok(account)
+    }
+
+    /// Pockets of the given main accounts, oldest first
+    pub async fn list_pockets(&self, parent_ids: &[i32]) -> Result<Vec<Model>, DBError> {
+        let db = self.db.get()?;
+        let pockets = Accounts::find()
+            .filter(entity::accounts::Column::ParentAccountId.is_in(parent_ids.to_vec()))
+            .order_by_asc(entity::accounts::Column::CreatedAt)
+            .order_by_asc(entity::accounts::Column::Id)
+            .all(db)
+            .await?;
+        Ok(pockets)
+    }
+
+    /// All accounts in the scope, including pockets
+    pub async fn list_accounts(&self, scope: AccountScope) -> Result<Vec<Model>, DBError> {
         let db = self.db.get()?;
 
         let accounts = Accounts::find()
-            .filter(entity::accounts::Column::UserId.eq(user_id))
+            .filter(scope.condition())
             .order_by_asc(entity::accounts::Column::Id)
             .all(db)
             .await?;
@@ -54,31 +272,106 @@ impl AccountsImpl {
         Ok(accounts)
     }
 
-    pub async fn add_money(&self, id: i32, amount: f64) -> Result<(), DBError> {
+    /// Lists the main accounts in the scope oldest first, one keyset page at a time.
+    /// Pockets are left out; load them with `list_pockets`.
+    pub async fn list_accounts_page(
+        &self,
+        scope: AccountScope,
+        page: PageRequest,
+    ) -> Result<Page<Model>, DBError> {
         let db = self.db.get()?;
-        let acc_from_db = self.find_account(id).await?;
-        if let Some(account) = acc_from_db {
-            let balance = account.balance.to_f64().unwrap();
-            let mut active_model: ActiveModel = account.into();
-            let added_money = balance + amount;
-            let decimal = Decimal::from_f64_retain(added_money).unwrap();
-            active_model.balance = Set(decimal);
-            Accounts::update(active_model).exec(db).await?;
+        let mut query = Accounts::find()
+            .filter(scope.condition())
+            .filter(entity::accounts::Column::ParentAccountId.is_null());
+        if let Some(after) = page.after {
+            query = query.filter(after.after(
+                entity::accounts::Column::CreatedAt,
+                entity::accounts::Column::Id,
+                false,
+            ));
         }
-        Ok(())
-    }
 
-    pub async fn deduct_money(&self, id: i32, amount: f64) -> Result<(), DBError> {
-        let db = self.db.get()?;
-        let acc_from_db = self.find_account(id).await?;
-        if let Some(account) = acc_from_db {
-            let balance = account.balance.to_f64().unwrap();
-            let mut active_model: ActiveModel = account.into();
-            let deducted_money = balance - amount;
-            let decimal = Decimal::from_f64_retain(deducted_money).unwrap();
-            active_model.balance = Set(decimal);
-            Accounts::update(active_model).exec(db).await?;
-        }
-        Ok(())
+        let accounts = query
+            .order_by_asc(entity::accounts::Column::CreatedAt)
+            .order_by_asc(entity::accounts::Column::Id)
+            .limit(page.limit + 1)
+            .all(db)
+            .await?;
+
+        Ok(Page::from_rows(accounts, page.limit, |account| {
+            Keyset::new(account.created_at, account.id)
+        }))
     }
 }
+
+/// Matches accounts that have the user as a member, and the pockets of those accounts
+fn member_of(user_id: i32) -> Condition {
+    let memberships = Query::select()
+        .column(account_members::Column::AccountId)
+        .from(AccountMembers)
+        .and_where(account_members::Column::UserId.eq(user_id))
+        .to_owned();
+    Condition::any()
+        .add(entity::accounts::Column::Id.in_subquery(memberships.clone()))
+        .add(entity::accounts::Column::ParentAccountId.in_subquery(memberships))
+}
+
+/// The account that holds the memberships: the parent of a pocket, or the account itself
+pub fn main_account_id(account: &Model) -> i32 {
+    account.parent_account_id.unwrap_or(account.id)
+}
+
+/// Balance that can still be spent: the balance minus funds held by open disputes
+pub fn available_balance(account: &Model) -> Decimal {
+    account.balance - account.held_balance
+}
+
+/// Loads an account with `SELECT ... FOR UPDATE` so that concurrent balance changes
+/// inside other database transactions wait for this one
+pub(crate) async fn lock_account<C: ConnectionTrait>(conn: &C, id: i32) -> Result<Model, DBError> {
+    Accounts::find_by_id(id)
+        .lock_exclusive()
+        .one(conn)
+        .await?
+        .ok_or(DBError::AccountNotFound)
+}
+
+/// Adds the given deltas to a locked account's balance and held balance and records
+/// the new balances in the outbox
+pub(crate) async fn adjust_balances<C: ConnectionTrait>(
+    conn: &C,
+    account: Model,
+    balance_delta: Decimal,
+    held_delta: Decimal,
+) -> Result<Model, DBError> {
+    let balance = account.balance + balance_delta;
+    let held_balance = account.held_balance + held_delta;
+    let mut active_model: ActiveModel = account.into();
+    active_model.balance = Set(balance);
+    active_model.held_balance = Set(held_balance);
+    active_model.updated_at = Set(Utc::now().fixed_offset());
+    let account = Accounts::update(active_model).exec(conn).await?;
+    record_event(
+        conn,
+        OutboxEventType::AccountBalanceChanged,
+        account.id,
+        account_data(&account),
+    )
+    .await?;
+    Ok(account)
+}
+
+/// Applies a settings change to an account and records it in the outbox
+async fn update_account(db: &DatabaseConnection, account: ActiveModel) -> Result<(), DBError> {
+    let txn = db.begin().await?;
+    let account = Accounts::update(account).exec(&txn).await?;
+    record_event(
+        &txn,
+        OutboxEventType::AccountUpdated,
+        account.id,
+        account_data(&account),
+    )
+    .await?;
+    txn.commit().await?;
+    Ok(())
+}
@@ -0,0 +1,90 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+use entity::accounts;
+use entity::balance_adjustments::{ActiveModel, Column, Model};
+use entity::prelude::BalanceAdjustments;
+use sea_orm::prelude::Decimal;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, QueryOrder, QuerySelect, Set,
+    TransactionTrait,
+};
+
+pub use entity::sea_orm_active_enums::AdjustmentReason;
+
+use crate::accounts::{adjust_balances, available_balance, lock_account};
+use crate::db_conn::DB;
+use crate::pagination::{Keyset, Page, PageRequest};
+use crate::util::{to_decimal, DBError};
+
+pub struct BalanceAdjustmentImpl {
+    db: Arc<DB>,
+}
+
+impl BalanceAdjustmentImpl {
+    pub fn new(db: Arc<DB>) -> Self {
+        Self { db }
+    }
+
+    /// Credits a positive `amount` to an account or debits a negative one, outside of any
+    /// transfer, and records who did it and why. A debit may not take the available
+    /// balance below zero. Frozen accounts can be adjusted.
+    pub async fn adjust(
+        &self,
+        account_id: i32,
+        adjusted_by: i32,
+        amount: f64,
+        reason: AdjustmentReason,
+        note: Option<String>,
+    ) -> Result<(Model, accounts::Model), DBError> {
+        let db = self.db.get()?;
+        let amount = to_decimal(amount)?;
+        let txn = db.begin().await?;
+
+        let account = lock_account(&txn, account_id).await?;
+        if available_balance(&account) + amount < Decimal::ZERO {
+            return Err(DBError::NotEnoughBalance);
+        }
+        let account = adjust_balances(&txn, account, amount, Decimal::ZERO).await?;
+
+        let adjustment = ActiveModel {
+            account_id: Set(account_id),
+            adjusted_by: Set(adjusted_by),
+            amount: Set(amount),
+            balance_after: Set(account.balance),
+            reason: Set(reason),
+            note: Set(note),
+            created_at: Set(Utc::now().fixed_offset()),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+
+        txn.commit().await?;
+        Ok((adjustment, account))
+    }
+
+    /// Adjustments made to an account, newest first
+    pub async fn list_adjustments(
+        &self,
+        account_id: i32,
+        page: PageRequest,
+    ) -> Result<Page<Model>, DBError> {
+        let db = self.db.get()?;
+        let mut query = BalanceAdjustments::find().filter(Column::AccountId.eq(account_id));
+        if let Some(after) = page.after {
+            query = query.filter(after.after(Column::CreatedAt, Column::Id, true));
+        }
+
+        let adjustments = query
+            .order_by_desc(Column::CreatedAt)
+            .order_by_desc(Column::Id)
+            .limit(page.limit + 1)
+            .all(db)
+            .await?;
+
+        Ok(Page::from_rows(adjustments, page.limit, |adjustment| {
+            Keyset::new(adjustment.created_at, adjustment.id)
+        }))
+    }
+}
@@ -0,0 +1,155 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+use entity::api_keys::{ActiveModel, Column, Model};
+use entity::prelude::ApiKeys;
+use rand::distributions::{Alphanumeric, DistString};
+use sea_orm::prelude::{DateTimeWithTimeZone, Expr};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, Condition, EntityTrait, QueryFilter, QueryOrder, Set,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::db_conn::DB;
+use crate::util::{secret_hash, DBError};
+
+/// Start of every API key, which tells keys apart from JWTs
+pub const KEY_PREFIX: &str = "sk_";
+/// Length of the random part of a key
+const KEY_LENGTH: usize = 40;
+/// How much of a key is kept in plain text, so that users can recognise it
+const DISPLAY_PREFIX_LENGTH: usize = 11;
+
+/// What an API key may be used for. Each area has a `read` scope for `GET` requests and
+/// a `write` scope for everything else.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ApiKeyScope {
+    /// Accounts, their members and pockets, and organizations
+    #[serde(rename = "accounts:read")]
+    AccountsRead,
+    #[serde(rename = "accounts:write")]
+    AccountsWrite,
+    /// Transfers, approvals and disputes
+    #[serde(rename = "transactions:read")]
+    TransactionsRead,
+    #[serde(rename = "transactions:write")]
+    TransactionsWrite,
+    /// Payment intents and links, invoices, subscriptions and split payments
+    #[serde(rename = "payments:read")]
+    PaymentsRead,
+    #[serde(rename = "payments:write")]
+    PaymentsWrite,
+    /// Webhook endpoints and their deliveries
+    #[serde(rename = "webhooks:read")]
+    WebhooksRead,
+    #[serde(rename = "webhooks:write")]
+    WebhooksWrite,
+}
+
+pub struct ApiKeyImpl {
+    db: Arc<DB>,
+}
+
+impl ApiKeyImpl {
+    pub fn new(db: Arc<DB>) -> Self {
+        Self { db }
+    }
+
+    /// Creates a key acting as `user_id`. Returns the key itself along with its record;
+    /// only its hash is stored, so it cannot be shown again.
+    pub async fn create_key(
+        &self,
+        user_id: i32,
+        name: String,
+        scopes: &[ApiKeyScope],
+        expires_at: Option<DateTimeWithTimeZone>,
+    ) -> Result<(Model, String), DBError> {
+        let db = self.db.get()?;
+        let key = format!(
+            "{KEY_PREFIX}{}",
+            Alphanumeric.sample_string(&mut rand::thread_rng(), KEY_LENGTH)
+        );
+        let api_key = ActiveModel {
+            user_id: Set(user_id),
+            name: Set(name),
+            prefix: Set(key[..DISPLAY_PREFIX_LENGTH].to_string()),
+            key_hash: Set(secret_hash(&key)),
+            scopes: Set(json!(scopes)),
+            expires_at: Set(expires_at),
+            created_at: Set(Utc::now().fixed_offset()),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+        Ok((api_key, key))
+    }
+
+    pub async fn find_key(&self, id: i32) -> Result<Option<Model>, DBError> {
+        let db = self.db.get()?;
+        let api_key = ApiKeys::find_by_id(id).one(db).await?;
+        Ok(api_key)
+    }
+
+    /// Keys of the user that have not been revoked, including expired ones, newest first
+    pub async fn list_keys(&self, user_id: i32) -> Result<Vec<Model>, DBError> {
+        let db = self.db.get()?;
+        let api_keys = ApiKeys::find()
+            .filter(Column::UserId.eq(user_id))
+            .filter(Column::RevokedAt.is_null())
+            .order_by_desc(Column::CreatedAt)
+            .order_by_desc(Column::Id)
+            .all(db)
+            .await?;
+        Ok(api_keys)
+    }
+
+    pub async fn revoke_key(&self, id: i32) -> Result<Model, DBError> {
+        let db = self.db.get()?;
+        let api_key = ApiKeys::find_by_id(id)
+            .one(db)
+            .await?
+            .ok_or(DBError::NotFound("API key"))?;
+        if api_key.revoked_at.is_some() {
+            return Err(DBError::Conflict(
+                "This API key has already been revoked".to_string(),
+            ));
+        }
+
+        let mut active_model: ActiveModel = api_key.into();
+        active_model.revoked_at = Set(Some(Utc::now().fixed_offset()));
+        let api_key = active_model.update(db).await?;
+        Ok(api_key)
+    }
+
+    /// The key's record if it is neither revoked nor expired, noting that it was used
+    pub async fn authenticate(&self, key: &str) -> Result<Option<Model>, DBError> {
+        let db = self.db.get()?;
+        let now = Utc::now().fixed_offset();
+        let Some(api_key) = ApiKeys::find()
+            .filter(Column::KeyHash.eq(secret_hash(key)))
+            .filter(Column::RevokedAt.is_null())
+            .filter(
+                Condition::any()
+                    .add(Column::ExpiresAt.is_null())
+                    .add(Column::ExpiresAt.gt(now)),
+            )
+            .one(db)
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        ApiKeys::update_many()
+            .col_expr(Column::LastUsedAt, Expr::value(now))
+            .filter(Column::Id.eq(api_key.id))
+            .exec(db)
+            .await?;
+        Ok(Some(api_key))
+    }
+}
+
+/// The scopes granted to a key
+pub fn scopes(api_key: &Model) -> Vec<ApiKeyScope> {
+    serde_json::from_value(api_key.scopes.clone()).unwrap_or_default()
+}
@@ -0,0 +1,266 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+use entity::prelude::{AccountMembers, Accounts, TransferApprovalEvents, TransferApprovals};
+use entity::transfer_approvals::{ActiveModel, Column, Model};
+use entity::{account_members, transactions, transfer_approval_events};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, EntityTrait, PaginatorTrait, QueryFilter,
+    QueryOrder, QuerySelect, Set, TransactionTrait,
+};
+
+pub use entity::sea_orm_active_enums::{ApprovalAction, ApprovalStatus};
+
+use crate::accounts::main_account_id;
+use crate::db_conn::DB;
+use crate::invoices::settle_matching_invoice;
+use crate::pagination::{Keyset, Page, PageRequest};
+use crate::transactions::execute_transfer;
+use crate::util::{to_decimal, DBError};
+
+pub struct TransferApprovalImpl {
+    db: Arc<DB>,
+}
+
+impl TransferApprovalImpl {
+    pub fn new(db: Arc<DB>) -> Self {
+        Self { db }
+    }
+
+    /// Whether a transfer of `amount` from the account has to be approved first.
+    /// Only organization accounts and accounts with several members are subject to
+    /// approvals; pockets follow the threshold of their main account. Subscription
+    /// renewals are not checked: the amount was checked when subscribing.
+    pub async fn requires_approval(&self, account_id: i32, amount: f64) -> Result<bool, DBError> {
+        let db = self.db.get()?;
+        let account = Accounts::find_by_id(account_id)
+            .one(db)
+            .await?
+            .ok_or(DBError::AccountNotFound)?;
+        let main_account = if account.parent_account_id.is_some() {
+            Accounts::find_by_id(main_account_id(&account))
+                .one(db)
+                .await?
+                .ok_or(DBError::AccountNotFound)?
+        } else {
+            account
+        };
+
+        let Some(threshold) = main_account.approval_threshold else {
+            return Ok(false);
+        };
+        if to_decimal(amount)? <= threshold {
+            return Ok(false);
+        }
+        if main_account.organization_id.is_some() {
+            return Ok(true);
+        }
+        let members = AccountMembers::find()
+            .filter(account_members::Column::AccountId.eq(main_account.id))
+            .count(db)
+            .await?;
+        Ok(members > 1)
+    }
+
+    /// Records a transfer that waits for a second user's approval
+    pub async fn request(
+        &self,
+        from: i32,
+        to: i32,
+        amount: f64,
+        requested_by: i32,
+    ) -> Result<Model, DBError> {
+        let db = self.db.get()?;
+        let txn = db.begin().await?;
+
+        let approval = ActiveModel {
+            from_account_id: Set(from),
+            to_account_id: Set(to),
+            amount: Set(to_decimal(amount)?),
+            status: Set(ApprovalStatus::Pending),
+            requested_by: Set(requested_by),
+            created_at: Set(Utc::now().fixed_offset()),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+        record_event(
+            &txn,
+            approval.id,
+            requested_by,
+            ApprovalAction::Requested,
+            None,
+        )
+        .await?;
+
+        txn.commit().await?;
+        Ok(approval)
+    }
+
+    pub async fn find_approval(&self, id: i32) -> Result<Option<Model>, DBError> {
+        let db = self.db.get()?;
+        let approval = TransferApprovals::find_by_id(id).one(db).await?;
+        Ok(approval)
+    }
+
+    /// Approvals of transfers from the given accounts, newest first
+    pub async fn list_approvals(
+        &self,
+        account_ids: &[i32],
+        status: Option<ApprovalStatus>,
+        page: PageRequest,
+    ) -> Result<Page<Model>, DBError> {
+        let db = self.db.get()?;
+        let mut query =
+            TransferApprovals::find().filter(Column::FromAccountId.is_in(account_ids.to_vec()));
+        if let Some(status) = status {
+            query = query.filter(Column::Status.eq(status));
+        }
+        if let Some(after) = page.after {
+            query = query.filter(after.after(Column::CreatedAt, Column::Id, true));
+        }
+
+        let approvals = query
+            .order_by_desc(Column::CreatedAt)
+            .order_by_desc(Column::Id)
+            .limit(page.limit + 1)
+            .all(db)
+            .await?;
+
+        Ok(Page::from_rows(approvals, page.limit, |approval| {
+            Keyset::new(approval.created_at, approval.id)
+        }))
+    }
+
+    /// Audit trail of an approval in the order the steps happened
+    pub async fn list_events(
+        &self,
+        approval_id: i32,
+    ) -> Result<Vec<transfer_approval_events::Model>, DBError> {
+        let db = self.db.get()?;
+        let events = TransferApprovalEvents::find()
+            .filter(transfer_approval_events::Column::ApprovalId.eq(approval_id))
+            .order_by_asc(transfer_approval_events::Column::CreatedAt)
+            .order_by_asc(transfer_approval_events::Column::Id)
+            .all(db)
+            .await?;
+        Ok(events)
+    }
+
+    /// Approves a pending transfer and executes it in the same database transaction,
+    /// paying an invoice it matches like any other transfer.
+    /// The requester can never approve their own transfer. If the balance no longer
+    /// covers the amount, nothing changes and the approval stays pending.
+    pub async fn approve(
+        &self,
+        id: i32,
+        approver: i32,
+        note: Option<String>,
+    ) -> Result<(Model, transactions::Model), DBError> {
+        let db = self.db.get()?;
+        let txn = db.begin().await?;
+
+        let approval = lock_pending(&txn, id).await?;
+        if approval.requested_by == approver {
+            return Err(DBError::Conflict(
+                "A transfer cannot be approved by the user who requested it".to_string(),
+            ));
+        }
+
+        let transaction = execute_transfer(
+            &txn,
+            approval.from_account_id,
+            approval.to_account_id,
+            approval.amount,
+            false,
+        )
+        .await?;
+        settle_matching_invoice(&txn, &transaction).await?;
+
+        let mut active_model: ActiveModel = approval.into();
+        active_model.status = Set(ApprovalStatus::Approved);
+        active_model.decided_by = Set(Some(approver));
+        active_model.transaction_id = Set(Some(transaction.id));
+        active_model.decided_at = Set(Some(Utc::now().fixed_offset()));
+        let approval = active_model.update(&txn).await?;
+        record_event(&txn, approval.id, approver, ApprovalAction::Approved, note).await?;
+
+        txn.commit().await?;
+        Ok((approval, transaction))
+    }
+
+    /// Closes a pending transfer without moving money. `Rejected` is a decision by
+    /// an approver, `Canceled` a withdrawal by the requester.
+    pub async fn close(
+        &self,
+        id: i32,
+        actor: i32,
+        action: ApprovalAction,
+        note: Option<String>,
+    ) -> Result<Model, DBError> {
+        let status = match action {
+            ApprovalAction::Rejected => ApprovalStatus::Rejected,
+            ApprovalAction::Canceled => ApprovalStatus::Canceled,
+            _ => {
+                return Err(DBError::Conflict(
+                    "Approvals are only closed by rejecting or canceling them".to_string(),
+                ))
+            }
+        };
+
+        let db = self.db.get()?;
+        let txn = db.begin().await?;
+
+        let approval = lock_pending(&txn, id).await?;
+        if action == ApprovalAction::Rejected && approval.requested_by == actor {
+            return Err(DBError::Conflict(
+                "A transfer cannot be rejected by the user who requested it; cancel it instead"
+                    .to_string(),
+            ));
+        }
+
+        let mut active_model: ActiveModel = approval.into();
+        active_model.status = Set(status);
+        active_model.decided_by = Set(Some(actor));
+        active_model.decided_at = Set(Some(Utc::now().fixed_offset()));
+        let approval = active_model.update(&txn).await?;
+        record_event(&txn, approval.id, actor, action, note).await?;
+
+        txn.commit().await?;
+        Ok(approval)
+    }
+}
+
+async fn lock_pending<C: ConnectionTrait>(conn: &C, id: i32) -> Result<Model, DBError> {
+    let approval = TransferApprovals::find_by_id(id)
+        .lock_exclusive()
+        .one(conn)
+        .await?
+        .ok_or(DBError::NotFound("Approval"))?;
+    if approval.status != ApprovalStatus::Pending {
+        return Err(DBError::Conflict(
+            "This transfer has already been decided".to_string(),
+        ));
+    }
+    Ok(approval)
+}
+
+async fn record_event<C: ConnectionTrait>(
+    conn: &C,
+    approval_id: i32,
+    actor_id: i32,
+    action: ApprovalAction,
+    note: Option<String>,
+) -> Result<(), DBError> {
+    transfer_approval_events::ActiveModel {
+        approval_id: Set(approval_id),
+        actor_id: Set(actor_id),
+        action: Set(action),
+        note: Set(note),
+        created_at: Set(Utc::now().fixed_offset()),
+        ..Default::default()
+    }
+    .insert(conn)
+    .await?;
+    Ok(())
+}
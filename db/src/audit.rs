@@ -0,0 +1,400 @@
+use std::sync::Arc;
+
+use chrono::{SecondsFormat, SubsecRound, Utc};
+use entity::audit_log::{ActiveModel, Column, Model};
+use entity::prelude::AuditLog;
+use sea_orm::prelude::DateTimeWithTimeZone;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, QueryOrder,
+    QuerySelect, Set, TransactionTrait,
+};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::db_conn::DB;
+use crate::pagination::{Keyset, Page, PageRequest};
+use crate::util::DBError;
+
+/// `prev_hash` of the first entry of the log
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Number of entries read at a time while verifying the chain
+const VERIFY_BATCH: u64 = 1000;
+
+/// An action to add to the audit log
+#[derive(Clone, Debug, Default)]
+pub struct AuditEntry {
+    /// The authenticated user who performed the action, if any
+    pub actor_id: Option<i32>,
+    pub action: String,
+    pub target: String,
+    /// HTTP status the action completed with
+    pub status: u16,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+    pub ip: Option<String>,
+    pub request_id: Option<String>,
+}
+
+/// Filters for listing the audit log. Every field that is set narrows the result;
+/// `since` is inclusive and `until` exclusive.
+#[derive(Clone, Debug, Default)]
+pub struct AuditFilter {
+    pub actor_id: Option<i32>,
+    pub action: Option<String>,
+    pub target: Option<String>,
+    pub request_id: Option<String>,
+    pub since: Option<DateTimeWithTimeZone>,
+    pub until: Option<DateTimeWithTimeZone>,
+}
+
+/// Result of checking the audit log's hash chain
+#[derive(Debug)]
+pub struct AuditVerification {
+    /// Number of entries whose link was intact
+    pub checked: u64,
+    /// Hash of the last intact entry. Recording it elsewhere lets a later check notice
+    /// entries removed from the end of the log, which the chain itself cannot show.
+    pub head: Option<String>,
+    /// The first entry that does not match the chain, if any
+    pub broken: Option<BrokenLink>,
+}
+
+/// An entry at which the hash chain breaks
+#[derive(Debug)]
+pub struct BrokenLink {
+    pub id: i32,
+    pub reason: &'static str,
+}
+
+pub struct AuditImpl {
+    db: Arc<DB>,
+}
+
+impl AuditImpl {
+    pub fn new(db: Arc<DB>) -> Self {
+        Self { db }
+    }
+
+    /// Appends an entry, chained to the last entry of the log
+    pub async fn append(&self, entry: AuditEntry) -> Result<Model, DBError> {
+        let db = self.db.get()?;
+        let txn = db.begin().await?;
+
+        // Appends take turns, so each one chains to the entry committed before it.
+        // The lock still lets the log be read meanwhile.
+        txn.execute_unprepared("LOCK TABLE audit_log IN SHARE ROW EXCLUSIVE MODE")
+            .await?;
+        let prev_hash = AuditLog::find()
+            .select_only()
+            .column(Column::Hash)
+            .order_by_desc(Column::Id)
+            .into_tuple::<String>()
+            .one(&txn)
+            .await?
+            .unwrap_or_else(|| GENESIS_HASH.to_string());
+
+        // Postgres keeps microseconds, and the hash must match the stored value
+        let created_at = Utc::now().trunc_subsecs(6).fixed_offset();
+        let model = chain_entry(entry, prev_hash, created_at);
+
+        let entry = ActiveModel {
+            actor_id: Set(model.actor_id),
+            action: Set(model.action),
+            target: Set(model.target),
+            status: Set(model.status),
+            before: Set(model.before),
+            after: Set(model.after),
+            ip: Set(model.ip),
+            request_id: Set(model.request_id),
+            created_at: Set(model.created_at),
+            prev_hash: Set(model.prev_hash),
+            hash: Set(model.hash),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+
+        txn.commit().await?;
+        Ok(entry)
+    }
+
+    pub async fn find_by_hash(&self, hash: &str) -> Result<Option<Model>, DBError> {
+        let db = self.db.get()?;
+        Ok(AuditLog::find()
+            .filter(Column::Hash.eq(hash))
+            .one(db)
+            .await?)
+    }
+
+    /// Entries matching `filter`, newest first
+    pub async fn list(
+        &self,
+        filter: AuditFilter,
+        page: PageRequest,
+    ) -> Result<Page<Model>, DBError> {
+        let db = self.db.get()?;
+        let mut query = AuditLog::find();
+        if let Some(actor_id) = filter.actor_id {
+            query = query.filter(Column::ActorId.eq(actor_id));
+        }
+        if let Some(action) = filter.action {
+            query = query.filter(Column::Action.eq(action));
+        }
+        if let Some(target) = filter.target {
+            query = query.filter(Column::Target.eq(target));
+        }
+        if let Some(request_id) = filter.request_id {
+            query = query.filter(Column::RequestId.eq(request_id));
+        }
+        if let Some(since) = filter.since {
+            query = query.filter(Column::CreatedAt.gte(since));
+        }
+        if let Some(until) = filter.until {
+            query = query.filter(Column::CreatedAt.lt(until));
+        }
+        if let Some(after) = page.after {
+            query = query.filter(after.after(Column::CreatedAt, Column::Id, true));
+        }
+
+        let entries = query
+            .order_by_desc(Column::CreatedAt)
+            .order_by_desc(Column::Id)
+            .limit(page.limit + 1)
+            .all(db)
+            .await?;
+
+        Ok(Page::from_rows(entries, page.limit, |entry| {
+            Keyset::new(entry.created_at, entry.id)
+        }))
+    }
+
+    /// Walks the log from the first entry, checking that every entry links to the one
+    /// before it and that its hash matches its contents. Stops at the first broken link.
+    pub async fn verify(&self) -> Result<AuditVerification, DBError> {
+        let db = self.db.get()?;
+        let mut prev_hash = GENESIS_HASH.to_string();
+        let mut last_id = 0;
+        let mut checked = 0;
+        let head = |checked: u64, hash: String| (checked > 0).then_some(hash);
+
+        loop {
+            let entries = AuditLog::find()
+                .filter(Column::Id.gt(last_id))
+                .order_by_asc(Column::Id)
+                .limit(VERIFY_BATCH)
+                .all(db)
+                .await?;
+            if entries.is_empty() {
+                break;
+            }
+
+            for entry in entries {
+                if let Some(reason) = broken_link(&prev_hash, &entry) {
+                    return Ok(AuditVerification {
+                        checked,
+                        head: head(checked, prev_hash),
+                        broken: Some(BrokenLink {
+                            id: entry.id,
+                            reason,
+                        }),
+                    });
+                }
+                checked += 1;
+                last_id = entry.id;
+                prev_hash = entry.hash;
+            }
+        }
+
+        Ok(AuditVerification {
+            checked,
+            head: head(checked, prev_hash),
+            broken: None,
+        })
+    }
+}
+
+/// The entry to store for an action, chained to the entry with hash `prev_hash`
+fn chain_entry(entry: AuditEntry, prev_hash: String, created_at: DateTimeWithTimeZone) -> Model {
+    let mut model = Model {
+        id: 0,
+        actor_id: entry.actor_id,
+        action: entry.action,
+        target: entry.target,
+        status: entry.status as i16,
+        before: entry.before,
+        after: entry.after,
+        ip: entry.ip,
+        request_id: entry.request_id,
+        created_at,
+        prev_hash,
+        hash: String::new(),
+    };
+    model.hash = entry_hash(&model);
+    model
+}
+
+/// Why an entry breaks the chain after the entry with hash `prev_hash`, if it does
+fn broken_link(prev_hash: &str, entry: &Model) -> Option<&'static str> {
+    if entry.prev_hash != prev_hash {
+        Some("The entry does not link to the entry before it")
+    } else if entry_hash(entry) != entry.hash {
+        Some("The entry's contents do not match its hash")
+    } else {
+        None
+    }
+}
+
+/// SHA-256, in hex, of an entry's `prev_hash` and contents written as canonical JSON.
+/// The id is left out: the chain itself fixes the order of the entries.
+fn entry_hash(entry: &Model) -> String {
+    let contents = json!({
+        "prev_hash": entry.prev_hash,
+        "actor_id": entry.actor_id,
+        "action": entry.action,
+        "target": entry.target,
+        "status": entry.status,
+        "before": entry.before,
+        "after": entry.after,
+        "ip": entry.ip,
+        "request_id": entry.request_id,
+        "created_at": entry
+            .created_at
+            .to_utc()
+            .to_rfc3339_opts(SecondsFormat::Micros, true),
+    });
+    let mut canonical = String::new();
+    write_canonical(&contents, &mut canonical);
+    hex::encode(Sha256::digest(canonical.as_bytes()))
+}
+
+/// Writes JSON without whitespace and with object keys sorted, so a value hashes the
+/// same after a round trip through a `jsonb` column
+fn write_canonical(value: &Value, out: &mut String) {
+    match value {
+        Value::Object(map) => {
+            let mut keys: Vec<_> = map.keys().collect();
+            keys.sort();
+            out.push('{');
+            for (i, key) in keys.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&Value::from(key.as_str()).to_string());
+                out.push(':');
+                write_canonical(&map[key], out);
+            }
+            out.push('}');
+        }
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(item, out);
+            }
+            out.push(']');
+        }
+        scalar => out.push_str(&scalar.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    /// A log of `len` entries, each chained to the one before it
+    fn chain(len: i32) -> Vec<Model> {
+        let mut prev_hash = GENESIS_HASH.to_string();
+        (1..=len)
+            .map(|id| {
+                let entry = AuditEntry {
+                    actor_id: Some(2),
+                    action: "account.freeze".to_string(),
+                    target: format!("account:{id}"),
+                    status: 200,
+                    before: Some(json!({ "frozen_at": null, "balance": "10.00" })),
+                    after: Some(json!({ "frozen_at": "2026-10-19T08:30:00Z", "balance": "10.00" })),
+                    ip: Some("203.0.113.9".to_string()),
+                    request_id: Some(format!("req-{id}")),
+                };
+                let created_at = Utc
+                    .timestamp_micros(1_792_400_000_000_000 + i64::from(id))
+                    .unwrap()
+                    .fixed_offset();
+                let mut model = chain_entry(entry, prev_hash.clone(), created_at);
+                model.id = id;
+                prev_hash = model.hash.clone();
+                model
+            })
+            .collect()
+    }
+
+    /// The id and reason of the first entry that breaks the chain
+    fn first_break(entries: &[Model]) -> Option<(i32, &'static str)> {
+        let mut prev_hash = GENESIS_HASH;
+        for entry in entries {
+            if let Some(reason) = broken_link(prev_hash, entry) {
+                return Some((entry.id, reason));
+            }
+            prev_hash = &entry.hash;
+        }
+        None
+    }
+
+    #[test]
+    fn an_untouched_log_verifies() {
+        let entries = chain(3);
+        assert_eq!(entries[0].prev_hash, GENESIS_HASH);
+        assert_eq!(first_break(&entries), None);
+    }
+
+    #[test]
+    fn an_edited_entry_breaks_the_chain() {
+        let mut entries = chain(3);
+        entries[1].after = Some(json!({ "frozen_at": null, "balance": "10.00" }));
+        assert_eq!(
+            first_break(&entries),
+            Some((2, "The entry's contents do not match its hash"))
+        );
+
+        let mut entries = chain(3);
+        entries[1].actor_id = Some(3);
+        assert_eq!(
+            first_break(&entries),
+            Some((2, "The entry's contents do not match its hash"))
+        );
+    }
+
+    #[test]
+    fn a_rehashed_entry_breaks_the_link_after_it() {
+        let mut entries = chain(3);
+        entries[1].status = 403;
+        entries[1].hash = entry_hash(&entries[1]);
+        assert_eq!(
+            first_break(&entries),
+            Some((3, "The entry does not link to the entry before it"))
+        );
+    }
+
+    #[test]
+    fn a_removed_entry_breaks_the_chain() {
+        let mut entries = chain(3);
+        entries.remove(1);
+        assert_eq!(
+            first_break(&entries),
+            Some((3, "The entry does not link to the entry before it"))
+        );
+    }
+
+    #[test]
+    fn the_hash_does_not_depend_on_the_time_zone_read_back() {
+        let mut entries = chain(1);
+        let offset = chrono::FixedOffset::east_opt(2 * 3600).unwrap();
+        entries[0].created_at = entries[0].created_at.with_timezone(&offset);
+        assert_eq!(first_break(&entries), None);
+    }
+}
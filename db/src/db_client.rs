@@ -1,26 +1,76 @@
 use std::sync::Arc;
 
 use crate::{
-    accounts::AccountsImpl, db_conn::DB, transactions::TransactionImpl, user::UserImpl,
-    util::DBError,
+    account_members::AccountMembersImpl, accounts::AccountsImpl,
+    adjustments::BalanceAdjustmentImpl, api_keys::ApiKeyImpl, approvals::TransferApprovalImpl,
+    audit::AuditImpl, db_conn::DB, disputes::DisputeImpl, invoices::InvoiceImpl,
+    organizations::OrganizationImpl, outbox::OutboxImpl, payment_links::PaymentLinkImpl,
+    payments::PaymentIntentImpl, refresh_tokens::RefreshTokenImpl,
+    split_payments::SplitPaymentImpl, subscriptions::SubscriptionImpl,
+    transactions::TransactionImpl, user::UserImpl, util::DBError, webhooks::WebhookImpl,
 };
 
 pub struct DbClient {
     pub user: UserImpl,
+    pub refresh_token: RefreshTokenImpl,
     pub account: AccountsImpl,
+    pub account_member: AccountMembersImpl,
+    pub adjustment: BalanceAdjustmentImpl,
+    pub api_key: ApiKeyImpl,
     pub transaction: TransactionImpl,
+    pub dispute: DisputeImpl,
+    pub approval: TransferApprovalImpl,
+    pub organization: OrganizationImpl,
+    pub payment: PaymentIntentImpl,
+    pub payment_link: PaymentLinkImpl,
+    pub invoice: InvoiceImpl,
+    pub subscription: SubscriptionImpl,
+    pub split_payment: SplitPaymentImpl,
+    pub webhook: WebhookImpl,
+    pub outbox: OutboxImpl,
+    pub audit: AuditImpl,
 }
 
 impl DbClient {
     pub async fn new() -> Result<Self, DBError> {
         let db = Arc::new(DB::new().await?);
         let user_client = UserImpl::new(db.clone());
+        let refresh_token_client = RefreshTokenImpl::new(db.clone());
         let transaction_client = TransactionImpl::new(db.clone());
         let accounts_client = AccountsImpl::new(db.clone());
+        let account_members_client = AccountMembersImpl::new(db.clone());
+        let adjustment_client = BalanceAdjustmentImpl::new(db.clone());
+        let api_key_client = ApiKeyImpl::new(db.clone());
+        let dispute_client = DisputeImpl::new(db.clone());
+        let approval_client = TransferApprovalImpl::new(db.clone());
+        let organization_client = OrganizationImpl::new(db.clone());
+        let payment_client = PaymentIntentImpl::new(db.clone());
+        let payment_link_client = PaymentLinkImpl::new(db.clone());
+        let invoice_client = InvoiceImpl::new(db.clone());
+        let subscription_client = SubscriptionImpl::new(db.clone());
+        let split_payment_client = SplitPaymentImpl::new(db.clone());
+        let webhook_client = WebhookImpl::new(db.clone());
+        let outbox_client = OutboxImpl::new(db.clone());
+        let audit_client = AuditImpl::new(db.clone());
         let db_client = DbClient {
             user: user_client,
+            refresh_token: refresh_token_client,
             account: accounts_client,
+            account_member: account_members_client,
+            adjustment: adjustment_client,
+            api_key: api_key_client,
             transaction: transaction_client,
+            dispute: dispute_client,
+            approval: approval_client,
+            organization: organization_client,
+            payment: payment_client,
+            payment_link: payment_link_client,
+            invoice: invoice_client,
+            subscription: subscription_client,
+            split_payment: split_payment_client,
+            webhook: webhook_client,
+            outbox: outbox_client,
+            audit: audit_client,
         };
         Ok(db_client)
     }
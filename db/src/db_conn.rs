@@ -28,6 +28,18 @@ impl DB {
         Ok(DB { db })
     }
 
+    /// Wraps a mock connection, for tests
+    #[cfg(test)]
+    pub(crate) fn mock(db: DatabaseConnection) -> Self {
+        DB { db }
+    }
+
+    /// The statements run against a mock connection
+    #[cfg(test)]
+    pub(crate) fn into_transaction_log(self) -> Vec<sea_orm::Transaction> {
+        self.db.into_transaction_log()
+    }
+
     pub fn get(&self) -> Result<&DatabaseConnection, DBError> {
         Ok(&self.db)
     }
@@ -0,0 +1,295 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+use entity::disputes::{ActiveModel, Column, Model};
+use entity::prelude::{DisputeNotes, Disputes, Transactions};
+use entity::{dispute_notes, transactions};
+use sea_orm::prelude::Decimal;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, QueryOrder, QuerySelect, Set,
+    TransactionTrait,
+};
+
+pub use entity::sea_orm_active_enums::DisputeStatus;
+
+use crate::accounts::{adjust_balances, lock_account};
+use crate::db_conn::DB;
+use crate::outbox::{record_event, OutboxEventType};
+use crate::pagination::{Keyset, Page, PageRequest};
+use crate::transactions::{execute_transfer, TransactionStatus};
+use crate::util::DBError;
+use crate::webhooks::{dispute_data, enqueue_event, transaction_data, WebhookEventType};
+
+/// Final decision on a dispute.
+/// `Won` upholds the dispute and reverses the transaction; `Lost` rejects it.
+#[derive(Clone, Copy, Debug)]
+pub enum DisputeOutcome {
+    Won,
+    Lost,
+}
+
+pub struct DisputeImpl {
+    db: Arc<DB>,
+}
+
+impl DisputeImpl {
+    pub fn new(db: Arc<DB>) -> Self {
+        Self { db }
+    }
+
+    /// Opens a dispute on a completed transaction and holds the disputed amount
+    /// on the receiving account until the dispute is resolved
+    pub async fn open_dispute(
+        &self,
+        transaction_id: i32,
+        opened_by: i32,
+        reason: String,
+    ) -> Result<Model, DBError> {
+        let db = self.db.get()?;
+        let txn = db.begin().await?;
+
+        let transaction = Transactions::find_by_id(transaction_id)
+            .lock_exclusive()
+            .one(&txn)
+            .await?
+            .ok_or(DBError::NotFound("Transaction"))?;
+        if transaction.status != TransactionStatus::Completed {
+            return Err(DBError::Conflict(
+                "Only completed transactions can be disputed".to_string(),
+            ));
+        }
+        let existing = Disputes::find()
+            .filter(Column::TransactionId.eq(transaction_id))
+            .one(&txn)
+            .await?;
+        if existing.is_some() {
+            return Err(DBError::Conflict(
+                "This transaction has already been disputed".to_string(),
+            ));
+        }
+
+        let held_account = lock_account(&txn, transaction.to_account_id).await?;
+        adjust_balances(&txn, held_account, Decimal::ZERO, transaction.amount).await?;
+
+        let now = Utc::now().fixed_offset();
+        let dispute = ActiveModel {
+            transaction_id: Set(transaction_id),
+            opened_by: Set(opened_by),
+            held_account_id: Set(transaction.to_account_id),
+            amount: Set(transaction.amount),
+            reason: Set(reason),
+            status: Set(DisputeStatus::Open),
+            created_at: Set(now),
+            updated_at: Set(now),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+        enqueue_event(
+            &txn,
+            WebhookEventType::DisputeOpened,
+            &[transaction.from_account_id, transaction.to_account_id],
+            dispute_data(&dispute),
+        )
+        .await?;
+
+        txn.commit().await?;
+        Ok(dispute)
+    }
+
+    pub async fn find_dispute(&self, id: i32) -> Result<Option<Model>, DBError> {
+        let db = self.db.get()?;
+        let dispute = Disputes::find_by_id(id).one(db).await?;
+        Ok(dispute)
+    }
+
+    /// Lists disputes newest first, optionally only those in one state
+    pub async fn list_disputes(
+        &self,
+        status: Option<DisputeStatus>,
+        page: PageRequest,
+    ) -> Result<Page<Model>, DBError> {
+        let db = self.db.get()?;
+        let mut query = Disputes::find();
+        if let Some(status) = status {
+            query = query.filter(Column::Status.eq(status));
+        }
+        if let Some(after) = page.after {
+            query = query.filter(after.after(Column::CreatedAt, Column::Id, true));
+        }
+
+        let disputes = query
+            .order_by_desc(Column::CreatedAt)
+            .order_by_desc(Column::Id)
+            .limit(page.limit + 1)
+            .all(db)
+            .await?;
+
+        Ok(Page::from_rows(disputes, page.limit, |dispute| {
+            Keyset::new(dispute.created_at, dispute.id)
+        }))
+    }
+
+    pub async fn add_note(
+        &self,
+        dispute_id: i32,
+        author_id: i32,
+        note: String,
+    ) -> Result<dispute_notes::Model, DBError> {
+        let db = self.db.get()?;
+        insert_note(db, dispute_id, author_id, note).await
+    }
+
+    /// Evidence notes of a dispute in the order they were added
+    pub async fn list_notes(&self, dispute_id: i32) -> Result<Vec<dispute_notes::Model>, DBError> {
+        let db = self.db.get()?;
+        let notes = DisputeNotes::find()
+            .filter(dispute_notes::Column::DisputeId.eq(dispute_id))
+            .order_by_asc(dispute_notes::Column::CreatedAt)
+            .order_by_asc(dispute_notes::Column::Id)
+            .all(db)
+            .await?;
+        Ok(notes)
+    }
+
+    /// Moves an open dispute under review
+    pub async fn start_review(&self, id: i32) -> Result<Model, DBError> {
+        let db = self.db.get()?;
+        let txn = db.begin().await?;
+
+        let dispute = lock_dispute(&txn, id).await?;
+        if dispute.status != DisputeStatus::Open {
+            return Err(DBError::Conflict(
+                "Only open disputes can be put under review".to_string(),
+            ));
+        }
+
+        let mut active_model: ActiveModel = dispute.into();
+        active_model.status = Set(DisputeStatus::UnderReview);
+        active_model.updated_at = Set(Utc::now().fixed_offset());
+        let dispute = active_model.update(&txn).await?;
+
+        txn.commit().await?;
+        Ok(dispute)
+    }
+
+    /// Resolves an active dispute. The hold is always released; a won dispute also
+    /// moves the amount back to the sender and marks the original transaction reversed.
+    /// The reversal goes through even if the receiving account has since gone below
+    /// the disputed amount. A `note` from the resolver is added in the same database
+    /// transaction.
+    pub async fn resolve(
+        &self,
+        id: i32,
+        resolved_by: i32,
+        outcome: DisputeOutcome,
+        note: Option<String>,
+    ) -> Result<Model, DBError> {
+        let db = self.db.get()?;
+        let txn = db.begin().await?;
+
+        let dispute = lock_dispute(&txn, id).await?;
+        if !matches!(
+            dispute.status,
+            DisputeStatus::Open | DisputeStatus::UnderReview
+        ) {
+            return Err(DBError::Conflict(
+                "This dispute has already been resolved".to_string(),
+            ));
+        }
+
+        let (status, reversal_transaction_id) = match outcome {
+            DisputeOutcome::Won => {
+                let original = Transactions::find_by_id(dispute.transaction_id)
+                    .lock_exclusive()
+                    .one(&txn)
+                    .await?
+                    .ok_or(DBError::NotFound("Transaction"))?;
+                let reversal = execute_transfer(
+                    &txn,
+                    original.to_account_id,
+                    original.from_account_id,
+                    original.amount,
+                    true,
+                )
+                .await?;
+                let mut original: transactions::ActiveModel = original.into();
+                original.status = Set(TransactionStatus::Reversed);
+                let original = original.update(&txn).await?;
+                record_event(
+                    &txn,
+                    OutboxEventType::TransactionReversed,
+                    original.id,
+                    transaction_data(&original),
+                )
+                .await?;
+                enqueue_event(
+                    &txn,
+                    WebhookEventType::TransactionReversed,
+                    &[original.from_account_id, original.to_account_id],
+                    transaction_data(&original),
+                )
+                .await?;
+                (DisputeStatus::Won, Some(reversal.id))
+            }
+            DisputeOutcome::Lost => (DisputeStatus::Lost, None),
+        };
+
+        // Locked after the reversal so that both accounts are locked in id order first.
+        let held_account = lock_account(&txn, dispute.held_account_id).await?;
+        adjust_balances(&txn, held_account, Decimal::ZERO, -dispute.amount).await?;
+
+        let now = Utc::now().fixed_offset();
+        let mut active_model: ActiveModel = dispute.into();
+        active_model.status = Set(status);
+        active_model.resolved_by = Set(Some(resolved_by));
+        active_model.reversal_transaction_id = Set(reversal_transaction_id);
+        active_model.resolved_at = Set(Some(now));
+        active_model.updated_at = Set(now);
+        let dispute = active_model.update(&txn).await?;
+        if let Some(note) = note {
+            insert_note(&txn, dispute.id, resolved_by, note).await?;
+        }
+
+        let transaction = Transactions::find_by_id(dispute.transaction_id)
+            .one(&txn)
+            .await?
+            .ok_or(DBError::NotFound("Transaction"))?;
+        enqueue_event(
+            &txn,
+            WebhookEventType::DisputeResolved,
+            &[transaction.from_account_id, transaction.to_account_id],
+            dispute_data(&dispute),
+        )
+        .await?;
+
+        txn.commit().await?;
+        Ok(dispute)
+    }
+}
+
+async fn insert_note<C: sea_orm::ConnectionTrait>(
+    conn: &C,
+    dispute_id: i32,
+    author_id: i32,
+    note: String,
+) -> Result<dispute_notes::Model, DBError> {
+    let note = dispute_notes::ActiveModel {
+        dispute_id: Set(dispute_id),
+        author_id: Set(author_id),
+        note: Set(note),
+        created_at: Set(Utc::now().fixed_offset()),
+        ..Default::default()
+    }
+    .insert(conn)
+    .await?;
+    Ok(note)
+}
+
+async fn lock_dispute<C: sea_orm::ConnectionTrait>(conn: &C, id: i32) -> Result<Model, DBError> {
+    Disputes::find_by_id(id)
+        .lock_exclusive()
+        .one(conn)
+        .await?
+        .ok_or(DBError::NotFound("Dispute"))
+}
@@ -0,0 +1,532 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+use entity::invoices::{ActiveModel, Column, Model};
+use entity::prelude::{Accounts, InvoiceLineItems, InvoicePayments, Invoices};
+use entity::{invoice_line_items, invoice_payments, transactions};
+use sea_orm::prelude::{Date, Decimal};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, Condition, ConnectionTrait, EntityTrait, PaginatorTrait,
+    QueryFilter, QueryOrder, QuerySelect, Set, TransactionTrait,
+};
+
+pub use entity::sea_orm_active_enums::InvoiceStatus;
+
+use crate::accounts::lock_account;
+use crate::db_conn::DB;
+use crate::outbox::{record_event, OutboxEventType};
+use crate::pagination::{Keyset, Page, PageRequest};
+use crate::transactions::execute_transfer;
+use crate::util::{to_cents, to_decimal, DBError};
+use crate::webhooks::{enqueue_event, invoice_data, WebhookEventType};
+
+/// A line of an invoice as entered by the issuer
+#[derive(Clone, Debug)]
+pub struct LineItemInput {
+    pub description: String,
+    pub quantity: i32,
+    pub unit_price: f64,
+}
+
+/// The parts of an invoice the issuer can edit until it is paid
+#[derive(Clone, Debug)]
+pub struct InvoiceInput {
+    pub memo: Option<String>,
+    /// Tax in percent of the subtotal
+    pub tax_rate: f64,
+    pub due_date: Date,
+    pub line_items: Vec<LineItemInput>,
+}
+
+/// Which invoices `InvoiceImpl::list_invoices` returns. Invoices issued from any of
+/// `issuer_account_ids` and invoices addressed to `recipient_user_id` are included;
+/// every other field that is set narrows the result.
+#[derive(Clone, Debug, Default)]
+pub struct InvoiceFilter {
+    pub issuer_account_ids: Vec<i32>,
+    pub recipient_user_id: Option<i32>,
+    pub status: Option<InvoiceStatus>,
+    pub overdue: Option<bool>,
+    pub due_after: Option<Date>,
+    pub due_before: Option<Date>,
+}
+
+/// Whether an unpaid invoice is past its due date
+pub fn is_overdue(invoice: &Model) -> bool {
+    matches!(
+        invoice.status,
+        InvoiceStatus::Open | InvoiceStatus::PartiallyPaid
+    ) && invoice.due_date < Utc::now().date_naive()
+}
+
+pub struct InvoiceImpl {
+    db: Arc<DB>,
+}
+
+impl InvoiceImpl {
+    pub fn new(db: Arc<DB>) -> Self {
+        Self { db }
+    }
+
+    /// Issues an invoice from `issuer_account_id` to another user. Invoices are numbered
+    /// `INV-000001`, `INV-000002`, ... per issuing account; the account row is locked
+    /// while the next number is taken.
+    pub async fn create_invoice(
+        &self,
+        issuer_account_id: i32,
+        issued_by: i32,
+        recipient_user_id: i32,
+        input: InvoiceInput,
+    ) -> Result<(Model, Vec<invoice_line_items::Model>), DBError> {
+        let db = self.db.get()?;
+        let txn = db.begin().await?;
+
+        lock_account(&txn, issuer_account_id).await?;
+        let issued = Invoices::find()
+            .filter(Column::IssuerAccountId.eq(issuer_account_id))
+            .count(&txn)
+            .await?;
+
+        let (line_items, totals) = price_line_items(&input)?;
+        let now = Utc::now().fixed_offset();
+        let invoice = ActiveModel {
+            issuer_account_id: Set(issuer_account_id),
+            issued_by: Set(issued_by),
+            recipient_user_id: Set(recipient_user_id),
+            number: Set(format!("INV-{:06}", issued + 1)),
+            memo: Set(input.memo),
+            subtotal: Set(totals.subtotal),
+            tax_rate: Set(totals.tax_rate),
+            tax_amount: Set(totals.tax_amount),
+            total: Set(totals.total),
+            amount_paid: Set(Decimal::ZERO),
+            status: Set(InvoiceStatus::Open),
+            due_date: Set(input.due_date),
+            created_at: Set(now),
+            updated_at: Set(now),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+        let line_items = insert_line_items(&txn, invoice.id, line_items).await?;
+        record_event(
+            &txn,
+            OutboxEventType::InvoiceCreated,
+            invoice.id,
+            invoice_data(&invoice),
+        )
+        .await?;
+
+        txn.commit().await?;
+        Ok((invoice, line_items))
+    }
+
+    pub async fn find_invoice(&self, id: i32) -> Result<Option<Model>, DBError> {
+        let db = self.db.get()?;
+        let invoice = Invoices::find_by_id(id).one(db).await?;
+        Ok(invoice)
+    }
+
+    pub async fn list_line_items(
+        &self,
+        invoice_id: i32,
+    ) -> Result<Vec<invoice_line_items::Model>, DBError> {
+        let db = self.db.get()?;
+        let line_items = InvoiceLineItems::find()
+            .filter(invoice_line_items::Column::InvoiceId.eq(invoice_id))
+            .order_by_asc(invoice_line_items::Column::Id)
+            .all(db)
+            .await?;
+        Ok(line_items)
+    }
+
+    /// Payments towards an invoice, oldest first
+    pub async fn list_payments(
+        &self,
+        invoice_id: i32,
+    ) -> Result<Vec<invoice_payments::Model>, DBError> {
+        let db = self.db.get()?;
+        let payments = InvoicePayments::find()
+            .filter(invoice_payments::Column::InvoiceId.eq(invoice_id))
+            .order_by_asc(invoice_payments::Column::CreatedAt)
+            .order_by_asc(invoice_payments::Column::Id)
+            .all(db)
+            .await?;
+        Ok(payments)
+    }
+
+    /// Invoices matching the filter, newest first
+    pub async fn list_invoices(
+        &self,
+        filter: &InvoiceFilter,
+        page: PageRequest,
+    ) -> Result<Page<Model>, DBError> {
+        let db = self.db.get()?;
+        let today = Utc::now().date_naive();
+
+        let mut scope = Condition::any();
+        if !filter.issuer_account_ids.is_empty() {
+            scope = scope.add(Column::IssuerAccountId.is_in(filter.issuer_account_ids.clone()));
+        }
+        if let Some(recipient_user_id) = filter.recipient_user_id {
+            scope = scope.add(Column::RecipientUserId.eq(recipient_user_id));
+        }
+        let overdue = Condition::all()
+            .add(Column::Status.is_in([InvoiceStatus::Open, InvoiceStatus::PartiallyPaid]))
+            .add(Column::DueDate.lt(today));
+
+        let mut condition = Condition::all()
+            .add(scope)
+            .add_option(filter.status.map(|status| Column::Status.eq(status)))
+            .add_option(filter.due_after.map(|after| Column::DueDate.gte(after)))
+            .add_option(filter.due_before.map(|before| Column::DueDate.lt(before)));
+        match filter.overdue {
+            Some(true) => condition = condition.add(overdue),
+            Some(false) => condition = condition.add(overdue.not()),
+            None => {}
+        }
+        if let Some(after) = page.after {
+            condition = condition.add(after.after(Column::CreatedAt, Column::Id, true));
+        }
+
+        let invoices = Invoices::find()
+            .filter(condition)
+            .order_by_desc(Column::CreatedAt)
+            .order_by_desc(Column::Id)
+            .limit(page.limit + 1)
+            .all(db)
+            .await?;
+
+        Ok(Page::from_rows(invoices, page.limit, |invoice| {
+            Keyset::new(invoice.created_at, invoice.id)
+        }))
+    }
+
+    /// Replaces the editable parts of an invoice and its line items.
+    /// Only open invoices without payments can be changed.
+    pub async fn update_invoice(
+        &self,
+        id: i32,
+        input: InvoiceInput,
+    ) -> Result<(Model, Vec<invoice_line_items::Model>), DBError> {
+        let db = self.db.get()?;
+        let txn = db.begin().await?;
+
+        let invoice = lock_invoice(&txn, id).await?;
+        ensure_unpaid(&invoice, "changed")?;
+
+        InvoiceLineItems::delete_many()
+            .filter(invoice_line_items::Column::InvoiceId.eq(invoice.id))
+            .exec(&txn)
+            .await?;
+        let (line_items, totals) = price_line_items(&input)?;
+        let line_items = insert_line_items(&txn, invoice.id, line_items).await?;
+
+        let mut active_model: ActiveModel = invoice.into();
+        active_model.memo = Set(input.memo);
+        active_model.subtotal = Set(totals.subtotal);
+        active_model.tax_rate = Set(totals.tax_rate);
+        active_model.tax_amount = Set(totals.tax_amount);
+        active_model.total = Set(totals.total);
+        active_model.due_date = Set(input.due_date);
+        active_model.updated_at = Set(Utc::now().fixed_offset());
+        let invoice = active_model.update(&txn).await?;
+
+        txn.commit().await?;
+        Ok((invoice, line_items))
+    }
+
+    /// Voids an open invoice without payments. The invoice and its number are kept.
+    pub async fn void_invoice(&self, id: i32) -> Result<Model, DBError> {
+        let db = self.db.get()?;
+        let txn = db.begin().await?;
+
+        let invoice = lock_invoice(&txn, id).await?;
+        ensure_unpaid(&invoice, "voided")?;
+
+        let mut active_model: ActiveModel = invoice.into();
+        active_model.status = Set(InvoiceStatus::Void);
+        active_model.updated_at = Set(Utc::now().fixed_offset());
+        let invoice = active_model.update(&txn).await?;
+
+        txn.commit().await?;
+        Ok(invoice)
+    }
+
+    /// Pays an invoice from `payer_account_id`, in full or, with an `amount`, in part.
+    /// The transfer and the payment record are written in one database transaction.
+    pub async fn pay_invoice(
+        &self,
+        id: i32,
+        payer_account_id: i32,
+        amount: Option<f64>,
+    ) -> Result<(Model, transactions::Model), DBError> {
+        let db = self.db.get()?;
+        let txn = db.begin().await?;
+
+        let invoice = lock_invoice(&txn, id).await?;
+        if !matches!(
+            invoice.status,
+            InvoiceStatus::Open | InvoiceStatus::PartiallyPaid
+        ) {
+            return Err(DBError::Conflict(
+                "This invoice does not accept payments".to_string(),
+            ));
+        }
+        if invoice.issuer_account_id == payer_account_id {
+            return Err(DBError::Conflict(
+                "An invoice cannot be paid from the account that issued it".to_string(),
+            ));
+        }
+
+        let amount = payment_amount(invoice.total - invoice.amount_paid, amount)?;
+
+        let transaction = execute_transfer(
+            &txn,
+            payer_account_id,
+            invoice.issuer_account_id,
+            amount,
+            false,
+        )
+        .await?;
+        let invoice = record_payment(&txn, invoice, &transaction, amount).await?;
+
+        txn.commit().await?;
+        Ok((invoice, transaction))
+    }
+}
+
+/// Applies a settled transfer to the invoice it pays, if any: the oldest-due unpaid
+/// invoice from the receiving account to the holder of the sending account whose
+/// outstanding amount equals the transfer. Runs on the transfer's database transaction.
+pub(crate) async fn settle_matching_invoice<C: ConnectionTrait>(
+    conn: &C,
+    transaction: &transactions::Model,
+) -> Result<Option<Model>, DBError> {
+    let sender = Accounts::find_by_id(transaction.from_account_id)
+        .one(conn)
+        .await?
+        .ok_or(DBError::AccountNotFound)?;
+
+    let candidates = Invoices::find()
+        .filter(Column::IssuerAccountId.eq(transaction.to_account_id))
+        .filter(Column::RecipientUserId.eq(sender.user_id))
+        .filter(Column::Status.is_in([InvoiceStatus::Open, InvoiceStatus::PartiallyPaid]))
+        .order_by_asc(Column::DueDate)
+        .order_by_asc(Column::Id)
+        .lock_exclusive()
+        .all(conn)
+        .await?;
+
+    let amount = transaction.amount.round_dp(2);
+    let Some(invoice) = candidates
+        .into_iter()
+        .find(|invoice| invoice.total - invoice.amount_paid == amount)
+    else {
+        return Ok(None);
+    };
+
+    let invoice = record_payment(conn, invoice, transaction, amount).await?;
+    Ok(Some(invoice))
+}
+
+/// The amount a payment of `amount`, or of everything when it is `None`, applies to an
+/// invoice with `outstanding` still due, rounded to the cent
+fn payment_amount(outstanding: Decimal, amount: Option<f64>) -> Result<Decimal, DBError> {
+    let amount = match amount {
+        Some(amount) => to_cents(amount)?,
+        None => outstanding,
+    };
+    if amount > outstanding {
+        return Err(DBError::Conflict(format!(
+            "The amount exceeds the {outstanding} still due on this invoice"
+        )));
+    }
+    Ok(amount)
+}
+
+struct Totals {
+    subtotal: Decimal,
+    tax_rate: Decimal,
+    tax_amount: Decimal,
+    total: Decimal,
+}
+
+/// Prices each line item and adds them up. Money is kept in cents; the tax is
+/// rounded to the cent after it is applied to the subtotal.
+fn price_line_items(
+    input: &InvoiceInput,
+) -> Result<(Vec<invoice_line_items::ActiveModel>, Totals), DBError> {
+    let line_items: Vec<invoice_line_items::ActiveModel> = input
+        .line_items
+        .iter()
+        .map(|item| {
+            let unit_price = to_cents(item.unit_price)?;
+            Ok(invoice_line_items::ActiveModel {
+                description: Set(item.description.clone()),
+                quantity: Set(item.quantity),
+                unit_price: Set(unit_price),
+                amount: Set(unit_price * Decimal::from(item.quantity)),
+                ..Default::default()
+            })
+        })
+        .collect::<Result<_, DBError>>()?;
+
+    let subtotal: Decimal = line_items
+        .iter()
+        .map(|item| item.amount.clone().unwrap())
+        .sum();
+    let tax_rate = to_decimal(input.tax_rate)?.round_dp(4);
+    let tax_amount = (subtotal * tax_rate / Decimal::ONE_HUNDRED).round_dp(2);
+
+    let totals = Totals {
+        subtotal,
+        tax_rate,
+        tax_amount,
+        total: subtotal + tax_amount,
+    };
+    Ok((line_items, totals))
+}
+
+async fn insert_line_items<C: ConnectionTrait>(
+    conn: &C,
+    invoice_id: i32,
+    line_items: Vec<invoice_line_items::ActiveModel>,
+) -> Result<Vec<invoice_line_items::Model>, DBError> {
+    let mut inserted = Vec::with_capacity(line_items.len());
+    for mut line_item in line_items {
+        line_item.invoice_id = Set(invoice_id);
+        inserted.push(line_item.insert(conn).await?);
+    }
+    Ok(inserted)
+}
+
+async fn lock_invoice<C: ConnectionTrait>(conn: &C, id: i32) -> Result<Model, DBError> {
+    Invoices::find_by_id(id)
+        .lock_exclusive()
+        .one(conn)
+        .await?
+        .ok_or(DBError::NotFound("Invoice"))
+}
+
+fn ensure_unpaid(invoice: &Model, action: &str) -> Result<(), DBError> {
+    if invoice.status != InvoiceStatus::Open || !invoice.amount_paid.is_zero() {
+        return Err(DBError::Conflict(format!(
+            "Only open invoices without payments can be {action}"
+        )));
+    }
+    Ok(())
+}
+
+/// Links a transaction to a locked invoice and marks the invoice paid once the
+/// payments cover its total
+async fn record_payment<C: ConnectionTrait>(
+    conn: &C,
+    invoice: Model,
+    transaction: &transactions::Model,
+    amount: Decimal,
+) -> Result<Model, DBError> {
+    let now = Utc::now().fixed_offset();
+    invoice_payments::ActiveModel {
+        invoice_id: Set(invoice.id),
+        transaction_id: Set(transaction.id),
+        amount: Set(amount),
+        created_at: Set(now),
+        ..Default::default()
+    }
+    .insert(conn)
+    .await?;
+
+    let amount_paid = invoice.amount_paid + amount;
+    let paid = amount_paid >= invoice.total;
+    let mut active_model: ActiveModel = invoice.into();
+    active_model.amount_paid = Set(amount_paid);
+    if paid {
+        active_model.status = Set(InvoiceStatus::Paid);
+        active_model.paid_at = Set(Some(now));
+    } else {
+        active_model.status = Set(InvoiceStatus::PartiallyPaid);
+    }
+    active_model.updated_at = Set(now);
+    let invoice = active_model.update(conn).await?;
+    if paid {
+        enqueue_event(
+            conn,
+            WebhookEventType::InvoicePaid,
+            &[invoice.issuer_account_id, transaction.from_account_id],
+            invoice_data(&invoice),
+        )
+        .await?;
+    }
+    Ok(invoice)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::dec;
+
+    #[test]
+    fn partial_payments_are_rounded_to_the_cent() {
+        assert_eq!(
+            payment_amount(dec("100.00"), Some(0.3)).unwrap(),
+            dec("0.30")
+        );
+        assert_eq!(
+            payment_amount(dec("100.00"), Some(33.33)).unwrap(),
+            dec("33.33")
+        );
+        assert_eq!(
+            payment_amount(dec("100.00"), Some(100.004)).unwrap(),
+            dec("100.00")
+        );
+    }
+
+    #[test]
+    fn partial_payments_add_up_to_the_total() {
+        let total = dec("100.00");
+        let mut amount_paid = Decimal::ZERO;
+        for _ in 0..3 {
+            amount_paid += payment_amount(total - amount_paid, Some(33.33)).unwrap();
+        }
+        assert_eq!(total - amount_paid, dec("0.01"));
+
+        amount_paid += payment_amount(total - amount_paid, None).unwrap();
+        assert_eq!(amount_paid, total);
+    }
+
+    #[test]
+    fn payments_cannot_exceed_what_is_due() {
+        assert!(matches!(
+            payment_amount(dec("10.00"), Some(10.01)),
+            Err(DBError::Conflict(_))
+        ));
+        assert!(payment_amount(dec("10.00"), Some(10.0)).is_ok());
+    }
+
+    #[test]
+    fn tax_is_rounded_once_on_the_subtotal() {
+        let input = InvoiceInput {
+            memo: None,
+            tax_rate: 8.875,
+            due_date: Date::from_ymd_opt(2026, 11, 1).unwrap(),
+            line_items: vec![
+                LineItemInput {
+                    description: "Hour".to_string(),
+                    quantity: 3,
+                    unit_price: 33.33,
+                },
+                LineItemInput {
+                    description: "Setup".to_string(),
+                    quantity: 1,
+                    unit_price: 0.1,
+                },
+            ],
+        };
+        let (_, totals) = price_line_items(&input).unwrap();
+        assert_eq!(totals.subtotal, dec("100.09"));
+        assert_eq!(totals.tax_rate, dec("8.875"));
+        assert_eq!(totals.tax_amount, dec("8.88"));
+        assert_eq!(totals.total, dec("108.97"));
+    }
+}
@@ -1,6 +1,22 @@
+pub mod account_members;
 pub mod accounts;
+pub mod adjustments;
+pub mod api_keys;
+pub mod approvals;
+pub mod audit;
 pub mod db_client;
 pub mod db_conn;
+pub mod disputes;
+pub mod invoices;
+pub mod organizations;
+pub mod outbox;
+pub mod pagination;
+pub mod payment_links;
+pub mod payments;
+pub mod refresh_tokens;
+pub mod split_payments;
+pub mod subscriptions;
 pub mod transactions;
 pub mod user;
 pub mod util;
+pub mod webhooks;
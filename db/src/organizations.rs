@@ -0,0 +1,220 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+use entity::organization_members::{self, Column};
+use entity::organizations::{ActiveModel, Model};
+use entity::prelude::{OrganizationMembers, Organizations};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, EntityTrait, PaginatorTrait, QueryFilter,
+    QueryOrder, QuerySelect, Set, TransactionTrait,
+};
+
+pub use entity::sea_orm_active_enums::OrganizationRole;
+
+use crate::db_conn::DB;
+use crate::util::DBError;
+
+pub struct OrganizationImpl {
+    db: Arc<DB>,
+}
+
+impl OrganizationImpl {
+    pub fn new(db: Arc<DB>) -> Self {
+        Self { db }
+    }
+
+    /// Creates an organization with its creator as the first admin
+    pub async fn create_organization(
+        &self,
+        name: String,
+        created_by: i32,
+    ) -> Result<(Model, organization_members::Model), DBError> {
+        let db = self.db.get()?;
+        let txn = db.begin().await?;
+
+        let now = Utc::now().fixed_offset();
+        let organization = ActiveModel {
+            name: Set(name),
+            created_by: Set(created_by),
+            created_at: Set(now),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+        let admin = organization_members::ActiveModel {
+            organization_id: Set(organization.id),
+            user_id: Set(created_by),
+            role: Set(OrganizationRole::Admin),
+            created_at: Set(now),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+
+        txn.commit().await?;
+        Ok((organization, admin))
+    }
+
+    pub async fn find_organization(&self, id: i32) -> Result<Option<Model>, DBError> {
+        let db = self.db.get()?;
+        let organization = Organizations::find_by_id(id).one(db).await?;
+        Ok(organization)
+    }
+
+    /// Organizations the user belongs to, with the user's membership in each
+    pub async fn list_user_organizations(
+        &self,
+        user_id: i32,
+    ) -> Result<Vec<(organization_members::Model, Option<Model>)>, DBError> {
+        let db = self.db.get()?;
+        let organizations = OrganizationMembers::find()
+            .filter(Column::UserId.eq(user_id))
+            .find_also_related(Organizations)
+            .order_by_asc(Column::CreatedAt)
+            .order_by_asc(Column::Id)
+            .all(db)
+            .await?;
+        Ok(organizations)
+    }
+
+    pub async fn find_membership(
+        &self,
+        organization_id: i32,
+        user_id: i32,
+    ) -> Result<Option<organization_members::Model>, DBError> {
+        let db = self.db.get()?;
+        find_member(db, organization_id, user_id).await
+    }
+
+    /// Members of an organization in the order they joined
+    pub async fn list_members(
+        &self,
+        organization_id: i32,
+    ) -> Result<Vec<organization_members::Model>, DBError> {
+        let db = self.db.get()?;
+        let members = OrganizationMembers::find()
+            .filter(Column::OrganizationId.eq(organization_id))
+            .order_by_asc(Column::CreatedAt)
+            .order_by_asc(Column::Id)
+            .all(db)
+            .await?;
+        Ok(members)
+    }
+
+    pub async fn add_member(
+        &self,
+        organization_id: i32,
+        user_id: i32,
+        role: OrganizationRole,
+    ) -> Result<organization_members::Model, DBError> {
+        let db = self.db.get()?;
+        let txn = db.begin().await?;
+
+        lock_organization(&txn, organization_id).await?;
+        if find_member(&txn, organization_id, user_id).await?.is_some() {
+            return Err(DBError::Conflict(
+                "This user is already a member of the organization".to_string(),
+            ));
+        }
+
+        let member = organization_members::ActiveModel {
+            organization_id: Set(organization_id),
+            user_id: Set(user_id),
+            role: Set(role),
+            created_at: Set(Utc::now().fixed_offset()),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+
+        txn.commit().await?;
+        Ok(member)
+    }
+
+    /// Changes a member's role. The last admin cannot be demoted.
+    pub async fn update_role(
+        &self,
+        organization_id: i32,
+        user_id: i32,
+        role: OrganizationRole,
+    ) -> Result<organization_members::Model, DBError> {
+        let db = self.db.get()?;
+        let txn = db.begin().await?;
+
+        lock_organization(&txn, organization_id).await?;
+        let member = find_member(&txn, organization_id, user_id)
+            .await?
+            .ok_or(DBError::NotFound("Organization member"))?;
+        if member.role == OrganizationRole::Admin && role != OrganizationRole::Admin {
+            ensure_other_admin(&txn, organization_id).await?;
+        }
+
+        let mut active_model: organization_members::ActiveModel = member.into();
+        active_model.role = Set(role);
+        let member = active_model.update(&txn).await?;
+
+        txn.commit().await?;
+        Ok(member)
+    }
+
+    /// Removes a member from an organization. The last admin cannot be removed.
+    pub async fn remove_member(&self, organization_id: i32, user_id: i32) -> Result<(), DBError> {
+        let db = self.db.get()?;
+        let txn = db.begin().await?;
+
+        lock_organization(&txn, organization_id).await?;
+        let member = find_member(&txn, organization_id, user_id)
+            .await?
+            .ok_or(DBError::NotFound("Organization member"))?;
+        if member.role == OrganizationRole::Admin {
+            ensure_other_admin(&txn, organization_id).await?;
+        }
+
+        OrganizationMembers::delete_by_id(member.id)
+            .exec(&txn)
+            .await?;
+
+        txn.commit().await?;
+        Ok(())
+    }
+}
+
+/// Serializes membership changes of one organization, so that concurrent requests
+/// cannot remove its last admins at the same time
+async fn lock_organization<C: ConnectionTrait>(conn: &C, id: i32) -> Result<Model, DBError> {
+    Organizations::find_by_id(id)
+        .lock_exclusive()
+        .one(conn)
+        .await?
+        .ok_or(DBError::NotFound("Organization"))
+}
+
+async fn find_member<C: ConnectionTrait>(
+    conn: &C,
+    organization_id: i32,
+    user_id: i32,
+) -> Result<Option<organization_members::Model>, DBError> {
+    let member = OrganizationMembers::find()
+        .filter(Column::OrganizationId.eq(organization_id))
+        .filter(Column::UserId.eq(user_id))
+        .one(conn)
+        .await?;
+    Ok(member)
+}
+
+async fn ensure_other_admin<C: ConnectionTrait>(
+    conn: &C,
+    organization_id: i32,
+) -> Result<(), DBError> {
+    let admins = OrganizationMembers::find()
+        .filter(Column::OrganizationId.eq(organization_id))
+        .filter(Column::Role.eq(OrganizationRole::Admin))
+        .count(conn)
+        .await?;
+    if admins <= 1 {
+        return Err(DBError::Conflict(
+            "An organization must keep at least one admin".to_string(),
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,419 @@
+use std::sync::Arc;
+
+use chrono::{Duration, Utc};
+use entity::outbox::{ActiveModel, Column, Model};
+use entity::prelude::{EmailDeliveries, Outbox, OutboxOffsets};
+use entity::{accounts, notification_preferences, user};
+use entity::{email_deliveries, outbox_offsets};
+use num_traits::ToPrimitive;
+use sea_orm::prelude::{DateTimeWithTimeZone, Expr, Json};
+use sea_orm::sea_query::OnConflict;
+use sea_orm::sqlx::postgres::PgListener;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, Condition, ConnectionTrait, DbErr, EntityTrait, QueryFilter,
+    QueryOrder, QuerySelect, RuntimeErr, Select, Set, Statement,
+};
+use serde_json::json;
+
+pub use entity::sea_orm_active_enums::OutboxEventType;
+
+use crate::db_conn::DB;
+use crate::util::DBError;
+
+/// Channel on which Postgres announces the id of every committed outbox event
+const NOTIFY_CHANNEL: &str = "outbox_events";
+
+/// Where a reader stands in the outbox. Events are read in the order of the database
+/// transaction that wrote them, then of their id, because ids are handed out when an
+/// event is inserted rather than when it is committed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct EventPosition {
+    pub transaction_id: i64,
+    pub event_id: i32,
+}
+
+impl EventPosition {
+    pub fn of(event: &Model) -> Self {
+        Self {
+            transaction_id: event.transaction_id,
+            event_id: event.id,
+        }
+    }
+}
+
+pub struct OutboxImpl {
+    db: Arc<DB>,
+}
+
+impl OutboxImpl {
+    pub fn new(db: Arc<DB>) -> Self {
+        Self { db }
+    }
+
+    /// Takes the lease on a sink's offset for `lease`. The first time a sink is seen its
+    /// offset is created at the start of the outbox, or with `from_start` unset, after
+    /// the events already committed. Returns `None` while another relay holds an
+    /// unexpired lease.
+    pub async fn claim_sink(
+        &self,
+        sink: &str,
+        lease: Duration,
+        from_start: bool,
+    ) -> Result<Option<outbox_offsets::Model>, DBError> {
+        let db = self.db.get()?;
+        let now = Utc::now().fixed_offset();
+        let start = if from_start {
+            EventPosition::default()
+        } else {
+            self.current_position().await?
+        };
+        OutboxOffsets::insert(outbox_offsets::ActiveModel {
+            sink: Set(sink.to_string()),
+            last_transaction_id: Set(start.transaction_id),
+            last_event_id: Set(start.event_id),
+            locked_until: Set(None),
+            updated_at: Set(now),
+        })
+        .on_conflict(
+            OnConflict::column(outbox_offsets::Column::Sink)
+                .do_nothing()
+                .to_owned(),
+        )
+        .exec_without_returning(db)
+        .await?;
+
+        let claimed = OutboxOffsets::update_many()
+            .col_expr(
+                outbox_offsets::Column::LockedUntil,
+                Expr::value(Some(now + lease)),
+            )
+            .filter(outbox_offsets::Column::Sink.eq(sink))
+            .filter(
+                Condition::any()
+                    .add(outbox_offsets::Column::LockedUntil.is_null())
+                    .add(outbox_offsets::Column::LockedUntil.lt(now)),
+            )
+            .exec_with_returning(db)
+            .await?;
+        Ok(claimed.into_iter().next())
+    }
+
+    /// Up to `limit` events after a sink's offset in the order they are published.
+    /// Events of database transactions that are still running, and of any that started
+    /// after the oldest of them, are held back until it ends.
+    pub async fn events_after(
+        &self,
+        offset: &outbox_offsets::Model,
+        limit: u64,
+    ) -> Result<Vec<Model>, DBError> {
+        let db = self.db.get()?;
+        let after = EventPosition {
+            transaction_id: offset.last_transaction_id,
+            event_id: offset.last_event_id,
+        };
+        let events = committed_after(after).limit(limit).all(db).await?;
+        Ok(events)
+    }
+
+    /// The position right before the first event that `events_after` cannot return yet.
+    /// Every transaction before the oldest running one has ended, so starting there
+    /// skips exactly the events that could already be read.
+    pub async fn current_position(&self) -> Result<EventPosition, DBError> {
+        let db = self.db.get()?;
+        let xmin = db
+            .query_one(Statement::from_string(
+                db.get_database_backend(),
+                "SELECT pg_snapshot_xmin(pg_current_snapshot())::text::bigint",
+            ))
+            .await?
+            .ok_or(DBError::NotFound("Transaction snapshot"))?
+            .try_get_by_index::<i64>(0)?;
+        Ok(EventPosition {
+            transaction_id: xmin - 1,
+            event_id: i32::MAX,
+        })
+    }
+
+    /// Moves a sink's offset past `event` and renews its lease
+    pub async fn advance(&self, sink: &str, event: &Model, lease: Duration) -> Result<(), DBError> {
+        let db = self.db.get()?;
+        let now = Utc::now().fixed_offset();
+        OutboxOffsets::update(outbox_offsets::ActiveModel {
+            sink: Set(sink.to_string()),
+            last_transaction_id: Set(event.transaction_id),
+            last_event_id: Set(event.id),
+            locked_until: Set(Some(now + lease)),
+            updated_at: Set(now),
+        })
+        .exec(db)
+        .await?;
+        Ok(())
+    }
+
+    /// Gives up the lease on a sink so that any relay can continue from its offset
+    pub async fn release_sink(&self, sink: &str) -> Result<(), DBError> {
+        let db = self.db.get()?;
+        OutboxOffsets::update_many()
+            .col_expr(
+                outbox_offsets::Column::LockedUntil,
+                Expr::value(Option::<DateTimeWithTimeZone>::None),
+            )
+            .filter(outbox_offsets::Column::Sink.eq(sink))
+            .exec(db)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn find_event(&self, id: i32) -> Result<Option<Model>, DBError> {
+        let db = self.db.get()?;
+        let event = Outbox::find_by_id(id).one(db).await?;
+        Ok(event)
+    }
+
+    /// Users already emailed about an event
+    pub async fn emailed_users(&self, event_id: i32) -> Result<Vec<i32>, DBError> {
+        let db = self.db.get()?;
+        let user_ids = EmailDeliveries::find()
+            .select_only()
+            .column(email_deliveries::Column::UserId)
+            .filter(email_deliveries::Column::EventId.eq(event_id))
+            .into_tuple()
+            .all(db)
+            .await?;
+        Ok(user_ids)
+    }
+
+    /// Remembers that a user was emailed about an event, so a retry does not email them again
+    pub async fn record_email(&self, event_id: i32, user_id: i32) -> Result<(), DBError> {
+        let db = self.db.get()?;
+        let delivery = email_deliveries::ActiveModel {
+            event_id: Set(event_id),
+            user_id: Set(user_id),
+            sent_at: Set(Utc::now().fixed_offset()),
+            ..Default::default()
+        };
+        EmailDeliveries::insert(delivery)
+            .on_conflict(
+                OnConflict::columns([
+                    email_deliveries::Column::EventId,
+                    email_deliveries::Column::UserId,
+                ])
+                .do_nothing()
+                .to_owned(),
+            )
+            .do_nothing()
+            .exec(db)
+            .await?;
+        Ok(())
+    }
+
+    /// Up to `limit` transaction and balance events of an account after `after`, held
+    /// back and ordered the same way as `events_after`
+    pub async fn account_events_after(
+        &self,
+        account_id: i32,
+        after: EventPosition,
+        limit: u64,
+    ) -> Result<Vec<Model>, DBError> {
+        let db = self.db.get()?;
+        let events = committed_after(after)
+            .filter(
+                Condition::any()
+                    .add(
+                        Condition::all()
+                            .add(Column::EventType.eq(OutboxEventType::AccountBalanceChanged))
+                            .add(Column::AggregateId.eq(account_id)),
+                    )
+                    .add(
+                        Condition::all()
+                            .add(Column::EventType.is_in([
+                                OutboxEventType::TransactionCreated,
+                                OutboxEventType::TransactionReversed,
+                            ]))
+                            .add(Expr::cust_with_values(
+                                "$1 IN ((payload->>'from_account_id')::int, (payload->>'to_account_id')::int)",
+                                [account_id],
+                            )),
+                    ),
+            )
+            .limit(limit)
+            .all(db)
+            .await?;
+        Ok(events)
+    }
+
+    /// Up to `limit` events a notification connection sends after `after`: invoices
+    /// issued to `recipient_user_id`, and transactions into and balance changes of the
+    /// given accounts. Held back and ordered the same way as `events_after`.
+    pub async fn notification_events_after(
+        &self,
+        recipient_user_id: Option<i32>,
+        account_ids: &[i32],
+        after: EventPosition,
+        limit: u64,
+    ) -> Result<Vec<Model>, DBError> {
+        let db = self.db.get()?;
+        let mut condition = Condition::any()
+            .add(
+                Condition::all()
+                    .add(Column::EventType.eq(OutboxEventType::AccountBalanceChanged))
+                    .add(Column::AggregateId.is_in(account_ids.iter().copied())),
+            )
+            .add(
+                Condition::all()
+                    .add(Column::EventType.eq(OutboxEventType::TransactionCreated))
+                    .add(Expr::cust_with_values(
+                        "(payload->>'to_account_id')::int = ANY($1)",
+                        [account_ids.to_vec()],
+                    )),
+            );
+        if let Some(recipient_user_id) = recipient_user_id {
+            condition = condition.add(
+                Condition::all()
+                    .add(Column::EventType.eq(OutboxEventType::InvoiceCreated))
+                    .add(Expr::cust_with_values(
+                        "(payload->>'recipient_user_id')::int = $1",
+                        [recipient_user_id],
+                    )),
+            );
+        }
+        let events = committed_after(after)
+            .filter(condition)
+            .limit(limit)
+            .all(db)
+            .await?;
+        Ok(events)
+    }
+
+    /// Opens a dedicated connection that is notified of every outbox event as it commits
+    pub async fn listen(&self) -> Result<OutboxListener, DBError> {
+        let db = self.db.get()?;
+        let mut listener = PgListener::connect_with(db.get_postgres_connection_pool())
+            .await
+            .map_err(sqlx_error)?;
+        listener.listen(NOTIFY_CHANNEL).await.map_err(sqlx_error)?;
+        Ok(OutboxListener { listener })
+    }
+}
+
+/// Ids of outbox events in the order their transactions commit
+pub struct OutboxListener {
+    listener: PgListener,
+}
+
+impl OutboxListener {
+    /// Waits for the next event id. `None` means the connection was lost and events may
+    /// have been missed; the connection is reopened on the next call.
+    pub async fn next(&mut self) -> Result<Option<i32>, DBError> {
+        let notification = self.listener.try_recv().await.map_err(sqlx_error)?;
+        Ok(notification.and_then(|notification| notification.payload().parse().ok()))
+    }
+}
+
+/// Whether an event from `account_events_after` concerns the account
+pub fn concerns_account(event: &Model, account_id: i32) -> bool {
+    match event.event_type {
+        OutboxEventType::AccountBalanceChanged => event.aggregate_id == account_id,
+        OutboxEventType::TransactionCreated | OutboxEventType::TransactionReversed => {
+            ["from_account_id", "to_account_id"]
+                .iter()
+                .any(|key| event.payload[key].as_i64() == Some(account_id.into()))
+        }
+        _ => false,
+    }
+}
+
+/// Whether an event is an invoice issued to the user
+pub fn concerns_user(event: &Model, user_id: i32) -> bool {
+    event.event_type == OutboxEventType::InvoiceCreated
+        && event.payload["recipient_user_id"].as_i64() == Some(user_id.into())
+}
+
+/// The events that `events_after` may return after `after`, in order
+fn committed_after(after: EventPosition) -> Select<Outbox> {
+    Outbox::find()
+        .filter(
+            Condition::any()
+                .add(Column::TransactionId.gt(after.transaction_id))
+                .add(
+                    Condition::all()
+                        .add(Column::TransactionId.eq(after.transaction_id))
+                        .add(Column::Id.gt(after.event_id)),
+                ),
+        )
+        .filter(Expr::cust(
+            "transaction_id < pg_snapshot_xmin(pg_current_snapshot())::text::bigint",
+        ))
+        .order_by_asc(Column::TransactionId)
+        .order_by_asc(Column::Id)
+}
+
+fn sqlx_error(err: sea_orm::sqlx::Error) -> DBError {
+    DBError::DBErr(DbErr::Conn(RuntimeErr::SqlxError(err)))
+}
+
+/// Writes a domain event to the outbox on the caller's database transaction, so that
+/// the event exists exactly when the change it describes is committed
+pub(crate) async fn record_event<C: ConnectionTrait>(
+    conn: &C,
+    event_type: OutboxEventType,
+    aggregate_id: i32,
+    payload: Json,
+) -> Result<(), DBError> {
+    ActiveModel {
+        event_type: Set(event_type),
+        aggregate_id: Set(aggregate_id),
+        payload: Set(payload),
+        created_at: Set(Utc::now().fixed_offset()),
+        ..Default::default()
+    }
+    .insert(conn)
+    .await?;
+    Ok(())
+}
+
+/// Event data describing a user, without the password
+pub(crate) fn user_data(user: &user::Model) -> Json {
+    json!({
+        "id": user.id,
+        "username": user.username,
+        "display_name": user.display_name,
+        "email": user.email,
+        "default_account_id": user.default_account_id,
+        "role": user.role,
+        "created_at": user.created_at,
+    })
+}
+
+/// Event data describing a user's notification preferences
+pub(crate) fn preferences_data(preferences: &notification_preferences::Model) -> Json {
+    json!({
+        "user_id": preferences.user_id,
+        "money_received": preferences.money_received,
+        "money_sent": preferences.money_sent,
+        "large_transfers": preferences.large_transfers,
+        "large_transfer_threshold": preferences.large_transfer_threshold.to_f64(),
+        "new_logins": preferences.new_logins,
+        "password_changes": preferences.password_changes,
+        "updated_at": preferences.updated_at,
+    })
+}
+
+/// Event data describing an account
+pub(crate) fn account_data(account: &accounts::Model) -> Json {
+    json!({
+        "id": account.id,
+        "user_id": account.user_id,
+        "organization_id": account.organization_id,
+        "parent_account_id": account.parent_account_id,
+        "name": account.name,
+        "alias": account.alias,
+        "balance": account.balance.to_f64(),
+        "held_balance": account.held_balance.to_f64(),
+        "approval_threshold": account.approval_threshold.and_then(|threshold| threshold.to_f64()),
+        "frozen_at": account.frozen_at,
+        "frozen_reason": account.frozen_reason,
+        "created_at": account.created_at,
+        "updated_at": account.updated_at,
+    })
+}
@@ -0,0 +1,84 @@
+use sea_orm::prelude::{DateTimeWithTimeZone, Decimal};
+use sea_orm::{ColumnTrait, Condition};
+
+/// Position of a row in a listing ordered by `(created_at, id)`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Keyset {
+    pub created_at: DateTimeWithTimeZone,
+    pub id: i32,
+    /// For listings that can be sorted several ways, the sort the keyset was taken in
+    pub sort: Option<SortPosition>,
+}
+
+/// The sort a keyset was taken in and, for sorts on another column than `created_at`,
+/// the row's value in that column. A keyset only continues the sort it was taken in.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SortPosition {
+    pub key: String,
+    pub value: Option<Decimal>,
+}
+
+impl Keyset {
+    pub fn new(created_at: DateTimeWithTimeZone, id: i32) -> Self {
+        Self {
+            created_at,
+            id,
+            sort: None,
+        }
+    }
+
+    /// Records the sort the keyset was taken in, and the row's value for it if any
+    pub fn with_sort(mut self, key: &str, value: Option<Decimal>) -> Self {
+        self.sort = Some(SortPosition {
+            key: key.to_string(),
+            value,
+        });
+        self
+    }
+
+    /// Condition matching rows that come strictly after this keyset
+    /// when the listing is ordered by `(created_at, id)` in the given direction.
+    pub fn after<C: ColumnTrait>(&self, created_at: C, id: C, descending: bool) -> Condition {
+        let (past_created_at, past_id) = if descending {
+            (created_at.lt(self.created_at), id.lt(self.id))
+        } else {
+            (created_at.gt(self.created_at), id.gt(self.id))
+        };
+        Condition::any().add(past_created_at).add(
+            Condition::all()
+                .add(created_at.eq(self.created_at))
+                .add(past_id),
+        )
+    }
+}
+
+/// Request for one page of a keyset-paginated listing
+#[derive(Clone, Debug)]
+pub struct PageRequest {
+    /// Maximum number of rows to return
+    pub limit: u64,
+    /// Keyset of the last row of the previous page, `None` for the first page
+    pub after: Option<Keyset>,
+}
+
+/// One page of rows plus the keyset to continue from, if there are more rows
+#[derive(Debug)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next: Option<Keyset>,
+}
+
+impl<T> Page<T> {
+    /// Builds a page from up to `limit + 1` fetched rows; the extra row only signals
+    /// that another page exists and is dropped.
+    pub(crate) fn from_rows(mut rows: Vec<T>, limit: u64, keyset: impl Fn(&T) -> Keyset) -> Self {
+        let has_more = rows.len() as u64 > limit;
+        rows.truncate(limit as usize);
+        let next = if has_more {
+            rows.last().map(keyset)
+        } else {
+            None
+        };
+        Page { items: rows, next }
+    }
+}
@@ -0,0 +1,167 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+use entity::payment_links::{ActiveModel, Column, Model};
+use entity::prelude::PaymentLinks;
+use entity::transactions;
+use rand::distributions::{Alphanumeric, DistString};
+use sea_orm::prelude::{DateTimeWithTimeZone, Decimal};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, QueryOrder, QuerySelect, Set,
+    TransactionTrait,
+};
+
+use crate::db_conn::DB;
+use crate::pagination::{Keyset, Page, PageRequest};
+use crate::transactions::execute_transfer;
+use crate::util::{to_decimal, DBError};
+
+/// Length of the random part of a payment link URL
+const SLUG_LENGTH: usize = 20;
+
+pub struct PaymentLinkImpl {
+    db: Arc<DB>,
+}
+
+impl PaymentLinkImpl {
+    pub fn new(db: Arc<DB>) -> Self {
+        Self { db }
+    }
+
+    /// Creates a payment link into `account_id` behind a random, unguessable slug.
+    /// Without an `amount` the payer chooses how much to pay.
+    pub async fn create_link(
+        &self,
+        account_id: i32,
+        created_by: i32,
+        amount: Option<f64>,
+        description: Option<String>,
+        single_use: bool,
+        expires_at: Option<DateTimeWithTimeZone>,
+    ) -> Result<Model, DBError> {
+        let db = self.db.get()?;
+        let now = Utc::now().fixed_offset();
+        let link = ActiveModel {
+            slug: Set(Alphanumeric.sample_string(&mut rand::thread_rng(), SLUG_LENGTH)),
+            account_id: Set(account_id),
+            created_by: Set(created_by),
+            amount: Set(amount.map(to_decimal).transpose()?),
+            description: Set(description),
+            single_use: Set(single_use),
+            active: Set(true),
+            expires_at: Set(expires_at),
+            payment_count: Set(0),
+            collected_amount: Set(Decimal::ZERO),
+            created_at: Set(now),
+            updated_at: Set(now),
+            ..Default::default()
+        };
+        Ok(link.insert(db).await?)
+    }
+
+    pub async fn find_by_slug(&self, slug: &str) -> Result<Option<Model>, DBError> {
+        let db = self.db.get()?;
+        let link = PaymentLinks::find()
+            .filter(Column::Slug.eq(slug))
+            .one(db)
+            .await?;
+        Ok(link)
+    }
+
+    /// Payment links into the given accounts, newest first
+    pub async fn list_links(
+        &self,
+        account_ids: &[i32],
+        page: PageRequest,
+    ) -> Result<Page<Model>, DBError> {
+        let db = self.db.get()?;
+        let mut query = PaymentLinks::find().filter(Column::AccountId.is_in(account_ids.to_vec()));
+        if let Some(after) = page.after {
+            query = query.filter(after.after(Column::CreatedAt, Column::Id, true));
+        }
+
+        let links = query
+            .order_by_desc(Column::CreatedAt)
+            .order_by_desc(Column::Id)
+            .limit(page.limit + 1)
+            .all(db)
+            .await?;
+
+        Ok(Page::from_rows(links, page.limit, |link| {
+            Keyset::new(link.created_at, link.id)
+        }))
+    }
+
+    /// Stops a link from accepting further payments
+    pub async fn deactivate(&self, id: i32) -> Result<Model, DBError> {
+        let db = self.db.get()?;
+        let link = ActiveModel {
+            id: Set(id),
+            active: Set(false),
+            updated_at: Set(Utc::now().fixed_offset()),
+            ..Default::default()
+        };
+        Ok(link.update(db).await?)
+    }
+
+    /// Pays `amount` through a link into its account and counts the payment on the link,
+    /// in one database transaction. Single-use links are deactivated by their first payment.
+    pub async fn pay(
+        &self,
+        slug: &str,
+        payer_account_id: i32,
+        amount: f64,
+    ) -> Result<(Model, transactions::Model), DBError> {
+        let db = self.db.get()?;
+        let amount = to_decimal(amount)?;
+        let txn = db.begin().await?;
+
+        let link = PaymentLinks::find()
+            .filter(Column::Slug.eq(slug))
+            .lock_exclusive()
+            .one(&txn)
+            .await?
+            .ok_or(DBError::NotFound("Payment link"))?;
+        if !link.active {
+            return Err(DBError::Conflict(
+                "This payment link no longer accepts payments".to_string(),
+            ));
+        }
+        if link
+            .expires_at
+            .is_some_and(|expires_at| expires_at <= Utc::now())
+        {
+            return Err(DBError::Conflict(
+                "This payment link has expired".to_string(),
+            ));
+        }
+        if link.amount.is_some_and(|fixed| fixed != amount) {
+            return Err(DBError::Conflict(
+                "The amount must match the amount of the payment link".to_string(),
+            ));
+        }
+        if link.account_id == payer_account_id {
+            return Err(DBError::Conflict(
+                "A payment link cannot be paid from its own account".to_string(),
+            ));
+        }
+
+        let transaction =
+            execute_transfer(&txn, payer_account_id, link.account_id, amount, false).await?;
+
+        let payment_count = link.payment_count + 1;
+        let collected_amount = link.collected_amount + amount;
+        let single_use = link.single_use;
+        let mut active_model: ActiveModel = link.into();
+        active_model.payment_count = Set(payment_count);
+        active_model.collected_amount = Set(collected_amount);
+        if single_use {
+            active_model.active = Set(false);
+        }
+        active_model.updated_at = Set(Utc::now().fixed_offset());
+        let link = active_model.update(&txn).await?;
+
+        txn.commit().await?;
+        Ok((link, transaction))
+    }
+}
@@ -0,0 +1,223 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+use entity::payment_intents::{ActiveModel, Column, Model};
+use entity::prelude::PaymentIntents;
+use entity::transactions;
+use sea_orm::prelude::Json;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, QueryOrder,
+    QuerySelect, Set, TransactionTrait,
+};
+
+pub use entity::sea_orm_active_enums::PaymentIntentStatus;
+
+use crate::db_conn::DB;
+use crate::pagination::{Keyset, Page, PageRequest};
+use crate::transactions::execute_transfer;
+use crate::util::{to_decimal, DBError};
+use crate::webhooks::{enqueue_event, payment_intent_data, WebhookEventType};
+
+pub struct PaymentIntentImpl {
+    db: Arc<DB>,
+}
+
+impl PaymentIntentImpl {
+    pub fn new(db: Arc<DB>) -> Self {
+        Self { db }
+    }
+
+    /// Creates a payment intent that waits for a customer to pay it
+    pub async fn create_intent(
+        &self,
+        merchant_account_id: i32,
+        amount: f64,
+        currency: String,
+        metadata: Option<Json>,
+        created_by: i32,
+    ) -> Result<Model, DBError> {
+        let db = self.db.get()?;
+        let now = Utc::now().fixed_offset();
+        let intent = ActiveModel {
+            merchant_account_id: Set(merchant_account_id),
+            amount: Set(to_decimal(amount)?),
+            currency: Set(currency),
+            metadata: Set(metadata),
+            status: Set(PaymentIntentStatus::RequiresPayment),
+            created_by: Set(created_by),
+            created_at: Set(now),
+            updated_at: Set(now),
+            ..Default::default()
+        };
+        Ok(intent.insert(db).await?)
+    }
+
+    pub async fn find_intent(&self, id: i32) -> Result<Option<Model>, DBError> {
+        let db = self.db.get()?;
+        let intent = PaymentIntents::find_by_id(id).one(db).await?;
+        Ok(intent)
+    }
+
+    /// Payment intents of the given merchant accounts, newest first
+    pub async fn list_intents(
+        &self,
+        merchant_account_ids: &[i32],
+        status: Option<PaymentIntentStatus>,
+        page: PageRequest,
+    ) -> Result<Page<Model>, DBError> {
+        let db = self.db.get()?;
+        let mut query = PaymentIntents::find()
+            .filter(Column::MerchantAccountId.is_in(merchant_account_ids.to_vec()));
+        if let Some(status) = status {
+            query = query.filter(Column::Status.eq(status));
+        }
+        if let Some(after) = page.after {
+            query = query.filter(after.after(Column::CreatedAt, Column::Id, true));
+        }
+
+        let intents = query
+            .order_by_desc(Column::CreatedAt)
+            .order_by_desc(Column::Id)
+            .limit(page.limit + 1)
+            .all(db)
+            .await?;
+
+        Ok(Page::from_rows(intents, page.limit, |intent| {
+            Keyset::new(intent.created_at, intent.id)
+        }))
+    }
+
+    /// Pays an intent from the customer's account.
+    /// The intent is first claimed as `processing`, which keeps a second confirmation from
+    /// paying it twice, and then settled by a transfer to the merchant account. If the
+    /// transfer fails, the intent goes back to `requires_payment` and can be paid again.
+    pub async fn confirm(
+        &self,
+        id: i32,
+        customer_account_id: i32,
+    ) -> Result<(Model, transactions::Model), DBError> {
+        let db = self.db.get()?;
+
+        let txn = db.begin().await?;
+        let intent = lock_intent(&txn, id).await?;
+        if intent.status != PaymentIntentStatus::RequiresPayment {
+            return Err(DBError::Conflict(
+                "This payment intent can no longer be paid".to_string(),
+            ));
+        }
+        if intent.merchant_account_id == customer_account_id {
+            return Err(DBError::Conflict(
+                "A payment intent cannot be paid from the merchant account".to_string(),
+            ));
+        }
+        let mut active_model: ActiveModel = intent.into();
+        active_model.status = Set(PaymentIntentStatus::Processing);
+        active_model.customer_account_id = Set(Some(customer_account_id));
+        active_model.updated_at = Set(Utc::now().fixed_offset());
+        active_model.update(&txn).await?;
+        txn.commit().await?;
+
+        match self.settle(id).await {
+            Ok(settled) => Ok(settled),
+            Err(err) => {
+                self.release(id).await?;
+                Err(err)
+            }
+        }
+    }
+
+    /// Cancels an intent that has not been paid yet. An intent left in `processing`
+    /// can be canceled too; its settlement then stops before any money moves.
+    pub async fn cancel(&self, id: i32) -> Result<Model, DBError> {
+        let db = self.db.get()?;
+        let txn = db.begin().await?;
+
+        let intent = lock_intent(&txn, id).await?;
+        if !matches!(
+            intent.status,
+            PaymentIntentStatus::RequiresPayment | PaymentIntentStatus::Processing
+        ) {
+            return Err(DBError::Conflict(
+                "Only unpaid payment intents can be canceled".to_string(),
+            ));
+        }
+
+        let mut active_model: ActiveModel = intent.into();
+        active_model.status = Set(PaymentIntentStatus::Canceled);
+        active_model.updated_at = Set(Utc::now().fixed_offset());
+        let intent = active_model.update(&txn).await?;
+
+        txn.commit().await?;
+        Ok(intent)
+    }
+
+    /// Moves the money of a `processing` intent and marks it `succeeded` in one
+    /// database transaction
+    async fn settle(&self, id: i32) -> Result<(Model, transactions::Model), DBError> {
+        let db = self.db.get()?;
+        let txn = db.begin().await?;
+
+        let intent = lock_intent(&txn, id).await?;
+        let Some(customer_account_id) = intent.customer_account_id else {
+            return Err(DBError::Conflict(
+                "This payment intent can no longer be paid".to_string(),
+            ));
+        };
+        if intent.status != PaymentIntentStatus::Processing {
+            return Err(DBError::Conflict(
+                "This payment intent was canceled before it was paid".to_string(),
+            ));
+        }
+
+        let transaction = execute_transfer(
+            &txn,
+            customer_account_id,
+            intent.merchant_account_id,
+            intent.amount,
+            false,
+        )
+        .await?;
+
+        let mut active_model: ActiveModel = intent.into();
+        active_model.status = Set(PaymentIntentStatus::Succeeded);
+        active_model.transaction_id = Set(Some(transaction.id));
+        active_model.updated_at = Set(Utc::now().fixed_offset());
+        let intent = active_model.update(&txn).await?;
+        enqueue_event(
+            &txn,
+            WebhookEventType::PaymentIntentSucceeded,
+            &[intent.merchant_account_id, customer_account_id],
+            payment_intent_data(&intent),
+        )
+        .await?;
+
+        txn.commit().await?;
+        Ok((intent, transaction))
+    }
+
+    /// Returns a `processing` intent whose settlement failed to `requires_payment`
+    async fn release(&self, id: i32) -> Result<(), DBError> {
+        let db = self.db.get()?;
+        let txn = db.begin().await?;
+
+        let intent = lock_intent(&txn, id).await?;
+        if intent.status == PaymentIntentStatus::Processing {
+            let mut active_model: ActiveModel = intent.into();
+            active_model.status = Set(PaymentIntentStatus::RequiresPayment);
+            active_model.customer_account_id = Set(None);
+            active_model.updated_at = Set(Utc::now().fixed_offset());
+            active_model.update(&txn).await?;
+        }
+
+        txn.commit().await?;
+        Ok(())
+    }
+}
+
+async fn lock_intent<C: ConnectionTrait>(conn: &C, id: i32) -> Result<Model, DBError> {
+    PaymentIntents::find_by_id(id)
+        .lock_exclusive()
+        .one(conn)
+        .await?
+        .ok_or(DBError::NotFound("Payment intent"))
+}
@@ -0,0 +1,237 @@
+use std::sync::Arc;
+
+use chrono::{Duration, Utc};
+use entity::prelude::RefreshTokens;
+use entity::refresh_tokens::{ActiveModel, Column};
+use rand::distributions::{Alphanumeric, DistString};
+use sea_orm::prelude::Expr;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, QuerySelect, Set,
+    TransactionTrait,
+};
+
+use crate::db_conn::DB;
+use crate::util::{secret_hash, DBError};
+
+/// Start of every refresh token
+const TOKEN_PREFIX: &str = "rt_";
+/// Length of the random part of a token
+const TOKEN_LENGTH: usize = 48;
+/// Length of the id shared by the tokens of one login
+const FAMILY_ID_LENGTH: usize = 32;
+
+/// What came of exchanging a refresh token
+pub enum Rotation {
+    /// The token was valid and has been replaced by `token`, which belongs to the same
+    /// family and is returned only here. `organization_id` is the organization the
+    /// family acts for.
+    Rotated {
+        user_id: i32,
+        organization_id: Option<i32>,
+        token: String,
+    },
+    /// The token is unknown, expired or was revoked
+    Invalid,
+    /// The token had already been exchanged, so it was most likely stolen: its whole
+    /// family has been revoked
+    Reused { user_id: i32 },
+}
+
+pub struct RefreshTokenImpl {
+    db: Arc<DB>,
+}
+
+impl RefreshTokenImpl {
+    pub fn new(db: Arc<DB>) -> Self {
+        Self { db }
+    }
+
+    /// Starts a new family of refresh tokens for a login, acting for `organization_id`
+    /// if set, and returns its first token, valid for `ttl`. Only its hash is stored.
+    pub async fn issue(
+        &self,
+        user_id: i32,
+        organization_id: Option<i32>,
+        ttl: Duration,
+    ) -> Result<String, DBError> {
+        let db = self.db.get()?;
+        let family_id = Alphanumeric.sample_string(&mut rand::thread_rng(), FAMILY_ID_LENGTH);
+        insert_token(db, user_id, organization_id, family_id, ttl).await
+    }
+
+    /// Exchanges a refresh token for a new one of the same family, valid for `ttl`. Each
+    /// token can be exchanged once; presenting it again revokes its family.
+    pub async fn rotate(&self, token: &str, ttl: Duration) -> Result<Rotation, DBError> {
+        let db = self.db.get()?;
+        let txn = db.begin().await?;
+
+        let Some(current) = RefreshTokens::find()
+            .filter(Column::TokenHash.eq(secret_hash(token)))
+            .lock_exclusive()
+            .one(&txn)
+            .await?
+        else {
+            return Ok(Rotation::Invalid);
+        };
+
+        let now = Utc::now().fixed_offset();
+        if current.used_at.is_some() {
+            revoke_family(&txn, &current.family_id).await?;
+            txn.commit().await?;
+            return Ok(Rotation::Reused {
+                user_id: current.user_id,
+            });
+        }
+        if current.revoked_at.is_some() || current.expires_at <= now {
+            return Ok(Rotation::Invalid);
+        }
+
+        let user_id = current.user_id;
+        let organization_id = current.organization_id;
+        let family_id = current.family_id.clone();
+        let mut active_model: ActiveModel = current.into();
+        active_model.used_at = Set(Some(now));
+        active_model.update(&txn).await?;
+        let token = insert_token(&txn, user_id, organization_id, family_id, ttl).await?;
+
+        txn.commit().await?;
+        Ok(Rotation::Rotated {
+            user_id,
+            organization_id,
+            token,
+        })
+    }
+
+    /// Revokes the family of a refresh token, ending that login. Returns the user of the
+    /// token, or `None` if it is unknown.
+    pub async fn revoke(&self, token: &str) -> Result<Option<i32>, DBError> {
+        let db = self.db.get()?;
+        let Some(current) = RefreshTokens::find()
+            .filter(Column::TokenHash.eq(secret_hash(token)))
+            .one(db)
+            .await?
+        else {
+            return Ok(None);
+        };
+        revoke_family(db, &current.family_id).await?;
+        Ok(Some(current.user_id))
+    }
+
+    /// Revokes every refresh token of a user, ending all of their logins
+    pub async fn revoke_all(&self, user_id: i32) -> Result<(), DBError> {
+        let db = self.db.get()?;
+        RefreshTokens::update_many()
+            .col_expr(Column::RevokedAt, Expr::value(Utc::now().fixed_offset()))
+            .filter(Column::UserId.eq(user_id))
+            .filter(Column::RevokedAt.is_null())
+            .exec(db)
+            .await?;
+        Ok(())
+    }
+}
+
+async fn insert_token(
+    conn: &impl ConnectionTrait,
+    user_id: i32,
+    organization_id: Option<i32>,
+    family_id: String,
+    ttl: Duration,
+) -> Result<String, DBError> {
+    let token = format!(
+        "{TOKEN_PREFIX}{}",
+        Alphanumeric.sample_string(&mut rand::thread_rng(), TOKEN_LENGTH)
+    );
+    let now = Utc::now().fixed_offset();
+    ActiveModel {
+        user_id: Set(user_id),
+        organization_id: Set(organization_id),
+        family_id: Set(family_id),
+        token_hash: Set(secret_hash(&token)),
+        expires_at: Set(now + ttl),
+        created_at: Set(now),
+        ..Default::default()
+    }
+    .insert(conn)
+    .await?;
+    Ok(token)
+}
+
+async fn revoke_family(conn: &impl ConnectionTrait, family_id: &str) -> Result<(), DBError> {
+    RefreshTokens::update_many()
+        .col_expr(Column::RevokedAt, Expr::value(Utc::now().fixed_offset()))
+        .filter(Column::FamilyId.eq(family_id))
+        .filter(Column::RevokedAt.is_null())
+        .exec(conn)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use entity::refresh_tokens::Model;
+    use sea_orm::{DatabaseBackend, MockDatabase, MockExecResult};
+
+    use super::*;
+
+    fn stored(used: bool, revoked: bool, expires_in: Duration) -> Model {
+        let now = Utc::now().fixed_offset();
+        Model {
+            id: 9,
+            user_id: 2,
+            family_id: "family-of-the-login".to_string(),
+            token_hash: secret_hash("rt_presented"),
+            expires_at: now + expires_in,
+            used_at: used.then_some(now),
+            revoked_at: revoked.then_some(now),
+            created_at: now,
+            organization_id: None,
+        }
+    }
+
+    /// Rotates `rt_presented` against a database holding `token`, returning the
+    /// outcome and the statements that were run
+    async fn rotate(token: Model, revoked: u64) -> (Rotation, String) {
+        let connection = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([[token]])
+            .append_exec_results([MockExecResult {
+                last_insert_id: 0,
+                rows_affected: revoked,
+            }])
+            .into_connection();
+        let db = Arc::new(DB::mock(connection));
+        let rotation = RefreshTokenImpl::new(db.clone())
+            .rotate("rt_presented", Duration::days(30))
+            .await
+            .unwrap();
+
+        let log = Arc::into_inner(db).unwrap().into_transaction_log();
+        (rotation, format!("{log:?}"))
+    }
+
+    #[tokio::test]
+    async fn reusing_a_token_revokes_its_whole_family() {
+        let (rotation, log) = rotate(stored(true, false, Duration::days(1)), 3).await;
+
+        assert!(matches!(rotation, Rotation::Reused { user_id: 2 }));
+        assert!(log.contains(
+            r#"SET \"revoked_at\" = $1 WHERE \"refresh_tokens\".\"family_id\" = $2 AND \"refresh_tokens\".\"revoked_at\" IS NULL""#
+        ));
+        assert!(log.contains(r#"String(Some("family-of-the-login"))"#));
+        assert!(log.contains(r#"sql: "COMMIT""#));
+        assert!(!log.contains(r#"sql: "INSERT"#));
+    }
+
+    #[tokio::test]
+    async fn revoked_and_expired_tokens_are_refused_without_changes() {
+        for token in [
+            stored(false, true, Duration::days(1)),
+            stored(false, false, Duration::seconds(-1)),
+        ] {
+            let (rotation, log) = rotate(token, 0).await;
+
+            assert!(matches!(rotation, Rotation::Invalid));
+            assert!(!log.contains(r#"sql: "UPDATE"#));
+            assert!(!log.contains(r#"sql: "INSERT"#));
+        }
+    }
+}
@@ -0,0 +1,376 @@
+use std::env;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use chrono::Utc;
+use entity::prelude::{SplitPaymentLegs, SplitPayments};
+use entity::split_payment_legs;
+use entity::split_payments::{ActiveModel, Column, Model};
+use sea_orm::prelude::Decimal;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, Condition, ConnectionTrait, EntityTrait, QueryFilter,
+    QueryOrder, QuerySelect, QueryTrait, Set, TransactionTrait,
+};
+
+pub use entity::sea_orm_active_enums::SplitLegKind;
+
+use crate::db_conn::DB;
+use crate::pagination::{Keyset, Page, PageRequest};
+use crate::transactions::execute_transfer;
+use crate::util::{to_cents, to_decimal, DBError};
+
+/// How much of a split payment one recipient receives
+#[derive(Clone, Copy, Debug)]
+pub enum SplitRule {
+    /// Percent of what is left after the platform fee and the fixed shares
+    Percentage(f64),
+    /// An exact amount
+    Fixed(f64),
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SplitRecipient {
+    pub account_id: i32,
+    pub rule: SplitRule,
+}
+
+/// A payment to be split across recipients
+#[derive(Clone, Debug)]
+pub struct SplitInput {
+    pub amount: f64,
+    pub description: Option<String>,
+    pub recipients: Vec<SplitRecipient>,
+}
+
+/// The platform's cut of every split payment and the house account it is paid into
+#[derive(Clone, Debug)]
+pub struct PlatformFee {
+    pub account_id: i32,
+    /// Percent of the payment amount
+    pub percent: Decimal,
+    /// Added to the percentage on every payment
+    pub fixed: Decimal,
+}
+
+impl PlatformFee {
+    /// Reads the house account from `PLATFORM_ACCOUNT_ID` and the fee from
+    /// `PLATFORM_FEE_PERCENT` and `PLATFORM_FEE_FIXED` (both default to 0).
+    /// Without a house account, split payments carry no fee.
+    pub fn from_env() -> Option<Self> {
+        let account_id = env::var("PLATFORM_ACCOUNT_ID").ok()?;
+        let decimal = |name: &str| {
+            env::var(name).map_or(Decimal::ZERO, |value| {
+                Decimal::from_str(value.trim())
+                    .unwrap_or_else(|_| panic!("{name} must be a decimal number"))
+            })
+        };
+        Some(Self {
+            account_id: account_id
+                .trim()
+                .parse()
+                .expect("PLATFORM_ACCOUNT_ID must be an account id"),
+            percent: decimal("PLATFORM_FEE_PERCENT"),
+            fixed: decimal("PLATFORM_FEE_FIXED"),
+        })
+    }
+}
+
+pub struct SplitPaymentImpl {
+    db: Arc<DB>,
+    platform_fee: Option<PlatformFee>,
+}
+
+impl SplitPaymentImpl {
+    pub fn new(db: Arc<DB>) -> Self {
+        Self {
+            db,
+            platform_fee: PlatformFee::from_env(),
+        }
+    }
+
+    /// Pays one amount from `payer_account_id` to several recipients at once.
+    /// The platform fee is taken first, then the fixed shares, and the percentage shares
+    /// divide what remains; a cent left over from rounding goes to the first percentage
+    /// recipient. Every share is its own transaction, linked to the parent payment by a
+    /// leg, and either all of them are committed or none.
+    pub async fn create_split(
+        &self,
+        payer_account_id: i32,
+        created_by: i32,
+        input: SplitInput,
+    ) -> Result<(Model, Vec<split_payment_legs::Model>), DBError> {
+        let platform_fee = self.platform_fee.as_ref();
+        if platform_fee.is_some_and(|fee| fee.account_id == payer_account_id) {
+            return Err(DBError::Conflict(
+                "Split payments cannot be made from the platform account".to_string(),
+            ));
+        }
+        let amount = to_cents(input.amount)?;
+        let fee = platform_fee
+            .map(|fee| (amount * fee.percent / Decimal::ONE_HUNDRED + fee.fixed).round_dp(2))
+            .unwrap_or(Decimal::ZERO);
+        let shares = allocate(amount - fee, &input.recipients)?;
+
+        let db = self.db.get()?;
+        let txn = db.begin().await?;
+
+        let split = ActiveModel {
+            payer_account_id: Set(payer_account_id),
+            created_by: Set(created_by),
+            amount: Set(amount),
+            platform_fee: Set(fee),
+            description: Set(input.description),
+            created_at: Set(Utc::now().fixed_offset()),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+
+        let mut legs = Vec::with_capacity(shares.len() + 1);
+        for (recipient, share) in input.recipients.iter().zip(shares) {
+            let (kind, rule_value) = match recipient.rule {
+                SplitRule::Percentage(percent) => (SplitLegKind::Percentage, percent),
+                SplitRule::Fixed(fixed) => (SplitLegKind::Fixed, fixed),
+            };
+            let rule_value = to_decimal(rule_value)?;
+            legs.push(pay_leg(&txn, &split, recipient.account_id, kind, rule_value, share).await?);
+        }
+        if let Some(platform_fee) = platform_fee.filter(|_| fee > Decimal::ZERO) {
+            legs.push(
+                pay_leg(
+                    &txn,
+                    &split,
+                    platform_fee.account_id,
+                    SplitLegKind::PlatformFee,
+                    platform_fee.percent,
+                    fee,
+                )
+                .await?,
+            );
+        }
+
+        txn.commit().await?;
+        Ok((split, legs))
+    }
+
+    pub async fn find_split(&self, id: i32) -> Result<Option<Model>, DBError> {
+        let db = self.db.get()?;
+        let split = SplitPayments::find_by_id(id).one(db).await?;
+        Ok(split)
+    }
+
+    /// Legs of a split payment in the order they were paid
+    pub async fn list_legs(
+        &self,
+        split_payment_id: i32,
+    ) -> Result<Vec<split_payment_legs::Model>, DBError> {
+        let db = self.db.get()?;
+        let legs = SplitPaymentLegs::find()
+            .filter(split_payment_legs::Column::SplitPaymentId.eq(split_payment_id))
+            .order_by_asc(split_payment_legs::Column::Id)
+            .all(db)
+            .await?;
+        Ok(legs)
+    }
+
+    /// Split payments paid from or into the given accounts, newest first
+    pub async fn list_splits(
+        &self,
+        account_ids: &[i32],
+        page: PageRequest,
+    ) -> Result<Page<Model>, DBError> {
+        let db = self.db.get()?;
+        let received = SplitPaymentLegs::find()
+            .select_only()
+            .column(split_payment_legs::Column::SplitPaymentId)
+            .filter(split_payment_legs::Column::RecipientAccountId.is_in(account_ids.to_vec()))
+            .into_query();
+        let mut query = SplitPayments::find().filter(
+            Condition::any()
+                .add(Column::PayerAccountId.is_in(account_ids.to_vec()))
+                .add(Column::Id.in_subquery(received)),
+        );
+        if let Some(after) = page.after {
+            query = query.filter(after.after(Column::CreatedAt, Column::Id, true));
+        }
+
+        let splits = query
+            .order_by_desc(Column::CreatedAt)
+            .order_by_desc(Column::Id)
+            .limit(page.limit + 1)
+            .all(db)
+            .await?;
+
+        Ok(Page::from_rows(splits, page.limit, |split| {
+            Keyset::new(split.created_at, split.id)
+        }))
+    }
+}
+
+/// Divides `net` between the recipients following their rules, in cents
+fn allocate(net: Decimal, recipients: &[SplitRecipient]) -> Result<Vec<Decimal>, DBError> {
+    if net <= Decimal::ZERO {
+        return Err(DBError::Conflict(
+            "The amount does not cover the platform fee".to_string(),
+        ));
+    }
+
+    let fixed_total: Decimal = recipients
+        .iter()
+        .filter_map(|recipient| match recipient.rule {
+            SplitRule::Fixed(fixed) => Some(to_cents(fixed)),
+            SplitRule::Percentage(_) => None,
+        })
+        .sum::<Result<_, _>>()?;
+    let rest = net - fixed_total;
+    let has_percentages = recipients
+        .iter()
+        .any(|recipient| matches!(recipient.rule, SplitRule::Percentage(_)));
+    if rest < Decimal::ZERO || (!has_percentages && !rest.is_zero()) {
+        return Err(DBError::Conflict(format!(
+            "The fixed shares must add up to {net} after the platform fee{}",
+            if has_percentages { " or less" } else { "" }
+        )));
+    }
+
+    let mut shares: Vec<Decimal> = recipients
+        .iter()
+        .map(|recipient| match recipient.rule {
+            SplitRule::Fixed(fixed) => to_cents(fixed),
+            SplitRule::Percentage(percent) => {
+                let percent = to_decimal(percent)?.round_dp(4);
+                Ok((rest * percent / Decimal::ONE_HUNDRED).trunc_with_scale(2))
+            }
+        })
+        .collect::<Result<_, _>>()?;
+    let remainder = net - shares.iter().sum::<Decimal>();
+    if let Some(first) = recipients
+        .iter()
+        .position(|recipient| matches!(recipient.rule, SplitRule::Percentage(_)))
+    {
+        shares[first] += remainder;
+    }
+
+    if shares.iter().any(|share| *share <= Decimal::ZERO) {
+        return Err(DBError::Conflict(
+            "Every recipient must receive a positive amount".to_string(),
+        ));
+    }
+    Ok(shares)
+}
+
+async fn pay_leg<C: ConnectionTrait>(
+    conn: &C,
+    split: &Model,
+    recipient_account_id: i32,
+    kind: SplitLegKind,
+    rule_value: Decimal,
+    amount: Decimal,
+) -> Result<split_payment_legs::Model, DBError> {
+    let transaction = execute_transfer(
+        conn,
+        split.payer_account_id,
+        recipient_account_id,
+        amount,
+        false,
+    )
+    .await?;
+    let leg = split_payment_legs::ActiveModel {
+        split_payment_id: Set(split.id),
+        recipient_account_id: Set(recipient_account_id),
+        kind: Set(kind),
+        rule_value: Set(rule_value),
+        amount: Set(amount),
+        transaction_id: Set(transaction.id),
+        ..Default::default()
+    };
+    Ok(leg.insert(conn).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::dec;
+
+    fn percentage(percent: f64) -> SplitRecipient {
+        SplitRecipient {
+            account_id: 1,
+            rule: SplitRule::Percentage(percent),
+        }
+    }
+
+    fn fixed(amount: f64) -> SplitRecipient {
+        SplitRecipient {
+            account_id: 1,
+            rule: SplitRule::Fixed(amount),
+        }
+    }
+
+    #[test]
+    fn the_first_percentage_share_takes_the_rounding_remainder() {
+        let shares = allocate(
+            dec("10.00"),
+            &[percentage(33.33), percentage(33.33), percentage(33.34)],
+        )
+        .unwrap();
+        assert_eq!(shares, [dec("3.34"), dec("3.33"), dec("3.33")]);
+    }
+
+    #[test]
+    fn percentages_are_not_skewed_by_floating_point() {
+        let shares = allocate(dec("100.00"), &[percentage(85.9), percentage(14.1)]).unwrap();
+        assert_eq!(shares, [dec("85.90"), dec("14.10")]);
+    }
+
+    #[test]
+    fn percentages_truncate_to_the_cent() {
+        let shares = allocate(dec("10.01"), &[percentage(50.0), percentage(50.0)]).unwrap();
+        assert_eq!(shares, [dec("5.01"), dec("5.00")]);
+    }
+
+    #[test]
+    fn percentages_split_what_the_fixed_shares_leave() {
+        let shares = allocate(
+            dec("99.99"),
+            &[fixed(20.0), percentage(50.0), percentage(50.0)],
+        )
+        .unwrap();
+        assert_eq!(shares, [dec("20.00"), dec("40.00"), dec("39.99")]);
+        assert_eq!(shares.iter().sum::<Decimal>(), dec("99.99"));
+    }
+
+    #[test]
+    fn shares_always_add_up_to_the_net_amount() {
+        for cents in [1_i64, 7, 99, 1001, 33333, 123457] {
+            let net = Decimal::new(cents * 3, 2);
+            let shares =
+                allocate(net, &[percentage(12.5), percentage(37.5), percentage(50.0)]).unwrap();
+            assert_eq!(shares.iter().sum::<Decimal>(), net);
+        }
+    }
+
+    #[test]
+    fn fixed_shares_alone_must_match_the_net_amount() {
+        assert!(allocate(dec("30.00"), &[fixed(10.0), fixed(20.0)]).is_ok());
+        assert!(matches!(
+            allocate(dec("30.00"), &[fixed(10.0), fixed(10.0)]),
+            Err(DBError::Conflict(_))
+        ));
+        assert!(matches!(
+            allocate(dec("30.00"), &[fixed(25.0), percentage(100.0), fixed(10.0)]),
+            Err(DBError::Conflict(_))
+        ));
+    }
+
+    #[test]
+    fn every_recipient_must_receive_something() {
+        assert!(matches!(
+            allocate(dec("0.01"), &[percentage(50.0), percentage(50.0)]),
+            Err(DBError::Conflict(_))
+        ));
+        assert!(matches!(
+            allocate(Decimal::ZERO, &[percentage(100.0)]),
+            Err(DBError::Conflict(_))
+        ));
+    }
+}
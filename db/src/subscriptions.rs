@@ -0,0 +1,676 @@
+use std::sync::Arc;
+
+use chrono::{Duration, Months, Utc};
+use entity::prelude::{SubscriptionCharges, SubscriptionPlans, Subscriptions};
+use entity::subscriptions::{ActiveModel, Column, Model};
+use entity::{subscription_charges, subscription_plans};
+use sea_orm::prelude::{DateTimeWithTimeZone, Decimal};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, Condition, ConnectionTrait, EntityTrait, QueryFilter,
+    QueryOrder, QuerySelect, Set, TransactionTrait,
+};
+
+pub use entity::sea_orm_active_enums::{
+    PlanInterval, SubscriptionChargeKind, SubscriptionChargeStatus, SubscriptionStatus,
+};
+
+use crate::db_conn::DB;
+use crate::pagination::{Keyset, Page, PageRequest};
+use crate::transactions::execute_transfer;
+use crate::util::{to_cents, DBError};
+
+/// Hours to wait before each retry of a failed renewal. A subscription is canceled
+/// when the last retry fails too.
+const DUNNING_RETRY_HOURS: [i64; 3] = [24, 72, 120];
+
+/// The terms of a new plan
+#[derive(Clone, Debug)]
+pub struct PlanInput {
+    pub name: String,
+    pub amount: f64,
+    pub interval: PlanInterval,
+    /// Number of intervals in one billing period
+    pub interval_count: i32,
+    pub trial_days: i32,
+}
+
+/// Which subscriptions `SubscriptionImpl::list_subscriptions` returns: those of
+/// `subscriber_user_id` and those to plans of `merchant_account_ids`
+#[derive(Clone, Debug, Default)]
+pub struct SubscriptionFilter {
+    pub subscriber_user_id: Option<i32>,
+    pub merchant_account_ids: Vec<i32>,
+    pub status: Option<SubscriptionStatus>,
+}
+
+pub struct SubscriptionImpl {
+    db: Arc<DB>,
+}
+
+impl SubscriptionImpl {
+    pub fn new(db: Arc<DB>) -> Self {
+        Self { db }
+    }
+
+    /// Creates a plan that bills its amount into the merchant account every
+    /// `interval_count` intervals
+    pub async fn create_plan(
+        &self,
+        merchant_account_id: i32,
+        created_by: i32,
+        input: PlanInput,
+    ) -> Result<subscription_plans::Model, DBError> {
+        let db = self.db.get()?;
+        let now = Utc::now().fixed_offset();
+        let plan = subscription_plans::ActiveModel {
+            merchant_account_id: Set(merchant_account_id),
+            name: Set(input.name),
+            amount: Set(to_cents(input.amount)?),
+            interval: Set(input.interval),
+            interval_count: Set(input.interval_count),
+            trial_days: Set(input.trial_days),
+            active: Set(true),
+            created_by: Set(created_by),
+            created_at: Set(now),
+            updated_at: Set(now),
+            ..Default::default()
+        };
+        Ok(plan.insert(db).await?)
+    }
+
+    pub async fn find_plan(&self, id: i32) -> Result<Option<subscription_plans::Model>, DBError> {
+        let db = self.db.get()?;
+        let plan = SubscriptionPlans::find_by_id(id).one(db).await?;
+        Ok(plan)
+    }
+
+    /// Plans of the given merchant accounts, newest first
+    pub async fn list_plans(
+        &self,
+        merchant_account_ids: &[i32],
+        page: PageRequest,
+    ) -> Result<Page<subscription_plans::Model>, DBError> {
+        let db = self.db.get()?;
+        let mut query = SubscriptionPlans::find().filter(
+            subscription_plans::Column::MerchantAccountId.is_in(merchant_account_ids.to_vec()),
+        );
+        if let Some(after) = page.after {
+            query = query.filter(after.after(
+                subscription_plans::Column::CreatedAt,
+                subscription_plans::Column::Id,
+                true,
+            ));
+        }
+
+        let plans = query
+            .order_by_desc(subscription_plans::Column::CreatedAt)
+            .order_by_desc(subscription_plans::Column::Id)
+            .limit(page.limit + 1)
+            .all(db)
+            .await?;
+
+        Ok(Page::from_rows(plans, page.limit, |plan| {
+            Keyset::new(plan.created_at, plan.id)
+        }))
+    }
+
+    /// Closes a plan to new subscribers. Existing subscriptions keep renewing.
+    pub async fn deactivate_plan(&self, id: i32) -> Result<subscription_plans::Model, DBError> {
+        let db = self.db.get()?;
+        let plan = subscription_plans::ActiveModel {
+            id: Set(id),
+            active: Set(false),
+            updated_at: Set(Utc::now().fixed_offset()),
+            ..Default::default()
+        };
+        Ok(plan.update(db).await?)
+    }
+
+    /// Subscribes a user to a plan, paying from `payer_account_id`.
+    /// Plans with a trial start `trialing` and are first charged when the trial ends;
+    /// otherwise the first period is charged right away and the subscription is only
+    /// created if that charge succeeds.
+    pub async fn subscribe(
+        &self,
+        plan_id: i32,
+        subscriber_user_id: i32,
+        payer_account_id: i32,
+    ) -> Result<Model, DBError> {
+        let db = self.db.get()?;
+        let txn = db.begin().await?;
+
+        let plan = SubscriptionPlans::find_by_id(plan_id)
+            .lock_shared()
+            .one(&txn)
+            .await?
+            .ok_or(DBError::NotFound("Plan"))?;
+        if !plan.active {
+            return Err(DBError::Conflict(
+                "This plan no longer accepts subscribers".to_string(),
+            ));
+        }
+        if plan.merchant_account_id == payer_account_id {
+            return Err(DBError::Conflict(
+                "A subscription cannot be paid from the merchant account".to_string(),
+            ));
+        }
+        let subscribed = Subscriptions::find()
+            .filter(Column::PlanId.eq(plan.id))
+            .filter(Column::SubscriberUserId.eq(subscriber_user_id))
+            .filter(Column::Status.ne(SubscriptionStatus::Canceled))
+            .one(&txn)
+            .await?;
+        if subscribed.is_some() {
+            return Err(DBError::Conflict(
+                "You are already subscribed to this plan".to_string(),
+            ));
+        }
+
+        let now = Utc::now().fixed_offset();
+        let mut subscription = ActiveModel {
+            plan_id: Set(plan.id),
+            subscriber_user_id: Set(subscriber_user_id),
+            payer_account_id: Set(payer_account_id),
+            current_period_start: Set(now),
+            failed_attempts: Set(0),
+            credit: Set(Decimal::ZERO),
+            cancel_at_period_end: Set(false),
+            created_at: Set(now),
+            updated_at: Set(now),
+            ..Default::default()
+        };
+
+        let subscription = if plan.trial_days > 0 {
+            let trial_end = now + Duration::days(plan.trial_days.into());
+            subscription.status = Set(SubscriptionStatus::Trialing);
+            subscription.current_period_end = Set(trial_end);
+            subscription.trial_end = Set(Some(trial_end));
+            subscription.next_charge_at = Set(Some(trial_end));
+            subscription.insert(&txn).await?
+        } else {
+            let transaction = execute_transfer(
+                &txn,
+                payer_account_id,
+                plan.merchant_account_id,
+                plan.amount,
+                false,
+            )
+            .await?;
+            let period_end = add_interval(now, &plan);
+            subscription.status = Set(SubscriptionStatus::Active);
+            subscription.current_period_end = Set(period_end);
+            subscription.next_charge_at = Set(Some(period_end));
+            let subscription = subscription.insert(&txn).await?;
+            record_charge(
+                &txn,
+                &subscription,
+                SubscriptionChargeKind::Initial,
+                plan.amount,
+                Ok(Some(transaction.id)),
+            )
+            .await?;
+            subscription
+        };
+
+        txn.commit().await?;
+        Ok(subscription)
+    }
+
+    pub async fn find_subscription(&self, id: i32) -> Result<Option<Model>, DBError> {
+        let db = self.db.get()?;
+        let subscription = Subscriptions::find_by_id(id).one(db).await?;
+        Ok(subscription)
+    }
+
+    /// Subscriptions matching the filter, newest first
+    pub async fn list_subscriptions(
+        &self,
+        filter: &SubscriptionFilter,
+        page: PageRequest,
+    ) -> Result<Page<Model>, DBError> {
+        let db = self.db.get()?;
+
+        let mut scope = Condition::any();
+        if let Some(subscriber_user_id) = filter.subscriber_user_id {
+            scope = scope.add(Column::SubscriberUserId.eq(subscriber_user_id));
+        }
+        if !filter.merchant_account_ids.is_empty() {
+            let plan_ids: Vec<i32> = SubscriptionPlans::find()
+                .filter(
+                    subscription_plans::Column::MerchantAccountId
+                        .is_in(filter.merchant_account_ids.clone()),
+                )
+                .all(db)
+                .await?
+                .into_iter()
+                .map(|plan| plan.id)
+                .collect();
+            scope = scope.add(Column::PlanId.is_in(plan_ids));
+        }
+
+        let mut condition = Condition::all()
+            .add(scope)
+            .add_option(filter.status.map(|status| Column::Status.eq(status)));
+        if let Some(after) = page.after {
+            condition = condition.add(after.after(Column::CreatedAt, Column::Id, true));
+        }
+
+        let subscriptions = Subscriptions::find()
+            .filter(condition)
+            .order_by_desc(Column::CreatedAt)
+            .order_by_desc(Column::Id)
+            .limit(page.limit + 1)
+            .all(db)
+            .await?;
+
+        Ok(Page::from_rows(subscriptions, page.limit, |subscription| {
+            Keyset::new(subscription.created_at, subscription.id)
+        }))
+    }
+
+    /// Charges of a subscription, newest first
+    pub async fn list_charges(
+        &self,
+        subscription_id: i32,
+    ) -> Result<Vec<subscription_charges::Model>, DBError> {
+        let db = self.db.get()?;
+        let charges = SubscriptionCharges::find()
+            .filter(subscription_charges::Column::SubscriptionId.eq(subscription_id))
+            .order_by_desc(subscription_charges::Column::CreatedAt)
+            .order_by_desc(subscription_charges::Column::Id)
+            .all(db)
+            .await?;
+        Ok(charges)
+    }
+
+    /// Ids of subscriptions whose next charge is due, oldest first
+    pub async fn due_subscriptions(&self, limit: u64) -> Result<Vec<i32>, DBError> {
+        let db = self.db.get()?;
+        let ids = Subscriptions::find()
+            .filter(Column::NextChargeAt.lte(Utc::now()))
+            .filter(Column::Status.ne(SubscriptionStatus::Canceled))
+            .order_by_asc(Column::NextChargeAt)
+            .limit(limit)
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|subscription| subscription.id)
+            .collect();
+        Ok(ids)
+    }
+
+    /// Renews a due subscription: charges the next period through a transfer to the
+    /// merchant account, after taking any credit into account. A charge that fails for
+    /// lack of balance puts the subscription `past_due` and schedules a retry; once the
+    /// retries run out the subscription is canceled. Subscriptions set to cancel at the
+    /// end of the period are canceled instead of charged.
+    pub async fn renew(&self, id: i32) -> Result<Model, DBError> {
+        let db = self.db.get()?;
+        let txn = db.begin().await?;
+
+        let subscription = lock_subscription(&txn, id).await?;
+        let now = Utc::now().fixed_offset();
+        let due = subscription
+            .next_charge_at
+            .is_some_and(|next_charge_at| next_charge_at <= now);
+        if subscription.status == SubscriptionStatus::Canceled || !due {
+            return Ok(subscription);
+        }
+
+        if subscription.cancel_at_period_end {
+            let subscription = mark_canceled(&txn, subscription).await?;
+            txn.commit().await?;
+            return Ok(subscription);
+        }
+
+        let plan = find_plan(&txn, subscription.plan_id).await?;
+        let amount = (plan.amount - subscription.credit).max(Decimal::ZERO);
+        let credit = (subscription.credit - plan.amount).max(Decimal::ZERO);
+        let kind = if subscription.status == SubscriptionStatus::Trialing {
+            SubscriptionChargeKind::Initial
+        } else {
+            SubscriptionChargeKind::Renewal
+        };
+
+        let transaction_id = if amount.is_zero() {
+            None
+        } else {
+            let charged = execute_transfer(
+                &txn,
+                subscription.payer_account_id,
+                plan.merchant_account_id,
+                amount,
+                false,
+            )
+            .await;
+            match charged {
+                Ok(transaction) => Some(transaction.id),
+                Err(err @ (DBError::NotEnoughBalance | DBError::AccountNotFound)) => {
+                    txn.rollback().await?;
+                    return self.record_failed_renewal(id, kind, amount, &err).await;
+                }
+                Err(err) => return Err(err),
+            }
+        };
+
+        record_charge(&txn, &subscription, kind, amount, Ok(transaction_id)).await?;
+
+        let period_start = subscription.current_period_end;
+        let period_end = add_interval(period_start, &plan);
+        let mut active_model: ActiveModel = subscription.into();
+        active_model.status = Set(SubscriptionStatus::Active);
+        active_model.current_period_start = Set(period_start);
+        active_model.current_period_end = Set(period_end);
+        active_model.next_charge_at = Set(Some(period_end));
+        active_model.failed_attempts = Set(0);
+        active_model.credit = Set(credit);
+        active_model.updated_at = Set(now);
+        let subscription = active_model.update(&txn).await?;
+
+        txn.commit().await?;
+        Ok(subscription)
+    }
+
+    /// Moves an active or trialing subscription to another plan of the same merchant.
+    /// The unused part of the current period is credited at the old price and, for a
+    /// plan with the same billing interval, charged at the new price for the rest of
+    /// the period. A plan with a different interval starts a new period now at its full
+    /// price. Any difference owed is charged immediately; anything left over is kept as
+    /// credit for the next renewal. Trials simply continue on the new plan.
+    pub async fn change_plan(&self, id: i32, new_plan_id: i32) -> Result<Model, DBError> {
+        let db = self.db.get()?;
+        let txn = db.begin().await?;
+
+        let subscription = lock_subscription(&txn, id).await?;
+        if !matches!(
+            subscription.status,
+            SubscriptionStatus::Trialing | SubscriptionStatus::Active
+        ) {
+            return Err(DBError::Conflict(
+                "Only active or trialing subscriptions can change plans".to_string(),
+            ));
+        }
+        let old_plan = find_plan(&txn, subscription.plan_id).await?;
+        let new_plan = find_plan(&txn, new_plan_id).await?;
+        if new_plan.id == old_plan.id {
+            return Err(DBError::Conflict(
+                "The subscription is already on this plan".to_string(),
+            ));
+        }
+        if !new_plan.active || new_plan.merchant_account_id != old_plan.merchant_account_id {
+            return Err(DBError::Conflict(
+                "The new plan must be an active plan of the same merchant".to_string(),
+            ));
+        }
+
+        let now = Utc::now().fixed_offset();
+        let mut active_model: ActiveModel = subscription.clone().into();
+        active_model.plan_id = Set(new_plan.id);
+        active_model.updated_at = Set(now);
+
+        if subscription.status == SubscriptionStatus::Active {
+            let unused = unused_share(
+                subscription.current_period_start,
+                subscription.current_period_end,
+                now,
+            );
+            let unused_credit = prorate(old_plan.amount, unused);
+
+            let same_interval = new_plan.interval == old_plan.interval
+                && new_plan.interval_count == old_plan.interval_count;
+            let new_cost = if same_interval {
+                prorate(new_plan.amount, unused)
+            } else {
+                let period_end = add_interval(now, &new_plan);
+                active_model.current_period_start = Set(now);
+                active_model.current_period_end = Set(period_end);
+                active_model.next_charge_at = Set(Some(period_end));
+                new_plan.amount
+            };
+
+            let owed = new_cost - unused_credit - subscription.credit;
+            if owed > Decimal::ZERO {
+                let transaction = execute_transfer(
+                    &txn,
+                    subscription.payer_account_id,
+                    new_plan.merchant_account_id,
+                    owed,
+                    false,
+                )
+                .await?;
+                let mut charged = subscription.clone();
+                charged.plan_id = new_plan.id;
+                record_charge(
+                    &txn,
+                    &charged,
+                    SubscriptionChargeKind::Proration,
+                    owed,
+                    Ok(Some(transaction.id)),
+                )
+                .await?;
+                active_model.credit = Set(Decimal::ZERO);
+            } else {
+                active_model.credit = Set(-owed);
+            }
+        }
+
+        let subscription = active_model.update(&txn).await?;
+        txn.commit().await?;
+        Ok(subscription)
+    }
+
+    /// Cancels a subscription, either right away or when the current period (or trial)
+    /// ends. Canceling never refunds the current period.
+    pub async fn cancel(&self, id: i32, at_period_end: bool) -> Result<Model, DBError> {
+        let db = self.db.get()?;
+        let txn = db.begin().await?;
+
+        let subscription = lock_subscription(&txn, id).await?;
+        if subscription.status == SubscriptionStatus::Canceled {
+            return Err(DBError::Conflict(
+                "This subscription is already canceled".to_string(),
+            ));
+        }
+
+        let subscription = if at_period_end && subscription.status != SubscriptionStatus::PastDue {
+            let mut active_model: ActiveModel = subscription.into();
+            active_model.cancel_at_period_end = Set(true);
+            active_model.updated_at = Set(Utc::now().fixed_offset());
+            active_model.update(&txn).await?
+        } else {
+            mark_canceled(&txn, subscription).await?
+        };
+
+        txn.commit().await?;
+        Ok(subscription)
+    }
+
+    async fn record_failed_renewal(
+        &self,
+        id: i32,
+        kind: SubscriptionChargeKind,
+        amount: Decimal,
+        err: &DBError,
+    ) -> Result<Model, DBError> {
+        let db = self.db.get()?;
+        let txn = db.begin().await?;
+
+        let subscription = lock_subscription(&txn, id).await?;
+        record_charge(&txn, &subscription, kind, amount, Err(err.to_string())).await?;
+
+        let failed_attempts = subscription.failed_attempts + 1;
+        let retry_in = usize::try_from(failed_attempts - 1)
+            .ok()
+            .and_then(|retry| DUNNING_RETRY_HOURS.get(retry));
+        let subscription = match retry_in {
+            Some(hours) => {
+                let now = Utc::now().fixed_offset();
+                let mut active_model: ActiveModel = subscription.into();
+                active_model.status = Set(SubscriptionStatus::PastDue);
+                active_model.failed_attempts = Set(failed_attempts);
+                active_model.next_charge_at = Set(Some(now + Duration::hours(*hours)));
+                active_model.updated_at = Set(now);
+                active_model.update(&txn).await?
+            }
+            None => {
+                let mut active_model: ActiveModel = subscription.into();
+                active_model.failed_attempts = Set(failed_attempts);
+                mark_canceled(&txn, active_model.update(&txn).await?).await?
+            }
+        };
+
+        txn.commit().await?;
+        Ok(subscription)
+    }
+}
+
+/// The end of a billing period of `plan` starting at `start`
+fn add_interval(
+    start: DateTimeWithTimeZone,
+    plan: &subscription_plans::Model,
+) -> DateTimeWithTimeZone {
+    let count = plan.interval_count.max(1);
+    match plan.interval {
+        PlanInterval::Day => start + Duration::days(count.into()),
+        PlanInterval::Week => start + Duration::weeks(count.into()),
+        PlanInterval::Month => start + Months::new(count.unsigned_abs()),
+        PlanInterval::Year => start + Months::new(count.unsigned_abs() * 12),
+    }
+}
+
+/// The share of the period from `start` to `end` that is still left at `now`, from 0 to 1
+fn unused_share(
+    start: DateTimeWithTimeZone,
+    end: DateTimeWithTimeZone,
+    now: DateTimeWithTimeZone,
+) -> Decimal {
+    let period = (end - start).num_seconds().max(1);
+    let remaining = (end - now).num_seconds().clamp(0, period);
+    Decimal::from(remaining) / Decimal::from(period)
+}
+
+/// `share` of a plan's price, rounded to the cent
+fn prorate(amount: Decimal, share: Decimal) -> Decimal {
+    (amount * share).round_dp(2)
+}
+
+async fn lock_subscription<C: ConnectionTrait>(conn: &C, id: i32) -> Result<Model, DBError> {
+    Subscriptions::find_by_id(id)
+        .lock_exclusive()
+        .one(conn)
+        .await?
+        .ok_or(DBError::NotFound("Subscription"))
+}
+
+async fn find_plan<C: ConnectionTrait>(
+    conn: &C,
+    id: i32,
+) -> Result<subscription_plans::Model, DBError> {
+    SubscriptionPlans::find_by_id(id)
+        .one(conn)
+        .await?
+        .ok_or(DBError::NotFound("Plan"))
+}
+
+async fn mark_canceled<C: ConnectionTrait>(
+    conn: &C,
+    subscription: Model,
+) -> Result<Model, DBError> {
+    let now = Utc::now().fixed_offset();
+    let mut active_model: ActiveModel = subscription.into();
+    active_model.status = Set(SubscriptionStatus::Canceled);
+    active_model.next_charge_at = Set(None);
+    active_model.canceled_at = Set(Some(now));
+    active_model.updated_at = Set(now);
+    Ok(active_model.update(conn).await?)
+}
+
+/// Records a charge attempt: `Ok` with the settling transaction id, or `Err` with
+/// the reason it failed. A zero amount covered by credit has no transaction.
+async fn record_charge<C: ConnectionTrait>(
+    conn: &C,
+    subscription: &Model,
+    kind: SubscriptionChargeKind,
+    amount: Decimal,
+    outcome: Result<Option<i32>, String>,
+) -> Result<(), DBError> {
+    let (status, transaction_id, failure_reason) = match outcome {
+        Ok(transaction_id) => (SubscriptionChargeStatus::Succeeded, transaction_id, None),
+        Err(reason) => (SubscriptionChargeStatus::Failed, None, Some(reason)),
+    };
+    subscription_charges::ActiveModel {
+        subscription_id: Set(subscription.id),
+        plan_id: Set(subscription.plan_id),
+        kind: Set(kind),
+        status: Set(status),
+        amount: Set(amount),
+        transaction_id: Set(transaction_id),
+        failure_reason: Set(failure_reason),
+        created_at: Set(Utc::now().fixed_offset()),
+        ..Default::default()
+    }
+    .insert(conn)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::dec;
+
+    fn at(value: &str) -> DateTimeWithTimeZone {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn unused_share_is_the_rest_of_the_period() {
+        let start = at("2026-10-01T00:00:00Z");
+        let end = at("2026-10-31T00:00:00Z");
+        assert_eq!(unused_share(start, end, start), Decimal::ONE);
+        assert_eq!(
+            unused_share(start, end, at("2026-10-16T00:00:00Z")),
+            dec("0.5")
+        );
+        assert_eq!(unused_share(start, end, end), Decimal::ZERO);
+    }
+
+    #[test]
+    fn unused_share_stays_within_the_period() {
+        let start = at("2026-10-01T00:00:00Z");
+        let end = at("2026-10-31T00:00:00Z");
+        assert_eq!(
+            unused_share(start, end, at("2026-09-01T00:00:00Z")),
+            Decimal::ONE
+        );
+        assert_eq!(
+            unused_share(start, end, at("2026-11-15T00:00:00Z")),
+            Decimal::ZERO
+        );
+        assert_eq!(unused_share(start, start, start), Decimal::ZERO);
+    }
+
+    #[test]
+    fn prorated_amounts_are_rounded_to_the_cent() {
+        let third = unused_share(
+            at("2026-10-01T00:00:00Z"),
+            at("2026-10-31T00:00:00Z"),
+            at("2026-10-21T00:00:00Z"),
+        );
+        assert_eq!(prorate(dec("10.00"), third), dec("3.33"));
+        assert_eq!(prorate(dec("20.00"), third), dec("6.67"));
+        assert_eq!(prorate(dec("9.99"), dec("0.5")), dec("5.00"));
+    }
+
+    #[test]
+    fn upgrade_charges_the_difference_for_the_rest_of_the_period() {
+        let third = unused_share(
+            at("2026-10-01T00:00:00Z"),
+            at("2026-10-31T00:00:00Z"),
+            at("2026-10-21T00:00:00Z"),
+        );
+        let unused_credit = prorate(dec("10.00"), third);
+        let new_cost = prorate(dec("25.00"), third);
+        assert_eq!(new_cost - unused_credit, dec("5.00"));
+    }
+}
@@ -1,17 +1,69 @@
-#![allow(deprecated)]
-
 use std::sync::Arc;
 
-use chrono::Local;
-use entity::prelude::Transactions;
-use entity::transactions::{ActiveModel, Model};
+use chrono::Utc;
+use entity::prelude::{Accounts, Transactions};
+use entity::transactions::{ActiveModel, Column, Model};
+
+pub use entity::sea_orm_active_enums::TransactionStatus;
 
+use crate::accounts::{adjust_balances, available_balance, lock_account, main_account_id};
 use crate::db_conn::DB;
-use crate::util::DBError;
-use sea_orm::prelude::Decimal;
+use crate::invoices::settle_matching_invoice;
+use crate::outbox::{record_event, OutboxEventType};
+use crate::pagination::{Keyset, Page, PageRequest};
+use crate::util::{to_decimal, DBError};
+use crate::webhooks::{enqueue_event, transaction_data, WebhookEventType};
+use sea_orm::prelude::{DateTimeWithTimeZone, Decimal};
 use sea_orm::QueryFilter;
 use sea_orm::QueryOrder;
-use sea_orm::{ColumnTrait, Condition, EntityTrait, Set};
+use sea_orm::QuerySelect;
+use sea_orm::TransactionTrait;
+use sea_orm::{ActiveModelTrait, ColumnTrait, Condition, ConnectionTrait, EntityTrait, Set};
+
+/// Direction of a transaction relative to the accounts it is listed for
+#[derive(Clone, Copy, Debug)]
+pub enum TransactionDirection {
+    /// Money received by one of the accounts
+    In,
+    /// Money sent from one of the accounts
+    Out,
+}
+
+/// Ordering applied to transaction listings
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TransactionSort {
+    #[default]
+    Newest,
+    Oldest,
+    AmountDesc,
+    AmountAsc,
+}
+
+impl TransactionSort {
+    /// Name of the sort in pagination keysets
+    fn key(self) -> &'static str {
+        match self {
+            Self::Newest => "newest",
+            Self::Oldest => "oldest",
+            Self::AmountDesc => "amount_desc",
+            Self::AmountAsc => "amount_asc",
+        }
+    }
+}
+
+/// Optional filters for `TransactionImpl::list_transactions_for_accounts`.
+/// Every field that is set narrows the result; `since` is inclusive and `until` exclusive.
+#[derive(Clone, Debug, Default)]
+pub struct TransactionFilter {
+    pub direction: Option<TransactionDirection>,
+    pub since: Option<DateTimeWithTimeZone>,
+    pub until: Option<DateTimeWithTimeZone>,
+    pub min_amount: Option<f64>,
+    pub max_amount: Option<f64>,
+    pub counterparty_account_id: Option<i32>,
+    pub status: Option<TransactionStatus>,
+    pub sort: TransactionSort,
+}
 
 pub struct TransactionImpl {
     db: Arc<DB>,
@@ -22,22 +74,46 @@ impl TransactionImpl {
         Self { db }
     }
 
-    pub async fn create_transaction(
+    /// Moves `amount` from one account to another in a single database transaction.
+    /// Fails with `DBError::NotEnoughBalance` if the sender's available balance is too low.
+    /// A transfer that matches an unpaid invoice pays it in the same database transaction.
+    pub async fn transfer(&self, from: i32, to: i32, amount: f64) -> Result<Model, DBError> {
+        let db = self.db.get()?;
+        let amount = to_decimal(amount)?;
+        let txn = db.begin().await?;
+        let transaction = execute_transfer(&txn, from, to, amount, false).await?;
+        settle_matching_invoice(&txn, &transaction).await?;
+        txn.commit().await?;
+        Ok(transaction)
+    }
+
+    /// Moves money between a main account and its pockets, or between two of its pockets.
+    /// Internal moves are settled instantly like any transfer.
+    pub async fn move_within(
         &self,
+        main_account: i32,
         from: i32,
         to: i32,
         amount: f64,
-    ) -> Result<i32, DBError> {
+    ) -> Result<Model, DBError> {
         let db = self.db.get()?;
-        let transaction = ActiveModel {
-            from_account_id: Set(from),
-            to_account_id: Set(to),
-            amount: Set(Decimal::from_f64_retain(amount).unwrap()),
-            created_at: Set(Local::today().naive_local()),
-            ..Default::default()
-        };
-        let result = Transactions::insert(transaction).exec(db).await?;
-        Ok(result.last_insert_id)
+        for id in [from, to] {
+            let account = Accounts::find_by_id(id)
+                .one(db)
+                .await?
+                .ok_or(DBError::AccountNotFound)?;
+            if main_account_id(&account) != main_account {
+                return Err(DBError::Conflict(
+                    "Both accounts must be the main account or one of its pockets".to_string(),
+                ));
+            }
+        }
+
+        let amount = to_decimal(amount)?;
+        let txn = db.begin().await?;
+        let transaction = execute_transfer(&txn, from, to, amount, false).await?;
+        txn.commit().await?;
+        Ok(transaction)
     }
 
     pub async fn find_transaction(&self, id: i32) -> Result<Option<Model>, DBError> {
@@ -49,17 +125,173 @@ impl TransactionImpl {
     pub async fn list_transactions_for_accounts(
         &self,
         account_ids: &[i32],
-    ) -> Result<Vec<Model>, DBError> {
+        filter: &TransactionFilter,
+        page: PageRequest,
+    ) -> Result<Page<Model>, DBError> {
+        let mut incoming = Condition::all().add(Column::ToAccountId.is_in(account_ids.to_vec()));
+        let mut outgoing = Condition::all().add(Column::FromAccountId.is_in(account_ids.to_vec()));
+        if let Some(counterparty) = filter.counterparty_account_id {
+            incoming = incoming.add(Column::FromAccountId.eq(counterparty));
+            outgoing = outgoing.add(Column::ToAccountId.eq(counterparty));
+        }
+        let scope = match filter.direction {
+            Some(TransactionDirection::In) => incoming,
+            Some(TransactionDirection::Out) => outgoing,
+            None => Condition::any().add(incoming).add(outgoing),
+        };
+        self.list_transactions(scope, filter, page).await
+    }
+
+    /// Transactions between any accounts, for admins. `direction` and
+    /// `counterparty_account_id` only apply to listings for given accounts and are ignored.
+    pub async fn list_all_transactions(
+        &self,
+        filter: &TransactionFilter,
+        page: PageRequest,
+    ) -> Result<Page<Model>, DBError> {
+        self.list_transactions(Condition::all(), filter, page).await
+    }
+
+    /// Transactions within `scope` that match the rest of the filter, in its sort order
+    async fn list_transactions(
+        &self,
+        scope: Condition,
+        filter: &TransactionFilter,
+        page: PageRequest,
+    ) -> Result<Page<Model>, DBError> {
         let db = self.db.get()?;
-        let transactions = Transactions::find()
-            .filter(
-                Condition::any()
-                    .add(entity::transactions::Column::FromAccountId.is_in(account_ids.to_vec()))
-                    .add(entity::transactions::Column::ToAccountId.is_in(account_ids.to_vec())),
+        let min_amount = filter.min_amount.map(to_decimal).transpose()?;
+        let max_amount = filter.max_amount.map(to_decimal).transpose()?;
+        let condition = Condition::all()
+            .add(scope)
+            .add_option(filter.since.map(|since| Column::CreatedAt.gte(since)))
+            .add_option(filter.until.map(|until| Column::CreatedAt.lt(until)))
+            .add_option(min_amount.map(|min| Column::Amount.gte(min)))
+            .add_option(max_amount.map(|max| Column::Amount.lte(max)))
+            .add_option(filter.status.map(|status| Column::Status.eq(status)));
+
+        let condition = match &page.after {
+            Some(after) => condition.add(after_keyset(after, filter.sort)?),
+            None => condition,
+        };
+
+        let query = Transactions::find().filter(condition);
+        let query = match filter.sort {
+            TransactionSort::Newest => query
+                .order_by_desc(Column::CreatedAt)
+                .order_by_desc(Column::Id),
+            TransactionSort::Oldest => query
+                .order_by_asc(Column::CreatedAt)
+                .order_by_asc(Column::Id),
+            TransactionSort::AmountDesc => query
+                .order_by_desc(Column::Amount)
+                .order_by_desc(Column::CreatedAt)
+                .order_by_desc(Column::Id),
+            TransactionSort::AmountAsc => query
+                .order_by_asc(Column::Amount)
+                .order_by_asc(Column::CreatedAt)
+                .order_by_asc(Column::Id),
+        };
+
+        let transactions = query.limit(page.limit + 1).all(db).await?;
+        let sorted_by_amount = matches!(
+            filter.sort,
+            TransactionSort::AmountDesc | TransactionSort::AmountAsc
+        );
+        Ok(Page::from_rows(transactions, page.limit, |t| {
+            Keyset::new(t.created_at, t.id)
+                .with_sort(filter.sort.key(), sorted_by_amount.then_some(t.amount))
+        }))
+    }
+}
+
+/// Condition selecting the transactions that follow `after` in the given sort order.
+/// Amount sorts continue from the amount stored in the keyset, with `(created_at, id)`
+/// breaking ties. Fails with `DBError::InvalidCursor` if the keyset was taken in
+/// another sort.
+fn after_keyset(after: &Keyset, sort: TransactionSort) -> Result<Condition, DBError> {
+    let position = after
+        .sort
+        .as_ref()
+        .filter(|position| position.key == sort.key())
+        .ok_or(DBError::InvalidCursor)?;
+    let condition = match sort {
+        TransactionSort::Newest => after.after(Column::CreatedAt, Column::Id, true),
+        TransactionSort::Oldest => after.after(Column::CreatedAt, Column::Id, false),
+        TransactionSort::AmountDesc | TransactionSort::AmountAsc => {
+            let descending = sort == TransactionSort::AmountDesc;
+            let amount = position.value.ok_or(DBError::InvalidCursor)?;
+            let past_amount = if descending {
+                Column::Amount.lt(amount)
+            } else {
+                Column::Amount.gt(amount)
+            };
+            Condition::any().add(past_amount).add(
+                Condition::all()
+                    .add(Column::Amount.eq(amount))
+                    .add(after.after(Column::CreatedAt, Column::Id, descending)),
             )
-            .order_by_desc(entity::transactions::Column::CreatedAt)
-            .all(db)
-            .await?;
-        Ok(transactions)
+        }
+    };
+    Ok(condition)
+}
+
+/// Moves `amount` between two accounts on `conn`, which must be an open database
+/// transaction. Both account rows are locked in id order to avoid deadlocks, and the
+/// sender's available balance and freeze are checked unless `allow_overdraft` is set,
+/// which is reserved for reversals that have to go through regardless. Records the transfer in
+/// the outbox and queues the `transaction.created` webhook event for both accounts.
+pub(crate) async fn execute_transfer<C: ConnectionTrait>(
+    conn: &C,
+    from: i32,
+    to: i32,
+    amount: Decimal,
+    allow_overdraft: bool,
+) -> Result<Model, DBError> {
+    let (first, second) = if from < to { (from, to) } else { (to, from) };
+    let first = lock_account(conn, first).await?;
+    let second = lock_account(conn, second).await?;
+    let (from_account, to_account) = if first.id == from {
+        (first, second)
+    } else {
+        (second, first)
+    };
+
+    if !allow_overdraft && from_account.frozen_at.is_some() {
+        return Err(DBError::Conflict(
+            "The sending account is frozen".to_string(),
+        ));
+    }
+    if !allow_overdraft && available_balance(&from_account) < amount {
+        return Err(DBError::NotEnoughBalance);
+    }
+
+    adjust_balances(conn, from_account, -amount, Decimal::ZERO).await?;
+    adjust_balances(conn, to_account, amount, Decimal::ZERO).await?;
+
+    let transaction = ActiveModel {
+        from_account_id: Set(from),
+        to_account_id: Set(to),
+        amount: Set(amount),
+        created_at: Set(Utc::now().fixed_offset()),
+        status: Set(TransactionStatus::Completed),
+        ..Default::default()
     }
+    .insert(conn)
+    .await?;
+    record_event(
+        conn,
+        OutboxEventType::TransactionCreated,
+        transaction.id,
+        transaction_data(&transaction),
+    )
+    .await?;
+    enqueue_event(
+        conn,
+        WebhookEventType::TransactionCreated,
+        &[from, to],
+        transaction_data(&transaction),
+    )
+    .await?;
+    Ok(transaction)
 }
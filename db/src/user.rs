@@ -1,16 +1,42 @@
-#![allow(deprecated)]
 #![allow(unused)]
 
-use chrono::Local;
-use entity::prelude::User;
+use chrono::Utc;
+use entity::notification_preferences;
+use entity::prelude::{NotificationPreferences, User};
+use entity::user::Column;
 use entity::user::{ActiveModel, Model};
+use sea_orm::prelude::{Decimal, Expr};
+use sea_orm::sea_query::extension::postgres::PgExpr;
+use sea_orm::sea_query::{Func, OnConflict, SimpleExpr};
 use sea_orm::ColumnTrait;
 use sea_orm::QueryFilter;
-use sea_orm::{EntityTrait, Set};
+use sea_orm::{
+    ActiveModelTrait, Condition, EntityTrait, PaginatorTrait, QueryOrder, QuerySelect, Set,
+    TransactionTrait,
+};
+use serde_json::json;
 use std::sync::Arc;
 
+pub use entity::sea_orm_active_enums::UserRole;
+
 use crate::db_conn::DB;
-use crate::util::DBError;
+use crate::outbox::{preferences_data, record_event, user_data, OutboxEventType};
+use crate::pagination::{Keyset, Page, PageRequest};
+use crate::util::{to_cents, DBError};
+
+/// Transfers of at least this amount are reported as large unless a user chose otherwise
+pub const DEFAULT_LARGE_TRANSFER_THRESHOLD: Decimal = Decimal::ONE_THOUSAND;
+
+/// Changes to a user's notification preferences; `None` keeps the current value
+#[derive(Clone, Debug, Default)]
+pub struct PreferencesUpdate {
+    pub money_received: Option<bool>,
+    pub money_sent: Option<bool>,
+    pub large_transfers: Option<bool>,
+    pub large_transfer_threshold: Option<f64>,
+    pub new_logins: Option<bool>,
+    pub password_changes: Option<bool>,
+}
 
 pub struct UserImpl {
     db: Arc<DB>,
@@ -21,18 +47,37 @@ impl UserImpl {
         Self { db }
     }
 
-    pub async fn create_user(&self, username: String, password: String) -> Result<i32, DBError> {
+    pub async fn create_user(
+        &self,
+        username: String,
+        password: String,
+        display_name: Option<String>,
+        email: Option<String>,
+    ) -> Result<i32, DBError> {
         let db = self.db.get()?;
+        let txn = db.begin().await?;
         let user = ActiveModel {
             username: Set(username),
             // TODO: hash this password
             password: Set(password),
-            created_at: Set(Local::today().naive_local()),
+            created_at: Set(Utc::now().fixed_offset()),
+            display_name: Set(display_name),
+            role: Set(UserRole::User),
+            email: Set(email),
             ..Default::default()
-        };
-        let result = User::insert(user).exec(db).await?;
+        }
+        .insert(&txn)
+        .await?;
+        record_event(
+            &txn,
+            OutboxEventType::UserCreated,
+            user.id,
+            user_data(&user),
+        )
+        .await?;
+        txn.commit().await?;
 
-        Ok(result.last_insert_id)
+        Ok(user.id)
     }
 
     pub async fn find_user(&self, id: i32) -> Result<Option<Model>, DBError> {
@@ -49,4 +94,248 @@ impl UserImpl {
             .await?;
         Ok(user)
     }
+
+    /// Finds the user whose username matches a recipient handle, ignoring case like
+    /// account aliases do. Usernames that differ only in case predate this rule; among
+    /// those only an exact match is returned.
+    pub async fn find_user_by_handle(&self, handle: &str) -> Result<Option<Model>, DBError> {
+        let db = self.db.get()?;
+        let users = User::find()
+            .filter(username_matches(handle))
+            .all(db)
+            .await?;
+        if users.len() <= 1 {
+            return Ok(users.into_iter().next());
+        }
+        Ok(users.into_iter().find(|user| user.username == handle))
+    }
+
+    /// Whether a username matches `handle` ignoring case
+    pub async fn handle_taken(&self, handle: &str) -> Result<bool, DBError> {
+        let db = self.db.get()?;
+        let count = User::find()
+            .filter(username_matches(handle))
+            .count(db)
+            .await?;
+        Ok(count > 0)
+    }
+
+    /// Finds the user with an email address, which is stored in lower case
+    pub async fn find_user_by_email(&self, email: &str) -> Result<Option<Model>, DBError> {
+        let db = self.db.get()?;
+        let user = User::find()
+            .filter(entity::user::Column::Email.eq(email))
+            .one(db)
+            .await?;
+        Ok(user)
+    }
+
+    /// Sets or clears the address that notification emails are sent to
+    pub async fn set_email(&self, id: i32, email: Option<String>) -> Result<Model, DBError> {
+        let db = self.db.get()?;
+        let txn = db.begin().await?;
+        let user = ActiveModel {
+            id: Set(id),
+            email: Set(email),
+            ..Default::default()
+        };
+        let user = User::update(user).exec(&txn).await?;
+        record_event(
+            &txn,
+            OutboxEventType::UserUpdated,
+            user.id,
+            user_data(&user),
+        )
+        .await?;
+        txn.commit().await?;
+        Ok(user)
+    }
+
+    /// Replaces the user's password hash
+    pub async fn change_password(&self, id: i32, password: String) -> Result<(), DBError> {
+        let db = self.db.get()?;
+        let txn = db.begin().await?;
+        let user = ActiveModel {
+            id: Set(id),
+            password: Set(password),
+            ..Default::default()
+        };
+        let user = User::update(user).exec(&txn).await?;
+        record_event(
+            &txn,
+            OutboxEventType::UserPasswordChanged,
+            user.id,
+            json!({
+                "user_id": user.id,
+                "changed_at": Utc::now().fixed_offset(),
+            }),
+        )
+        .await?;
+        txn.commit().await?;
+        Ok(())
+    }
+
+    /// Records a successful login, with the client's address and user agent if known
+    pub async fn record_login(
+        &self,
+        id: i32,
+        ip: Option<String>,
+        user_agent: Option<String>,
+    ) -> Result<(), DBError> {
+        let db = self.db.get()?;
+        record_event(
+            db,
+            OutboxEventType::UserLoggedIn,
+            id,
+            json!({
+                "user_id": id,
+                "ip": ip,
+                "user_agent": user_agent,
+                "logged_in_at": Utc::now().fixed_offset(),
+            }),
+        )
+        .await
+    }
+
+    /// The user's email notification settings, or the defaults if they never changed them
+    pub async fn find_preferences(
+        &self,
+        user_id: i32,
+    ) -> Result<notification_preferences::Model, DBError> {
+        let db = self.db.get()?;
+        let preferences = NotificationPreferences::find_by_id(user_id).one(db).await?;
+        Ok(
+            preferences.unwrap_or_else(|| notification_preferences::Model {
+                user_id,
+                money_received: true,
+                money_sent: true,
+                large_transfers: true,
+                large_transfer_threshold: DEFAULT_LARGE_TRANSFER_THRESHOLD,
+                new_logins: true,
+                password_changes: true,
+                updated_at: Utc::now().fixed_offset(),
+            }),
+        )
+    }
+
+    /// Changes the given notification preferences and keeps the others, recording the
+    /// change in the outbox
+    pub async fn update_preferences(
+        &self,
+        user_id: i32,
+        update: PreferencesUpdate,
+    ) -> Result<notification_preferences::Model, DBError> {
+        use notification_preferences::Column;
+
+        let current = self.find_preferences(user_id).await?;
+        let db = self.db.get()?;
+        let threshold = update.large_transfer_threshold.map(to_cents).transpose()?;
+        let txn = db.begin().await?;
+        let preferences = notification_preferences::ActiveModel {
+            user_id: Set(user_id),
+            money_received: Set(update.money_received.unwrap_or(current.money_received)),
+            money_sent: Set(update.money_sent.unwrap_or(current.money_sent)),
+            large_transfers: Set(update.large_transfers.unwrap_or(current.large_transfers)),
+            large_transfer_threshold: Set(threshold.unwrap_or(current.large_transfer_threshold)),
+            new_logins: Set(update.new_logins.unwrap_or(current.new_logins)),
+            password_changes: Set(update.password_changes.unwrap_or(current.password_changes)),
+            updated_at: Set(Utc::now().fixed_offset()),
+        };
+        let preferences = NotificationPreferences::insert(preferences)
+            .on_conflict(
+                OnConflict::column(Column::UserId)
+                    .update_columns([
+                        Column::MoneyReceived,
+                        Column::MoneySent,
+                        Column::LargeTransfers,
+                        Column::LargeTransferThreshold,
+                        Column::NewLogins,
+                        Column::PasswordChanges,
+                        Column::UpdatedAt,
+                    ])
+                    .to_owned(),
+            )
+            .exec_with_returning(&txn)
+            .await?;
+        record_event(
+            &txn,
+            OutboxEventType::UserPreferencesChanged,
+            user_id,
+            preferences_data(&preferences),
+        )
+        .await?;
+        txn.commit().await?;
+        Ok(preferences)
+    }
+
+    /// Sets the account that payments addressed to the user's username are credited to
+    pub async fn set_default_account(&self, id: i32, account_id: i32) -> Result<(), DBError> {
+        let db = self.db.get()?;
+        let txn = db.begin().await?;
+        let user = ActiveModel {
+            id: Set(id),
+            default_account_id: Set(Some(account_id)),
+            ..Default::default()
+        };
+        let user = User::update(user).exec(&txn).await?;
+        record_event(
+            &txn,
+            OutboxEventType::UserUpdated,
+            user.id,
+            user_data(&user),
+        )
+        .await?;
+        txn.commit().await?;
+        Ok(())
+    }
+
+    /// Users whose username, display name or email contains `query`, ignoring case,
+    /// optionally only those with `role`. Newest first.
+    pub async fn search_users(
+        &self,
+        query: Option<&str>,
+        role: Option<UserRole>,
+        page: PageRequest,
+    ) -> Result<Page<Model>, DBError> {
+        let db = self.db.get()?;
+        let mut select = User::find();
+        if let Some(query) = query {
+            let pattern = format!("%{}%", escape_like(query));
+            select = select.filter(
+                Condition::any()
+                    .add(Expr::col(Column::Username).ilike(&pattern))
+                    .add(Expr::col(Column::DisplayName).ilike(&pattern))
+                    .add(Expr::col(Column::Email).ilike(&pattern)),
+            );
+        }
+        if let Some(role) = role {
+            select = select.filter(Column::Role.eq(role));
+        }
+        if let Some(after) = page.after {
+            select = select.filter(after.after(Column::CreatedAt, Column::Id, true));
+        }
+
+        let users = select
+            .order_by_desc(Column::CreatedAt)
+            .order_by_desc(Column::Id)
+            .limit(page.limit + 1)
+            .all(db)
+            .await?;
+
+        Ok(Page::from_rows(users, page.limit, |user| {
+            Keyset::new(user.created_at, user.id)
+        }))
+    }
+}
+
+/// Escapes the `LIKE` wildcards in user input so that they match literally
+fn escape_like(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+/// Compares usernames in lower case, the way recipient handles and aliases are compared
+fn username_matches(handle: &str) -> SimpleExpr {
+    Expr::expr(Func::lower(Expr::col(Column::Username))).eq(handle.to_lowercase())
 }
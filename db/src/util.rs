@@ -1,7 +1,9 @@
 use std::env::VarError;
 
 use common::error::thiserror;
+use sea_orm::prelude::Decimal;
 use sea_orm::DbErr;
+use sha2::{Digest, Sha256};
 
 #[derive(thiserror::Error, Debug)]
 pub enum DBError {
@@ -10,4 +12,46 @@ pub enum DBError {
 
     #[error("The Environment Variable: DATABASE_URL must be set")]
     VarError(#[from] VarError),
+
+    #[error("Account not found")]
+    AccountNotFound,
+
+    #[error("The account has not enough balance to proceed the transaction")]
+    NotEnoughBalance,
+
+    #[error("{0} not found")]
+    NotFound(&'static str),
+
+    #[error("{0}")]
+    Conflict(String),
+
+    #[error("The amount is out of range")]
+    InvalidAmount,
+
+    #[error("The pagination cursor does not belong to this listing")]
+    InvalidCursor,
+}
+
+/// Converts an amount from a request, failing instead of panicking for values a
+/// `Decimal` cannot hold
+pub fn to_decimal(amount: f64) -> Result<Decimal, DBError> {
+    Decimal::from_f64_retain(amount).ok_or(DBError::InvalidAmount)
+}
+
+/// Converts an amount from a request, rounded to the cent
+pub fn to_cents(amount: f64) -> Result<Decimal, DBError> {
+    Ok(to_decimal(amount)?.round_dp(2))
+}
+
+/// Hex SHA-256 of a generated secret such as an API key or refresh token. Secrets are
+/// long and random, so a plain SHA-256 is enough to store them safely.
+pub(crate) fn secret_hash(secret: &str) -> String {
+    hex::encode(Sha256::digest(secret.as_bytes()))
+}
+
+/// Parses a decimal literal in tests
+#[cfg(test)]
+pub(crate) fn dec(value: &str) -> Decimal {
+    use std::str::FromStr;
+    Decimal::from_str(value).unwrap()
 }
@@ -0,0 +1,439 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use chrono::{Duration, Utc};
+use entity::prelude::{Accounts, WebhookAttempts, WebhookDeliveries, WebhookEndpoints};
+use entity::webhook_deliveries::{self, Column, Model};
+use entity::{
+    disputes, invoices, payment_intents, transactions, webhook_attempts, webhook_endpoints,
+};
+use num_traits::ToPrimitive;
+use rand::distributions::{Alphanumeric, DistString};
+use sea_orm::prelude::{DateTimeWithTimeZone, Expr, Json};
+use sea_orm::sea_query::{LockBehavior, LockType};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, QueryOrder,
+    QuerySelect, Set, TransactionTrait,
+};
+use serde_json::json;
+
+pub use entity::sea_orm_active_enums::{WebhookDeliveryStatus, WebhookEventType};
+
+use crate::accounts::main_account_id;
+use crate::db_conn::DB;
+use crate::pagination::{Keyset, Page, PageRequest};
+use crate::util::DBError;
+
+/// Length of the random part of an endpoint's signing secret
+const SECRET_LENGTH: usize = 32;
+/// A delivery is given up after this many failed attempts
+const MAX_ATTEMPTS: i32 = 8;
+/// Wait before the first retry; every further retry waits twice as long
+const RETRY_BASE_SECS: i64 = 30;
+/// How long a claimed delivery is hidden from other dispatchers while it is being sent
+const CLAIM_LEASE_SECS: i64 = 60;
+
+pub struct WebhookImpl {
+    db: Arc<DB>,
+}
+
+impl WebhookImpl {
+    pub fn new(db: Arc<DB>) -> Self {
+        Self { db }
+    }
+
+    /// Registers a URL that receives the given events of `account_id` and its pockets.
+    /// Every delivery is signed with a new random secret, returned only here.
+    pub async fn create_endpoint(
+        &self,
+        account_id: i32,
+        created_by: i32,
+        url: String,
+        event_types: &[WebhookEventType],
+    ) -> Result<webhook_endpoints::Model, DBError> {
+        let db = self.db.get()?;
+        let now = Utc::now().fixed_offset();
+        let endpoint = webhook_endpoints::ActiveModel {
+            account_id: Set(account_id),
+            created_by: Set(created_by),
+            url: Set(url),
+            secret: Set(format!(
+                "whsec_{}",
+                Alphanumeric.sample_string(&mut rand::thread_rng(), SECRET_LENGTH)
+            )),
+            event_types: Set(json!(event_types)),
+            active: Set(true),
+            created_at: Set(now),
+            updated_at: Set(now),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+        Ok(endpoint)
+    }
+
+    pub async fn find_endpoint(
+        &self,
+        id: i32,
+    ) -> Result<Option<webhook_endpoints::Model>, DBError> {
+        let db = self.db.get()?;
+        let endpoint = WebhookEndpoints::find_by_id(id).one(db).await?;
+        Ok(endpoint)
+    }
+
+    /// Active endpoints of the given accounts, newest first
+    pub async fn list_endpoints(
+        &self,
+        account_ids: &[i32],
+    ) -> Result<Vec<webhook_endpoints::Model>, DBError> {
+        let db = self.db.get()?;
+        let endpoints = WebhookEndpoints::find()
+            .filter(webhook_endpoints::Column::AccountId.is_in(account_ids.to_vec()))
+            .filter(webhook_endpoints::Column::Active.eq(true))
+            .order_by_desc(webhook_endpoints::Column::CreatedAt)
+            .order_by_desc(webhook_endpoints::Column::Id)
+            .all(db)
+            .await?;
+        Ok(endpoints)
+    }
+
+    /// Stops sending events to an endpoint. Deliveries still waiting for a retry
+    /// are marked failed.
+    pub async fn deactivate_endpoint(&self, id: i32) -> Result<webhook_endpoints::Model, DBError> {
+        let db = self.db.get()?;
+        let txn = db.begin().await?;
+
+        let endpoint = WebhookEndpoints::find_by_id(id)
+            .lock_exclusive()
+            .one(&txn)
+            .await?
+            .ok_or(DBError::NotFound("Webhook endpoint"))?;
+        if !endpoint.active {
+            return Err(DBError::Conflict(
+                "This webhook endpoint has already been deleted".to_string(),
+            ));
+        }
+
+        let now = Utc::now().fixed_offset();
+        WebhookDeliveries::update_many()
+            .col_expr(Column::Status, Expr::value(WebhookDeliveryStatus::Failed))
+            .col_expr(
+                Column::NextAttemptAt,
+                Expr::value(Option::<DateTimeWithTimeZone>::None),
+            )
+            .col_expr(Column::UpdatedAt, Expr::value(now))
+            .filter(Column::EndpointId.eq(id))
+            .filter(Column::Status.eq(WebhookDeliveryStatus::Pending))
+            .exec(&txn)
+            .await?;
+
+        let mut active_model: webhook_endpoints::ActiveModel = endpoint.into();
+        active_model.active = Set(false);
+        active_model.updated_at = Set(now);
+        let endpoint = active_model.update(&txn).await?;
+
+        txn.commit().await?;
+        Ok(endpoint)
+    }
+
+    pub async fn find_delivery(&self, id: i32) -> Result<Option<Model>, DBError> {
+        let db = self.db.get()?;
+        let delivery = WebhookDeliveries::find_by_id(id).one(db).await?;
+        Ok(delivery)
+    }
+
+    /// Deliveries to an endpoint, newest first
+    pub async fn list_deliveries(
+        &self,
+        endpoint_id: i32,
+        status: Option<WebhookDeliveryStatus>,
+        page: PageRequest,
+    ) -> Result<Page<Model>, DBError> {
+        let db = self.db.get()?;
+        let mut query = WebhookDeliveries::find().filter(Column::EndpointId.eq(endpoint_id));
+        if let Some(status) = status {
+            query = query.filter(Column::Status.eq(status));
+        }
+        if let Some(after) = page.after {
+            query = query.filter(after.after(Column::CreatedAt, Column::Id, true));
+        }
+
+        let deliveries = query
+            .order_by_desc(Column::CreatedAt)
+            .order_by_desc(Column::Id)
+            .limit(page.limit + 1)
+            .all(db)
+            .await?;
+
+        Ok(Page::from_rows(deliveries, page.limit, |delivery| {
+            Keyset::new(delivery.created_at, delivery.id)
+        }))
+    }
+
+    /// Attempts of a delivery in the order they were made
+    pub async fn list_attempts(
+        &self,
+        delivery_id: i32,
+    ) -> Result<Vec<webhook_attempts::Model>, DBError> {
+        let db = self.db.get()?;
+        let attempts = WebhookAttempts::find()
+            .filter(webhook_attempts::Column::DeliveryId.eq(delivery_id))
+            .order_by_asc(webhook_attempts::Column::Id)
+            .all(db)
+            .await?;
+        Ok(attempts)
+    }
+
+    /// Takes up to `limit` pending deliveries that are due, oldest first, together with
+    /// their endpoints. Claimed deliveries are pushed back by a short lease so that other
+    /// dispatchers skip them; `record_attempt` then reschedules or closes them.
+    pub async fn claim_due_deliveries(
+        &self,
+        limit: u64,
+    ) -> Result<Vec<(Model, webhook_endpoints::Model)>, DBError> {
+        let db = self.db.get()?;
+        let txn = db.begin().await?;
+
+        let now = Utc::now().fixed_offset();
+        let due = WebhookDeliveries::find()
+            .filter(Column::Status.eq(WebhookDeliveryStatus::Pending))
+            .filter(Column::NextAttemptAt.lte(now))
+            .order_by_asc(Column::NextAttemptAt)
+            .order_by_asc(Column::Id)
+            .limit(limit)
+            .lock_with_behavior(LockType::Update, LockBehavior::SkipLocked)
+            .all(&txn)
+            .await?;
+
+        let lease_until = now + Duration::seconds(CLAIM_LEASE_SECS);
+        let mut claimed = Vec::with_capacity(due.len());
+        for delivery in due {
+            let endpoint = WebhookEndpoints::find_by_id(delivery.endpoint_id)
+                .one(&txn)
+                .await?
+                .ok_or(DBError::NotFound("Webhook endpoint"))?;
+            let mut active_model: webhook_deliveries::ActiveModel = delivery.into();
+            active_model.next_attempt_at = Set(Some(lease_until));
+            claimed.push((active_model.update(&txn).await?, endpoint));
+        }
+
+        txn.commit().await?;
+        Ok(claimed)
+    }
+
+    /// Logs one attempt at sending a delivery. A 2xx response completes the delivery;
+    /// anything else schedules a retry with exponential backoff until the attempts run out.
+    pub async fn record_attempt(
+        &self,
+        delivery_id: i32,
+        response_status: Option<i32>,
+        error: Option<String>,
+        duration_ms: i32,
+    ) -> Result<Model, DBError> {
+        let db = self.db.get()?;
+        let txn = db.begin().await?;
+
+        let delivery = WebhookDeliveries::find_by_id(delivery_id)
+            .lock_exclusive()
+            .one(&txn)
+            .await?
+            .ok_or(DBError::NotFound("Webhook delivery"))?;
+        let now = Utc::now().fixed_offset();
+        webhook_attempts::ActiveModel {
+            delivery_id: Set(delivery_id),
+            response_status: Set(response_status),
+            error: Set(error),
+            duration_ms: Set(duration_ms),
+            attempted_at: Set(now),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+
+        // The endpoint may have been deleted while the request was in flight.
+        if delivery.status != WebhookDeliveryStatus::Pending {
+            txn.commit().await?;
+            return Ok(delivery);
+        }
+
+        let attempt_count = delivery.attempt_count + 1;
+        let succeeded = response_status.is_some_and(|status| (200..300).contains(&status));
+        let (status, next_attempt_at) = after_attempt(attempt_count, succeeded, now);
+
+        let mut active_model: webhook_deliveries::ActiveModel = delivery.into();
+        active_model.status = Set(status);
+        active_model.attempt_count = Set(attempt_count);
+        active_model.next_attempt_at = Set(next_attempt_at);
+        active_model.updated_at = Set(now);
+        let delivery = active_model.update(&txn).await?;
+
+        txn.commit().await?;
+        Ok(delivery)
+    }
+}
+
+/// The status of a delivery after its `attempt_count`-th attempt, and when to try again
+fn after_attempt(
+    attempt_count: i32,
+    succeeded: bool,
+    now: DateTimeWithTimeZone,
+) -> (WebhookDeliveryStatus, Option<DateTimeWithTimeZone>) {
+    if succeeded {
+        (WebhookDeliveryStatus::Succeeded, None)
+    } else if attempt_count >= MAX_ATTEMPTS {
+        (WebhookDeliveryStatus::Failed, None)
+    } else {
+        let backoff = RETRY_BASE_SECS << (attempt_count - 1);
+        (
+            WebhookDeliveryStatus::Pending,
+            Some(now + Duration::seconds(backoff)),
+        )
+    }
+}
+
+/// Queues `event_type` for every active endpoint subscribed to it on the given accounts
+/// or their main accounts. Runs on the caller's database transaction, so an event is only
+/// sent if the change it describes is committed.
+pub(crate) async fn enqueue_event<C: ConnectionTrait>(
+    conn: &C,
+    event_type: WebhookEventType,
+    account_ids: &[i32],
+    data: Json,
+) -> Result<(), DBError> {
+    let mut owner_ids: HashSet<i32> = account_ids.iter().copied().collect();
+    let accounts = Accounts::find()
+        .filter(entity::accounts::Column::Id.is_in(account_ids.to_vec()))
+        .all(conn)
+        .await?;
+    owner_ids.extend(accounts.iter().map(main_account_id));
+
+    let endpoints = WebhookEndpoints::find()
+        .filter(webhook_endpoints::Column::AccountId.is_in(owner_ids))
+        .filter(webhook_endpoints::Column::Active.eq(true))
+        .all(conn)
+        .await?;
+
+    let now = Utc::now().fixed_offset();
+    for endpoint in endpoints {
+        let subscribed: Vec<WebhookEventType> =
+            serde_json::from_value(endpoint.event_types).unwrap_or_default();
+        if !subscribed.contains(&event_type) {
+            continue;
+        }
+        webhook_deliveries::ActiveModel {
+            endpoint_id: Set(endpoint.id),
+            event_type: Set(event_type),
+            payload: Set(data.clone()),
+            status: Set(WebhookDeliveryStatus::Pending),
+            attempt_count: Set(0),
+            next_attempt_at: Set(Some(now)),
+            created_at: Set(now),
+            updated_at: Set(now),
+            ..Default::default()
+        }
+        .insert(conn)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Event data describing a transaction
+pub(crate) fn transaction_data(transaction: &transactions::Model) -> Json {
+    json!({
+        "id": transaction.id,
+        "from_account_id": transaction.from_account_id,
+        "to_account_id": transaction.to_account_id,
+        "amount": transaction.amount.to_f64(),
+        "status": transaction.status,
+        "created_at": transaction.created_at,
+    })
+}
+
+/// Event data describing a dispute
+pub(crate) fn dispute_data(dispute: &disputes::Model) -> Json {
+    json!({
+        "id": dispute.id,
+        "transaction_id": dispute.transaction_id,
+        "held_account_id": dispute.held_account_id,
+        "amount": dispute.amount.to_f64(),
+        "status": dispute.status,
+        "reversal_transaction_id": dispute.reversal_transaction_id,
+        "created_at": dispute.created_at,
+        "resolved_at": dispute.resolved_at,
+    })
+}
+
+/// Event data describing a payment intent
+pub(crate) fn payment_intent_data(intent: &payment_intents::Model) -> Json {
+    json!({
+        "id": intent.id,
+        "merchant_account_id": intent.merchant_account_id,
+        "customer_account_id": intent.customer_account_id,
+        "amount": intent.amount.to_f64(),
+        "currency": intent.currency,
+        "metadata": intent.metadata,
+        "status": intent.status,
+        "transaction_id": intent.transaction_id,
+        "created_at": intent.created_at,
+    })
+}
+
+/// Event data describing an invoice
+pub(crate) fn invoice_data(invoice: &invoices::Model) -> Json {
+    json!({
+        "id": invoice.id,
+        "issuer_account_id": invoice.issuer_account_id,
+        "recipient_user_id": invoice.recipient_user_id,
+        "number": invoice.number,
+        "total": invoice.total.to_f64(),
+        "amount_paid": invoice.amount_paid.to_f64(),
+        "status": invoice.status,
+        "due_date": invoice.due_date,
+        "paid_at": invoice.paid_at,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now() -> DateTimeWithTimeZone {
+        "2026-10-19T12:00:00Z".parse().unwrap()
+    }
+
+    #[test]
+    fn success_completes_the_delivery() {
+        assert_eq!(
+            after_attempt(1, true, now()),
+            (WebhookDeliveryStatus::Succeeded, None)
+        );
+        assert_eq!(
+            after_attempt(MAX_ATTEMPTS, true, now()),
+            (WebhookDeliveryStatus::Succeeded, None)
+        );
+    }
+
+    #[test]
+    fn retries_back_off_exponentially() {
+        let waits: Vec<i64> = (1..MAX_ATTEMPTS)
+            .map(|attempt_count| {
+                let (status, next_attempt_at) = after_attempt(attempt_count, false, now());
+                assert_eq!(status, WebhookDeliveryStatus::Pending);
+                (next_attempt_at.unwrap() - now()).num_seconds()
+            })
+            .collect();
+        assert_eq!(waits, [30, 60, 120, 240, 480, 960, 1920]);
+    }
+
+    #[test]
+    fn gives_up_after_the_last_attempt() {
+        assert_eq!(
+            after_attempt(MAX_ATTEMPTS, false, now()),
+            (WebhookDeliveryStatus::Failed, None)
+        );
+        assert_eq!(
+            after_attempt(MAX_ATTEMPTS + 1, false, now()),
+            (WebhookDeliveryStatus::Failed, None)
+        );
+    }
+}
@@ -1,12 +1,17 @@
 use crate::constants;
+use crate::features::accounts::events::OutboxNotice;
+use crate::features::notifications::session::Connections;
 use db::db_client::DbClient;
 use db::util::DBError;
+use tokio::sync::broadcast;
 use tracing::info;
 
 /// Global application state shared across all requests, containing Application name and Database connection pool.
 pub struct AppState {
     name: String,
     db: DbClient,
+    account_events: broadcast::Sender<OutboxNotice>,
+    notification_connections: Connections,
 }
 
 impl AppState {
@@ -14,6 +19,8 @@ impl AppState {
         let app_state = AppState {
             name: constants::APP_NAME.to_string(),
             db: DbClient::new().await?,
+            account_events: broadcast::channel(constants::ACCOUNT_EVENTS_CHANNEL_CAPACITY).0,
+            notification_connections: Connections::default(),
         };
         info!("Creating new Global App State for {}", app_state.name());
         Ok(app_state)
@@ -23,6 +30,17 @@ impl AppState {
         &self.db
     }
 
+    /// Outbox events committed by any server instance, for the account event streams
+    /// and notification connections
+    pub fn account_events(&self) -> &broadcast::Sender<OutboxNotice> {
+        &self.account_events
+    }
+
+    /// The notification connections open on this server instance
+    pub fn notification_connections(&self) -> &Connections {
+        &self.notification_connections
+    }
+
     pub fn name(&self) -> String {
         self.name.clone()
     }
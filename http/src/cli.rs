@@ -0,0 +1,57 @@
+use std::process;
+
+use db::db_client::DbClient;
+
+use crate::util::ApiError;
+
+const USAGE: &str = "Usage:
+    http                       Start the server
+    http verify-audit [HEAD]   Check the audit log's hash chain. HEAD is a hash printed by
+                               an earlier check; the check then fails if that entry is gone";
+
+/// Runs a command given on the command line instead of starting the server
+pub async fn run(command: &str, args: &[String]) -> Result<(), ApiError> {
+    match (command, args) {
+        ("verify-audit", [] | [_]) => verify_audit(args.first().map(String::as_str)).await,
+        ("help" | "--help" | "-h", []) => {
+            println!("{USAGE}");
+            Ok(())
+        }
+        _ => {
+            eprintln!("{USAGE}");
+            process::exit(2);
+        }
+    }
+}
+
+/// Verifies the audit log and prints its head, exiting with status 1 if the chain is
+/// broken or the expected head is missing
+async fn verify_audit(expected_head: Option<&str>) -> Result<(), ApiError> {
+    let db = DbClient::new().await?;
+    let verification = db.audit.verify().await?;
+
+    if let Some(broken) = verification.broken {
+        eprintln!(
+            "Audit log broken at entry {}: {}. Entries verified before it: {}.",
+            broken.id, broken.reason, verification.checked
+        );
+        process::exit(1);
+    }
+    if let Some(expected_head) = expected_head {
+        if db.audit.find_by_hash(expected_head).await?.is_none() {
+            eprintln!(
+                "Audit log entry {expected_head} is missing. Entries were removed from the end of the log."
+            );
+            process::exit(1);
+        }
+    }
+
+    match verification.head {
+        Some(head) => println!(
+            "Audit log intact: {} entries verified. Head: {head}",
+            verification.checked
+        ),
+        None => println!("Audit log is empty."),
+    }
+    Ok(())
+}
@@ -3,3 +3,32 @@
 pub const AUTHORIZATION: &str = "Authorization";
 pub const APP_NAME: &str = "Dodo_Payments_Assignment";
 pub const BIND: &str = "0.0.0.0:3000";
+pub const DEFAULT_PAGE_LIMIT: u64 = 20;
+pub const MAX_PAGE_LIMIT: u64 = 100;
+pub const CURRENCY: &str = "USD";
+pub const MAX_AMOUNT: f64 = 1_000_000_000.0;
+pub const MAX_AMOUNT_DECIMAL_PLACES: usize = 2;
+pub const MAX_METADATA_KEYS: usize = 50;
+pub const SUBSCRIPTION_POLL_INTERVAL_SECS: u64 = 60;
+pub const SUBSCRIPTION_BATCH_SIZE: u64 = 100;
+pub const WEBHOOK_POLL_INTERVAL_SECS: u64 = 5;
+pub const WEBHOOK_BATCH_SIZE: u64 = 50;
+pub const WEBHOOK_TIMEOUT_SECS: u64 = 10;
+pub const OUTBOX_POLL_INTERVAL_SECS: u64 = 2;
+pub const OUTBOX_BATCH_SIZE: u64 = 100;
+pub const OUTBOX_LEASE_SECS: i64 = 30;
+pub const ACCOUNT_EVENTS_CHANNEL_CAPACITY: usize = 1024;
+pub const ACCOUNT_EVENTS_REPLAY_BATCH: u64 = 500;
+pub const ACCOUNT_EVENTS_HEARTBEAT_SECS: u64 = 15;
+pub const ACCOUNT_EVENTS_RETRY_MS: u64 = 3000;
+pub const ACCOUNT_EVENTS_HOLD_BACK_RETRY_MS: u64 = 250;
+pub const NOTIFICATIONS_HEARTBEAT_SECS: u64 = 15;
+pub const NOTIFICATIONS_CLIENT_TIMEOUT_SECS: u64 = 45;
+pub const NOTIFICATIONS_MAX_FRAME_SIZE: usize = 64 * 1024;
+pub const NOTIFICATIONS_MAX_SUBSCRIPTIONS: usize = 50;
+pub const NOTIFICATIONS_REPLAY_BATCH: u64 = 500;
+pub const AUDIT_MAX_SNAPSHOT_SIZE: usize = 64 * 1024;
+pub const ACCESS_TOKEN_TTL_SECS: i64 = 15 * 60;
+pub const REFRESH_TOKEN_TTL_DAYS: i64 = 30;
+pub const JWT_ISSUER: &str = "payment-service";
+pub const JWT_AUDIENCE: &str = "payment-service-api";
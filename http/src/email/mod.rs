@@ -0,0 +1,10 @@
+//! Notification emails about payments and security events
+//!
+//! Emails are sent by a sink of the outbox relay, so they go out after the change they
+//! report is committed and never hold up the request that made it. Rendering uses the
+//! text templates in `templates/email`, and delivery goes through the transport chosen
+//! in the environment.
+
+pub mod notifier;
+pub mod templates;
+pub mod transport;
@@ -0,0 +1,490 @@
+use std::future::Future;
+
+use actix_web::web;
+use async_trait::async_trait;
+use db::accounts::main_account_id;
+use db::outbox::OutboxEventType;
+use db::util::DBError;
+use entity::{accounts, notification_preferences, outbox, user};
+use num_traits::ToPrimitive;
+
+use crate::app_state::AppState;
+use crate::constants::CURRENCY;
+use crate::outbox::sinks::{Sink, SinkError};
+
+use super::templates::Template;
+use super::transport::{self, Email, EmailTransport};
+
+/// Emails users about payments and security events as the events are published from
+/// the outbox, after the change they describe is committed
+pub struct EmailSink {
+    state: web::Data<AppState>,
+    transport: Box<dyn EmailTransport>,
+}
+
+/// The email sink, if an email transport is configured
+pub fn from_env(state: web::Data<AppState>) -> Option<Box<dyn Sink>> {
+    let transport = transport::from_env()?;
+    Some(Box::new(EmailSink { state, transport }))
+}
+
+#[async_trait]
+impl Sink for EmailSink {
+    fn name(&self) -> &str {
+        "email"
+    }
+
+    fn replays_history(&self) -> bool {
+        false
+    }
+
+    async fn publish(&self, event: &outbox::Model) -> Result<(), SinkError> {
+        let notifications = match event.event_type {
+            OutboxEventType::TransactionCreated => self.transfer(event).await?,
+            OutboxEventType::UserLoggedIn => self
+                .recipient(event.aggregate_id)
+                .await?
+                .and_then(|recipient| new_login(event, &recipient))
+                .into_iter()
+                .collect(),
+            OutboxEventType::UserPasswordChanged => self
+                .recipient(event.aggregate_id)
+                .await?
+                .and_then(|recipient| password_changed(event, &recipient))
+                .into_iter()
+                .collect(),
+            _ => Vec::new(),
+        };
+        if notifications.is_empty() {
+            return Ok(());
+        }
+
+        let db = self.state.db();
+        let emailed = db.outbox.emailed_users(event.id).await?;
+        deliver(
+            self.transport.as_ref(),
+            notifications,
+            &emailed,
+            |user_id| async move { db.outbox.record_email(event.id, user_id).await },
+        )
+        .await
+    }
+}
+
+impl EmailSink {
+    /// The emails about a transfer to the users of the sending and the receiving account
+    async fn transfer(&self, event: &outbox::Model) -> Result<Vec<Notification>, SinkError> {
+        let db = self.state.db();
+        let account_id = |key: &str| {
+            event.payload[key]
+                .as_i64()
+                .and_then(|id| i32::try_from(id).ok())
+        };
+        let (Some(from_id), Some(to_id)) =
+            (account_id("from_account_id"), account_id("to_account_id"))
+        else {
+            return Ok(Vec::new());
+        };
+        let (Some(from), Some(to)) = (
+            db.account.find_account(from_id).await?,
+            db.account.find_account(to_id).await?,
+        ) else {
+            return Ok(Vec::new());
+        };
+
+        let from = self.party(&from).await?;
+        let to = self.party(&to).await?;
+        Ok(transfer_notifications(event, &from, &to))
+    }
+
+    /// An account with the users who can see it: the members of its organization, or
+    /// the members of the main account for pockets and accounts of people
+    async fn party(&self, account: &accounts::Model) -> Result<Party, SinkError> {
+        let db = self.state.db();
+        let user_ids: Vec<i32> = match account.organization_id {
+            Some(organization_id) => db
+                .organization
+                .list_members(organization_id)
+                .await?
+                .iter()
+                .map(|member| member.user_id)
+                .collect(),
+            None => db
+                .account_member
+                .list_members(main_account_id(account))
+                .await?
+                .iter()
+                .map(|member| member.user_id)
+                .collect(),
+        };
+        let mut recipients = Vec::with_capacity(user_ids.len());
+        for user_id in user_ids {
+            recipients.extend(self.recipient(user_id).await?);
+        }
+
+        let holder = match account.organization_id {
+            Some(_) => None,
+            None => db.user.find_user(account.user_id).await?,
+        };
+        Ok(Party {
+            account: account_label(account),
+            name: holder
+                .as_ref()
+                .map(display_name)
+                .unwrap_or_else(|| account_label(account)),
+            recipients,
+        })
+    }
+
+    /// A user with their notification preferences, if they have an email address
+    async fn recipient(&self, user_id: i32) -> Result<Option<Recipient>, SinkError> {
+        let db = self.state.db();
+        let Some(user) = db.user.find_user(user_id).await? else {
+            return Ok(None);
+        };
+        if user.email.is_none() {
+            return Ok(None);
+        }
+        let preferences = db.user.find_preferences(user_id).await?;
+        Ok(Some(Recipient { user, preferences }))
+    }
+}
+
+/// A user with an email address and the notifications they chose to receive
+pub struct Recipient {
+    pub user: user::Model,
+    pub preferences: notification_preferences::Model,
+}
+
+impl Recipient {
+    fn notify(&self, template: Template, values: &[(&str, String)]) -> Option<Notification> {
+        let to = self.user.email.as_ref()?;
+        let mut values = values.to_vec();
+        values.push(("name", display_name(&self.user)));
+        Some(Notification {
+            user_id: self.user.id,
+            email: template.render(to, &values),
+        })
+    }
+}
+
+/// An email to one user
+pub struct Notification {
+    pub user_id: i32,
+    pub email: Email,
+}
+
+/// One side of a transfer: how its account is shown, the name the other side sees and
+/// the users who are told about it
+pub struct Party {
+    pub account: String,
+    pub name: String,
+    pub recipients: Vec<Recipient>,
+}
+
+/// Tells the users of the receiving account about the money received and the users of
+/// the sending account about the money sent, or about a large transfer if the amount
+/// reaches their threshold. Users who can see both accounts are not told, so moves
+/// between one's own accounts are not reported.
+fn transfer_notifications(event: &outbox::Model, from: &Party, to: &Party) -> Vec<Notification> {
+    let amount = event.payload["amount"].as_f64().unwrap_or_default();
+    let values = |account: &str, counterparty: &str| {
+        vec![
+            ("amount", money(amount)),
+            ("account", account.to_string()),
+            ("counterparty", counterparty.to_string()),
+            ("transaction_id", event.payload["id"].to_string()),
+            ("date", date(event, "created_at")),
+        ]
+    };
+    let on_both_sides = |recipient: &Recipient| {
+        from.recipients
+            .iter()
+            .any(|sender| sender.user.id == recipient.user.id)
+            && to
+                .recipients
+                .iter()
+                .any(|receiver| receiver.user.id == recipient.user.id)
+    };
+
+    let mut notifications = Vec::new();
+    for recipient in &to.recipients {
+        if on_both_sides(recipient) || !recipient.preferences.money_received {
+            continue;
+        }
+        notifications
+            .extend(recipient.notify(Template::MoneyReceived, &values(&to.account, &from.name)));
+    }
+    for recipient in &from.recipients {
+        if on_both_sides(recipient) {
+            continue;
+        }
+        let preferences = &recipient.preferences;
+        let threshold = preferences
+            .large_transfer_threshold
+            .to_f64()
+            .unwrap_or(f64::MAX);
+        let values = values(&from.account, &to.name);
+        if preferences.large_transfers && amount >= threshold {
+            let mut values = values;
+            values.push(("threshold", money(threshold)));
+            notifications.extend(recipient.notify(Template::LargeTransfer, &values));
+        } else if preferences.money_sent {
+            notifications.extend(recipient.notify(Template::MoneySent, &values));
+        }
+    }
+    notifications
+}
+
+fn new_login(event: &outbox::Model, recipient: &Recipient) -> Option<Notification> {
+    if !recipient.preferences.new_logins {
+        return None;
+    }
+    let detail = |key: &str| event.payload[key].as_str().unwrap_or("unknown").to_string();
+    recipient.notify(
+        Template::NewLogin,
+        &[
+            ("date", date(event, "logged_in_at")),
+            ("ip", detail("ip")),
+            ("user_agent", detail("user_agent")),
+        ],
+    )
+}
+
+fn password_changed(event: &outbox::Model, recipient: &Recipient) -> Option<Notification> {
+    if !recipient.preferences.password_changes {
+        return None;
+    }
+    recipient.notify(
+        Template::PasswordChanged,
+        &[("date", date(event, "changed_at"))],
+    )
+}
+
+/// Sends the notifications of one event, skipping users emailed about it by an earlier
+/// attempt and recording each email once it is sent. An email is only sent again if the
+/// server stops between sending and recording it.
+async fn deliver<F, Fut>(
+    transport: &dyn EmailTransport,
+    notifications: Vec<Notification>,
+    emailed: &[i32],
+    mut record: F,
+) -> Result<(), SinkError>
+where
+    F: FnMut(i32) -> Fut,
+    Fut: Future<Output = Result<(), DBError>>,
+{
+    for notification in notifications {
+        if emailed.contains(&notification.user_id) {
+            continue;
+        }
+        transport.send(&notification.email).await?;
+        record(notification.user_id).await?;
+    }
+    Ok(())
+}
+
+fn display_name(user: &user::Model) -> String {
+    user.display_name
+        .clone()
+        .unwrap_or_else(|| user.username.clone())
+}
+
+fn account_label(account: &accounts::Model) -> String {
+    match (&account.name, &account.alias) {
+        (Some(name), _) => format!("{name} (#{})", account.id),
+        (None, Some(alias)) => format!("@{alias} (#{})", account.id),
+        (None, None) => format!("account #{}", account.id),
+    }
+}
+
+fn money(amount: f64) -> String {
+    format!("{amount:.2} {CURRENCY}")
+}
+
+/// A timestamp of the event's payload, or when the event was recorded
+fn date(event: &outbox::Model, key: &str) -> String {
+    let at = event.payload[key]
+        .as_str()
+        .and_then(|at| chrono::DateTime::parse_from_rfc3339(at).ok())
+        .unwrap_or(event.created_at);
+    at.to_utc().format("%Y-%m-%d %H:%M UTC").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use db::user::{UserRole, DEFAULT_LARGE_TRANSFER_THRESHOLD};
+    use db::util::to_cents;
+    use serde_json::json;
+
+    use super::*;
+    use crate::email::transport::MemoryTransport;
+
+    fn recipient(id: i32, name: &str) -> Recipient {
+        let now = Utc::now().fixed_offset();
+        Recipient {
+            user: user::Model {
+                id,
+                username: name.to_lowercase(),
+                password: String::new(),
+                created_at: now,
+                display_name: Some(name.to_string()),
+                default_account_id: None,
+                role: UserRole::User,
+                email: Some(format!("{}@example.com", name.to_lowercase())),
+            },
+            preferences: notification_preferences::Model {
+                user_id: id,
+                money_received: true,
+                money_sent: true,
+                large_transfers: true,
+                large_transfer_threshold: DEFAULT_LARGE_TRANSFER_THRESHOLD,
+                new_logins: true,
+                password_changes: true,
+                updated_at: now,
+            },
+        }
+    }
+
+    fn party(account: &str, name: &str, recipients: Vec<Recipient>) -> Party {
+        Party {
+            account: account.to_string(),
+            name: name.to_string(),
+            recipients,
+        }
+    }
+
+    fn event(event_type: OutboxEventType, payload: serde_json::Value) -> outbox::Model {
+        outbox::Model {
+            id: 7,
+            transaction_id: 812,
+            event_type,
+            aggregate_id: 2,
+            payload,
+            created_at: Utc::now().fixed_offset(),
+        }
+    }
+
+    fn transfer(amount: f64) -> outbox::Model {
+        event(
+            OutboxEventType::TransactionCreated,
+            json!({ "id": 31, "amount": amount, "created_at": "2026-10-19T08:30:00Z" }),
+        )
+    }
+
+    fn sent_to(notifications: &[Notification]) -> Vec<(i32, &str)> {
+        notifications
+            .iter()
+            .map(|notification| (notification.user_id, notification.email.subject.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn every_member_of_both_accounts_is_told() {
+        let from = party(
+            "Team (#4)",
+            "Bob",
+            vec![recipient(2, "Bob"), recipient(5, "Eve")],
+        );
+        let to = party(
+            "account #3",
+            "Carol",
+            vec![recipient(3, "Carol"), recipient(6, "Dan")],
+        );
+
+        let notifications = transfer_notifications(&transfer(25.0), &from, &to);
+
+        assert_eq!(
+            sent_to(&notifications),
+            vec![
+                (3, "You received 25.00 USD"),
+                (6, "You received 25.00 USD"),
+                (2, "You sent 25.00 USD"),
+                (5, "You sent 25.00 USD"),
+            ]
+        );
+        let received = &notifications[0].email;
+        assert_eq!(received.to, "carol@example.com");
+        assert!(received.body.contains("Hi Carol,"));
+        assert!(received.body.contains("from Bob on account #3"));
+    }
+
+    #[test]
+    fn members_only_get_the_emails_they_chose() {
+        let mut quiet = recipient(3, "Carol");
+        quiet.preferences.money_received = false;
+        let mut careful = recipient(5, "Eve");
+        careful.preferences.large_transfer_threshold = to_cents(20.0).unwrap();
+        let from = party("Team (#4)", "Bob", vec![recipient(2, "Bob"), careful]);
+        let to = party("account #3", "Carol", vec![quiet, recipient(6, "Dan")]);
+
+        let notifications = transfer_notifications(&transfer(25.0), &from, &to);
+
+        assert_eq!(
+            sent_to(&notifications),
+            vec![
+                (6, "You received 25.00 USD"),
+                (2, "You sent 25.00 USD"),
+                (5, "Large transfer of 25.00 USD from your account"),
+            ]
+        );
+        assert!(notifications[2]
+            .email
+            .body
+            .contains("the\n20.00 USD you asked"));
+    }
+
+    #[test]
+    fn users_on_both_sides_are_not_told() {
+        let from = party("Savings (#5)", "Bob", vec![recipient(2, "Bob")]);
+        let to = party(
+            "account #1",
+            "Bob",
+            vec![recipient(2, "Bob"), recipient(6, "Dan")],
+        );
+
+        let notifications = transfer_notifications(&transfer(25.0), &from, &to);
+
+        assert_eq!(sent_to(&notifications), vec![(6, "You received 25.00 USD")]);
+    }
+
+    #[test]
+    fn security_emails_follow_the_preferences() {
+        let login = event(
+            OutboxEventType::UserLoggedIn,
+            json!({ "ip": "203.0.113.9", "logged_in_at": "2026-10-19T08:30:00Z" }),
+        );
+        let mut quiet = recipient(2, "Bob");
+        quiet.preferences.new_logins = false;
+
+        let email = new_login(&login, &recipient(2, "Bob")).unwrap().email;
+        assert!(email.body.contains("203.0.113.9"));
+        assert!(email.body.contains("2026-10-19 08:30 UTC"));
+        assert!(new_login(&login, &quiet).is_none());
+    }
+
+    #[actix_web::test]
+    async fn a_retry_skips_users_already_emailed() {
+        let from = party("account #1", "Bob", vec![recipient(2, "Bob")]);
+        let to = party(
+            "account #3",
+            "Carol",
+            vec![recipient(3, "Carol"), recipient(6, "Dan")],
+        );
+        let notifications = transfer_notifications(&transfer(25.0), &from, &to);
+        let transport = MemoryTransport::default();
+        let recorded = std::sync::Mutex::new(Vec::new());
+
+        deliver(&transport, notifications, &[3], |user_id| {
+            recorded.lock().unwrap().push(user_id);
+            async { Ok(()) }
+        })
+        .await
+        .unwrap();
+
+        let sent: Vec<String> = transport.sent().into_iter().map(|email| email.to).collect();
+        assert_eq!(sent, ["dan@example.com", "bob@example.com"]);
+        assert_eq!(*recorded.lock().unwrap(), [6, 2]);
+    }
+}
@@ -0,0 +1,74 @@
+use super::transport::Email;
+
+/// The emails users can receive. Each template is a text file under `templates/email`
+/// starting with a `Subject:` line, followed by a blank line and the body, with
+/// `{{name}}` placeholders for the values it is rendered with.
+#[derive(Clone, Copy, Debug)]
+pub enum Template {
+    MoneyReceived,
+    MoneySent,
+    LargeTransfer,
+    NewLogin,
+    PasswordChanged,
+}
+
+impl Template {
+    fn source(self) -> &'static str {
+        match self {
+            Self::MoneyReceived => include_str!("../../templates/email/money_received.txt"),
+            Self::MoneySent => include_str!("../../templates/email/money_sent.txt"),
+            Self::LargeTransfer => include_str!("../../templates/email/large_transfer.txt"),
+            Self::NewLogin => include_str!("../../templates/email/new_login.txt"),
+            Self::PasswordChanged => include_str!("../../templates/email/password_changed.txt"),
+        }
+    }
+
+    /// Fills in the template's placeholders; placeholders without a value are left as is
+    pub fn render(self, to: &str, values: &[(&str, String)]) -> Email {
+        let mut text = self.source().to_string();
+        for (name, value) in values {
+            text = text.replace(&format!("{{{{{name}}}}}"), value);
+        }
+
+        let (subject, body) = text.split_once("\n\n").unwrap_or((&text, ""));
+        Email {
+            to: to.to_string(),
+            subject: subject
+                .strip_prefix("Subject:")
+                .unwrap_or(subject)
+                .trim()
+                .to_string(),
+            body: body.trim_end().to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_the_subject_and_body() {
+        let email = Template::PasswordChanged.render(
+            "bob@example.com",
+            &[
+                ("name", "Bob".to_string()),
+                ("date", "2026-10-19 08:30 UTC".to_string()),
+            ],
+        );
+
+        assert_eq!(email.to, "bob@example.com");
+        assert!(!email.subject.starts_with("Subject:"));
+        assert!(email.body.starts_with("Hi Bob,"));
+        assert!(email.body.contains("2026-10-19 08:30 UTC"));
+        assert!(!email.body.ends_with('\n'));
+    }
+
+    #[test]
+    fn keeps_placeholders_without_a_value() {
+        let email = Template::MoneySent.render("bob@example.com", &[]);
+
+        assert_eq!(email.subject, "You sent {{amount}}");
+        assert!(email.body.contains("Hi {{name}},"));
+    }
+}
@@ -0,0 +1,207 @@
+use std::env;
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use common::error::thiserror;
+use lettre::message::header::ContentType;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use tokio::fs::File;
+use tokio::io::AsyncWriteExt;
+
+/// A rendered email, addressed to one user
+#[derive(Clone, Debug)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Why an email could not be sent
+#[derive(thiserror::Error, Debug)]
+pub enum EmailError {
+    #[error("Could not write the email: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid email address: {0}")]
+    Address(#[from] lettre::address::AddressError),
+    #[error("Could not build the email: {0}")]
+    Message(#[from] lettre::error::Error),
+    #[error("SMTP delivery failed: {0}")]
+    Smtp(#[from] lettre::transport::smtp::Error),
+}
+
+/// Delivers emails. `send` returns once the email is handed over, so that a failed
+/// send is retried.
+#[async_trait]
+pub trait EmailTransport: Send + Sync {
+    async fn send(&self, email: &Email) -> Result<(), EmailError>;
+}
+
+/// Builds the transport selected by `EMAIL_TRANSPORT`: `smtp`, `file` or `memory`.
+/// Returns `None`, and no emails are sent, if it is unset.
+///
+/// The SMTP transport connects to `SMTP_HOST` on `SMTP_PORT` (default 587) using
+/// `SMTP_TLS` (`starttls` by default, `tls` or `none`), logs in with `SMTP_USERNAME` and
+/// `SMTP_PASSWORD` if set, and sends from `EMAIL_FROM`. The file transport appends to
+/// `EMAIL_LOG_FILE` (default `emails.log`).
+pub fn from_env() -> Option<Box<dyn EmailTransport>> {
+    let name = env::var("EMAIL_TRANSPORT").ok()?;
+    let transport: Box<dyn EmailTransport> = match name.trim() {
+        "smtp" => Box::new(SmtpTransport::from_env()),
+        "file" => Box::new(FileTransport::open(
+            &env::var("EMAIL_LOG_FILE").unwrap_or_else(|_| "emails.log".to_string()),
+        )),
+        "memory" => Box::new(MemoryTransport::default()),
+        other => panic!("Unknown email transport {other:?} in EMAIL_TRANSPORT"),
+    };
+    Some(transport)
+}
+
+/// Sends emails through an SMTP server
+pub struct SmtpTransport {
+    mailer: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpTransport {
+    fn from_env() -> Self {
+        let host = env::var("SMTP_HOST").expect("SMTP_HOST must be set for the smtp transport");
+        let port = env::var("SMTP_PORT")
+            .ok()
+            .map(|port| port.parse().expect("SMTP_PORT must be a port number"))
+            .unwrap_or(587);
+        let mut builder = match env::var("SMTP_TLS").as_deref().unwrap_or("starttls") {
+            "starttls" => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&host)
+                .expect("SMTP_HOST must be a valid host name"),
+            "tls" => AsyncSmtpTransport::<Tokio1Executor>::relay(&host)
+                .expect("SMTP_HOST must be a valid host name"),
+            "none" => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&host),
+            other => panic!("Unknown SMTP_TLS mode {other:?}"),
+        }
+        .port(port);
+        if let (Ok(username), Ok(password)) = (env::var("SMTP_USERNAME"), env::var("SMTP_PASSWORD"))
+        {
+            builder = builder.credentials(Credentials::new(username, password));
+        }
+
+        let from = env::var("EMAIL_FROM")
+            .expect("EMAIL_FROM must be set for the smtp transport")
+            .parse()
+            .expect("EMAIL_FROM must be an email address");
+        Self {
+            mailer: builder.build(),
+            from,
+        }
+    }
+}
+
+#[async_trait]
+impl EmailTransport for SmtpTransport {
+    async fn send(&self, email: &Email) -> Result<(), EmailError> {
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(email.to.parse()?)
+            .subject(&email.subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(email.body.clone())?;
+        self.mailer.send(message).await?;
+        Ok(())
+    }
+}
+
+/// Appends every email to a file, for local runs. Writes go through `tokio::fs`, so a
+/// slow disk does not block the runtime.
+pub struct FileTransport {
+    file: tokio::sync::Mutex<File>,
+}
+
+impl FileTransport {
+    fn open(path: &str) -> Self {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .unwrap_or_else(|err| panic!("Could not open the email log {path:?}: {err}"));
+        Self {
+            file: tokio::sync::Mutex::new(File::from_std(file)),
+        }
+    }
+}
+
+#[async_trait]
+impl EmailTransport for FileTransport {
+    async fn send(&self, email: &Email) -> Result<(), EmailError> {
+        let entry = format!(
+            "Date: {}\nTo: {}\nSubject: {}\n\n{}\n\n",
+            Utc::now().to_rfc2822(),
+            email.to,
+            email.subject,
+            email.body
+        );
+        let mut file = self.file.lock().await;
+        file.write_all(entry.as_bytes()).await?;
+        file.sync_data().await?;
+        Ok(())
+    }
+}
+
+/// Keeps every email in memory instead of sending it, for tests
+#[derive(Default)]
+pub struct MemoryTransport {
+    sent: Mutex<Vec<Email>>,
+}
+
+impl MemoryTransport {
+    /// The emails sent so far, oldest first
+    #[cfg(test)]
+    pub fn sent(&self) -> Vec<Email> {
+        self.sent.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl EmailTransport for MemoryTransport {
+    async fn send(&self, email: &Email) -> Result<(), EmailError> {
+        self.sent.lock().unwrap().push(email.clone());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn email(to: &str) -> Email {
+        Email {
+            to: to.to_string(),
+            subject: "You sent 25.00 USD".to_string(),
+            body: "Hi Bob,".to_string(),
+        }
+    }
+
+    #[actix_web::test]
+    async fn memory_transport_keeps_emails_in_order() {
+        let transport = MemoryTransport::default();
+        transport.send(&email("bob@example.com")).await.unwrap();
+        transport.send(&email("carol@example.com")).await.unwrap();
+
+        let sent: Vec<String> = transport.sent().into_iter().map(|email| email.to).collect();
+        assert_eq!(sent, ["bob@example.com", "carol@example.com"]);
+    }
+
+    #[actix_web::test]
+    async fn file_transport_appends_emails() {
+        let path = env::temp_dir().join(format!("emails-{}.log", std::process::id()));
+        let path = path.to_str().unwrap();
+        let transport = FileTransport::open(path);
+        transport.send(&email("bob@example.com")).await.unwrap();
+        transport.send(&email("carol@example.com")).await.unwrap();
+
+        let log = std::fs::read_to_string(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert!(log.contains("To: bob@example.com\nSubject: You sent 25.00 USD\n\nHi Bob,"));
+        assert_eq!(log.matches("\nTo: ").count(), 2);
+    }
+}
@@ -0,0 +1,149 @@
+use db::account_members::AccountRole;
+use db::accounts::{main_account_id, AccountScope};
+use db::db_client::DbClient;
+use db::organizations::OrganizationRole;
+use entity::accounts;
+
+use crate::middlewares::auth::JWTClaim;
+use crate::util::{ApiError, AuthError};
+
+/// What a user wants to do with an account.
+/// - `View`: read details, balance and history (every member)
+/// - `Spend`: send money from the account (owners and spenders; organization admins,
+///   approvers and spenders)
+/// - `Approve`: approve transfers that need a second user (owners; organization admins
+///   and approvers)
+/// - `Manage`: change settings and membership (owners; organization admins)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccountAccess {
+    View,
+    Spend,
+    Approve,
+    Manage,
+}
+
+impl AccountAccess {
+    pub fn allowed_for(self, role: AccountRole) -> bool {
+        match self {
+            AccountAccess::View => true,
+            AccountAccess::Spend => matches!(role, AccountRole::Owner | AccountRole::Spender),
+            AccountAccess::Approve | AccountAccess::Manage => role == AccountRole::Owner,
+        }
+    }
+
+    pub fn allowed_for_organization(self, role: OrganizationRole) -> bool {
+        match self {
+            AccountAccess::View => true,
+            AccountAccess::Spend => matches!(
+                role,
+                OrganizationRole::Admin | OrganizationRole::Approver | OrganizationRole::Spender
+            ),
+            AccountAccess::Approve => {
+                matches!(role, OrganizationRole::Admin | OrganizationRole::Approver)
+            }
+            AccountAccess::Manage => role == OrganizationRole::Admin,
+        }
+    }
+}
+
+/// Loads an account and checks that the caller's role grants `access` to it.
+/// Every account and transaction endpoint authorizes through here.
+pub async fn authorize_account(
+    db: &DbClient,
+    account_id: i32,
+    claim: &JWTClaim,
+    access: AccountAccess,
+) -> Result<accounts::Model, ApiError> {
+    let account = db
+        .account
+        .find_account(account_id)
+        .await?
+        .ok_or(ApiError::AuthError(AuthError::AccountNotFound))?;
+
+    if !is_allowed(db, &account, claim, access).await? {
+        return Err(ApiError::AuthError(AuthError::Unauthorized));
+    }
+
+    Ok(account)
+}
+
+/// Whether the caller's role on the account grants `access`
+pub async fn has_access(
+    db: &DbClient,
+    account_id: i32,
+    claim: &JWTClaim,
+    access: AccountAccess,
+) -> Result<bool, ApiError> {
+    match db.account.find_account(account_id).await? {
+        Some(account) => is_allowed(db, &account, claim, access).await,
+        None => Ok(false),
+    }
+}
+
+/// The accounts listed for the caller: those of the active organization, or the
+/// user's own accounts outside of an organization context
+pub async fn account_scope(db: &DbClient, claim: &JWTClaim) -> Result<AccountScope, ApiError> {
+    match claim.organization_id() {
+        Some(organization_id) => {
+            organization_role(db, organization_id, claim).await?;
+            Ok(AccountScope::Organization(organization_id))
+        }
+        None => Ok(AccountScope::Member(claim.id())),
+    }
+}
+
+/// Ids of every account in the caller's scope, pockets included
+pub async fn scope_account_ids(db: &DbClient, claim: &JWTClaim) -> Result<Vec<i32>, ApiError> {
+    let accounts = db
+        .account
+        .list_accounts(account_scope(db, claim).await?)
+        .await?;
+    let mut ids: Vec<i32> = accounts.iter().map(|account| account.id).collect();
+    let pockets = db.account.list_pockets(&ids).await?;
+    ids.extend(pockets.iter().map(|pocket| pocket.id));
+    Ok(ids)
+}
+
+/// The caller's role in the organization, failing unless the organization is
+/// active in the token and the user still belongs to it
+pub async fn organization_role(
+    db: &DbClient,
+    organization_id: i32,
+    claim: &JWTClaim,
+) -> Result<OrganizationRole, ApiError> {
+    if claim.organization_id() != Some(organization_id) {
+        return Err(ApiError::AuthError(AuthError::Unauthorized));
+    }
+    let membership = db
+        .organization
+        .find_membership(organization_id, claim.id())
+        .await?
+        .ok_or(ApiError::AuthError(AuthError::Unauthorized))?;
+    Ok(membership.role)
+}
+
+/// Organization accounts are only reachable with that organization active in the token.
+/// Pockets have no members of their own and follow their main account.
+async fn is_allowed(
+    db: &DbClient,
+    account: &accounts::Model,
+    claim: &JWTClaim,
+    access: AccountAccess,
+) -> Result<bool, ApiError> {
+    if let Some(organization_id) = account.organization_id {
+        if claim.organization_id() != Some(organization_id) {
+            return Ok(false);
+        }
+        let membership = db
+            .organization
+            .find_membership(organization_id, claim.id())
+            .await?;
+        return Ok(membership.is_some_and(|member| access.allowed_for_organization(member.role)));
+    }
+
+    let membership = db
+        .account_member
+        .find_membership(main_account_id(account), claim.id())
+        .await?;
+    Ok(membership.is_some_and(|member| access.allowed_for(member.role)))
+}
@@ -1,6 +1,12 @@
 use ::serde::Deserialize;
+use chrono::{DateTime, Utc};
+use db::account_members::AccountRole;
+use entity::accounts;
+use num_traits::cast::ToPrimitive;
 use serde::Serialize;
 
+use crate::validation::{FieldError, Validate, Validator};
+
 /// Request body for creating a new account
 #[derive(Deserialize)]
 pub struct CreateAccountRequest {
@@ -8,17 +14,27 @@ pub struct CreateAccountRequest {
     pub initial_balance: Option<f64>,
 }
 
+impl Validate for CreateAccountRequest {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::new();
+        if let Some(initial_balance) = self.initial_balance {
+            v.non_negative_amount(initial_balance, "initial_balance");
+        }
+        v.finish()
+    }
+}
+
 /// Response for account creation
 #[derive(Serialize)]
 pub struct CreateAccountResponse {
     account_id: i32,
     user_id: i32,
     balance: f64,
-    created_at: String,
+    created_at: DateTime<Utc>,
 }
 
 impl CreateAccountResponse {
-    pub fn new(account_id: i32, user_id: i32, balance: f64, created_at: String) -> Self {
+    pub fn new(account_id: i32, user_id: i32, balance: f64, created_at: DateTime<Utc>) -> Self {
         Self {
             account_id,
             user_id,
@@ -28,23 +44,116 @@ impl CreateAccountResponse {
     }
 }
 
-/// Response for account details
+/// Response for account details.
+/// Main accounts carry their pockets and a `total_balance` that includes them.
 #[derive(Debug, Serialize)]
 pub struct AccountResponse {
     pub id: i32,
     pub user_id: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent_account_id: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub organization_id: Option<i32>,
+    pub name: Option<String>,
     pub balance: f64,
-    pub created_at: String,
+    /// Part of the balance held by open disputes
+    pub held_balance: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_balance: Option<f64>,
+    pub alias: Option<String>,
+    /// Transfers above this amount need a second user's approval
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub approval_threshold: Option<f64>,
+    /// Set while the account is frozen and cannot send money
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frozen_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frozen_reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pockets: Option<Vec<AccountResponse>>,
 }
 
 impl AccountResponse {
-    pub fn new(id: i32, user_id: i32, balance: f64, created_at: String) -> Self {
+    /// Nests the pockets under a main account and adds their balances to its total
+    pub fn with_pockets(mut self, pockets: Vec<accounts::Model>) -> Self {
+        let pockets: Vec<AccountResponse> = pockets.into_iter().map(Self::from).collect();
+        let pocket_total: f64 = pockets.iter().map(|pocket| pocket.balance).sum();
+        self.total_balance = Some(self.balance + pocket_total);
+        self.pockets = Some(pockets);
+        self
+    }
+}
+
+impl From<accounts::Model> for AccountResponse {
+    fn from(account: accounts::Model) -> Self {
         Self {
-            id,
-            user_id,
-            balance,
-            created_at,
+            id: account.id,
+            user_id: account.user_id,
+            parent_account_id: account.parent_account_id,
+            organization_id: account.organization_id,
+            name: account.name,
+            balance: account.balance.to_f64().unwrap(),
+            held_balance: account.held_balance.to_f64().unwrap(),
+            total_balance: None,
+            alias: account.alias,
+            approval_threshold: account
+                .approval_threshold
+                .map(|threshold| threshold.to_f64().unwrap()),
+            frozen_at: account.frozen_at.map(|at| at.with_timezone(&Utc)),
+            frozen_reason: account.frozen_reason,
+            created_at: account.created_at.with_timezone(&Utc),
+            updated_at: account.updated_at.with_timezone(&Utc),
+            pockets: None,
+        }
+    }
+}
+
+/// Request body for setting or clearing an account alias
+#[derive(Deserialize)]
+pub struct SetAliasRequest {
+    pub alias: Option<String>,
+}
+
+impl SetAliasRequest {
+    /// The alias as stored and looked up: trimmed and lowercased
+    pub fn normalized_alias(&self) -> Option<String> {
+        self.alias.as_ref().map(|alias| alias.trim().to_lowercase())
+    }
+}
+
+impl Validate for SetAliasRequest {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::new();
+        if let Some(alias) = self.normalized_alias() {
+            v.length(&alias, "alias", 3, 32);
+            v.check(
+                alias
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "._-".contains(c)),
+                "alias",
+                "format",
+                "May only contain letters, digits, '.', '_' and '-'",
+            );
         }
+        v.finish()
+    }
+}
+
+/// Request body for setting the approval threshold of an account
+#[derive(Debug, Deserialize)]
+pub struct SetApprovalThresholdRequest {
+    pub threshold: Option<f64>,
+}
+
+impl Validate for SetApprovalThresholdRequest {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::new();
+        if let Some(threshold) = self.threshold {
+            v.non_negative_amount(threshold, "threshold");
+        }
+        v.finish()
     }
 }
 
@@ -52,6 +161,7 @@ impl AccountResponse {
 #[derive(Debug, Serialize)]
 pub struct ListAccountsResponse {
     pub accounts: Vec<AccountResponse>,
+    pub next_cursor: Option<String>,
 }
 
 /// Response for account balance query
@@ -59,13 +169,112 @@ pub struct ListAccountsResponse {
 pub struct GetBalanceResponse {
     pub account_id: i32,
     pub balance: f64,
+    pub held_balance: f64,
+    pub available_balance: f64,
+    /// Balance including the pockets of a main account
+    pub total_balance: f64,
 }
 
 impl GetBalanceResponse {
-    pub fn new(account_id: i32, balance: f64) -> Self {
+    pub fn new(
+        account_id: i32,
+        balance: f64,
+        held_balance: f64,
+        available_balance: f64,
+        total_balance: f64,
+    ) -> Self {
         Self {
             account_id,
             balance,
+            held_balance,
+            available_balance,
+            total_balance,
+        }
+    }
+}
+
+/// Request body for adding a user to an account
+#[derive(Deserialize)]
+pub struct AddMemberRequest {
+    pub username: String,
+    pub role: AccountRole,
+}
+
+impl Validate for AddMemberRequest {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::new();
+        v.not_blank(&self.username, "username");
+        v.finish()
+    }
+}
+
+/// Response for a single account member
+#[derive(Debug, Serialize)]
+pub struct MemberResponse {
+    pub user_id: i32,
+    pub username: String,
+    pub display_name: Option<String>,
+    pub role: AccountRole,
+    pub joined_at: DateTime<Utc>,
+}
+
+impl MemberResponse {
+    pub fn new(
+        user_id: i32,
+        username: String,
+        display_name: Option<String>,
+        role: AccountRole,
+        joined_at: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            user_id,
+            username,
+            display_name,
+            role,
+            joined_at,
         }
     }
 }
+
+/// Response for listing the members of an account
+#[derive(Debug, Serialize)]
+pub struct ListMembersResponse {
+    pub members: Vec<MemberResponse>,
+}
+
+/// Request body for opening a pocket under a main account
+#[derive(Deserialize)]
+pub struct CreatePocketRequest {
+    pub name: String,
+}
+
+impl Validate for CreatePocketRequest {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::new();
+        v.not_blank(&self.name, "name");
+        v.length(self.name.trim(), "name", 1, 64);
+        v.finish()
+    }
+}
+
+/// Request body for moving money between a main account and its pockets
+#[derive(Deserialize)]
+pub struct MoveMoneyRequest {
+    pub from_account_id: i32,
+    pub to_account_id: i32,
+    pub amount: f64,
+}
+
+impl Validate for MoveMoneyRequest {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::new();
+        v.positive_amount(self.amount, "amount");
+        v.check(
+            self.from_account_id != self.to_account_id,
+            "to_account_id",
+            "same_account",
+            "Cannot move money to the account it is moved from",
+        );
+        v.finish()
+    }
+}
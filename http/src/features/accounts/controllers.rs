@@ -1,17 +1,27 @@
-use actix_web::{get, post, web, Responder};
-use chrono::Local;
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse, Responder};
+use chrono::Utc;
 
 use crate::{
     app_state::AppState,
-    middlewares::auth::JWTClaim,
+    features::transactions::transaction_types::TransactionResponse,
+    middlewares::{audit, auth::JWTClaim},
+    pagination::{next_cursor, PageQuery},
     util::{ApiError, AuthError},
+    validation::{ValidJson, ValidQuery},
 };
 
+use super::access::{account_scope, authorize_account, organization_role, AccountAccess};
 use super::account_types::{
-    AccountResponse, CreateAccountRequest, CreateAccountResponse, GetBalanceResponse,
-    ListAccountsResponse,
+    AccountResponse, AddMemberRequest, CreateAccountRequest, CreateAccountResponse,
+    CreatePocketRequest, GetBalanceResponse, ListAccountsResponse, ListMembersResponse,
+    MemberResponse, MoveMoneyRequest, SetAliasRequest, SetApprovalThresholdRequest,
 };
+use super::events::account_event_stream;
 
+use db::accounts::available_balance;
+use db::db_client::DbClient;
+use db::organizations::OrganizationRole;
+use entity::accounts;
 use num_traits::cast::ToPrimitive;
 
 type State = web::Data<AppState>;
@@ -25,14 +35,16 @@ type State = web::Data<AppState>;
 ///     "account_id": integer,
 ///     "user_id": integer,
 ///     "balance": float,
-///     "created_at": string
+///     "created_at": string (RFC 3339)
 /// }
-/// Requires authentication. Initial balance defaults to 0 if not provided
+/// Requires authentication. Initial balance defaults to 0 if not provided.
+/// With an organization active in the token the account belongs to the organization,
+/// which requires the organization admin role
 #[post("/create")]
 async fn create_account(
     state: State,
     claim: JWTClaim,
-    request: web::Json<CreateAccountRequest>,
+    request: ValidJson<CreateAccountRequest>,
 ) -> Result<impl Responder, ApiError> {
     let db = state.db();
     let user_id = claim.id();
@@ -43,9 +55,17 @@ async fn create_account(
         .await?
         .ok_or(ApiError::AuthError(AuthError::UserNotFound))?;
 
+    let organization_id = claim.organization_id();
+    if let Some(organization_id) = organization_id {
+        let role = organization_role(db, organization_id, &claim).await?;
+        if role != OrganizationRole::Admin {
+            return Err(ApiError::AuthError(AuthError::Unauthorized));
+        }
+    }
+
     let account_id = db
         .account
-        .create_account(request.initial_balance, user_id)
+        .create_account(request.initial_balance, user_id, organization_id)
         .await?;
 
     let account = db
@@ -58,7 +78,7 @@ async fn create_account(
         account_id,
         user_id,
         account.balance.to_f64().unwrap(),
-        Local::now().date_naive().to_string(),
+        account.created_at.with_timezone(&Utc),
     );
 
     Ok(web::Json(response))
@@ -70,10 +90,16 @@ async fn create_account(
 /// Response Body: {
 ///     "id": integer,
 ///     "user_id": integer,
+///     "name": string | null,
 ///     "balance": float,
-///     "created_at": string
+///     "held_balance": float,
+///     "total_balance": float (main accounts only, includes pockets),
+///     "alias": string | null,
+///     "created_at": string (RFC 3339),
+///     "updated_at": string (RFC 3339),
+///     "pockets": [ account, ... ] (main accounts only)
 /// }
-/// Requires authentication. Returns error if account doesn't belong to user
+/// Requires authentication. Returns error if the user is not a member of the account
 #[get("/{account_id}")]
 async fn get_account(
     state: State,
@@ -90,43 +116,44 @@ async fn get_account(
         .await?
         .ok_or(ApiError::AuthError(AuthError::UserNotFound))?;
 
-    let account = db
-        .account
-        .find_account(account_id)
-        .await?
-        .ok_or(ApiError::AuthError(AuthError::AccountNotFound))?;
-
-    if account.user_id != user_id {
-        return Err(ApiError::AuthError(AuthError::Unauthorized));
-    }
-
-    let response = AccountResponse::new(
-        account.id,
-        account.user_id,
-        account.balance.to_f64().unwrap(),
-        Local::now().date_naive().to_string(),
-    );
+    let account = authorize_account(db, account_id, &claim, AccountAccess::View).await?;
+    let response = account_response(db, account).await?;
 
     Ok(web::Json(response))
 }
 
-/// List all accounts owned by the authenticated user
+/// List the main accounts the authenticated user is a member of, oldest first,
+/// with their pockets nested under them. With an organization active in the token,
+/// lists the organization's accounts instead
 /// Endpoint: GET /api/account/list/account
+/// Query Parameters: limit (integer, optional), cursor (string, optional)
 /// Response Body: {
 ///     "accounts": [
 ///         {
 ///             "id": integer,
 ///             "user_id": integer,
+///             "name": string | null,
 ///             "balance": float,
-///             "created_at": string
+///             "held_balance": float,
+///             "total_balance": float,
+///             "alias": string | null,
+///             "created_at": string (RFC 3339),
+///             "updated_at": string (RFC 3339),
+///             "pockets": [ account, ... ]
 ///         }
-///     ]
+///     ],
+///     "next_cursor": string | null
 /// }
 /// Requires authentication
 #[get("/list/account")]
-async fn list_accounts(state: State, claim: JWTClaim) -> Result<impl Responder, ApiError> {
+async fn list_accounts(
+    state: State,
+    claim: JWTClaim,
+    page: ValidQuery<PageQuery>,
+) -> Result<impl Responder, ApiError> {
     let db = state.db();
     let user_id = claim.id();
+    let page_request = page.page_request()?;
 
     let _user = db
         .user
@@ -134,22 +161,28 @@ async fn list_accounts(state: State, claim: JWTClaim) -> Result<impl Responder,
         .await?
         .ok_or(ApiError::AuthError(AuthError::UserNotFound))?;
 
-    let accounts = db.account.list_user_accounts(user_id).await?;
+    let scope = account_scope(db, &claim).await?;
+    let accounts = db.account.list_accounts_page(scope, page_request).await?;
+    let next_cursor = next_cursor(&accounts);
+
+    let account_ids: Vec<i32> = accounts.items.iter().map(|account| account.id).collect();
+    let mut pockets = db.account.list_pockets(&account_ids).await?;
 
     let account_responses: Vec<AccountResponse> = accounts
+        .items
         .into_iter()
         .map(|account| {
-            AccountResponse::new(
-                account.id,
-                account.user_id,
-                account.balance.to_f64().unwrap(),
-                Local::now().date_naive().to_string(),
-            )
+            let (own, rest) = pockets
+                .drain(..)
+                .partition(|pocket| pocket.parent_account_id == Some(account.id));
+            pockets = rest;
+            AccountResponse::from(account).with_pockets(own)
         })
         .collect();
 
     Ok(web::Json(ListAccountsResponse {
         accounts: account_responses,
+        next_cursor,
     }))
 }
 
@@ -158,10 +191,13 @@ async fn list_accounts(state: State, claim: JWTClaim) -> Result<impl Responder,
 /// Path Parameters: account_id (integer)
 /// Response Body: {
 ///     "account_id": integer,
-///     "balance": float
+///     "balance": float,
+///     "held_balance": float,
+///     "available_balance": float (balance minus held_balance),
+///     "total_balance": float (balance including pockets)
 /// }
 /// Requires authentication.
-/// Returns error if an account doesn't belong to user
+/// Returns error if the user is not a member of the account
 #[get("/{account_id}/balance")]
 async fn get_balance(
     state: State,
@@ -178,15 +214,419 @@ async fn get_balance(
         .await?
         .ok_or(ApiError::AuthError(AuthError::UserNotFound))?;
 
+    let account = authorize_account(db, account_id, &claim, AccountAccess::View).await?;
+    let pockets = if account.parent_account_id.is_none() {
+        db.account.list_pockets(&[account.id]).await?
+    } else {
+        vec![]
+    };
+    let total_balance = pockets
+        .iter()
+        .fold(account.balance, |total, pocket| total + pocket.balance);
+
+    let response = GetBalanceResponse::new(
+        account_id,
+        account.balance.to_f64().unwrap(),
+        account.held_balance.to_f64().unwrap(),
+        available_balance(&account).to_f64().unwrap(),
+        total_balance.to_f64().unwrap(),
+    );
+    Ok(web::Json(response))
+}
+
+/// Stream the account's transactions and balance changes as they happen
+/// Endpoint: GET /api/account/{account_id}/events
+/// Path Parameters: account_id (integer)
+/// Headers: Last-Event-ID (string, optional) - resume after this event
+/// Response: `text/event-stream` of events like
+///     id: "{transaction_id}-{event_id}"
+///     event: "transaction.created" | "transaction.reversed" | "account.balance_changed"
+///     data: the transaction or the account with its new balances, as JSON
+/// Requires authentication. Returns error if the user is not a member of the account.
+/// Browsers reconnect with `Last-Event-ID` on their own; the events missed in between
+/// are sent first
+#[get("/{account_id}/events")]
+async fn account_events(
+    state: State,
+    claim: JWTClaim,
+    path: web::Path<i32>,
+    request: HttpRequest,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+    let account = authorize_account(db, path.into_inner(), &claim, AccountAccess::View).await?;
+
+    let last_event_id = request
+        .headers()
+        .get("Last-Event-ID")
+        .and_then(|value| value.to_str().ok());
+    let events = account_event_stream(state.clone(), account.id, last_event_id).await?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .insert_header(("X-Accel-Buffering", "no"))
+        .streaming(events))
+}
+
+/// Make an account the user's default receiving account
+/// Endpoint: PUT /api/account/{account_id}/default
+/// Path Parameters: account_id (integer)
+/// Response Body: {
+///     "id": integer,
+///     "user_id": integer,
+///     "name": string | null,
+///     "balance": float,
+///     "held_balance": float,
+///     "total_balance": float (main accounts only, includes pockets),
+///     "alias": string | null,
+///     "created_at": string (RFC 3339),
+///     "updated_at": string (RFC 3339)
+/// }
+/// Requires authentication. Payments addressed to the user's username are credited
+/// to this account. Requires the owner or spender role on the account
+#[put("/{account_id}/default")]
+async fn set_default_account(
+    state: State,
+    claim: JWTClaim,
+    path: web::Path<i32>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+    let user_id = claim.id();
+    let account_id = path.into_inner();
+
+    let account = authorize_account(db, account_id, &claim, AccountAccess::Spend).await?;
+
+    db.user.set_default_account(user_id, account_id).await?;
+
+    let response = account_response(db, account).await?;
+    Ok(web::Json(response))
+}
+
+/// Set or clear the alias payers can use instead of the account id
+/// Endpoint: PUT /api/account/{account_id}/alias
+/// Path Parameters: account_id (integer)
+/// Request Body: {
+///     "alias": string | null
+/// }
+/// Response Body: {
+///     "id": integer,
+///     "user_id": integer,
+///     "name": string | null,
+///     "balance": float,
+///     "held_balance": float,
+///     "total_balance": float (main accounts only, includes pockets),
+///     "alias": string | null,
+///     "created_at": string (RFC 3339),
+///     "updated_at": string (RFC 3339)
+/// }
+/// Requires authentication. Aliases are lowercased, must be 3 to 32 characters of
+/// a-z, 0-9, '.', '_' or '-' and may not collide with another alias or a username.
+/// Requires the owner role on the account
+#[put("/{account_id}/alias")]
+async fn set_account_alias(
+    state: State,
+    claim: JWTClaim,
+    path: web::Path<i32>,
+    request: ValidJson<SetAliasRequest>,
+    http_request: HttpRequest,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+    let account_id = path.into_inner();
+
+    let account = authorize_account(db, account_id, &claim, AccountAccess::Manage).await?;
+    audit::record_before(&http_request, &account_response(db, account).await?);
+
+    let alias = request.normalized_alias();
+    if let Some(alias) = &alias {
+        let taken_by_account = db
+            .account
+            .find_account_by_alias(alias)
+            .await?
+            .is_some_and(|other| other.id != account_id);
+        let taken_by_user = db.user.handle_taken(alias).await?;
+        if taken_by_account || taken_by_user {
+            return Err(ApiError::AliasTaken);
+        }
+    }
+
+    db.account.set_alias(account_id, alias).await?;
+
+    let account = db
+        .account
+        .find_account(account_id)
+        .await?
+        .ok_or(ApiError::AuthError(AuthError::AccountNotFound))?;
+
+    let response = account_response(db, account).await?;
+
+    Ok(web::Json(response))
+}
+
+/// Set or clear the amount above which transfers need a second user's approval
+/// Endpoint: PUT /api/account/{account_id}/approval-threshold
+/// Path Parameters: account_id (integer)
+/// Request Body: {
+///     "threshold": float | null
+/// }
+/// Response Body: account as returned by PUT /api/account/{account_id}/alias,
+///     with "approval_threshold": float when set
+/// Requires authentication. The threshold only applies to organization accounts and
+/// accounts with more than one member, and covers the account's pockets.
+/// Requires the owner role on the account. Returns 409 for pockets
+#[put("/{account_id}/approval-threshold")]
+async fn set_approval_threshold(
+    state: State,
+    claim: JWTClaim,
+    path: web::Path<i32>,
+    request: ValidJson<SetApprovalThresholdRequest>,
+    http_request: HttpRequest,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+    let account_id = path.into_inner();
+
+    let account = authorize_account(db, account_id, &claim, AccountAccess::Manage).await?;
+    if account.parent_account_id.is_some() {
+        return Err(ApiError::Conflict(
+            "Pockets follow the approval threshold of their main account".to_string(),
+        ));
+    }
+    audit::record_before(&http_request, &account_response(db, account).await?);
+
+    db.account
+        .set_approval_threshold(account_id, request.threshold)
+        .await?;
+
     let account = db
         .account
         .find_account(account_id)
         .await?
         .ok_or(ApiError::AuthError(AuthError::AccountNotFound))?;
 
-    if account.user_id != user_id {
-        return Err(ApiError::AuthError(AuthError::Unauthorized));
+    let response = account_response(db, account).await?;
+
+    Ok(web::Json(response))
+}
+
+/// List the members of an account and their roles
+/// Endpoint: GET /api/account/{account_id}/members
+/// Path Parameters: account_id (integer)
+/// Response Body: {
+///     "members": [
+///         {
+///             "user_id": integer,
+///             "username": string,
+///             "display_name": string | null,
+///             "role": "owner" | "spender" | "viewer",
+///             "joined_at": string (RFC 3339)
+///         }
+///     ]
+/// }
+/// Requires authentication. Returns error if the user is not a member of the account
+#[get("/{account_id}/members")]
+async fn list_members(
+    state: State,
+    claim: JWTClaim,
+    path: web::Path<i32>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+    let account_id = path.into_inner();
+
+    authorize_account(db, account_id, &claim, AccountAccess::View).await?;
+
+    let members = member_responses(db, account_id).await?;
+    Ok(web::Json(ListMembersResponse { members }))
+}
+
+/// Add a user to an account with the given role
+/// Endpoint: POST /api/account/{account_id}/members
+/// Path Parameters: account_id (integer)
+/// Request Body: {
+///     "username": string,
+///     "role": "owner" | "spender" | "viewer"
+/// }
+/// Response Body: {
+///     "user_id": integer,
+///     "username": string,
+///     "display_name": string | null,
+///     "role": string,
+///     "joined_at": string (RFC 3339)
+/// }
+/// Requires authentication and the owner role on the account.
+/// Spenders can send money from the account, viewers can only see it.
+/// Returns 409 if the user is already a member
+#[post("/{account_id}/members")]
+async fn add_member(
+    state: State,
+    claim: JWTClaim,
+    path: web::Path<i32>,
+    request: ValidJson<AddMemberRequest>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+    let account_id = path.into_inner();
+
+    let account = authorize_account(db, account_id, &claim, AccountAccess::Manage).await?;
+    if account.organization_id.is_some() {
+        return Err(ApiError::Conflict(
+            "Organization accounts are shared through organization membership".to_string(),
+        ));
     }
-    let response = GetBalanceResponse::new(account_id, account.balance.to_f64().unwrap());
+
+    let username = request.username.trim().trim_start_matches('@').to_string();
+    let member_user = db
+        .user
+        .find_user_by_username(username)
+        .await?
+        .ok_or(ApiError::AuthError(AuthError::UserNotFound))?;
+
+    let member = db
+        .account_member
+        .add_member(account_id, member_user.id, request.role)
+        .await?;
+
+    let response = MemberResponse::new(
+        member_user.id,
+        member_user.username,
+        member_user.display_name,
+        member.role,
+        member.created_at.with_timezone(&Utc),
+    );
+    Ok(web::Json(response))
+}
+
+/// Remove a member from an account
+/// Endpoint: DELETE /api/account/{account_id}/members/{user_id}
+/// Path Parameters: account_id (integer), user_id (integer)
+/// Response: 204 No Content
+/// Requires authentication. Owners can remove anyone and every member can remove
+/// themselves. Returns 409 when removing the last owner
+#[delete("/{account_id}/members/{member_user_id}")]
+async fn remove_member(
+    state: State,
+    claim: JWTClaim,
+    path: web::Path<(i32, i32)>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+    let user_id = claim.id();
+    let (account_id, member_user_id) = path.into_inner();
+
+    let access = if member_user_id == user_id {
+        AccountAccess::View
+    } else {
+        AccountAccess::Manage
+    };
+    authorize_account(db, account_id, &claim, access).await?;
+
+    db.account_member
+        .remove_member(account_id, member_user_id)
+        .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Open a savings pocket under a main account
+/// Endpoint: POST /api/account/{account_id}/pockets
+/// Path Parameters: account_id (integer)
+/// Request Body: {
+///     "name": string
+/// }
+/// Response Body: account, with "parent_account_id": integer
+/// Requires authentication and the owner role on the main account.
+/// Pockets start empty, share the main account's members and cannot contain pockets
+#[post("/{account_id}/pockets")]
+async fn create_pocket(
+    state: State,
+    claim: JWTClaim,
+    path: web::Path<i32>,
+    request: ValidJson<CreatePocketRequest>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+    let account_id = path.into_inner();
+
+    authorize_account(db, account_id, &claim, AccountAccess::Manage).await?;
+
+    let pocket = db
+        .account
+        .create_pocket(account_id, request.name.trim().to_string())
+        .await?;
+
+    Ok(web::Json(AccountResponse::from(pocket)))
+}
+
+/// Move money between a main account and its pockets
+/// Endpoint: POST /api/account/{account_id}/move
+/// Path Parameters: account_id (integer) - the main account
+/// Request Body: {
+///     "from_account_id": integer,
+///     "to_account_id": integer,
+///     "amount": float
+/// }
+/// Response Body: transaction, as returned by POST /api/transaction/create
+/// Requires authentication and the owner or spender role on the main account.
+/// Both accounts must be the main account or one of its pockets. Moves settle instantly
+#[post("/{account_id}/move")]
+async fn move_money(
+    state: State,
+    claim: JWTClaim,
+    path: web::Path<i32>,
+    request: ValidJson<MoveMoneyRequest>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+    let account_id = path.into_inner();
+
+    authorize_account(db, account_id, &claim, AccountAccess::Spend).await?;
+
+    let transaction = db
+        .transaction
+        .move_within(
+            account_id,
+            request.from_account_id,
+            request.to_account_id,
+            request.amount,
+        )
+        .await?;
+
+    let response = TransactionResponse::new(
+        transaction.id,
+        transaction.from_account_id,
+        transaction.to_account_id,
+        transaction.amount.to_f64().unwrap(),
+        transaction.status,
+        transaction.created_at.with_timezone(&Utc),
+    );
     Ok(web::Json(response))
 }
+
+/// Account details, with pockets nested under main accounts
+pub(crate) async fn account_response(
+    db: &DbClient,
+    account: accounts::Model,
+) -> Result<AccountResponse, ApiError> {
+    if account.parent_account_id.is_some() {
+        return Ok(AccountResponse::from(account));
+    }
+    let pockets = db.account.list_pockets(&[account.id]).await?;
+    Ok(AccountResponse::from(account).with_pockets(pockets))
+}
+
+/// The members of an account with their user details, oldest membership first
+pub(crate) async fn member_responses(
+    db: &DbClient,
+    account_id: i32,
+) -> Result<Vec<MemberResponse>, ApiError> {
+    let mut members = Vec::new();
+    for member in db.account_member.list_members(account_id).await? {
+        let user = db
+            .user
+            .find_user(member.user_id)
+            .await?
+            .ok_or(ApiError::AuthError(AuthError::UserNotFound))?;
+        members.push(MemberResponse::new(
+            user.id,
+            user.username,
+            user.display_name,
+            member.role,
+            member.created_at.with_timezone(&Utc),
+        ));
+    }
+    Ok(members)
+}
@@ -0,0 +1,240 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::rt::time::{interval, sleep, Interval};
+use actix_web::web::{self, Bytes};
+use db::outbox::{concerns_account, EventPosition};
+use entity::outbox;
+use futures_util::stream::{self, Stream, StreamExt};
+use serde_json::json;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::warn;
+
+use crate::app_state::AppState;
+use crate::constants::{
+    ACCOUNT_EVENTS_HEARTBEAT_SECS, ACCOUNT_EVENTS_HOLD_BACK_RETRY_MS, ACCOUNT_EVENTS_REPLAY_BATCH,
+    ACCOUNT_EVENTS_RETRY_MS,
+};
+use crate::util::ApiError;
+
+/// What the outbox listener passes on to open event streams
+#[derive(Clone, Debug)]
+pub enum OutboxNotice {
+    /// An outbox event that has just been committed
+    Event(Arc<outbox::Model>),
+    /// Notifications may have been lost; streams end so that clients resume from
+    /// their `Last-Event-ID`
+    Reset,
+}
+
+/// Forwards the outbox events committed by any server instance to the streams open on
+/// this one, using Postgres `LISTEN`. Runs for as long as the server does.
+pub async fn listen(state: web::Data<AppState>) {
+    let db = state.db();
+    loop {
+        let mut listener = match db.outbox.listen().await {
+            Ok(listener) => listener,
+            Err(err) => {
+                warn!("Could not listen for outbox events: {}", err);
+                sleep(Duration::from_secs(5)).await;
+                continue;
+            }
+        };
+
+        loop {
+            match listener.next().await {
+                Ok(Some(id)) => {
+                    if state.account_events().receiver_count() == 0 {
+                        continue;
+                    }
+                    match db.outbox.find_event(id).await {
+                        Ok(Some(event)) => {
+                            let _ = state
+                                .account_events()
+                                .send(OutboxNotice::Event(Arc::new(event)));
+                        }
+                        Ok(None) => {}
+                        Err(err) => {
+                            warn!("Could not load outbox event {}: {}", id, err);
+                            let _ = state.account_events().send(OutboxNotice::Reset);
+                        }
+                    }
+                }
+                Ok(None) => {
+                    warn!("Lost the outbox event listener connection, reconnecting");
+                    let _ = state.account_events().send(OutboxNotice::Reset);
+                }
+                Err(err) => {
+                    warn!("Outbox event listener failed: {}", err);
+                    let _ = state.account_events().send(OutboxNotice::Reset);
+                    sleep(Duration::from_secs(5)).await;
+                    break;
+                }
+            }
+        }
+    }
+}
+
+struct StreamState {
+    app: web::Data<AppState>,
+    account_id: i32,
+    /// Events read from the outbox and not sent yet
+    ready: VecDeque<outbox::Model>,
+    /// Position of the last event read from the outbox
+    position: EventPosition,
+    /// The last read filled a whole batch, so there may be more to read right away
+    more: bool,
+    /// The newest event announced on the live channel for this account. It has not been
+    /// read yet while an older transaction is still running.
+    waiting_for: Option<EventPosition>,
+    events: broadcast::Receiver<OutboxNotice>,
+    heartbeat: Interval,
+}
+
+/// Server-sent events for one account: the events after `last_event_id` first, then
+/// every new transaction and balance event as it commits. Events are sent in the order
+/// `events_after` publishes them, with `{transaction_id}-{event_id}` as their id, so a
+/// client resuming from an id cannot miss an event committed late. A comment is sent
+/// every few seconds to keep proxies from closing the connection. The stream ends if
+/// this server may have missed events, and the client reconnects with the last id it saw.
+pub async fn account_event_stream(
+    app: web::Data<AppState>,
+    account_id: i32,
+    last_event_id: Option<&str>,
+) -> Result<impl Stream<Item = Result<Bytes, actix_web::Error>>, ApiError> {
+    // Subscribed before reading the outbox so that nothing committed in between is lost.
+    let events = app.account_events().subscribe();
+    let position = match last_event_id.and_then(resume_position) {
+        Some(position) => position,
+        None => app.db().outbox.current_position().await?,
+    };
+
+    let mut stream_state = StreamState {
+        app,
+        account_id,
+        ready: VecDeque::new(),
+        position,
+        more: false,
+        waiting_for: None,
+        events,
+        heartbeat: interval(Duration::from_secs(ACCOUNT_EVENTS_HEARTBEAT_SECS)),
+    };
+    if last_event_id.is_some() {
+        read_events(&mut stream_state).await?;
+    }
+    let retry =
+        stream::once(async { Ok(Bytes::from(format!("retry: {ACCOUNT_EVENTS_RETRY_MS}\n\n"))) });
+
+    Ok(retry.chain(stream::unfold(stream_state, next_frame)))
+}
+
+/// The position a `Last-Event-ID` of the form `{transaction_id}-{event_id}` refers to.
+/// Anything else is ignored and the stream starts with new events.
+pub fn resume_position(last_event_id: &str) -> Option<EventPosition> {
+    let (transaction_id, event_id) = last_event_id.trim().split_once('-')?;
+    Some(EventPosition {
+        transaction_id: transaction_id.parse().ok()?,
+        event_id: event_id.parse().ok()?,
+    })
+}
+
+/// Reads the next batch of the account's events that can be sent
+async fn read_events(state: &mut StreamState) -> Result<(), ApiError> {
+    let events = state
+        .app
+        .db()
+        .outbox
+        .account_events_after(
+            state.account_id,
+            state.position,
+            ACCOUNT_EVENTS_REPLAY_BATCH,
+        )
+        .await?;
+    state.more = events.len() as u64 == ACCOUNT_EVENTS_REPLAY_BATCH;
+    if let Some(last) = events.last() {
+        state.position = EventPosition::of(last);
+    }
+    if state.waiting_for <= Some(state.position) {
+        state.waiting_for = None;
+    }
+    state.ready.extend(events);
+    Ok(())
+}
+
+async fn next_frame(
+    mut state: StreamState,
+) -> Option<(Result<Bytes, actix_web::Error>, StreamState)> {
+    loop {
+        if let Some(event) = state.ready.pop_front() {
+            return Some((Ok(frame(&event)), state));
+        }
+        if state.more {
+            if let Err(err) = read_events(&mut state).await {
+                warn!("Could not read account events: {}", err);
+                return None;
+            }
+            continue;
+        }
+
+        tokio::select! {
+            notice = state.events.recv() => match notice {
+                Ok(OutboxNotice::Event(event)) => {
+                    let position = EventPosition::of(&event);
+                    if !concerns_account(&event, state.account_id) || position <= state.position {
+                        continue;
+                    }
+                    state.waiting_for = state.waiting_for.max(Some(position));
+                }
+                Ok(OutboxNotice::Reset) | Err(RecvError::Lagged(_)) | Err(RecvError::Closed) => {
+                    return None;
+                }
+            },
+            // Announced events of transactions newer than a running one are read again
+            // until that one ends.
+            _ = sleep(Duration::from_millis(ACCOUNT_EVENTS_HOLD_BACK_RETRY_MS)),
+                if state.waiting_for.is_some() => {}
+            _ = state.heartbeat.tick() => {
+                return Some((Ok(Bytes::from_static(b": keep-alive\n\n")), state));
+            }
+        }
+        if let Err(err) = read_events(&mut state).await {
+            warn!("Could not read account events: {}", err);
+            return None;
+        }
+    }
+}
+
+/// One event in the `text/event-stream` format
+fn frame(event: &outbox::Model) -> Bytes {
+    Bytes::from(format!(
+        "id: {}-{}\nevent: {}\ndata: {}\n\n",
+        event.transaction_id,
+        event.id,
+        json!(event.event_type).as_str().unwrap_or_default(),
+        event.payload
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resumes_from_transaction_and_event_ids() {
+        assert_eq!(
+            resume_position(" 812-45 "),
+            Some(EventPosition {
+                transaction_id: 812,
+                event_id: 45,
+            })
+        );
+    }
+
+    #[test]
+    fn ignores_ids_in_any_other_form() {
+        for last_event_id in ["", "45", "812-", "-45", "812-45-1", "abc-def"] {
+            assert_eq!(resume_position(last_event_id), None, "{last_event_id:?}");
+        }
+    }
+}
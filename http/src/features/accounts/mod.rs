@@ -1,2 +1,4 @@
+pub mod access;
 pub mod account_types;
 pub mod controllers;
+pub mod events;
@@ -0,0 +1,291 @@
+use chrono::{DateTime, Utc};
+use db::adjustments::AdjustmentReason;
+use db::audit::AuditFilter;
+use db::disputes::{DisputeOutcome, DisputeStatus};
+use db::user::UserRole;
+use entity::{audit_log, balance_adjustments, user};
+use num_traits::ToPrimitive;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::features::accounts::account_types::{AccountResponse, MemberResponse};
+use crate::features::disputes::dispute_types::DisputeResponse;
+use crate::validation::{FieldError, Validate, Validator};
+
+/// Query parameters for the dispute queue
+#[derive(Debug, Deserialize)]
+pub struct ListDisputesQuery {
+    pub status: Option<DisputeStatus>,
+}
+
+impl Validate for ListDisputesQuery {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        Ok(())
+    }
+}
+
+/// Response for a page of disputes
+#[derive(Debug, Serialize)]
+pub struct ListDisputesResponse {
+    pub disputes: Vec<DisputeResponse>,
+    pub next_cursor: Option<String>,
+}
+
+/// Outcome of a dispute in `ResolveDisputeRequest`
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutcomeParam {
+    Won,
+    Lost,
+}
+
+impl From<OutcomeParam> for DisputeOutcome {
+    fn from(outcome: OutcomeParam) -> Self {
+        match outcome {
+            OutcomeParam::Won => DisputeOutcome::Won,
+            OutcomeParam::Lost => DisputeOutcome::Lost,
+        }
+    }
+}
+
+/// Request body for resolving a dispute, with an optional closing note for the timeline
+#[derive(Debug, Deserialize)]
+pub struct ResolveDisputeRequest {
+    pub outcome: OutcomeParam,
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+impl Validate for ResolveDisputeRequest {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::new();
+        if let Some(note) = &self.note {
+            v.not_blank(note, "note");
+            v.length(note, "note", 1, 2000);
+        }
+        v.finish()
+    }
+}
+
+/// Query parameters for the audit log
+#[derive(Debug, Deserialize)]
+pub struct ListAuditLogQuery {
+    pub actor_id: Option<i32>,
+    pub action: Option<String>,
+    pub target: Option<String>,
+    pub request_id: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl Validate for ListAuditLogQuery {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::new();
+        if let (Some(since), Some(until)) = (self.since, self.until) {
+            v.check(since < until, "until", "range", "Must be later than since");
+        }
+        v.finish()
+    }
+}
+
+impl ListAuditLogQuery {
+    pub fn filter(&self) -> AuditFilter {
+        AuditFilter {
+            actor_id: self.actor_id,
+            action: self.action.clone(),
+            target: self.target.clone(),
+            request_id: self.request_id.clone(),
+            since: self.since.map(|since| since.fixed_offset()),
+            until: self.until.map(|until| until.fixed_offset()),
+        }
+    }
+}
+
+/// One entry of the audit log, with the hashes that chain it to the entry before it
+#[derive(Debug, Serialize)]
+pub struct AuditEntryResponse {
+    pub id: i32,
+    pub actor_id: Option<i32>,
+    pub action: String,
+    pub target: String,
+    pub status: i16,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+    pub ip: Option<String>,
+    pub request_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub prev_hash: String,
+    pub hash: String,
+}
+
+impl From<audit_log::Model> for AuditEntryResponse {
+    fn from(entry: audit_log::Model) -> Self {
+        Self {
+            id: entry.id,
+            actor_id: entry.actor_id,
+            action: entry.action,
+            target: entry.target,
+            status: entry.status,
+            before: entry.before,
+            after: entry.after,
+            ip: entry.ip,
+            request_id: entry.request_id,
+            created_at: entry.created_at.with_timezone(&Utc),
+            prev_hash: entry.prev_hash,
+            hash: entry.hash,
+        }
+    }
+}
+
+/// Response for a page of the audit log
+#[derive(Debug, Serialize)]
+pub struct ListAuditLogResponse {
+    pub entries: Vec<AuditEntryResponse>,
+    pub next_cursor: Option<String>,
+}
+
+/// Query parameters for searching users
+#[derive(Debug, Deserialize)]
+pub struct SearchUsersQuery {
+    /// Part of the username, display name or email, ignoring case
+    pub q: Option<String>,
+    pub role: Option<UserRole>,
+}
+
+impl Validate for SearchUsersQuery {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::new();
+        if let Some(q) = &self.q {
+            v.not_blank(q, "q");
+            v.length(q, "q", 1, 100);
+        }
+        v.finish()
+    }
+}
+
+/// A user as seen by staff, including their role and email address
+#[derive(Debug, Serialize)]
+pub struct AdminUserResponse {
+    pub id: i32,
+    pub username: String,
+    pub display_name: Option<String>,
+    pub email: Option<String>,
+    pub role: UserRole,
+    pub default_account_id: Option<i32>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<user::Model> for AdminUserResponse {
+    fn from(user: user::Model) -> Self {
+        Self {
+            id: user.id,
+            username: user.username,
+            display_name: user.display_name,
+            email: user.email,
+            role: user.role,
+            default_account_id: user.default_account_id,
+            created_at: user.created_at.with_timezone(&Utc),
+        }
+    }
+}
+
+/// Response for a page of users
+#[derive(Debug, Serialize)]
+pub struct ListUsersResponse {
+    pub users: Vec<AdminUserResponse>,
+    pub next_cursor: Option<String>,
+}
+
+/// A user with the accounts they are a member of
+#[derive(Debug, Serialize)]
+pub struct UserDetailResponse {
+    #[serde(flatten)]
+    pub user: AdminUserResponse,
+    pub accounts: Vec<AccountResponse>,
+}
+
+/// An account with its members
+#[derive(Debug, Serialize)]
+pub struct AccountDetailResponse {
+    #[serde(flatten)]
+    pub account: AccountResponse,
+    pub members: Vec<MemberResponse>,
+}
+
+/// Request body for freezing an account
+#[derive(Debug, Deserialize)]
+pub struct FreezeAccountRequest {
+    pub reason: String,
+}
+
+impl Validate for FreezeAccountRequest {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::new();
+        v.not_blank(&self.reason, "reason");
+        v.length(&self.reason, "reason", 1, 500);
+        v.finish()
+    }
+}
+
+/// Request body for adjusting an account's balance. A positive amount credits the
+/// account, a negative one debits it.
+#[derive(Debug, Deserialize)]
+pub struct CreateAdjustmentRequest {
+    pub amount: f64,
+    pub reason: AdjustmentReason,
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+impl Validate for CreateAdjustmentRequest {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::new();
+        v.check(
+            self.amount.is_finite() && self.amount != 0.0,
+            "amount",
+            "range",
+            "Must be a non-zero amount",
+        );
+        v.amount_limits(self.amount, "amount");
+        if let Some(note) = &self.note {
+            v.not_blank(note, "note");
+            v.length(note, "note", 1, 500);
+        }
+        v.finish()
+    }
+}
+
+/// A manual change to an account's balance
+#[derive(Debug, Serialize)]
+pub struct AdjustmentResponse {
+    pub id: i32,
+    pub account_id: i32,
+    pub adjusted_by: i32,
+    pub amount: f64,
+    pub balance_after: f64,
+    pub reason: AdjustmentReason,
+    pub note: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<balance_adjustments::Model> for AdjustmentResponse {
+    fn from(adjustment: balance_adjustments::Model) -> Self {
+        Self {
+            id: adjustment.id,
+            account_id: adjustment.account_id,
+            adjusted_by: adjustment.adjusted_by,
+            amount: adjustment.amount.to_f64().unwrap(),
+            balance_after: adjustment.balance_after.to_f64().unwrap(),
+            reason: adjustment.reason,
+            note: adjustment.note,
+            created_at: adjustment.created_at.with_timezone(&Utc),
+        }
+    }
+}
+
+/// Response for a page of an account's adjustments
+#[derive(Debug, Serialize)]
+pub struct ListAdjustmentsResponse {
+    pub adjustments: Vec<AdjustmentResponse>,
+    pub next_cursor: Option<String>,
+}
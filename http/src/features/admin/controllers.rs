@@ -0,0 +1,491 @@
+use actix_web::{get, post, web, HttpRequest, Responder};
+use chrono::Utc;
+use db::accounts::AccountScope;
+use num_traits::ToPrimitive;
+
+use crate::app_state::AppState;
+use crate::features::accounts::account_types::AccountResponse;
+use crate::features::accounts::controllers::{account_response, member_responses};
+use crate::features::admin::admin_types::{
+    AccountDetailResponse, AdjustmentResponse, AdminUserResponse, AuditEntryResponse,
+    CreateAdjustmentRequest, FreezeAccountRequest, ListAdjustmentsResponse, ListAuditLogQuery,
+    ListAuditLogResponse, ListDisputesQuery, ListDisputesResponse, ListUsersResponse,
+    ResolveDisputeRequest, SearchUsersQuery, UserDetailResponse,
+};
+use crate::features::disputes::dispute_types::DisputeResponse;
+use crate::features::transactions::transaction_types::{
+    ListTransactionsQuery, ListTransactionsResponse, TransactionResponse,
+};
+use crate::middlewares::audit;
+use crate::middlewares::auth::{Permission, StaffClaim};
+use crate::pagination::{next_cursor, PageQuery};
+use crate::util::ApiError;
+use crate::validation::{ValidJson, ValidQuery};
+
+type State = web::Data<AppState>;
+
+/// List disputes, newest first
+/// Endpoint: GET /api/admin/disputes
+/// Query Parameters (all optional):
+///     status: "open" | "under_review" | "won" | "lost"
+///     limit: integer, cursor: string - keyset pagination
+/// Response Body: {
+///     "disputes": [ dispute, ... ],
+///     "next_cursor": string | null
+/// }
+/// Requires authentication as an admin
+#[get("/disputes")]
+async fn list_disputes(
+    state: State,
+    staff: StaffClaim,
+    query: ValidQuery<ListDisputesQuery>,
+    page: ValidQuery<PageQuery>,
+) -> Result<impl Responder, ApiError> {
+    staff.require(Permission::ResolveDisputes)?;
+    let db = state.db();
+    let page_request = page.page_request()?;
+
+    let disputes = db.dispute.list_disputes(query.status, page_request).await?;
+    let next_cursor = next_cursor(&disputes);
+
+    let response = ListDisputesResponse {
+        disputes: disputes
+            .items
+            .into_iter()
+            .map(DisputeResponse::from)
+            .collect(),
+        next_cursor,
+    };
+
+    Ok(web::Json(response))
+}
+
+/// Take an open dispute under review
+/// Endpoint: POST /api/admin/disputes/{dispute_id}/review
+/// Path Parameters: dispute_id (integer)
+/// Response Body: dispute
+/// Requires authentication as an admin. Returns 409 unless the dispute is open
+#[post("/disputes/{dispute_id}/review")]
+async fn review_dispute(
+    state: State,
+    staff: StaffClaim,
+    path: web::Path<i32>,
+    http_request: HttpRequest,
+) -> Result<impl Responder, ApiError> {
+    staff.require(Permission::ResolveDisputes)?;
+    let db = state.db();
+    let dispute = db
+        .dispute
+        .find_dispute(path.into_inner())
+        .await?
+        .ok_or(ApiError::NotFound("Dispute"))?;
+    audit::record_before(&http_request, &DisputeResponse::from(dispute.clone()));
+
+    let dispute = db.dispute.start_review(dispute.id).await?;
+    Ok(web::Json(DisputeResponse::from(dispute)))
+}
+
+/// Resolve a dispute
+/// Endpoint: POST /api/admin/disputes/{dispute_id}/resolve
+/// Path Parameters: dispute_id (integer)
+/// Request Body: {
+///     "outcome": "won" | "lost",
+///     "note": string (optional)
+/// }
+/// Response Body: dispute
+/// Requires authentication as an admin.
+/// `won` reverses the transaction back to the sender; either outcome releases the hold.
+/// Returns 409 if the dispute is already resolved
+#[post("/disputes/{dispute_id}/resolve")]
+async fn resolve_dispute(
+    state: State,
+    staff: StaffClaim,
+    path: web::Path<i32>,
+    request: ValidJson<ResolveDisputeRequest>,
+    http_request: HttpRequest,
+) -> Result<impl Responder, ApiError> {
+    staff.require(Permission::ResolveDisputes)?;
+    let db = state.db();
+    let dispute = db
+        .dispute
+        .find_dispute(path.into_inner())
+        .await?
+        .ok_or(ApiError::NotFound("Dispute"))?;
+    audit::record_before(&http_request, &DisputeResponse::from(dispute.clone()));
+    let dispute_id = dispute.id;
+
+    let note = request.note.as_deref().map(|note| note.trim().to_string());
+    let dispute = db
+        .dispute
+        .resolve(dispute_id, staff.id(), request.outcome.into(), note)
+        .await?;
+
+    Ok(web::Json(DisputeResponse::from(dispute)))
+}
+
+/// Search the audit log, newest first
+/// Endpoint: GET /api/admin/audit
+/// Query Parameters (all optional):
+///     actor_id: integer
+///     action: string - method and route, e.g. "PUT /api/account/{account_id}/alias"
+///     target: string - path that was called, e.g. "/api/account/5/alias"
+///     request_id: string
+///     since, until: string (RFC 3339) - since is inclusive, until exclusive
+///     limit: integer, cursor: string - keyset pagination
+/// Response Body: {
+///     "entries": [
+///         {
+///             "id": integer,
+///             "actor_id": integer | null,
+///             "action": string,
+///             "target": string,
+///             "status": integer,
+///             "before": object | null,
+///             "after": object | null,
+///             "ip": string | null,
+///             "request_id": string | null,
+///             "created_at": string (RFC 3339),
+///             "prev_hash": string,
+///             "hash": string
+///         }
+///     ],
+///     "next_cursor": string | null
+/// }
+/// Requires authentication as an admin
+#[get("/audit")]
+async fn list_audit_log(
+    state: State,
+    staff: StaffClaim,
+    query: ValidQuery<ListAuditLogQuery>,
+    page: ValidQuery<PageQuery>,
+) -> Result<impl Responder, ApiError> {
+    staff.require(Permission::ViewAuditLog)?;
+    let db = state.db();
+    let page_request = page.page_request()?;
+
+    let entries = db.audit.list(query.filter(), page_request).await?;
+    let next_cursor = next_cursor(&entries);
+
+    let response = ListAuditLogResponse {
+        entries: entries
+            .items
+            .into_iter()
+            .map(AuditEntryResponse::from)
+            .collect(),
+        next_cursor,
+    };
+
+    Ok(web::Json(response))
+}
+
+/// Search users, newest first
+/// Endpoint: GET /api/admin/users
+/// Query Parameters (all optional):
+///     q: string - part of the username, display name or email, ignoring case
+///     role: "user" | "support" | "admin"
+///     limit: integer, cursor: string - keyset pagination
+/// Response Body: {
+///     "users": [
+///         {
+///             "id": integer,
+///             "username": string,
+///             "display_name": string | null,
+///             "email": string | null,
+///             "role": string,
+///             "default_account_id": integer | null,
+///             "created_at": string (RFC 3339)
+///         }
+///     ],
+///     "next_cursor": string | null
+/// }
+/// Requires authentication as support or an admin
+#[get("/users")]
+async fn search_users(
+    state: State,
+    staff: StaffClaim,
+    query: ValidQuery<SearchUsersQuery>,
+    page: ValidQuery<PageQuery>,
+) -> Result<impl Responder, ApiError> {
+    staff.require(Permission::ViewUsers)?;
+    let db = state.db();
+    let page_request = page.page_request()?;
+
+    let search = query.q.as_deref().map(str::trim);
+    let users = db
+        .user
+        .search_users(search, query.role, page_request)
+        .await?;
+    let next_cursor = next_cursor(&users);
+
+    let response = ListUsersResponse {
+        users: users
+            .items
+            .into_iter()
+            .map(AdminUserResponse::from)
+            .collect(),
+        next_cursor,
+    };
+
+    Ok(web::Json(response))
+}
+
+/// Get a user with the accounts they are a member of
+/// Endpoint: GET /api/admin/users/{user_id}
+/// Path Parameters: user_id (integer)
+/// Response Body: user, plus "accounts": [ account, ... ]
+/// Requires authentication as support or an admin
+#[get("/users/{user_id}")]
+async fn get_user(
+    state: State,
+    staff: StaffClaim,
+    path: web::Path<i32>,
+) -> Result<impl Responder, ApiError> {
+    staff.require(Permission::ViewUsers)?;
+    let db = state.db();
+    let user = db
+        .user
+        .find_user(path.into_inner())
+        .await?
+        .ok_or(ApiError::NotFound("User"))?;
+
+    let accounts = db
+        .account
+        .list_accounts(AccountScope::Member(user.id))
+        .await?
+        .into_iter()
+        .map(AccountResponse::from)
+        .collect();
+
+    Ok(web::Json(UserDetailResponse {
+        user: AdminUserResponse::from(user),
+        accounts,
+    }))
+}
+
+/// Get any account with its pockets and members
+/// Endpoint: GET /api/admin/accounts/{account_id}
+/// Path Parameters: account_id (integer)
+/// Response Body: account, plus "members": [ member, ... ]
+/// Requires authentication as support or an admin
+#[get("/accounts/{account_id}")]
+async fn get_account(
+    state: State,
+    staff: StaffClaim,
+    path: web::Path<i32>,
+) -> Result<impl Responder, ApiError> {
+    staff.require(Permission::ViewAccounts)?;
+    let db = state.db();
+    let account = db
+        .account
+        .find_account(path.into_inner())
+        .await?
+        .ok_or(ApiError::NotFound("Account"))?;
+
+    let members = member_responses(db, account.id).await?;
+    let account = account_response(db, account).await?;
+    Ok(web::Json(AccountDetailResponse { account, members }))
+}
+
+/// Freeze an account and its pockets. A frozen account can still receive money, but
+/// cannot send any.
+/// Endpoint: POST /api/admin/accounts/{account_id}/freeze
+/// Path Parameters: account_id (integer)
+/// Request Body: {
+///     "reason": string
+/// }
+/// Response Body: account
+/// Requires authentication as support or an admin.
+/// Returns 409 for pockets, which are frozen with their parent, or if already frozen
+#[post("/accounts/{account_id}/freeze")]
+async fn freeze_account(
+    state: State,
+    staff: StaffClaim,
+    path: web::Path<i32>,
+    request: ValidJson<FreezeAccountRequest>,
+    http_request: HttpRequest,
+) -> Result<impl Responder, ApiError> {
+    staff.require(Permission::FreezeAccounts)?;
+    let db = state.db();
+    let account = db
+        .account
+        .find_account(path.into_inner())
+        .await?
+        .ok_or(ApiError::NotFound("Account"))?;
+    audit::record_before(&http_request, &AccountResponse::from(account.clone()));
+
+    let account = db
+        .account
+        .freeze(account.id, request.reason.trim().to_string())
+        .await?;
+    Ok(web::Json(account_response(db, account).await?))
+}
+
+/// Unfreeze an account and its pockets
+/// Endpoint: POST /api/admin/accounts/{account_id}/unfreeze
+/// Path Parameters: account_id (integer)
+/// Response Body: account
+/// Requires authentication as support or an admin. Returns 409 unless the account is frozen
+#[post("/accounts/{account_id}/unfreeze")]
+async fn unfreeze_account(
+    state: State,
+    staff: StaffClaim,
+    path: web::Path<i32>,
+    http_request: HttpRequest,
+) -> Result<impl Responder, ApiError> {
+    staff.require(Permission::FreezeAccounts)?;
+    let db = state.db();
+    let account = db
+        .account
+        .find_account(path.into_inner())
+        .await?
+        .ok_or(ApiError::NotFound("Account"))?;
+    audit::record_before(&http_request, &AccountResponse::from(account.clone()));
+
+    let account = db.account.unfreeze(account.id).await?;
+    Ok(web::Json(account_response(db, account).await?))
+}
+
+/// Credit or debit an account outside of a transfer, e.g. to correct an error or
+/// reverse a fee
+/// Endpoint: POST /api/admin/accounts/{account_id}/adjustments
+/// Path Parameters: account_id (integer)
+/// Request Body: {
+///     "amount": float - positive to credit, negative to debit
+///     "reason": "correction" | "refund" | "fee_reversal" | "chargeback" | "goodwill" | "fraud_recovery",
+///     "note": string (optional)
+/// }
+/// Response Body: {
+///     "id": integer,
+///     "account_id": integer,
+///     "adjusted_by": integer,
+///     "amount": float,
+///     "balance_after": float,
+///     "reason": string,
+///     "note": string | null,
+///     "created_at": string (RFC 3339)
+/// }
+/// Requires authentication as an admin.
+/// Returns 400 if a debit is larger than the available balance
+#[post("/accounts/{account_id}/adjustments")]
+async fn create_adjustment(
+    state: State,
+    staff: StaffClaim,
+    path: web::Path<i32>,
+    request: ValidJson<CreateAdjustmentRequest>,
+    http_request: HttpRequest,
+) -> Result<impl Responder, ApiError> {
+    staff.require(Permission::AdjustBalances)?;
+    let db = state.db();
+    let account = db
+        .account
+        .find_account(path.into_inner())
+        .await?
+        .ok_or(ApiError::NotFound("Account"))?;
+    audit::record_before(&http_request, &AccountResponse::from(account.clone()));
+
+    let note = request.note.as_deref().map(|note| note.trim().to_string());
+    let (adjustment, _account) = db
+        .adjustment
+        .adjust(account.id, staff.id(), request.amount, request.reason, note)
+        .await?;
+    Ok(web::Json(AdjustmentResponse::from(adjustment)))
+}
+
+/// List the adjustments of an account, newest first
+/// Endpoint: GET /api/admin/accounts/{account_id}/adjustments
+/// Path Parameters: account_id (integer)
+/// Query Parameters (optional): limit: integer, cursor: string - keyset pagination
+/// Response Body: {
+///     "adjustments": [ adjustment, ... ],
+///     "next_cursor": string | null
+/// }
+/// Requires authentication as support or an admin
+#[get("/accounts/{account_id}/adjustments")]
+async fn list_adjustments(
+    state: State,
+    staff: StaffClaim,
+    path: web::Path<i32>,
+    page: ValidQuery<PageQuery>,
+) -> Result<impl Responder, ApiError> {
+    staff.require(Permission::ViewAccounts)?;
+    let db = state.db();
+    let page_request = page.page_request()?;
+    let account = db
+        .account
+        .find_account(path.into_inner())
+        .await?
+        .ok_or(ApiError::NotFound("Account"))?;
+
+    let adjustments = db
+        .adjustment
+        .list_adjustments(account.id, page_request)
+        .await?;
+    let next_cursor = next_cursor(&adjustments);
+
+    let response = ListAdjustmentsResponse {
+        adjustments: adjustments
+            .items
+            .into_iter()
+            .map(AdjustmentResponse::from)
+            .collect(),
+        next_cursor,
+    };
+
+    Ok(web::Json(response))
+}
+
+/// List transactions across all accounts
+/// Endpoint: GET /api/admin/transactions
+/// Query Parameters (all optional): the filters of GET /api/transaction/user/tx, where
+///     account_id may be any account and direction applies to it
+/// Response Body: {
+///     "transactions": [ transaction, ... ],
+///     "next_cursor": string | null
+/// }
+/// Requires authentication as support or an admin
+#[get("/transactions")]
+async fn list_transactions(
+    state: State,
+    staff: StaffClaim,
+    query: ValidQuery<ListTransactionsQuery>,
+    page: ValidQuery<PageQuery>,
+) -> Result<impl Responder, ApiError> {
+    staff.require(Permission::ViewTransactions)?;
+    let db = state.db();
+    let page_request = page.page_request()?;
+
+    let filter = query.filter();
+    let transactions = match query.account_id {
+        Some(account_id) => {
+            db.transaction
+                .list_transactions_for_accounts(&[account_id], &filter, page_request)
+                .await?
+        }
+        None => {
+            db.transaction
+                .list_all_transactions(&filter, page_request)
+                .await?
+        }
+    };
+    let next_cursor = next_cursor(&transactions);
+
+    let response = ListTransactionsResponse {
+        transactions: transactions
+            .items
+            .into_iter()
+            .map(|t| {
+                TransactionResponse::new(
+                    t.id,
+                    t.from_account_id,
+                    t.to_account_id,
+                    t.amount.to_f64().unwrap(),
+                    t.status,
+                    t.created_at.with_timezone(&Utc),
+                )
+            })
+            .collect(),
+        next_cursor,
+    };
+
+    Ok(web::Json(response))
+}
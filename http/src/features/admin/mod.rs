@@ -0,0 +1,2 @@
+pub mod admin_types;
+pub mod controllers;
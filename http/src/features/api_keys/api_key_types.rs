@@ -0,0 +1,94 @@
+use std::collections::HashSet;
+
+use chrono::{DateTime, Utc};
+use db::api_keys::{self, ApiKeyScope};
+use entity::api_keys as api_keys_entity;
+use serde::{Deserialize, Serialize};
+
+use crate::validation::{FieldError, Validate, Validator};
+
+/// Request body for creating an API key
+#[derive(Debug, Deserialize)]
+pub struct CreateApiKeyRequest {
+    pub name: String,
+    pub scopes: Vec<ApiKeyScope>,
+    /// The key stops working at this time; it never expires if left out
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl Validate for CreateApiKeyRequest {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::new();
+        v.not_blank(&self.name, "name");
+        v.length(self.name.trim(), "name", 1, 100);
+        v.check(
+            !self.scopes.is_empty(),
+            "scopes",
+            "required",
+            "Must grant at least one scope",
+        );
+        v.check(
+            self.scopes.iter().collect::<HashSet<_>>().len() == self.scopes.len(),
+            "scopes",
+            "duplicate",
+            "Must not list a scope twice",
+        );
+        v.check(
+            self.expires_at.is_none_or(|at| at > Utc::now()),
+            "expires_at",
+            "range",
+            "Must be in the future",
+        );
+        v.finish()
+    }
+}
+
+/// Response for an API key. The key itself is only included when it is created.
+#[derive(Debug, Serialize)]
+pub struct ApiKeyResponse {
+    pub id: i32,
+    pub name: String,
+    /// Start of the key, to recognise it
+    pub prefix: String,
+    pub scopes: Vec<ApiKeyScope>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revoked_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<api_keys_entity::Model> for ApiKeyResponse {
+    fn from(api_key: api_keys_entity::Model) -> Self {
+        Self {
+            id: api_key.id,
+            scopes: api_keys::scopes(&api_key),
+            name: api_key.name,
+            prefix: api_key.prefix,
+            expires_at: api_key.expires_at.map(|at| at.with_timezone(&Utc)),
+            last_used_at: api_key.last_used_at.map(|at| at.with_timezone(&Utc)),
+            revoked_at: api_key.revoked_at.map(|at| at.with_timezone(&Utc)),
+            secret: None,
+            created_at: api_key.created_at.with_timezone(&Utc),
+        }
+    }
+}
+
+impl ApiKeyResponse {
+    /// Response for a new key, the only time the key is shown
+    pub fn with_secret(api_key: api_keys_entity::Model, secret: String) -> Self {
+        Self {
+            secret: Some(secret),
+            ..Self::from(api_key)
+        }
+    }
+}
+
+/// Response for the user's API keys
+#[derive(Debug, Serialize)]
+pub struct ListApiKeysResponse {
+    pub keys: Vec<ApiKeyResponse>,
+}
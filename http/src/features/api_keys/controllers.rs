@@ -0,0 +1,98 @@
+use actix_web::{delete, get, post, web, HttpRequest, Responder};
+
+use crate::app_state::AppState;
+use crate::features::api_keys::api_key_types::{
+    ApiKeyResponse, CreateApiKeyRequest, ListApiKeysResponse,
+};
+use crate::middlewares::audit;
+use crate::middlewares::auth::JWTClaim;
+use crate::util::ApiError;
+use crate::validation::ValidJson;
+
+type State = web::Data<AppState>;
+
+/// Create an API key for server-to-server clients, sent as `Authorization: Bearer sk_...`
+/// Endpoint: POST /api/key/create
+/// Request Body: {
+///     "name": string,
+///     "scopes": [ "accounts:read" | "accounts:write" | "transactions:read"
+///                 | "transactions:write" | "payments:read" | "payments:write"
+///                 | "webhooks:read" | "webhooks:write" ],
+///     "expires_at": string (RFC 3339, optional)
+/// }
+/// Response Body: {
+///     "id": integer,
+///     "name": string,
+///     "prefix": string,
+///     "scopes": [string],
+///     "expires_at": string (RFC 3339) | null,
+///     "last_used_at": string (RFC 3339) | null,
+///     "secret": string,
+///     "created_at": string (RFC 3339)
+/// }
+/// Requires authentication with a token, not an API key.
+/// The key acts as the user within its scopes. Only its hash is stored, so `secret` is
+/// only returned here
+#[post("/create")]
+async fn create_key(
+    state: State,
+    claim: JWTClaim,
+    request: ValidJson<CreateApiKeyRequest>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+
+    let (api_key, secret) = db
+        .api_key
+        .create_key(
+            claim.id(),
+            request.name.trim().to_string(),
+            &request.scopes,
+            request.expires_at.map(|at| at.fixed_offset()),
+        )
+        .await?;
+
+    Ok(web::Json(ApiKeyResponse::with_secret(api_key, secret)))
+}
+
+/// List the user's API keys that have not been revoked, newest first
+/// Endpoint: GET /api/key/list
+/// Response Body: {
+///     "keys": [ key as returned by POST /api/key/create, without the secret ]
+/// }
+/// Requires authentication with a token, not an API key
+#[get("/list")]
+async fn list_keys(state: State, claim: JWTClaim) -> Result<impl Responder, ApiError> {
+    let keys = state.db().api_key.list_keys(claim.id()).await?;
+
+    Ok(web::Json(ListApiKeysResponse {
+        keys: keys.into_iter().map(ApiKeyResponse::from).collect(),
+    }))
+}
+
+/// Revoke an API key; requests made with it are rejected from then on
+/// Endpoint: DELETE /api/key/{key_id}
+/// Path Parameters: key_id (integer)
+/// Response Body: key as returned by POST /api/key/create, without the secret, with
+///     "revoked_at": string (RFC 3339)
+/// Requires authentication with a token, not an API key.
+/// Returns 404 for keys of other users and 409 if the key is already revoked
+#[delete("/{key_id}")]
+async fn revoke_key(
+    state: State,
+    claim: JWTClaim,
+    path: web::Path<i32>,
+    http_request: HttpRequest,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+
+    let api_key = db
+        .api_key
+        .find_key(path.into_inner())
+        .await?
+        .filter(|api_key| api_key.user_id == claim.id())
+        .ok_or(ApiError::NotFound("API key"))?;
+    audit::record_before(&http_request, &ApiKeyResponse::from(api_key.clone()));
+    let api_key = db.api_key.revoke_key(api_key.id).await?;
+
+    Ok(web::Json(ApiKeyResponse::from(api_key)))
+}
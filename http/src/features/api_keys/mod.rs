@@ -0,0 +1,2 @@
+pub mod api_key_types;
+pub mod controllers;
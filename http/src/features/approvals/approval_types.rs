@@ -0,0 +1,113 @@
+use chrono::{DateTime, Utc};
+use db::approvals::{ApprovalAction, ApprovalStatus};
+use entity::{transfer_approval_events, transfer_approvals};
+use num_traits::cast::ToPrimitive;
+use serde::{Deserialize, Serialize};
+
+use crate::features::transactions::transaction_types::TransactionResponse;
+use crate::validation::{FieldError, Validate, Validator};
+
+/// Query parameters for listing approvals
+#[derive(Debug, Deserialize)]
+pub struct ListApprovalsQuery {
+    pub account_id: Option<i32>,
+    pub status: Option<ApprovalStatus>,
+}
+
+impl Validate for ListApprovalsQuery {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        Ok(())
+    }
+}
+
+/// Request body for approving, rejecting or canceling a transfer
+#[derive(Debug, Default, Deserialize)]
+pub struct DecideApprovalRequest {
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+impl Validate for DecideApprovalRequest {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::new();
+        if let Some(note) = &self.note {
+            v.not_blank(note, "note");
+            v.length(note, "note", 1, 2000);
+        }
+        v.finish()
+    }
+}
+
+/// Response for a transfer waiting for, or decided by, a second user
+#[derive(Debug, Serialize)]
+pub struct ApprovalResponse {
+    pub id: i32,
+    pub from_account_id: i32,
+    pub to_account_id: i32,
+    pub amount: f64,
+    pub status: ApprovalStatus,
+    pub requested_by: i32,
+    pub decided_by: Option<i32>,
+    pub transaction_id: Option<i32>,
+    pub created_at: DateTime<Utc>,
+    pub decided_at: Option<DateTime<Utc>>,
+}
+
+impl From<transfer_approvals::Model> for ApprovalResponse {
+    fn from(approval: transfer_approvals::Model) -> Self {
+        Self {
+            id: approval.id,
+            from_account_id: approval.from_account_id,
+            to_account_id: approval.to_account_id,
+            amount: approval.amount.to_f64().unwrap(),
+            status: approval.status,
+            requested_by: approval.requested_by,
+            decided_by: approval.decided_by,
+            transaction_id: approval.transaction_id,
+            created_at: approval.created_at.with_timezone(&Utc),
+            decided_at: approval.decided_at.map(|at| at.with_timezone(&Utc)),
+        }
+    }
+}
+
+/// Response for one step in the audit trail of an approval
+#[derive(Debug, Serialize)]
+pub struct ApprovalEventResponse {
+    pub actor_id: i32,
+    pub action: ApprovalAction,
+    pub note: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<transfer_approval_events::Model> for ApprovalEventResponse {
+    fn from(event: transfer_approval_events::Model) -> Self {
+        Self {
+            actor_id: event.actor_id,
+            action: event.action,
+            note: event.note,
+            created_at: event.created_at.with_timezone(&Utc),
+        }
+    }
+}
+
+/// Response for an approval together with its audit trail
+#[derive(Debug, Serialize)]
+pub struct ApprovalDetailsResponse {
+    #[serde(flatten)]
+    pub approval: ApprovalResponse,
+    pub events: Vec<ApprovalEventResponse>,
+}
+
+/// Response for an approved transfer and the transaction it executed
+#[derive(Debug, Serialize)]
+pub struct ApprovedTransferResponse {
+    pub approval: ApprovalResponse,
+    pub transaction: TransactionResponse,
+}
+
+/// Response for a page of approvals
+#[derive(Debug, Serialize)]
+pub struct ListApprovalsResponse {
+    pub approvals: Vec<ApprovalResponse>,
+    pub next_cursor: Option<String>,
+}
@@ -0,0 +1,270 @@
+use actix_web::{get, post, web, Responder};
+use chrono::Utc;
+use db::approvals::ApprovalAction;
+use num_traits::cast::ToPrimitive;
+
+use crate::app_state::AppState;
+use crate::features::accounts::access::{
+    authorize_account, has_access, scope_account_ids, AccountAccess,
+};
+use crate::features::approvals::approval_types::{
+    ApprovalDetailsResponse, ApprovalEventResponse, ApprovalResponse, ApprovedTransferResponse,
+    DecideApprovalRequest, ListApprovalsQuery, ListApprovalsResponse,
+};
+use crate::features::transactions::transaction_types::TransactionResponse;
+use crate::middlewares::auth::JWTClaim;
+use crate::pagination::{next_cursor, PageQuery};
+use crate::util::{ApiError, AuthError};
+use crate::validation::{ValidJson, ValidQuery};
+
+type State = web::Data<AppState>;
+
+/// List transfers waiting for, or decided by, a second user
+/// Endpoint: GET /api/approval/list
+/// Query Parameters (all optional):
+///     account_id: integer - only transfers from this account
+///     status: "pending" | "approved" | "rejected" | "canceled"
+///     limit: integer, cursor: string - keyset pagination
+/// Response Body: {
+///     "approvals": [
+///         {
+///             "id": integer,
+///             "from_account_id": integer,
+///             "to_account_id": integer,
+///             "amount": float,
+///             "status": "pending" | "approved" | "rejected" | "canceled",
+///             "requested_by": integer,
+///             "decided_by": integer | null,
+///             "transaction_id": integer | null,
+///             "created_at": string (RFC 3339),
+///             "decided_at": string (RFC 3339) | null
+///         }
+///     ],
+///     "next_cursor": string | null
+/// }
+/// Requires authentication. Lists transfers from every account the user can see
+#[get("/list")]
+async fn list_approvals(
+    state: State,
+    claim: JWTClaim,
+    query: ValidQuery<ListApprovalsQuery>,
+    page: ValidQuery<PageQuery>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+    let page_request = page.page_request()?;
+
+    let _user = db
+        .user
+        .find_user(claim.id())
+        .await?
+        .ok_or(ApiError::AuthError(AuthError::UserNotFound))?;
+
+    let account_ids: Vec<i32> = match query.account_id {
+        Some(account_id) => {
+            let account = authorize_account(db, account_id, &claim, AccountAccess::View).await?;
+            vec![account.id]
+        }
+        None => scope_account_ids(db, &claim).await?,
+    };
+
+    if account_ids.is_empty() {
+        return Ok(web::Json(ListApprovalsResponse {
+            approvals: vec![],
+            next_cursor: None,
+        }));
+    }
+
+    let approvals = db
+        .approval
+        .list_approvals(&account_ids, query.status, page_request)
+        .await?;
+    let next_cursor = next_cursor(&approvals);
+
+    Ok(web::Json(ListApprovalsResponse {
+        approvals: approvals
+            .items
+            .into_iter()
+            .map(ApprovalResponse::from)
+            .collect(),
+        next_cursor,
+    }))
+}
+
+/// Get a transfer approval and its audit trail
+/// Endpoint: GET /api/approval/{approval_id}
+/// Path Parameters: approval_id (integer)
+/// Response Body: {
+///     ...approval fields as returned by GET /api/approval/list,
+///     "events": [
+///         {
+///             "actor_id": integer,
+///             "action": "requested" | "approved" | "rejected" | "canceled",
+///             "note": string | null,
+///             "created_at": string (RFC 3339)
+///         }
+///     ]
+/// }
+/// Requires authentication. The user must be able to see the sending account
+#[get("/{approval_id}")]
+async fn get_approval(
+    state: State,
+    claim: JWTClaim,
+    path: web::Path<i32>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+    let approval_id = path.into_inner();
+
+    let approval = db
+        .approval
+        .find_approval(approval_id)
+        .await?
+        .ok_or(ApiError::NotFound("Approval"))?;
+
+    if !has_access(db, approval.from_account_id, &claim, AccountAccess::View).await? {
+        return Err(ApiError::AuthError(AuthError::Unauthorized));
+    }
+
+    let events = db.approval.list_events(approval.id).await?;
+
+    Ok(web::Json(ApprovalDetailsResponse {
+        approval: ApprovalResponse::from(approval),
+        events: events
+            .into_iter()
+            .map(ApprovalEventResponse::from)
+            .collect(),
+    }))
+}
+
+/// Approve a pending transfer, which executes it immediately
+/// Endpoint: POST /api/approval/{approval_id}/approve
+/// Path Parameters: approval_id (integer)
+/// Request Body: {
+///     "note": string (optional)
+/// }
+/// Response Body: {
+///     "approval": approval as returned by GET /api/approval/list,
+///     "transaction": transaction as returned by POST /api/transaction/create
+/// }
+/// Requires authentication.
+/// Requires the owner role on the sending account, or the admin or approver role in its
+/// organization. Returns 409 if the user requested the transfer or it is no longer pending,
+/// and 400 if the balance no longer covers it
+#[post("/{approval_id}/approve")]
+async fn approve_transfer(
+    state: State,
+    claim: JWTClaim,
+    path: web::Path<i32>,
+    request: ValidJson<DecideApprovalRequest>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+    let approval_id = path.into_inner();
+
+    let approval = db
+        .approval
+        .find_approval(approval_id)
+        .await?
+        .ok_or(ApiError::NotFound("Approval"))?;
+    authorize_account(db, approval.from_account_id, &claim, AccountAccess::Approve).await?;
+
+    let (approval, transaction) = db
+        .approval
+        .approve(approval.id, claim.id(), trimmed(request.note.as_deref()))
+        .await?;
+
+    Ok(web::Json(ApprovedTransferResponse {
+        approval: ApprovalResponse::from(approval),
+        transaction: TransactionResponse::new(
+            transaction.id,
+            transaction.from_account_id,
+            transaction.to_account_id,
+            transaction.amount.to_f64().unwrap(),
+            transaction.status,
+            transaction.created_at.with_timezone(&Utc),
+        ),
+    }))
+}
+
+/// Reject a pending transfer without moving money
+/// Endpoint: POST /api/approval/{approval_id}/reject
+/// Path Parameters: approval_id (integer)
+/// Request Body: {
+///     "note": string (optional)
+/// }
+/// Response Body: approval as returned by GET /api/approval/list
+/// Requires authentication.
+/// Requires the same roles as approving. Returns 409 if the user requested the transfer
+/// or it is no longer pending
+#[post("/{approval_id}/reject")]
+async fn reject_transfer(
+    state: State,
+    claim: JWTClaim,
+    path: web::Path<i32>,
+    request: ValidJson<DecideApprovalRequest>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+    let approval_id = path.into_inner();
+
+    let approval = db
+        .approval
+        .find_approval(approval_id)
+        .await?
+        .ok_or(ApiError::NotFound("Approval"))?;
+    authorize_account(db, approval.from_account_id, &claim, AccountAccess::Approve).await?;
+
+    let approval = db
+        .approval
+        .close(
+            approval.id,
+            claim.id(),
+            ApprovalAction::Rejected,
+            trimmed(request.note.as_deref()),
+        )
+        .await?;
+
+    Ok(web::Json(ApprovalResponse::from(approval)))
+}
+
+/// Cancel a pending transfer the user requested
+/// Endpoint: POST /api/approval/{approval_id}/cancel
+/// Path Parameters: approval_id (integer)
+/// Request Body: {
+///     "note": string (optional)
+/// }
+/// Response Body: approval as returned by GET /api/approval/list
+/// Requires authentication. Only the requester can cancel.
+/// Returns 409 if the transfer is no longer pending
+#[post("/{approval_id}/cancel")]
+async fn cancel_transfer(
+    state: State,
+    claim: JWTClaim,
+    path: web::Path<i32>,
+    request: ValidJson<DecideApprovalRequest>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+    let approval_id = path.into_inner();
+
+    let approval = db
+        .approval
+        .find_approval(approval_id)
+        .await?
+        .ok_or(ApiError::NotFound("Approval"))?;
+    if approval.requested_by != claim.id() {
+        return Err(ApiError::AuthError(AuthError::Unauthorized));
+    }
+
+    let approval = db
+        .approval
+        .close(
+            approval.id,
+            claim.id(),
+            ApprovalAction::Canceled,
+            trimmed(request.note.as_deref()),
+        )
+        .await?;
+
+    Ok(web::Json(ApprovalResponse::from(approval)))
+}
+
+fn trimmed(note: Option<&str>) -> Option<String> {
+    note.map(|note| note.trim().to_string())
+}
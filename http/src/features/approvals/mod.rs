@@ -0,0 +1,2 @@
+pub mod approval_types;
+pub mod controllers;
@@ -0,0 +1,187 @@
+use actix_web::{get, post, web, Responder};
+use db::db_client::DbClient;
+
+use crate::app_state::AppState;
+use crate::features::accounts::access::{has_access, AccountAccess};
+use crate::features::disputes::dispute_types::{
+    AddNoteRequest, DisputeDetailsResponse, DisputeNoteResponse, DisputeResponse,
+    OpenDisputeRequest,
+};
+use crate::middlewares::auth::{JWTClaim, Permission, StaffClaim};
+use crate::util::{ApiError, AuthError};
+use crate::validation::ValidJson;
+
+type State = web::Data<AppState>;
+
+/// Open a dispute on a transaction
+/// Endpoint: POST /api/dispute/create
+/// Request Body: {
+///     "transaction_id": integer,
+///     "reason": string
+/// }
+/// Response Body: {
+///     "id": integer,
+///     "transaction_id": integer,
+///     "opened_by": integer,
+///     "held_account_id": integer,
+///     "amount": float,
+///     "reason": string,
+///     "status": "open" | "under_review" | "won" | "lost",
+///     "resolved_by": integer | null,
+///     "reversal_transaction_id": integer | null,
+///     "created_at": string (RFC 3339),
+///     "updated_at": string (RFC 3339),
+///     "resolved_at": string (RFC 3339) | null
+/// }
+/// Requires authentication. The user must be a member of either account of the transaction.
+/// The amount is held on the receiving account until the dispute is resolved.
+/// Returns 409 if the transaction is not completed or already disputed
+#[post("/create")]
+async fn open_dispute(
+    state: State,
+    claim: JWTClaim,
+    request: ValidJson<OpenDisputeRequest>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+    let user_id = claim.id();
+
+    let _user = db
+        .user
+        .find_user(user_id)
+        .await?
+        .ok_or(ApiError::AuthError(AuthError::UserNotFound))?;
+
+    if !is_transaction_participant(db, request.transaction_id, &claim).await? {
+        return Err(ApiError::AuthError(AuthError::Unauthorized));
+    }
+
+    let dispute = db
+        .dispute
+        .open_dispute(
+            request.transaction_id,
+            user_id,
+            request.reason.trim().to_string(),
+        )
+        .await?;
+
+    Ok(web::Json(DisputeResponse::from(dispute)))
+}
+
+/// Get a dispute and its timeline of evidence notes
+/// Endpoint: GET /api/dispute/{dispute_id}
+/// Path Parameters: dispute_id (integer)
+/// Response Body: {
+///     ...dispute fields as returned by POST /api/dispute/create,
+///     "notes": [
+///         {
+///             "id": integer,
+///             "author_id": integer,
+///             "note": string,
+///             "created_at": string (RFC 3339)
+///         }
+///     ]
+/// }
+/// Requires authentication. Visible to both parties of the transaction and to staff
+/// allowed to resolve disputes
+#[get("/{dispute_id}")]
+async fn get_dispute(
+    state: State,
+    claim: JWTClaim,
+    path: web::Path<i32>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+    let dispute_id = path.into_inner();
+
+    let staff = StaffClaim::of(db, claim.clone()).await?;
+
+    let dispute = db
+        .dispute
+        .find_dispute(dispute_id)
+        .await?
+        .ok_or(ApiError::AuthError(AuthError::DisputeNotFound))?;
+
+    if !staff.is_some_and(|staff| staff.can(Permission::ResolveDisputes))
+        && !is_transaction_participant(db, dispute.transaction_id, &claim).await?
+    {
+        return Err(ApiError::AuthError(AuthError::Unauthorized));
+    }
+
+    let notes = db.dispute.list_notes(dispute.id).await?;
+    let response = DisputeDetailsResponse {
+        dispute: DisputeResponse::from(dispute),
+        notes: notes.into_iter().map(DisputeNoteResponse::from).collect(),
+    };
+
+    Ok(web::Json(response))
+}
+
+/// Add evidence or a comment to a dispute
+/// Endpoint: POST /api/dispute/{dispute_id}/notes
+/// Path Parameters: dispute_id (integer)
+/// Request Body: {
+///     "note": string
+/// }
+/// Response Body: {
+///     "id": integer,
+///     "author_id": integer,
+///     "note": string,
+///     "created_at": string (RFC 3339)
+/// }
+/// Requires authentication. Open to both parties of the transaction and to staff
+/// allowed to resolve disputes, while the dispute is not yet resolved
+#[post("/{dispute_id}/notes")]
+async fn add_dispute_note(
+    state: State,
+    claim: JWTClaim,
+    path: web::Path<i32>,
+    request: ValidJson<AddNoteRequest>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+    let user_id = claim.id();
+    let dispute_id = path.into_inner();
+
+    let staff = StaffClaim::of(db, claim.clone()).await?;
+
+    let dispute = db
+        .dispute
+        .find_dispute(dispute_id)
+        .await?
+        .ok_or(ApiError::AuthError(AuthError::DisputeNotFound))?;
+
+    if !staff.is_some_and(|staff| staff.can(Permission::ResolveDisputes))
+        && !is_transaction_participant(db, dispute.transaction_id, &claim).await?
+    {
+        return Err(ApiError::AuthError(AuthError::Unauthorized));
+    }
+
+    if dispute.resolved_at.is_some() {
+        return Err(ApiError::Conflict(
+            "This dispute has already been resolved".to_string(),
+        ));
+    }
+
+    let note = db
+        .dispute
+        .add_note(dispute.id, user_id, request.note.trim().to_string())
+        .await?;
+
+    Ok(web::Json(DisputeNoteResponse::from(note)))
+}
+
+/// Whether the user is a member of the sending or the receiving account of a transaction
+async fn is_transaction_participant(
+    db: &DbClient,
+    transaction_id: i32,
+    claim: &JWTClaim,
+) -> Result<bool, ApiError> {
+    let transaction = db
+        .transaction
+        .find_transaction(transaction_id)
+        .await?
+        .ok_or(ApiError::AuthError(AuthError::TransactionNotFound))?;
+
+    Ok(
+        has_access(db, transaction.from_account_id, claim, AccountAccess::View).await?
+            || has_access(db, transaction.to_account_id, claim, AccountAccess::View).await?,
+    )
+}
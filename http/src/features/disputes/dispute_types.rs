@@ -0,0 +1,104 @@
+use chrono::{DateTime, Utc};
+use db::disputes::DisputeStatus;
+use entity::{dispute_notes, disputes};
+use num_traits::cast::ToPrimitive;
+use serde::{Deserialize, Serialize};
+
+use crate::validation::{FieldError, Validate, Validator};
+
+const MAX_TEXT_LENGTH: usize = 2000;
+
+/// Request body for disputing a transaction
+#[derive(Debug, Deserialize)]
+pub struct OpenDisputeRequest {
+    pub transaction_id: i32,
+    pub reason: String,
+}
+
+impl Validate for OpenDisputeRequest {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::new();
+        v.not_blank(&self.reason, "reason");
+        v.length(&self.reason, "reason", 1, MAX_TEXT_LENGTH);
+        v.finish()
+    }
+}
+
+/// Request body for adding evidence or a comment to a dispute
+#[derive(Debug, Deserialize)]
+pub struct AddNoteRequest {
+    pub note: String,
+}
+
+impl Validate for AddNoteRequest {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::new();
+        v.not_blank(&self.note, "note");
+        v.length(&self.note, "note", 1, MAX_TEXT_LENGTH);
+        v.finish()
+    }
+}
+
+/// Response for a single dispute
+#[derive(Debug, Serialize)]
+pub struct DisputeResponse {
+    pub id: i32,
+    pub transaction_id: i32,
+    pub opened_by: i32,
+    pub held_account_id: i32,
+    pub amount: f64,
+    pub reason: String,
+    pub status: DisputeStatus,
+    pub resolved_by: Option<i32>,
+    pub reversal_transaction_id: Option<i32>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub resolved_at: Option<DateTime<Utc>>,
+}
+
+impl From<disputes::Model> for DisputeResponse {
+    fn from(dispute: disputes::Model) -> Self {
+        Self {
+            id: dispute.id,
+            transaction_id: dispute.transaction_id,
+            opened_by: dispute.opened_by,
+            held_account_id: dispute.held_account_id,
+            amount: dispute.amount.to_f64().unwrap(),
+            reason: dispute.reason,
+            status: dispute.status,
+            resolved_by: dispute.resolved_by,
+            reversal_transaction_id: dispute.reversal_transaction_id,
+            created_at: dispute.created_at.with_timezone(&Utc),
+            updated_at: dispute.updated_at.with_timezone(&Utc),
+            resolved_at: dispute.resolved_at.map(|at| at.with_timezone(&Utc)),
+        }
+    }
+}
+
+/// Response for a single evidence note
+#[derive(Debug, Serialize)]
+pub struct DisputeNoteResponse {
+    pub id: i32,
+    pub author_id: i32,
+    pub note: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<dispute_notes::Model> for DisputeNoteResponse {
+    fn from(note: dispute_notes::Model) -> Self {
+        Self {
+            id: note.id,
+            author_id: note.author_id,
+            note: note.note,
+            created_at: note.created_at.with_timezone(&Utc),
+        }
+    }
+}
+
+/// Response for a dispute together with its timeline of notes
+#[derive(Debug, Serialize)]
+pub struct DisputeDetailsResponse {
+    #[serde(flatten)]
+    pub dispute: DisputeResponse,
+    pub notes: Vec<DisputeNoteResponse>,
+}
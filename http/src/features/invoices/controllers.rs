@@ -0,0 +1,341 @@
+use actix_web::{delete, get, post, put, web, HttpRequest, Responder};
+use db::db_client::DbClient;
+use db::invoices::InvoiceFilter;
+use entity::invoices;
+use num_traits::cast::ToPrimitive;
+
+use crate::app_state::AppState;
+use crate::features::accounts::access::{
+    authorize_account, has_access, scope_account_ids, AccountAccess,
+};
+use crate::features::invoices::invoice_types::{
+    CreateInvoiceRequest, InvoiceDetailsResponse, InvoiceDirection, InvoiceResponse,
+    ListInvoicesQuery, ListInvoicesResponse, PayInvoiceRequest, UpdateInvoiceRequest,
+};
+use crate::middlewares::audit;
+use crate::middlewares::auth::JWTClaim;
+use crate::pagination::{next_cursor, PageQuery};
+use crate::util::{ApiError, AuthError};
+use crate::validation::{FieldError, ValidJson, ValidQuery};
+
+type State = web::Data<AppState>;
+
+/// Issue an invoice to another user
+/// Endpoint: POST /api/invoice/create
+/// Request Body: {
+///     "issuer_account_id": integer,
+///     "recipient": string (username),
+///     "memo": string (optional),
+///     "tax_rate": float (percent, optional, default 0),
+///     "due_date": string (YYYY-MM-DD),
+///     "line_items": [
+///         {
+///             "description": string,
+///             "quantity": integer,
+///             "unit_price": float
+///         }
+///     ]
+/// }
+/// Response Body: {
+///     "id": integer,
+///     "number": string (e.g. "INV-000001", unique per issuing account),
+///     "issuer_account_id": integer,
+///     "issued_by": integer,
+///     "recipient_user_id": integer,
+///     "memo": string | null,
+///     "subtotal": float,
+///     "tax_rate": float,
+///     "tax_amount": float,
+///     "total": float,
+///     "amount_paid": float,
+///     "amount_due": float,
+///     "status": "open" | "partially_paid" | "paid" | "void",
+///     "overdue": boolean,
+///     "due_date": string (YYYY-MM-DD),
+///     "created_at": string (RFC 3339),
+///     "updated_at": string (RFC 3339),
+///     "paid_at": string (RFC 3339) | null,
+///     "line_items": [
+///         {
+///             "description": string,
+///             "quantity": integer,
+///             "unit_price": float,
+///             "amount": float
+///         }
+///     ],
+///     "payments": [
+///         {
+///             "transaction_id": integer,
+///             "amount": float,
+///             "created_at": string (RFC 3339)
+///         }
+///     ]
+/// }
+/// Requires authentication.
+/// Requires the owner or spender role on the issuing account
+#[post("/create")]
+async fn create_invoice(
+    state: State,
+    claim: JWTClaim,
+    request: ValidJson<CreateInvoiceRequest>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+
+    let issuer_account =
+        authorize_account(db, request.issuer_account_id, &claim, AccountAccess::Spend).await?;
+
+    let recipient = db
+        .user
+        .find_user_by_username(request.recipient.trim().trim_start_matches('@').to_string())
+        .await?
+        .ok_or(ApiError::AuthError(AuthError::RecipientNotFound))?;
+    if recipient.id == claim.id() {
+        return Err(ApiError::Validation(vec![FieldError::new(
+            "recipient",
+            "self",
+            "Cannot issue an invoice to yourself",
+        )]));
+    }
+
+    let (invoice, line_items) = db
+        .invoice
+        .create_invoice(issuer_account.id, claim.id(), recipient.id, request.input())
+        .await?;
+
+    Ok(web::Json(InvoiceDetailsResponse::new(
+        invoice,
+        line_items,
+        vec![],
+    )))
+}
+
+/// List invoices issued from or addressed to the user
+/// Endpoint: GET /api/invoice/list
+/// Query Parameters (all optional):
+///     direction: "issued" | "received" - both if omitted
+///     account_id: integer - only invoices issued from this account
+///     status: "open" | "partially_paid" | "paid" | "void"
+///     overdue: boolean - unpaid invoices past their due date, or all others
+///     due_after, due_before: YYYY-MM-DD - due date range, `due_before` exclusive
+///     limit: integer, cursor: string - keyset pagination
+/// Response Body: {
+///     "invoices": [ invoice as returned by POST /api/invoice/create, without
+///                   line items and payments ],
+///     "next_cursor": string | null
+/// }
+/// Requires authentication
+#[get("/list")]
+async fn list_invoices(
+    state: State,
+    claim: JWTClaim,
+    query: ValidQuery<ListInvoicesQuery>,
+    page: ValidQuery<PageQuery>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+    let page_request = page.page_request()?;
+
+    let issued = !matches!(query.direction, Some(InvoiceDirection::Received));
+    let received =
+        !matches!(query.direction, Some(InvoiceDirection::Issued)) && query.account_id.is_none();
+
+    let issuer_account_ids = match (issued, query.account_id) {
+        (false, _) => vec![],
+        (true, Some(account_id)) => {
+            let account = authorize_account(db, account_id, &claim, AccountAccess::View).await?;
+            vec![account.id]
+        }
+        (true, None) => scope_account_ids(db, &claim).await?,
+    };
+
+    if issuer_account_ids.is_empty() && !received {
+        return Ok(web::Json(ListInvoicesResponse {
+            invoices: vec![],
+            next_cursor: None,
+        }));
+    }
+
+    let filter = InvoiceFilter {
+        issuer_account_ids,
+        recipient_user_id: received.then(|| claim.id()),
+        status: query.status,
+        overdue: query.overdue,
+        due_after: query.due_after,
+        due_before: query.due_before,
+    };
+    let invoices = db.invoice.list_invoices(&filter, page_request).await?;
+    let next_cursor = next_cursor(&invoices);
+
+    Ok(web::Json(ListInvoicesResponse {
+        invoices: invoices
+            .items
+            .into_iter()
+            .map(InvoiceResponse::from)
+            .collect(),
+        next_cursor,
+    }))
+}
+
+/// Get an invoice with its line items and payments
+/// Endpoint: GET /api/invoice/{invoice_id}
+/// Path Parameters: invoice_id (integer)
+/// Response Body: invoice as returned by POST /api/invoice/create
+/// Requires authentication. The user must be the recipient or able to see the issuing account
+#[get("/{invoice_id}")]
+async fn get_invoice(
+    state: State,
+    claim: JWTClaim,
+    path: web::Path<i32>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+    let invoice_id = path.into_inner();
+
+    let invoice = db
+        .invoice
+        .find_invoice(invoice_id)
+        .await?
+        .ok_or(ApiError::NotFound("Invoice"))?;
+
+    let is_party = invoice.recipient_user_id == claim.id()
+        || has_access(db, invoice.issuer_account_id, &claim, AccountAccess::View).await?;
+    if !is_party {
+        return Err(ApiError::AuthError(AuthError::Unauthorized));
+    }
+
+    invoice_details(db, invoice).await.map(web::Json)
+}
+
+/// Replace the memo, tax rate, due date and line items of an invoice
+/// Endpoint: PUT /api/invoice/{invoice_id}
+/// Path Parameters: invoice_id (integer)
+/// Request Body: {
+///     "memo": string (optional),
+///     "tax_rate": float (percent, optional, default 0),
+///     "due_date": string (YYYY-MM-DD),
+///     "line_items": [ line items as for POST /api/invoice/create ]
+/// }
+/// Response Body: invoice as returned by POST /api/invoice/create
+/// Requires authentication.
+/// Requires the owner or spender role on the issuing account. Returns 409 unless the
+/// invoice is open and has no payments
+#[put("/{invoice_id}")]
+async fn update_invoice(
+    state: State,
+    claim: JWTClaim,
+    path: web::Path<i32>,
+    request: ValidJson<UpdateInvoiceRequest>,
+    http_request: HttpRequest,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+    let invoice_id = path.into_inner();
+
+    let invoice = db
+        .invoice
+        .find_invoice(invoice_id)
+        .await?
+        .ok_or(ApiError::NotFound("Invoice"))?;
+    authorize_account(db, invoice.issuer_account_id, &claim, AccountAccess::Spend).await?;
+    audit::record_before(&http_request, &InvoiceResponse::from(invoice.clone()));
+
+    let (invoice, line_items) = db
+        .invoice
+        .update_invoice(invoice.id, request.input())
+        .await?;
+
+    Ok(web::Json(InvoiceDetailsResponse::new(
+        invoice,
+        line_items,
+        vec![],
+    )))
+}
+
+/// Void an invoice
+/// Endpoint: DELETE /api/invoice/{invoice_id}
+/// Path Parameters: invoice_id (integer)
+/// Response Body: invoice as returned by GET /api/invoice/list
+/// Requires authentication.
+/// Requires the owner or spender role on the issuing account. Returns 409 unless the
+/// invoice is open and has no payments. Voided invoices keep their number
+#[delete("/{invoice_id}")]
+async fn void_invoice(
+    state: State,
+    claim: JWTClaim,
+    path: web::Path<i32>,
+    http_request: HttpRequest,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+    let invoice_id = path.into_inner();
+
+    let invoice = db
+        .invoice
+        .find_invoice(invoice_id)
+        .await?
+        .ok_or(ApiError::NotFound("Invoice"))?;
+    authorize_account(db, invoice.issuer_account_id, &claim, AccountAccess::Spend).await?;
+    audit::record_before(&http_request, &InvoiceResponse::from(invoice.clone()));
+
+    let invoice = db.invoice.void_invoice(invoice.id).await?;
+
+    Ok(web::Json(InvoiceResponse::from(invoice)))
+}
+
+/// Pay an invoice addressed to the user, in full or in part
+/// Endpoint: POST /api/invoice/{invoice_id}/pay
+/// Path Parameters: invoice_id (integer)
+/// Request Body: {
+///     "account_id": integer,
+///     "amount": float (optional, defaults to the amount due)
+/// }
+/// Response Body: invoice as returned by POST /api/invoice/create
+/// Requires authentication. Only the recipient can pay, and requires the owner or spender
+/// role on the paying account. Returns 409 if the invoice is paid or void, the amount
+/// exceeds the amount due, or the account needs approval for this amount
+#[post("/{invoice_id}/pay")]
+async fn pay_invoice(
+    state: State,
+    claim: JWTClaim,
+    path: web::Path<i32>,
+    request: ValidJson<PayInvoiceRequest>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+    let invoice_id = path.into_inner();
+
+    let invoice = db
+        .invoice
+        .find_invoice(invoice_id)
+        .await?
+        .ok_or(ApiError::NotFound("Invoice"))?;
+    if invoice.recipient_user_id != claim.id() {
+        return Err(ApiError::AuthError(AuthError::Unauthorized));
+    }
+    let payer_account =
+        authorize_account(db, request.account_id, &claim, AccountAccess::Spend).await?;
+
+    let amount = request
+        .amount
+        .unwrap_or_else(|| (invoice.total - invoice.amount_paid).to_f64().unwrap());
+    if db
+        .approval
+        .requires_approval(payer_account.id, amount)
+        .await?
+    {
+        return Err(ApiError::Conflict(
+            "Payments above the approval threshold cannot be made from this account".to_string(),
+        ));
+    }
+
+    let (invoice, _transaction) = db
+        .invoice
+        .pay_invoice(invoice.id, payer_account.id, request.amount)
+        .await?;
+
+    invoice_details(db, invoice).await.map(web::Json)
+}
+
+async fn invoice_details(
+    db: &DbClient,
+    invoice: invoices::Model,
+) -> Result<InvoiceDetailsResponse, ApiError> {
+    let line_items = db.invoice.list_line_items(invoice.id).await?;
+    let payments = db.invoice.list_payments(invoice.id).await?;
+    Ok(InvoiceDetailsResponse::new(invoice, line_items, payments))
+}
@@ -0,0 +1,317 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use db::invoices::{is_overdue, InvoiceInput, InvoiceStatus, LineItemInput};
+use entity::{invoice_line_items, invoice_payments, invoices};
+use num_traits::cast::ToPrimitive;
+use serde::{Deserialize, Serialize};
+
+use crate::validation::{FieldError, Validate, Validator};
+
+/// A line of an invoice in a create or update request
+#[derive(Debug, Deserialize)]
+pub struct LineItemRequest {
+    pub description: String,
+    pub quantity: i32,
+    pub unit_price: f64,
+}
+
+/// Request body for issuing an invoice
+#[derive(Debug, Deserialize)]
+pub struct CreateInvoiceRequest {
+    pub issuer_account_id: i32,
+    /// Username of the user the invoice is addressed to
+    pub recipient: String,
+    pub memo: Option<String>,
+    #[serde(default)]
+    pub tax_rate: f64,
+    pub due_date: NaiveDate,
+    pub line_items: Vec<LineItemRequest>,
+}
+
+impl CreateInvoiceRequest {
+    pub fn input(&self) -> InvoiceInput {
+        invoice_input(&self.memo, self.tax_rate, self.due_date, &self.line_items)
+    }
+}
+
+impl Validate for CreateInvoiceRequest {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::new();
+        v.not_blank(&self.recipient, "recipient");
+        validate_invoice(
+            &mut v,
+            &self.memo,
+            self.tax_rate,
+            self.due_date,
+            &self.line_items,
+        );
+        v.finish()
+    }
+}
+
+/// Request body for replacing the editable parts of an invoice
+#[derive(Debug, Deserialize)]
+pub struct UpdateInvoiceRequest {
+    pub memo: Option<String>,
+    #[serde(default)]
+    pub tax_rate: f64,
+    pub due_date: NaiveDate,
+    pub line_items: Vec<LineItemRequest>,
+}
+
+impl UpdateInvoiceRequest {
+    pub fn input(&self) -> InvoiceInput {
+        invoice_input(&self.memo, self.tax_rate, self.due_date, &self.line_items)
+    }
+}
+
+impl Validate for UpdateInvoiceRequest {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::new();
+        validate_invoice(
+            &mut v,
+            &self.memo,
+            self.tax_rate,
+            self.due_date,
+            &self.line_items,
+        );
+        v.finish()
+    }
+}
+
+fn validate_invoice(
+    v: &mut Validator,
+    memo: &Option<String>,
+    tax_rate: f64,
+    due_date: NaiveDate,
+    line_items: &[LineItemRequest],
+) {
+    if let Some(memo) = memo {
+        v.not_blank(memo, "memo");
+        v.length(memo.trim(), "memo", 1, 2000);
+    }
+    v.check(
+        tax_rate.is_finite() && (0.0..=100.0).contains(&tax_rate),
+        "tax_rate",
+        "range",
+        "Must be a percentage between 0 and 100",
+    );
+    v.check(
+        due_date >= Utc::now().date_naive(),
+        "due_date",
+        "future",
+        "Must not be in the past",
+    );
+    v.check(
+        (1..=100).contains(&line_items.len()),
+        "line_items",
+        "length",
+        "Must contain between 1 and 100 line items",
+    );
+    for (index, item) in line_items.iter().enumerate() {
+        let field = |name: &str| format!("line_items[{index}].{name}");
+        v.not_blank(&item.description, &field("description"));
+        v.length(item.description.trim(), &field("description"), 1, 200);
+        v.check(
+            (1..=1_000_000).contains(&item.quantity),
+            &field("quantity"),
+            "range",
+            "Must be between 1 and 1000000",
+        );
+        v.positive_amount(item.unit_price, &field("unit_price"));
+    }
+}
+
+fn invoice_input(
+    memo: &Option<String>,
+    tax_rate: f64,
+    due_date: NaiveDate,
+    line_items: &[LineItemRequest],
+) -> InvoiceInput {
+    InvoiceInput {
+        memo: memo.as_deref().map(|memo| memo.trim().to_string()),
+        tax_rate,
+        due_date,
+        line_items: line_items
+            .iter()
+            .map(|item| LineItemInput {
+                description: item.description.trim().to_string(),
+                quantity: item.quantity,
+                unit_price: item.unit_price,
+            })
+            .collect(),
+    }
+}
+
+/// Request body for paying an invoice
+#[derive(Debug, Deserialize)]
+pub struct PayInvoiceRequest {
+    pub account_id: i32,
+    /// Part of the amount due; the whole amount due is paid if omitted
+    pub amount: Option<f64>,
+}
+
+impl Validate for PayInvoiceRequest {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::new();
+        if let Some(amount) = self.amount {
+            v.positive_amount(amount, "amount");
+        }
+        v.finish()
+    }
+}
+
+/// Which side of an invoice to list
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InvoiceDirection {
+    /// Invoices issued from the user's accounts
+    Issued,
+    /// Invoices addressed to the user
+    Received,
+}
+
+/// Query parameters for listing invoices
+#[derive(Debug, Deserialize)]
+pub struct ListInvoicesQuery {
+    pub direction: Option<InvoiceDirection>,
+    pub account_id: Option<i32>,
+    pub status: Option<InvoiceStatus>,
+    pub overdue: Option<bool>,
+    pub due_after: Option<NaiveDate>,
+    pub due_before: Option<NaiveDate>,
+}
+
+impl Validate for ListInvoicesQuery {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::new();
+        if let (Some(after), Some(before)) = (self.due_after, self.due_before) {
+            v.check(
+                after < before,
+                "due_before",
+                "range",
+                "Must be later than due_after",
+            );
+        }
+        v.finish()
+    }
+}
+
+/// Response for an invoice
+#[derive(Debug, Serialize)]
+pub struct InvoiceResponse {
+    pub id: i32,
+    pub number: String,
+    pub issuer_account_id: i32,
+    pub issued_by: i32,
+    pub recipient_user_id: i32,
+    pub memo: Option<String>,
+    pub subtotal: f64,
+    pub tax_rate: f64,
+    pub tax_amount: f64,
+    pub total: f64,
+    pub amount_paid: f64,
+    pub amount_due: f64,
+    pub status: InvoiceStatus,
+    /// Unpaid and past its due date
+    pub overdue: bool,
+    pub due_date: NaiveDate,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub paid_at: Option<DateTime<Utc>>,
+}
+
+impl From<invoices::Model> for InvoiceResponse {
+    fn from(invoice: invoices::Model) -> Self {
+        Self {
+            overdue: is_overdue(&invoice),
+            id: invoice.id,
+            number: invoice.number,
+            issuer_account_id: invoice.issuer_account_id,
+            issued_by: invoice.issued_by,
+            recipient_user_id: invoice.recipient_user_id,
+            memo: invoice.memo,
+            subtotal: invoice.subtotal.to_f64().unwrap(),
+            tax_rate: invoice.tax_rate.to_f64().unwrap(),
+            tax_amount: invoice.tax_amount.to_f64().unwrap(),
+            total: invoice.total.to_f64().unwrap(),
+            amount_paid: invoice.amount_paid.to_f64().unwrap(),
+            amount_due: (invoice.total - invoice.amount_paid).to_f64().unwrap(),
+            status: invoice.status,
+            due_date: invoice.due_date,
+            created_at: invoice.created_at.with_timezone(&Utc),
+            updated_at: invoice.updated_at.with_timezone(&Utc),
+            paid_at: invoice.paid_at.map(|at| at.with_timezone(&Utc)),
+        }
+    }
+}
+
+/// Response for a line of an invoice
+#[derive(Debug, Serialize)]
+pub struct LineItemResponse {
+    pub description: String,
+    pub quantity: i32,
+    pub unit_price: f64,
+    pub amount: f64,
+}
+
+impl From<invoice_line_items::Model> for LineItemResponse {
+    fn from(item: invoice_line_items::Model) -> Self {
+        Self {
+            description: item.description,
+            quantity: item.quantity,
+            unit_price: item.unit_price.to_f64().unwrap(),
+            amount: item.amount.to_f64().unwrap(),
+        }
+    }
+}
+
+/// Response for a payment towards an invoice
+#[derive(Debug, Serialize)]
+pub struct InvoicePaymentResponse {
+    pub transaction_id: i32,
+    pub amount: f64,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<invoice_payments::Model> for InvoicePaymentResponse {
+    fn from(payment: invoice_payments::Model) -> Self {
+        Self {
+            transaction_id: payment.transaction_id,
+            amount: payment.amount.to_f64().unwrap(),
+            created_at: payment.created_at.with_timezone(&Utc),
+        }
+    }
+}
+
+/// Response for an invoice with its line items and payments
+#[derive(Debug, Serialize)]
+pub struct InvoiceDetailsResponse {
+    #[serde(flatten)]
+    pub invoice: InvoiceResponse,
+    pub line_items: Vec<LineItemResponse>,
+    pub payments: Vec<InvoicePaymentResponse>,
+}
+
+impl InvoiceDetailsResponse {
+    pub fn new(
+        invoice: invoices::Model,
+        line_items: Vec<invoice_line_items::Model>,
+        payments: Vec<invoice_payments::Model>,
+    ) -> Self {
+        Self {
+            invoice: InvoiceResponse::from(invoice),
+            line_items: line_items.into_iter().map(LineItemResponse::from).collect(),
+            payments: payments
+                .into_iter()
+                .map(InvoicePaymentResponse::from)
+                .collect(),
+        }
+    }
+}
+
+/// Response for a page of invoices
+#[derive(Debug, Serialize)]
+pub struct ListInvoicesResponse {
+    pub invoices: Vec<InvoiceResponse>,
+    pub next_cursor: Option<String>,
+}
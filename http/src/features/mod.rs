@@ -1,5 +1,18 @@
 pub mod accounts;
+pub mod admin;
+pub mod api_keys;
+pub mod approvals;
+pub mod disputes;
 pub mod healthcheck;
+pub mod invoices;
+pub mod notifications;
+pub mod organizations;
+pub mod payment_links;
+pub mod payments;
+pub mod splits;
+pub mod subscriptions;
 pub mod user;
+pub mod webhooks;
+pub mod well_known;
 
 pub mod transactions;
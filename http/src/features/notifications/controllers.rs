@@ -0,0 +1,64 @@
+use actix_web::{get, web, HttpRequest, Responder};
+
+use crate::{
+    app_state::AppState,
+    constants::NOTIFICATIONS_MAX_FRAME_SIZE,
+    middlewares::auth::JWTClaim,
+    util::{ApiError, AuthError},
+    validation::ValidQuery,
+};
+
+use super::notification_types::ConnectQuery;
+use super::session;
+
+type State = web::Data<AppState>;
+
+/// Open the user's realtime notification connection
+/// Endpoint: GET /ws (WebSocket upgrade)
+/// Query Parameters: last_event_id (string, optional) - resume after this notification
+/// Client Messages (JSON text): {
+///     "type": "subscribe",
+///     "account_ids": [integer],
+///     "balance_limit": float (optional)
+/// } | {
+///     "type": "unsubscribe",
+///     "account_ids": [integer]
+/// } | {
+///     "type": "ping"
+/// }
+/// Server Messages (JSON text), told apart by "type": "subscribed", "unsubscribed",
+/// "pong", "incoming_payment", "payment_request", "limit_warning" and "error"
+/// Requires authentication. A user has one connection per server; opening another
+/// closes the previous one with code 4000. The connection is closed with code 1008 when
+/// the access token expires. Subscribing requires view access to every listed account
+#[get("")]
+async fn connect(
+    state: State,
+    claim: JWTClaim,
+    query: ValidQuery<ConnectQuery>,
+    request: HttpRequest,
+    body: web::Payload,
+) -> Result<impl Responder, actix_web::Error> {
+    state
+        .db()
+        .user
+        .find_user(claim.id())
+        .await
+        .map_err(ApiError::from)?
+        .ok_or(ApiError::AuthError(AuthError::UserNotFound))?;
+
+    let (response, ws_session, messages) = actix_ws::handle(&request, body)?;
+    let messages = messages
+        .max_frame_size(NOTIFICATIONS_MAX_FRAME_SIZE)
+        .aggregate_continuations()
+        .max_continuation_size(NOTIFICATIONS_MAX_FRAME_SIZE);
+    actix_web::rt::spawn(session::run(
+        state.clone(),
+        claim,
+        ws_session,
+        messages,
+        query.last_event_position(),
+    ));
+
+    Ok(response)
+}
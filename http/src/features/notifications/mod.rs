@@ -0,0 +1,3 @@
+pub mod controllers;
+pub mod notification_types;
+pub mod session;
@@ -0,0 +1,133 @@
+use std::collections::HashSet;
+
+use db::outbox::EventPosition;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::constants::NOTIFICATIONS_MAX_SUBSCRIPTIONS;
+use crate::features::accounts::events::resume_position;
+use crate::types::ErrorResponse;
+use crate::validation::{FieldError, Validate, Validator};
+
+/// Query parameters for opening a notification connection
+#[derive(Debug, Deserialize)]
+pub struct ConnectQuery {
+    /// Id of the last notification the client received on its previous connection
+    pub last_event_id: Option<String>,
+}
+
+impl ConnectQuery {
+    pub fn last_event_position(&self) -> Option<EventPosition> {
+        self.last_event_id.as_deref().and_then(resume_position)
+    }
+}
+
+impl Validate for ConnectQuery {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::new();
+        v.check(
+            self.last_event_id.is_none() || self.last_event_position().is_some(),
+            "last_event_id",
+            "format",
+            "Must be the event_id of a notification",
+        );
+        v.finish()
+    }
+}
+
+/// Messages a client sends over its notification connection
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    Subscribe(SubscribeRequest),
+    Unsubscribe(UnsubscribeRequest),
+    Ping,
+}
+
+/// Starts notifications for the given accounts, or changes their balance limit
+#[derive(Debug, Deserialize)]
+pub struct SubscribeRequest {
+    pub account_ids: Vec<i32>,
+    /// Warn when the available balance of an account drops below this amount
+    pub balance_limit: Option<f64>,
+}
+
+impl Validate for SubscribeRequest {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::new();
+        v.check(
+            (1..=NOTIFICATIONS_MAX_SUBSCRIPTIONS).contains(&self.account_ids.len()),
+            "account_ids",
+            "length",
+            &format!("Must list between 1 and {NOTIFICATIONS_MAX_SUBSCRIPTIONS} accounts"),
+        );
+        v.check(
+            self.account_ids.iter().collect::<HashSet<_>>().len() == self.account_ids.len(),
+            "account_ids",
+            "duplicate",
+            "Must not list an account twice",
+        );
+        if let Some(balance_limit) = self.balance_limit {
+            v.positive_amount(balance_limit, "balance_limit");
+        }
+        v.finish()
+    }
+}
+
+/// Stops notifications for the given accounts
+#[derive(Debug, Deserialize)]
+pub struct UnsubscribeRequest {
+    pub account_ids: Vec<i32>,
+}
+
+impl Validate for UnsubscribeRequest {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::new();
+        v.check(
+            !self.account_ids.is_empty(),
+            "account_ids",
+            "required",
+            "Must list at least one account",
+        );
+        v.finish()
+    }
+}
+
+/// Messages the server sends over a notification connection. Notifications carry the
+/// id of the event they describe, which the client passes as `last_event_id` when it
+/// reconnects.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    Subscribed {
+        account_ids: Vec<i32>,
+    },
+    Unsubscribed {
+        account_ids: Vec<i32>,
+    },
+    Pong,
+    /// Money arrived on a subscribed account
+    IncomingPayment {
+        event_id: String,
+        account_id: i32,
+        transaction: Value,
+    },
+    /// An invoice was issued to the user
+    PaymentRequest {
+        event_id: String,
+        invoice: Value,
+    },
+    /// The available balance of a subscribed account dropped below its limit. Sent
+    /// without an event id when the balance is already below the limit on subscribing.
+    LimitWarning {
+        event_id: Option<String>,
+        account_id: i32,
+        available_balance: f64,
+        balance_limit: f64,
+    },
+    /// A client message was rejected
+    Error {
+        #[serde(flatten)]
+        error: ErrorResponse,
+    },
+}
@@ -0,0 +1,506 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use actix_web::rt::time::{interval, sleep};
+use actix_web::web;
+use actix_ws::{
+    AggregatedMessage, AggregatedMessageStream, CloseCode, CloseReason, Closed, Session,
+};
+use chrono::Utc;
+use db::accounts::available_balance;
+use db::outbox::{concerns_user, EventPosition, OutboxEventType};
+use entity::outbox;
+use num_traits::ToPrimitive;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::warn;
+
+use crate::app_state::AppState;
+use crate::constants::{
+    ACCOUNT_EVENTS_HOLD_BACK_RETRY_MS, NOTIFICATIONS_CLIENT_TIMEOUT_SECS,
+    NOTIFICATIONS_HEARTBEAT_SECS, NOTIFICATIONS_MAX_SUBSCRIPTIONS, NOTIFICATIONS_REPLAY_BATCH,
+};
+use crate::features::accounts::access::{authorize_account, AccountAccess};
+use crate::features::accounts::events::OutboxNotice;
+use crate::middlewares::auth::JWTClaim;
+use crate::util::ApiError;
+use crate::validation::{FieldError, Validate};
+
+use super::notification_types::{
+    ClientMessage, ServerMessage, SubscribeRequest, UnsubscribeRequest,
+};
+
+/// Close code telling a client that a newer connection of the same user took over
+const REPLACED: u16 = 4000;
+
+/// The open notification connection of each user on this server instance
+#[derive(Default)]
+pub struct Connections {
+    next_id: AtomicU64,
+    open: Mutex<HashMap<i32, (u64, Session)>>,
+}
+
+impl Connections {
+    /// Records the connection of a user and closes the one it replaces. Returns the
+    /// id to unregister the connection with.
+    async fn register(&self, user_id: i32, session: Session) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let replaced = self.open.lock().unwrap().insert(user_id, (id, session));
+        if let Some((_, session)) = replaced {
+            let reason = close_reason(CloseCode::Other(REPLACED), "Replaced by a newer connection");
+            let _ = session.close(Some(reason)).await;
+        }
+        id
+    }
+
+    fn unregister(&self, user_id: i32, id: u64) {
+        let mut open = self.open.lock().unwrap();
+        if open
+            .get(&user_id)
+            .is_some_and(|(open_id, _)| *open_id == id)
+        {
+            open.remove(&user_id);
+        }
+    }
+}
+
+/// What the connection watches on a subscribed account
+struct Subscription {
+    balance_limit: Option<f64>,
+    /// Whether the available balance was below the limit at the last balance seen
+    below_limit: Option<bool>,
+}
+
+struct Connection {
+    state: web::Data<AppState>,
+    claim: JWTClaim,
+    session: Session,
+    subscriptions: HashMap<i32, Subscription>,
+    /// The client's `last_event_id`, from which newly subscribed accounts catch up
+    resume_from: Option<EventPosition>,
+    /// Position of the last event read for the connection
+    position: EventPosition,
+    /// The newest event announced on the live channel for this connection. It has not
+    /// been read yet while an older transaction is still running.
+    waiting_for: Option<EventPosition>,
+}
+
+/// Serves one notification connection until either side closes it. Notifications are
+/// read from the outbox in the order `events_after` publishes them, with
+/// `{transaction_id}-{event_id}` as their id, so a client that reconnects with the id of
+/// the last notification it received misses nothing. The client is pinged every few
+/// seconds and dropped once it stops answering. The connection is closed with 1012
+/// (service restart) when this server may have missed events, and with 1008 (policy
+/// violation) once the access token expires, for the client to reconnect with a new one.
+pub async fn run(
+    state: web::Data<AppState>,
+    claim: JWTClaim,
+    session: Session,
+    mut messages: AggregatedMessageStream,
+    last_event_id: Option<EventPosition>,
+) {
+    let user_id = claim.id();
+    // Subscribed before reading the outbox so that nothing committed in between is lost.
+    let mut events = state.account_events().subscribe();
+    let connection_id = state
+        .notification_connections()
+        .register(user_id, session.clone())
+        .await;
+
+    let position = match last_event_id {
+        Some(position) => Ok(position),
+        None => state.db().outbox.current_position().await,
+    };
+    let close_reason = match position {
+        Ok(position) => {
+            let mut connection = Connection {
+                state: state.clone(),
+                claim,
+                session: session.clone(),
+                subscriptions: HashMap::new(),
+                resume_from: last_event_id,
+                position,
+                waiting_for: None,
+            };
+            serve(&mut connection, &mut messages, &mut events).await
+        }
+        Err(err) => {
+            warn!("Could not open the notification connection of user {user_id}: {err}");
+            Some(close_reason(
+                CloseCode::Error,
+                "Could not read notifications",
+            ))
+        }
+    };
+
+    state
+        .notification_connections()
+        .unregister(user_id, connection_id);
+    if let Some(reason) = close_reason {
+        let _ = session.close(Some(reason)).await;
+    }
+}
+
+/// Handles client messages, live events and heartbeats until the connection ends.
+/// Returns why the server closes the connection, or `None` if the client is gone.
+async fn serve(
+    connection: &mut Connection,
+    messages: &mut AggregatedMessageStream,
+    events: &mut broadcast::Receiver<OutboxNotice>,
+) -> Option<CloseReason> {
+    // Payment requests missed since `last_event_id`; accounts catch up as they are
+    // subscribed to.
+    connection.read_events().await.ok()?;
+
+    let mut heartbeat = interval(Duration::from_secs(NOTIFICATIONS_HEARTBEAT_SECS));
+    let mut last_heard = Instant::now();
+    let token_lifetime = connection.claim.expires_at() - Utc::now().timestamp();
+    let token_expiry = sleep(Duration::from_secs(token_lifetime.max(0) as u64));
+    tokio::pin!(token_expiry);
+    loop {
+        let sent = tokio::select! {
+            message = messages.recv() => match message {
+                Some(Ok(AggregatedMessage::Close(reason))) => {
+                    break Some(reason.unwrap_or_else(|| CloseCode::Normal.into()));
+                }
+                Some(Ok(message)) => {
+                    last_heard = Instant::now();
+                    connection.receive(message).await
+                }
+                Some(Err(err)) => break Some(close_reason(CloseCode::Protocol, &err.to_string())),
+                None => break None,
+            },
+            notice = events.recv() => match notice {
+                Ok(OutboxNotice::Event(event)) => connection.announce(&event).await,
+                Ok(OutboxNotice::Reset) | Err(RecvError::Lagged(_)) | Err(RecvError::Closed) => {
+                    break Some(close_reason(
+                        CloseCode::Restart,
+                        "Notifications may have been missed, reconnect with the last event id",
+                    ));
+                }
+            },
+            _ = heartbeat.tick() => {
+                if last_heard.elapsed() > Duration::from_secs(NOTIFICATIONS_CLIENT_TIMEOUT_SECS) {
+                    break Some(close_reason(CloseCode::Away, "Heartbeat timed out"));
+                }
+                connection.session.ping(b"").await
+            }
+            // An announced event is held back behind an older transaction; try again
+            _ = sleep(Duration::from_millis(ACCOUNT_EVENTS_HOLD_BACK_RETRY_MS)),
+                if connection.waiting_for.is_some() => connection.read_events().await,
+            _ = &mut token_expiry => {
+                break Some(close_reason(CloseCode::Policy, "The access token expired"));
+            }
+        };
+        sent.ok()?;
+    }
+}
+
+fn close_reason(code: CloseCode, description: &str) -> CloseReason {
+    CloseReason {
+        code,
+        description: Some(description.to_string()),
+    }
+}
+
+impl Connection {
+    async fn receive(&mut self, message: AggregatedMessage) -> Result<(), Closed> {
+        match message {
+            AggregatedMessage::Text(text) => match serde_json::from_str::<ClientMessage>(&text) {
+                Ok(ClientMessage::Subscribe(request)) => self.subscribe(request).await,
+                Ok(ClientMessage::Unsubscribe(request)) => self.unsubscribe(request).await,
+                Ok(ClientMessage::Ping) => self.send(&ServerMessage::Pong).await,
+                Err(err) => {
+                    let error = FieldError::new("message", "format", err.to_string());
+                    self.reject(ApiError::Validation(vec![error])).await
+                }
+            },
+            AggregatedMessage::Binary(_) => {
+                let error = FieldError::new("message", "format", "Messages must be JSON text");
+                self.reject(ApiError::Validation(vec![error])).await
+            }
+            AggregatedMessage::Ping(bytes) => self.session.pong(&bytes).await,
+            AggregatedMessage::Pong(_) => Ok(()),
+            AggregatedMessage::Close(_) => Err(Closed),
+        }
+    }
+
+    /// Subscribes to every listed account or, if the user may not view one of them,
+    /// to none. Accounts new to the connection get their events after the connection's
+    /// `last_event_id` replayed, then the connection reads on from its position.
+    async fn subscribe(&mut self, request: SubscribeRequest) -> Result<(), Closed> {
+        if let Err(errors) = request.validate() {
+            return self.reject(ApiError::Validation(errors)).await;
+        }
+        let new_ids: Vec<i32> = request
+            .account_ids
+            .iter()
+            .copied()
+            .filter(|id| !self.subscriptions.contains_key(id))
+            .collect();
+        if self.subscriptions.len() + new_ids.len() > NOTIFICATIONS_MAX_SUBSCRIPTIONS {
+            let error = FieldError::new(
+                "account_ids",
+                "length",
+                format!(
+                    "Must not subscribe to more than {NOTIFICATIONS_MAX_SUBSCRIPTIONS} accounts"
+                ),
+            );
+            return self.reject(ApiError::Validation(vec![error])).await;
+        }
+
+        let mut accounts = Vec::with_capacity(request.account_ids.len());
+        for &account_id in &request.account_ids {
+            match authorize_account(
+                self.state.db(),
+                account_id,
+                &self.claim,
+                AccountAccess::View,
+            )
+            .await
+            {
+                Ok(account) => accounts.push(account),
+                Err(err) => return self.reject(err).await,
+            }
+        }
+
+        for account in &accounts {
+            self.subscriptions.insert(
+                account.id,
+                Subscription {
+                    balance_limit: request.balance_limit,
+                    below_limit: None,
+                },
+            );
+        }
+        self.send(&ServerMessage::Subscribed {
+            account_ids: request.account_ids,
+        })
+        .await?;
+
+        if let Some(resume_from) = self.resume_from {
+            self.catch_up(&new_ids, resume_from).await?;
+        }
+        self.read_events().await?;
+        for account in &accounts {
+            let available = available_balance(account).to_f64().unwrap_or_default();
+            self.check_limit(None, account.id, available, true).await?;
+        }
+        Ok(())
+    }
+
+    async fn unsubscribe(&mut self, request: UnsubscribeRequest) -> Result<(), Closed> {
+        if let Err(errors) = request.validate() {
+            return self.reject(ApiError::Validation(errors)).await;
+        }
+        for account_id in &request.account_ids {
+            self.subscriptions.remove(account_id);
+        }
+        self.send(&ServerMessage::Unsubscribed {
+            account_ids: request.account_ids,
+        })
+        .await
+    }
+
+    /// Reads the connection's events up to the ones committed so far and sends them
+    async fn read_events(&mut self) -> Result<(), Closed> {
+        let account_ids: Vec<i32> = self.subscriptions.keys().copied().collect();
+        // Anything announced before this position can be read, so an announced event
+        // that is not read concerns an account unsubscribed from in the meantime.
+        let readable = match self.waiting_for {
+            Some(_) => match self.state.db().outbox.current_position().await {
+                Ok(position) => Some(position),
+                Err(err) => return self.reject(err.into()).await,
+            },
+            None => None,
+        };
+        loop {
+            let events = match self
+                .state
+                .db()
+                .outbox
+                .notification_events_after(
+                    Some(self.claim.id()),
+                    &account_ids,
+                    self.position,
+                    NOTIFICATIONS_REPLAY_BATCH,
+                )
+                .await
+            {
+                Ok(events) => events,
+                Err(err) => return self.reject(err.into()).await,
+            };
+            for event in &events {
+                self.position = EventPosition::of(event);
+                self.forward(event).await?;
+            }
+            if (events.len() as u64) < NOTIFICATIONS_REPLAY_BATCH {
+                break;
+            }
+        }
+        if self.waiting_for <= Some(self.position) || self.waiting_for <= readable {
+            self.waiting_for = None;
+        }
+        Ok(())
+    }
+
+    /// Sends the events of newly subscribed accounts from `resume_from` up to the
+    /// connection's position; later ones are read with the rest of the connection's
+    async fn catch_up(
+        &mut self,
+        account_ids: &[i32],
+        resume_from: EventPosition,
+    ) -> Result<(), Closed> {
+        let mut after = resume_from;
+        while after < self.position {
+            let events = match self
+                .state
+                .db()
+                .outbox
+                .notification_events_after(None, account_ids, after, NOTIFICATIONS_REPLAY_BATCH)
+                .await
+            {
+                Ok(events) => events,
+                Err(err) => return self.reject(err.into()).await,
+            };
+            for event in &events {
+                after = EventPosition::of(event);
+                if after > self.position {
+                    return Ok(());
+                }
+                self.forward(event).await?;
+            }
+            if (events.len() as u64) < NOTIFICATIONS_REPLAY_BATCH {
+                return Ok(());
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the connection's events when one it notifies about has committed
+    async fn announce(&mut self, event: &outbox::Model) -> Result<(), Closed> {
+        let position = EventPosition::of(event);
+        if position <= self.position || !self.notifies(event) {
+            return Ok(());
+        }
+        self.waiting_for = self.waiting_for.max(Some(position));
+        self.read_events().await
+    }
+
+    /// Whether the connection sends a notification for the event
+    fn notifies(&self, event: &outbox::Model) -> bool {
+        match event.event_type {
+            OutboxEventType::TransactionCreated => event.payload["to_account_id"]
+                .as_i64()
+                .and_then(|id| i32::try_from(id).ok())
+                .is_some_and(|id| self.subscriptions.contains_key(&id)),
+            OutboxEventType::AccountBalanceChanged => {
+                self.subscriptions.contains_key(&event.aggregate_id)
+            }
+            OutboxEventType::InvoiceCreated => concerns_user(event, self.claim.id()),
+            _ => false,
+        }
+    }
+
+    /// Sends the notification an outbox event amounts to for this connection, if any
+    async fn forward(&mut self, event: &outbox::Model) -> Result<(), Closed> {
+        match event.event_type {
+            OutboxEventType::TransactionCreated => {
+                let Some(account_id) = event.payload["to_account_id"]
+                    .as_i64()
+                    .and_then(|id| i32::try_from(id).ok())
+                else {
+                    return Ok(());
+                };
+                if !self.subscriptions.contains_key(&account_id) {
+                    return Ok(());
+                }
+                self.send(&ServerMessage::IncomingPayment {
+                    event_id: event_id(event),
+                    account_id,
+                    transaction: event.payload.clone(),
+                })
+                .await
+            }
+            OutboxEventType::AccountBalanceChanged => {
+                let balance = event.payload["balance"].as_f64().unwrap_or_default();
+                let held_balance = event.payload["held_balance"].as_f64().unwrap_or_default();
+                self.check_limit(
+                    Some(event_id(event)),
+                    event.aggregate_id,
+                    balance - held_balance,
+                    false,
+                )
+                .await
+            }
+            OutboxEventType::InvoiceCreated if concerns_user(event, self.claim.id()) => {
+                self.send(&ServerMessage::PaymentRequest {
+                    event_id: event_id(event),
+                    invoice: event.payload.clone(),
+                })
+                .await
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Warns when the available balance of a subscribed account has dropped below its
+    /// limit since the last balance seen. `initial` balances only count if no balance
+    /// of the account was seen yet.
+    async fn check_limit(
+        &mut self,
+        event_id: Option<String>,
+        account_id: i32,
+        available: f64,
+        initial: bool,
+    ) -> Result<(), Closed> {
+        let Some(subscription) = self.subscriptions.get_mut(&account_id) else {
+            return Ok(());
+        };
+        let Some(balance_limit) = subscription.balance_limit else {
+            return Ok(());
+        };
+        if initial && subscription.below_limit.is_some() {
+            return Ok(());
+        }
+
+        let was_below = subscription.below_limit.replace(available < balance_limit);
+        if available >= balance_limit || was_below == Some(true) {
+            return Ok(());
+        }
+        self.send(&ServerMessage::LimitWarning {
+            event_id,
+            account_id,
+            available_balance: available,
+            balance_limit,
+        })
+        .await
+    }
+
+    async fn reject(&mut self, err: ApiError) -> Result<(), Closed> {
+        if let ApiError::DBError(db_err) = &err {
+            warn!(
+                "Notification connection of user {} failed: {}",
+                self.claim.id(),
+                db_err
+            );
+        }
+        self.send(&ServerMessage::Error { error: err.body() }).await
+    }
+
+    async fn send(&mut self, message: &ServerMessage) -> Result<(), Closed> {
+        match serde_json::to_string(message) {
+            Ok(text) => self.session.text(text).await,
+            Err(err) => {
+                warn!("Could not serialize a notification: {}", err);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The id a notification carries, as used for `last_event_id`
+fn event_id(event: &outbox::Model) -> String {
+    format!("{}-{}", event.transaction_id, event.id)
+}
@@ -0,0 +1,299 @@
+use actix_web::{delete, get, post, put, web, HttpResponse, Responder};
+use chrono::Utc;
+use db::db_client::DbClient;
+use db::organizations::OrganizationRole;
+use entity::organization_members;
+
+use crate::app_state::AppState;
+use crate::features::organizations::organization_types::{
+    AddOrganizationMemberRequest, CreateOrganizationRequest, ListOrganizationMembersResponse,
+    ListOrganizationsResponse, OrganizationMemberResponse, OrganizationResponse,
+    SwitchOrganizationRequest, SwitchOrganizationResponse, UpdateOrganizationMemberRequest,
+};
+use crate::features::user::controllers::issue_tokens;
+use crate::middlewares::auth::JWTClaim;
+use crate::util::{ApiError, AuthError};
+use crate::validation::ValidJson;
+
+type State = web::Data<AppState>;
+
+/// Create an organization with the authenticated user as its first admin
+/// Endpoint: POST /api/org/create
+/// Request Body: {
+///     "name": string
+/// }
+/// Response Body: {
+///     "id": integer,
+///     "name": string,
+///     "role": "admin",
+///     "created_at": string (RFC 3339)
+/// }
+/// Requires authentication
+#[post("/create")]
+async fn create_organization(
+    state: State,
+    claim: JWTClaim,
+    request: ValidJson<CreateOrganizationRequest>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+    let user_id = claim.id();
+
+    let _user = db
+        .user
+        .find_user(user_id)
+        .await?
+        .ok_or(ApiError::AuthError(AuthError::UserNotFound))?;
+
+    let (organization, admin) = db
+        .organization
+        .create_organization(request.name.trim().to_string(), user_id)
+        .await?;
+
+    let response = OrganizationResponse::new(
+        organization.id,
+        organization.name,
+        admin.role,
+        organization.created_at.with_timezone(&Utc),
+    );
+    Ok(web::Json(response))
+}
+
+/// List the organizations the authenticated user belongs to
+/// Endpoint: GET /api/org/list
+/// Response Body: {
+///     "organizations": [
+///         {
+///             "id": integer,
+///             "name": string,
+///             "role": "admin" | "approver" | "spender" | "viewer",
+///             "created_at": string (RFC 3339)
+///         }
+///     ]
+/// }
+/// Requires authentication
+#[get("/list")]
+async fn list_organizations(state: State, claim: JWTClaim) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+
+    let organizations = db
+        .organization
+        .list_user_organizations(claim.id())
+        .await?
+        .into_iter()
+        .filter_map(|(member, organization)| {
+            organization.map(|organization| {
+                OrganizationResponse::new(
+                    organization.id,
+                    organization.name,
+                    member.role,
+                    organization.created_at.with_timezone(&Utc),
+                )
+            })
+        })
+        .collect();
+
+    Ok(web::Json(ListOrganizationsResponse { organizations }))
+}
+
+/// Switch the organization the token acts for
+/// Endpoint: POST /api/org/switch
+/// Request Body: {
+///     "organization_id": integer | null
+/// }
+/// Response Body: {
+///     "organization_id": integer | null,
+///     "token": string,
+///     "refresh_token": string,
+///     "expires_in": integer
+/// }
+/// Requires authentication. The returned tokens replace the current ones: with an
+/// organization active, account and transaction endpoints work against the organization's
+/// accounts, also after refreshing. `null` switches back to personal accounts
+#[post("/switch")]
+async fn switch_organization(
+    state: State,
+    claim: JWTClaim,
+    request: ValidJson<SwitchOrganizationRequest>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+    let user_id = claim.id();
+
+    if let Some(organization_id) = request.organization_id {
+        require_member(db, organization_id, user_id).await?;
+    }
+
+    let tokens = issue_tokens(db, user_id, claim.role(), request.organization_id).await?;
+
+    let response = SwitchOrganizationResponse::new(request.organization_id, tokens);
+    Ok(web::Json(response))
+}
+
+/// List the members of an organization and their roles
+/// Endpoint: GET /api/org/{organization_id}/members
+/// Path Parameters: organization_id (integer)
+/// Response Body: {
+///     "members": [
+///         {
+///             "user_id": integer,
+///             "username": string,
+///             "display_name": string | null,
+///             "role": "admin" | "approver" | "spender" | "viewer",
+///             "joined_at": string (RFC 3339)
+///         }
+///     ]
+/// }
+/// Requires authentication and membership of the organization
+#[get("/{organization_id}/members")]
+async fn list_organization_members(
+    state: State,
+    claim: JWTClaim,
+    path: web::Path<i32>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+    let organization_id = path.into_inner();
+
+    require_member(db, organization_id, claim.id()).await?;
+
+    let mut members = Vec::new();
+    for member in db.organization.list_members(organization_id).await? {
+        members.push(member_response(db, member).await?);
+    }
+
+    Ok(web::Json(ListOrganizationMembersResponse { members }))
+}
+
+/// Add a user to an organization
+/// Endpoint: POST /api/org/{organization_id}/members
+/// Path Parameters: organization_id (integer)
+/// Request Body: {
+///     "username": string,
+///     "role": "admin" | "approver" | "spender" | "viewer"
+/// }
+/// Response Body: member
+/// Requires authentication and the admin role in the organization.
+/// Returns 409 if the user is already a member
+#[post("/{organization_id}/members")]
+async fn add_organization_member(
+    state: State,
+    claim: JWTClaim,
+    path: web::Path<i32>,
+    request: ValidJson<AddOrganizationMemberRequest>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+    let organization_id = path.into_inner();
+
+    require_admin(db, organization_id, claim.id()).await?;
+
+    let username = request.username.trim().trim_start_matches('@').to_string();
+    let user = db
+        .user
+        .find_user_by_username(username)
+        .await?
+        .ok_or(ApiError::AuthError(AuthError::UserNotFound))?;
+
+    let member = db
+        .organization
+        .add_member(organization_id, user.id, request.role)
+        .await?;
+
+    Ok(web::Json(member_response(db, member).await?))
+}
+
+/// Change the role of an organization member
+/// Endpoint: PUT /api/org/{organization_id}/members/{user_id}
+/// Path Parameters: organization_id (integer), user_id (integer)
+/// Request Body: {
+///     "role": "admin" | "approver" | "spender" | "viewer"
+/// }
+/// Response Body: member
+/// Requires authentication and the admin role in the organization.
+/// Returns 409 when demoting the last admin
+#[put("/{organization_id}/members/{member_user_id}")]
+async fn update_organization_member(
+    state: State,
+    claim: JWTClaim,
+    path: web::Path<(i32, i32)>,
+    request: ValidJson<UpdateOrganizationMemberRequest>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+    let (organization_id, member_user_id) = path.into_inner();
+
+    require_admin(db, organization_id, claim.id()).await?;
+
+    let member = db
+        .organization
+        .update_role(organization_id, member_user_id, request.role)
+        .await?;
+
+    Ok(web::Json(member_response(db, member).await?))
+}
+
+/// Remove a member from an organization, or leave it
+/// Endpoint: DELETE /api/org/{organization_id}/members/{user_id}
+/// Path Parameters: organization_id (integer), user_id (integer)
+/// Response: 204 No Content
+/// Requires authentication. Admins can remove anyone and every member can remove
+/// themselves. Returns 409 when removing the last admin
+#[delete("/{organization_id}/members/{member_user_id}")]
+async fn remove_organization_member(
+    state: State,
+    claim: JWTClaim,
+    path: web::Path<(i32, i32)>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+    let user_id = claim.id();
+    let (organization_id, member_user_id) = path.into_inner();
+
+    if member_user_id == user_id {
+        require_member(db, organization_id, user_id).await?;
+    } else {
+        require_admin(db, organization_id, user_id).await?;
+    }
+
+    db.organization
+        .remove_member(organization_id, member_user_id)
+        .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+async fn require_member(
+    db: &DbClient,
+    organization_id: i32,
+    user_id: i32,
+) -> Result<organization_members::Model, ApiError> {
+    db.organization
+        .find_organization(organization_id)
+        .await?
+        .ok_or(ApiError::NotFound("Organization"))?;
+    db.organization
+        .find_membership(organization_id, user_id)
+        .await?
+        .ok_or(ApiError::AuthError(AuthError::Unauthorized))
+}
+
+async fn require_admin(db: &DbClient, organization_id: i32, user_id: i32) -> Result<(), ApiError> {
+    let member = require_member(db, organization_id, user_id).await?;
+    if member.role != OrganizationRole::Admin {
+        return Err(ApiError::AuthError(AuthError::Unauthorized));
+    }
+    Ok(())
+}
+
+async fn member_response(
+    db: &DbClient,
+    member: organization_members::Model,
+) -> Result<OrganizationMemberResponse, ApiError> {
+    let user = db
+        .user
+        .find_user(member.user_id)
+        .await?
+        .ok_or(ApiError::AuthError(AuthError::UserNotFound))?;
+    Ok(OrganizationMemberResponse::new(
+        user.id,
+        user.username,
+        user.display_name,
+        member.role,
+        member.created_at.with_timezone(&Utc),
+    ))
+}
@@ -0,0 +1,138 @@
+use chrono::{DateTime, Utc};
+use db::organizations::OrganizationRole;
+use serde::{Deserialize, Serialize};
+
+use crate::features::user::user_types::TokenResponse;
+use crate::validation::{FieldError, Validate, Validator};
+
+/// Request body for creating an organization
+#[derive(Deserialize)]
+pub struct CreateOrganizationRequest {
+    pub name: String,
+}
+
+impl Validate for CreateOrganizationRequest {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::new();
+        v.not_blank(&self.name, "name");
+        v.length(self.name.trim(), "name", 1, 64);
+        v.finish()
+    }
+}
+
+/// Request body for adding a user to an organization
+#[derive(Deserialize)]
+pub struct AddOrganizationMemberRequest {
+    pub username: String,
+    pub role: OrganizationRole,
+}
+
+impl Validate for AddOrganizationMemberRequest {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::new();
+        v.not_blank(&self.username, "username");
+        v.finish()
+    }
+}
+
+/// Request body for changing a member's role
+#[derive(Deserialize)]
+pub struct UpdateOrganizationMemberRequest {
+    pub role: OrganizationRole,
+}
+
+impl Validate for UpdateOrganizationMemberRequest {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        Ok(())
+    }
+}
+
+/// Request body for switching the organization the token acts for.
+/// `null` switches back to the user's personal accounts.
+#[derive(Deserialize)]
+pub struct SwitchOrganizationRequest {
+    pub organization_id: Option<i32>,
+}
+
+impl Validate for SwitchOrganizationRequest {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        Ok(())
+    }
+}
+
+/// Response for an organization, with the caller's role in it
+#[derive(Debug, Serialize)]
+pub struct OrganizationResponse {
+    pub id: i32,
+    pub name: String,
+    pub role: OrganizationRole,
+    pub created_at: DateTime<Utc>,
+}
+
+impl OrganizationResponse {
+    pub fn new(id: i32, name: String, role: OrganizationRole, created_at: DateTime<Utc>) -> Self {
+        Self {
+            id,
+            name,
+            role,
+            created_at,
+        }
+    }
+}
+
+/// Response for listing the user's organizations
+#[derive(Debug, Serialize)]
+pub struct ListOrganizationsResponse {
+    pub organizations: Vec<OrganizationResponse>,
+}
+
+/// Response for a single organization member
+#[derive(Debug, Serialize)]
+pub struct OrganizationMemberResponse {
+    pub user_id: i32,
+    pub username: String,
+    pub display_name: Option<String>,
+    pub role: OrganizationRole,
+    pub joined_at: DateTime<Utc>,
+}
+
+impl OrganizationMemberResponse {
+    pub fn new(
+        user_id: i32,
+        username: String,
+        display_name: Option<String>,
+        role: OrganizationRole,
+        joined_at: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            user_id,
+            username,
+            display_name,
+            role,
+            joined_at,
+        }
+    }
+}
+
+/// Response for listing the members of an organization
+#[derive(Debug, Serialize)]
+pub struct ListOrganizationMembersResponse {
+    pub members: Vec<OrganizationMemberResponse>,
+}
+
+/// Response for switching organizations, carrying tokens for the new context
+#[derive(Serialize)]
+pub struct SwitchOrganizationResponse {
+    pub organization_id: Option<i32>,
+    #[serde(flatten)]
+    pub tokens: TokenResponse,
+}
+
+impl SwitchOrganizationResponse {
+    pub fn new(organization_id: Option<i32>, tokens: TokenResponse) -> Self {
+        Self {
+            organization_id,
+            tokens,
+        }
+    }
+}
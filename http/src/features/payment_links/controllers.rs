@@ -0,0 +1,255 @@
+use actix_web::{delete, get, post, web, Responder};
+use chrono::Utc;
+use num_traits::cast::ToPrimitive;
+
+use crate::app_state::AppState;
+use crate::features::accounts::access::{authorize_account, scope_account_ids, AccountAccess};
+use crate::features::payment_links::payment_link_types::{
+    CreatePaymentLinkRequest, ListPaymentLinksQuery, ListPaymentLinksResponse,
+    PayPaymentLinkRequest, PaymentLinkResponse, PublicPaymentLinkResponse,
+};
+use crate::features::transactions::transaction_types::TransactionResponse;
+use crate::features::user::recipients::payee_display_name;
+use crate::middlewares::auth::JWTClaim;
+use crate::pagination::{next_cursor, PageQuery};
+use crate::util::{ApiError, AuthError};
+use crate::validation::{FieldError, ValidJson, ValidQuery};
+
+type State = web::Data<AppState>;
+
+/// Create a shareable link that collects money into an account
+/// Endpoint: POST /api/link/create
+/// Request Body: {
+///     "account_id": integer,
+///     "amount": float (optional, the payer chooses the amount if omitted),
+///     "description": string (optional, at most 200 characters),
+///     "single_use": boolean (optional, default false),
+///     "expires_at": string (RFC 3339, optional)
+/// }
+/// Response Body: {
+///     "id": integer,
+///     "slug": string,
+///     "account_id": integer,
+///     "amount": float | null,
+///     "description": string | null,
+///     "single_use": boolean,
+///     "active": boolean,
+///     "expires_at": string (RFC 3339) | null,
+///     "payment_count": integer,
+///     "collected_amount": float,
+///     "created_at": string (RFC 3339),
+///     "updated_at": string (RFC 3339)
+/// }
+/// Requires authentication.
+/// Requires the owner or spender role on the account
+#[post("/create")]
+async fn create_payment_link(
+    state: State,
+    claim: JWTClaim,
+    request: ValidJson<CreatePaymentLinkRequest>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+
+    let account = authorize_account(db, request.account_id, &claim, AccountAccess::Spend).await?;
+
+    let link = db
+        .payment_link
+        .create_link(
+            account.id,
+            claim.id(),
+            request.amount,
+            request
+                .description
+                .as_deref()
+                .map(|description| description.trim().to_string()),
+            request.single_use,
+            request.expires_at.map(|at| at.fixed_offset()),
+        )
+        .await?;
+
+    Ok(web::Json(PaymentLinkResponse::from(link)))
+}
+
+/// List the payment links of the user's accounts
+/// Endpoint: GET /api/link/list
+/// Query Parameters (all optional):
+///     account_id: integer - only links into this account
+///     limit: integer, cursor: string - keyset pagination
+/// Response Body: {
+///     "payment_links": [ payment link as returned by POST /api/link/create ],
+///     "next_cursor": string | null
+/// }
+/// Requires authentication
+#[get("/list")]
+async fn list_payment_links(
+    state: State,
+    claim: JWTClaim,
+    query: ValidQuery<ListPaymentLinksQuery>,
+    page: ValidQuery<PageQuery>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+    let page_request = page.page_request()?;
+
+    let account_ids: Vec<i32> = match query.account_id {
+        Some(account_id) => {
+            let account = authorize_account(db, account_id, &claim, AccountAccess::View).await?;
+            vec![account.id]
+        }
+        None => scope_account_ids(db, &claim).await?,
+    };
+
+    if account_ids.is_empty() {
+        return Ok(web::Json(ListPaymentLinksResponse {
+            payment_links: vec![],
+            next_cursor: None,
+        }));
+    }
+
+    let links = db
+        .payment_link
+        .list_links(&account_ids, page_request)
+        .await?;
+    let next_cursor = next_cursor(&links);
+
+    Ok(web::Json(ListPaymentLinksResponse {
+        payment_links: links
+            .items
+            .into_iter()
+            .map(PaymentLinkResponse::from)
+            .collect(),
+        next_cursor,
+    }))
+}
+
+/// Show who a payment link pays and how much
+/// Endpoint: GET /api/link/{slug}
+/// Path Parameters: slug (string)
+/// Response Body: {
+///     "slug": string,
+///     "payee_display_name": string,
+///     "amount": float | null (the payer chooses the amount),
+///     "description": string | null,
+///     "expires_at": string (RFC 3339) | null,
+///     "accepts_payments": boolean
+/// }
+/// Does not require authentication
+#[get("/{slug}")]
+async fn get_payment_link(
+    state: State,
+    path: web::Path<String>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+    let slug = path.into_inner();
+
+    let link = db
+        .payment_link
+        .find_by_slug(&slug)
+        .await?
+        .ok_or(ApiError::NotFound("Payment link"))?;
+    let account = db
+        .account
+        .find_account(link.account_id)
+        .await?
+        .ok_or(ApiError::AuthError(AuthError::AccountNotFound))?;
+    let display_name = payee_display_name(db, &account).await?;
+
+    Ok(web::Json(PublicPaymentLinkResponse::new(
+        link,
+        display_name,
+    )))
+}
+
+/// Pay through a payment link from one of the user's accounts
+/// Endpoint: POST /api/link/{slug}/pay
+/// Path Parameters: slug (string)
+/// Request Body: {
+///     "account_id": integer,
+///     "amount": float (required unless the link has a fixed amount)
+/// }
+/// Response Body: transaction as returned by POST /api/transaction/create
+/// Requires authentication.
+/// Requires the owner or spender role on the paying account. Returns 409 if the link is
+/// inactive, expired or already used, if the amount differs from the link's fixed amount,
+/// or if the account needs approval for this amount
+#[post("/{slug}/pay")]
+async fn pay_payment_link(
+    state: State,
+    claim: JWTClaim,
+    path: web::Path<String>,
+    request: ValidJson<PayPaymentLinkRequest>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+    let slug = path.into_inner();
+
+    let payer_account =
+        authorize_account(db, request.account_id, &claim, AccountAccess::Spend).await?;
+
+    let link = db
+        .payment_link
+        .find_by_slug(&slug)
+        .await?
+        .ok_or(ApiError::NotFound("Payment link"))?;
+
+    let amount = match (request.amount, link.amount) {
+        (Some(amount), _) => amount,
+        (None, Some(fixed)) => fixed.to_f64().unwrap(),
+        (None, None) => {
+            return Err(ApiError::Validation(vec![FieldError::new(
+                "amount",
+                "required",
+                "This payment link lets the payer choose the amount",
+            )]))
+        }
+    };
+
+    if db
+        .approval
+        .requires_approval(payer_account.id, amount)
+        .await?
+    {
+        return Err(ApiError::Conflict(
+            "Payments above the approval threshold cannot be made from this account".to_string(),
+        ));
+    }
+
+    let (_link, transaction) = db
+        .payment_link
+        .pay(&link.slug, payer_account.id, amount)
+        .await?;
+
+    Ok(web::Json(TransactionResponse::new(
+        transaction.id,
+        transaction.from_account_id,
+        transaction.to_account_id,
+        transaction.amount.to_f64().unwrap(),
+        transaction.status,
+        transaction.created_at.with_timezone(&Utc),
+    )))
+}
+
+/// Stop a payment link from accepting payments
+/// Endpoint: DELETE /api/link/{slug}
+/// Path Parameters: slug (string)
+/// Response Body: payment link as returned by POST /api/link/create
+/// Requires authentication.
+/// Requires the owner or spender role on the link's account
+#[delete("/{slug}")]
+async fn deactivate_payment_link(
+    state: State,
+    claim: JWTClaim,
+    path: web::Path<String>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+    let slug = path.into_inner();
+
+    let link = db
+        .payment_link
+        .find_by_slug(&slug)
+        .await?
+        .ok_or(ApiError::NotFound("Payment link"))?;
+    authorize_account(db, link.account_id, &claim, AccountAccess::Spend).await?;
+
+    let link = db.payment_link.deactivate(link.id).await?;
+
+    Ok(web::Json(PaymentLinkResponse::from(link)))
+}
@@ -0,0 +1,2 @@
+pub mod controllers;
+pub mod payment_link_types;
@@ -0,0 +1,140 @@
+use chrono::{DateTime, Utc};
+use entity::payment_links;
+use num_traits::cast::ToPrimitive;
+use serde::{Deserialize, Serialize};
+
+use crate::validation::{FieldError, Validate, Validator};
+
+/// Request body for creating a payment link
+#[derive(Debug, Deserialize)]
+pub struct CreatePaymentLinkRequest {
+    pub account_id: i32,
+    /// Fixed amount; the payer chooses the amount if omitted
+    pub amount: Option<f64>,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub single_use: bool,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl Validate for CreatePaymentLinkRequest {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::new();
+        if let Some(amount) = self.amount {
+            v.positive_amount(amount, "amount");
+        }
+        if let Some(description) = &self.description {
+            v.not_blank(description, "description");
+            v.length(description.trim(), "description", 1, 200);
+        }
+        if let Some(expires_at) = self.expires_at {
+            v.check(
+                expires_at > Utc::now(),
+                "expires_at",
+                "future",
+                "Must be in the future",
+            );
+        }
+        v.finish()
+    }
+}
+
+/// Request body for paying through a payment link
+#[derive(Debug, Deserialize)]
+pub struct PayPaymentLinkRequest {
+    pub account_id: i32,
+    /// Required when the link lets the payer choose the amount
+    pub amount: Option<f64>,
+}
+
+impl Validate for PayPaymentLinkRequest {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::new();
+        if let Some(amount) = self.amount {
+            v.positive_amount(amount, "amount");
+        }
+        v.finish()
+    }
+}
+
+/// Query parameters for listing payment links
+#[derive(Debug, Deserialize)]
+pub struct ListPaymentLinksQuery {
+    pub account_id: Option<i32>,
+}
+
+impl Validate for ListPaymentLinksQuery {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        Ok(())
+    }
+}
+
+/// Response for a payment link as seen by the account it collects into
+#[derive(Debug, Serialize)]
+pub struct PaymentLinkResponse {
+    pub id: i32,
+    pub slug: String,
+    pub account_id: i32,
+    pub amount: Option<f64>,
+    pub description: Option<String>,
+    pub single_use: bool,
+    pub active: bool,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub payment_count: i32,
+    pub collected_amount: f64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<payment_links::Model> for PaymentLinkResponse {
+    fn from(link: payment_links::Model) -> Self {
+        Self {
+            id: link.id,
+            slug: link.slug,
+            account_id: link.account_id,
+            amount: link.amount.map(|amount| amount.to_f64().unwrap()),
+            description: link.description,
+            single_use: link.single_use,
+            active: link.active,
+            expires_at: link.expires_at.map(|at| at.with_timezone(&Utc)),
+            payment_count: link.payment_count,
+            collected_amount: link.collected_amount.to_f64().unwrap(),
+            created_at: link.created_at.with_timezone(&Utc),
+            updated_at: link.updated_at.with_timezone(&Utc),
+        }
+    }
+}
+
+/// Response for a payment link as shown to anyone holding its URL
+#[derive(Debug, Serialize)]
+pub struct PublicPaymentLinkResponse {
+    pub slug: String,
+    pub payee_display_name: String,
+    /// `null` when the payer chooses the amount
+    pub amount: Option<f64>,
+    pub description: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Whether the link is active and not expired
+    pub accepts_payments: bool,
+}
+
+impl PublicPaymentLinkResponse {
+    pub fn new(link: payment_links::Model, payee_display_name: String) -> Self {
+        let expired = link.expires_at.is_some_and(|at| at <= Utc::now());
+        Self {
+            slug: link.slug,
+            payee_display_name,
+            amount: link.amount.map(|amount| amount.to_f64().unwrap()),
+            description: link.description,
+            expires_at: link.expires_at.map(|at| at.with_timezone(&Utc)),
+            accepts_payments: link.active && !expired,
+        }
+    }
+}
+
+/// Response for a page of payment links
+#[derive(Debug, Serialize)]
+pub struct ListPaymentLinksResponse {
+    pub payment_links: Vec<PaymentLinkResponse>,
+    pub next_cursor: Option<String>,
+}
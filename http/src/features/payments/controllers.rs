@@ -0,0 +1,222 @@
+use actix_web::{get, post, web, Responder};
+use num_traits::cast::ToPrimitive;
+
+use crate::app_state::AppState;
+use crate::features::accounts::access::{authorize_account, scope_account_ids, AccountAccess};
+use crate::features::payments::payment_types::{
+    ConfirmPaymentIntentRequest, CreatePaymentIntentRequest, ListPaymentIntentsQuery,
+    ListPaymentIntentsResponse, PaymentIntentResponse,
+};
+use crate::middlewares::auth::JWTClaim;
+use crate::pagination::{next_cursor, PageQuery};
+use crate::util::{ApiError, AuthError};
+use crate::validation::{ValidJson, ValidQuery};
+
+type State = web::Data<AppState>;
+
+/// Create a payment intent for a customer to pay into a merchant account
+/// Endpoint: POST /api/payment/intent/create
+/// Request Body: {
+///     "merchant_account_id": integer,
+///     "amount": float,
+///     "currency": string (ISO 4217, only "USD" is supported),
+///     "metadata": object (optional, at most 50 keys)
+/// }
+/// Response Body: {
+///     "id": integer,
+///     "merchant_account_id": integer,
+///     "amount": float,
+///     "currency": string,
+///     "metadata": object | null,
+///     "status": "requires_payment" | "processing" | "succeeded" | "canceled",
+///     "customer_account_id": integer | null,
+///     "transaction_id": integer | null,
+///     "created_at": string (RFC 3339),
+///     "updated_at": string (RFC 3339)
+/// }
+/// Requires authentication.
+/// Requires the owner or spender role on the merchant account
+#[post("/intent/create")]
+async fn create_payment_intent(
+    state: State,
+    claim: JWTClaim,
+    request: ValidJson<CreatePaymentIntentRequest>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+
+    let merchant_account = authorize_account(
+        db,
+        request.merchant_account_id,
+        &claim,
+        AccountAccess::Spend,
+    )
+    .await?;
+
+    let intent = db
+        .payment
+        .create_intent(
+            merchant_account.id,
+            request.amount,
+            request.normalized_currency(),
+            request.metadata.clone(),
+            claim.id(),
+        )
+        .await?;
+
+    Ok(web::Json(PaymentIntentResponse::from(intent)))
+}
+
+/// List payment intents of the user's merchant accounts
+/// Endpoint: GET /api/payment/intent/list
+/// Query Parameters (all optional):
+///     merchant_account_id: integer - only intents of this account
+///     status: "requires_payment" | "processing" | "succeeded" | "canceled"
+///     limit: integer, cursor: string - keyset pagination
+/// Response Body: {
+///     "payment_intents": [ payment intent as returned by POST /api/payment/intent/create ],
+///     "next_cursor": string | null
+/// }
+/// Requires authentication
+#[get("/intent/list")]
+async fn list_payment_intents(
+    state: State,
+    claim: JWTClaim,
+    query: ValidQuery<ListPaymentIntentsQuery>,
+    page: ValidQuery<PageQuery>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+    let page_request = page.page_request()?;
+
+    let account_ids: Vec<i32> = match query.merchant_account_id {
+        Some(account_id) => {
+            let account = authorize_account(db, account_id, &claim, AccountAccess::View).await?;
+            vec![account.id]
+        }
+        None => scope_account_ids(db, &claim).await?,
+    };
+
+    if account_ids.is_empty() {
+        return Ok(web::Json(ListPaymentIntentsResponse {
+            payment_intents: vec![],
+            next_cursor: None,
+        }));
+    }
+
+    let intents = db
+        .payment
+        .list_intents(&account_ids, query.status, page_request)
+        .await?;
+    let next_cursor = next_cursor(&intents);
+
+    Ok(web::Json(ListPaymentIntentsResponse {
+        payment_intents: intents
+            .items
+            .into_iter()
+            .map(PaymentIntentResponse::from)
+            .collect(),
+        next_cursor,
+    }))
+}
+
+/// Get a payment intent
+/// Endpoint: GET /api/payment/intent/{intent_id}
+/// Path Parameters: intent_id (integer)
+/// Response Body: payment intent as returned by POST /api/payment/intent/create
+/// Requires authentication. Any user can look up an intent to pay it
+#[get("/intent/{intent_id}")]
+async fn get_payment_intent(
+    state: State,
+    claim: JWTClaim,
+    path: web::Path<i32>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+    let intent_id = path.into_inner();
+
+    let _user = db
+        .user
+        .find_user(claim.id())
+        .await?
+        .ok_or(ApiError::AuthError(AuthError::UserNotFound))?;
+
+    let intent = db
+        .payment
+        .find_intent(intent_id)
+        .await?
+        .ok_or(ApiError::NotFound("Payment intent"))?;
+
+    Ok(web::Json(PaymentIntentResponse::from(intent)))
+}
+
+/// Pay a payment intent from one of the user's accounts
+/// Endpoint: POST /api/payment/intent/{intent_id}/confirm
+/// Path Parameters: intent_id (integer)
+/// Request Body: {
+///     "account_id": integer
+/// }
+/// Response Body: payment intent as returned by POST /api/payment/intent/create,
+///     "succeeded" with the settling "transaction_id"
+/// Requires authentication.
+/// Requires the owner or spender role on the paying account. Returns 400 if the balance
+/// does not cover the amount, in which case the intent can be paid again, and 409 if the
+/// intent is not awaiting payment or the account needs approval for this amount
+#[post("/intent/{intent_id}/confirm")]
+async fn confirm_payment_intent(
+    state: State,
+    claim: JWTClaim,
+    path: web::Path<i32>,
+    request: ValidJson<ConfirmPaymentIntentRequest>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+    let intent_id = path.into_inner();
+
+    let customer_account =
+        authorize_account(db, request.account_id, &claim, AccountAccess::Spend).await?;
+
+    let intent = db
+        .payment
+        .find_intent(intent_id)
+        .await?
+        .ok_or(ApiError::NotFound("Payment intent"))?;
+
+    if db
+        .approval
+        .requires_approval(customer_account.id, intent.amount.to_f64().unwrap())
+        .await?
+    {
+        return Err(ApiError::Conflict(
+            "Payments above the approval threshold cannot be made from this account".to_string(),
+        ));
+    }
+
+    let (intent, _transaction) = db.payment.confirm(intent.id, customer_account.id).await?;
+
+    Ok(web::Json(PaymentIntentResponse::from(intent)))
+}
+
+/// Cancel a payment intent that has not been paid
+/// Endpoint: POST /api/payment/intent/{intent_id}/cancel
+/// Path Parameters: intent_id (integer)
+/// Response Body: payment intent as returned by POST /api/payment/intent/create
+/// Requires authentication.
+/// Requires the owner or spender role on the merchant account. Returns 409 if the intent
+/// already succeeded or was canceled
+#[post("/intent/{intent_id}/cancel")]
+async fn cancel_payment_intent(
+    state: State,
+    claim: JWTClaim,
+    path: web::Path<i32>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+    let intent_id = path.into_inner();
+
+    let intent = db
+        .payment
+        .find_intent(intent_id)
+        .await?
+        .ok_or(ApiError::NotFound("Payment intent"))?;
+    authorize_account(db, intent.merchant_account_id, &claim, AccountAccess::Spend).await?;
+
+    let intent = db.payment.cancel(intent.id).await?;
+
+    Ok(web::Json(PaymentIntentResponse::from(intent)))
+}
@@ -0,0 +1,114 @@
+use chrono::{DateTime, Utc};
+use db::payments::PaymentIntentStatus;
+use entity::payment_intents;
+use num_traits::cast::ToPrimitive;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::constants::{CURRENCY, MAX_METADATA_KEYS};
+use crate::validation::{FieldError, Validate, Validator};
+
+/// Request body for creating a payment intent
+#[derive(Debug, Deserialize)]
+pub struct CreatePaymentIntentRequest {
+    pub merchant_account_id: i32,
+    pub amount: f64,
+    pub currency: String,
+    #[serde(default)]
+    pub metadata: Option<Value>,
+}
+
+impl CreatePaymentIntentRequest {
+    /// The currency code as stored: trimmed and uppercased
+    pub fn normalized_currency(&self) -> String {
+        self.currency.trim().to_uppercase()
+    }
+}
+
+impl Validate for CreatePaymentIntentRequest {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::new();
+        v.positive_amount(self.amount, "amount");
+        v.check(
+            self.normalized_currency() == CURRENCY,
+            "currency",
+            "unsupported",
+            &format!("Balances are kept in {CURRENCY}; no other currency is supported"),
+        );
+        if let Some(metadata) = &self.metadata {
+            v.check(
+                metadata
+                    .as_object()
+                    .is_some_and(|object| object.len() <= MAX_METADATA_KEYS),
+                "metadata",
+                "format",
+                &format!("Must be an object with at most {MAX_METADATA_KEYS} keys"),
+            );
+        }
+        v.finish()
+    }
+}
+
+/// Request body for paying a payment intent
+#[derive(Debug, Deserialize)]
+pub struct ConfirmPaymentIntentRequest {
+    pub account_id: i32,
+}
+
+impl Validate for ConfirmPaymentIntentRequest {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        Ok(())
+    }
+}
+
+/// Query parameters for listing payment intents
+#[derive(Debug, Deserialize)]
+pub struct ListPaymentIntentsQuery {
+    pub merchant_account_id: Option<i32>,
+    pub status: Option<PaymentIntentStatus>,
+}
+
+impl Validate for ListPaymentIntentsQuery {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        Ok(())
+    }
+}
+
+/// Response for a payment intent
+#[derive(Debug, Serialize)]
+pub struct PaymentIntentResponse {
+    pub id: i32,
+    pub merchant_account_id: i32,
+    pub amount: f64,
+    pub currency: String,
+    pub metadata: Option<Value>,
+    pub status: PaymentIntentStatus,
+    pub customer_account_id: Option<i32>,
+    pub transaction_id: Option<i32>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<payment_intents::Model> for PaymentIntentResponse {
+    fn from(intent: payment_intents::Model) -> Self {
+        Self {
+            id: intent.id,
+            merchant_account_id: intent.merchant_account_id,
+            amount: intent.amount.to_f64().unwrap(),
+            currency: intent.currency,
+            metadata: intent.metadata,
+            status: intent.status,
+            customer_account_id: intent.customer_account_id,
+            transaction_id: intent.transaction_id,
+            created_at: intent.created_at.with_timezone(&Utc),
+            updated_at: intent.updated_at.with_timezone(&Utc),
+        }
+    }
+}
+
+/// Response for a page of payment intents
+#[derive(Debug, Serialize)]
+pub struct ListPaymentIntentsResponse {
+    pub payment_intents: Vec<PaymentIntentResponse>,
+    pub next_cursor: Option<String>,
+}
@@ -0,0 +1,161 @@
+use actix_web::{get, post, web, Responder};
+
+use crate::app_state::AppState;
+use crate::features::accounts::access::{
+    authorize_account, has_access, scope_account_ids, AccountAccess,
+};
+use crate::features::splits::split_types::{
+    CreateSplitPaymentRequest, ListSplitPaymentsQuery, ListSplitPaymentsResponse,
+    SplitPaymentDetailsResponse, SplitPaymentResponse,
+};
+use crate::middlewares::auth::JWTClaim;
+use crate::pagination::{next_cursor, PageQuery};
+use crate::util::{ApiError, AuthError};
+use crate::validation::{ValidJson, ValidQuery};
+
+type State = web::Data<AppState>;
+
+/// Pay one amount to several recipients, minus the platform fee
+/// Endpoint: POST /api/split/create
+/// Request Body: {
+///     "account_id": integer,
+///     "amount": float,
+///     "description": string (optional),
+///     "recipients": [
+///         {
+///             "account_id": integer,
+///             "type": "percentage" | "fixed",
+///             "value": float
+///         }
+///     ]
+/// }
+/// Response Body: {
+///     "id": integer,
+///     "payer_account_id": integer,
+///     "created_by": integer,
+///     "amount": float,
+///     "platform_fee": float,
+///     "description": string | null,
+///     "created_at": string (RFC 3339),
+///     "legs": [
+///         {
+///             "recipient_account_id": integer,
+///             "kind": "percentage" | "fixed" | "platform_fee",
+///             "rule_value": float,
+///             "amount": float,
+///             "transaction_id": integer
+///         }
+///     ]
+/// }
+/// Requires authentication.
+/// Requires the owner or spender role on the paying account. Fixed shares are paid first
+/// and percentage shares, which must add up to 100, divide the rest. Returns 409 if the
+/// shares don't add up to the amount after the platform fee, or the account needs
+/// approval for this amount. Nothing is paid unless every share can be paid
+#[post("/create")]
+async fn create_split_payment(
+    state: State,
+    claim: JWTClaim,
+    request: ValidJson<CreateSplitPaymentRequest>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+
+    let payer_account =
+        authorize_account(db, request.account_id, &claim, AccountAccess::Spend).await?;
+    if db
+        .approval
+        .requires_approval(payer_account.id, request.amount)
+        .await?
+    {
+        return Err(ApiError::Conflict(
+            "Payments above the approval threshold cannot be made from this account".to_string(),
+        ));
+    }
+
+    let (split, legs) = db
+        .split_payment
+        .create_split(payer_account.id, claim.id(), request.input())
+        .await?;
+
+    Ok(web::Json(SplitPaymentDetailsResponse::new(split, legs)))
+}
+
+/// List split payments paid from or into the user's accounts
+/// Endpoint: GET /api/split/list
+/// Query Parameters (all optional):
+///     account_id: integer - only split payments from or into this account
+///     limit: integer, cursor: string - keyset pagination
+/// Response Body: {
+///     "split_payments": [ split payment as returned by POST /api/split/create,
+///                         without legs ],
+///     "next_cursor": string | null
+/// }
+/// Requires authentication
+#[get("/list")]
+async fn list_split_payments(
+    state: State,
+    claim: JWTClaim,
+    query: ValidQuery<ListSplitPaymentsQuery>,
+    page: ValidQuery<PageQuery>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+    let page_request = page.page_request()?;
+
+    let account_ids = match query.account_id {
+        Some(account_id) => {
+            let account = authorize_account(db, account_id, &claim, AccountAccess::View).await?;
+            vec![account.id]
+        }
+        None => scope_account_ids(db, &claim).await?,
+    };
+
+    let splits = db
+        .split_payment
+        .list_splits(&account_ids, page_request)
+        .await?;
+    let next_cursor = next_cursor(&splits);
+
+    Ok(web::Json(ListSplitPaymentsResponse {
+        split_payments: splits
+            .items
+            .into_iter()
+            .map(SplitPaymentResponse::from)
+            .collect(),
+        next_cursor,
+    }))
+}
+
+/// Get a split payment with its shares
+/// Endpoint: GET /api/split/{split_payment_id}
+/// Path Parameters: split_payment_id (integer)
+/// Response Body: split payment as returned by POST /api/split/create
+/// Requires authentication. The user must be able to see the paying account or one of
+/// the receiving accounts
+#[get("/{split_payment_id}")]
+async fn get_split_payment(
+    state: State,
+    claim: JWTClaim,
+    path: web::Path<i32>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+
+    let split = db
+        .split_payment
+        .find_split(path.into_inner())
+        .await?
+        .ok_or(ApiError::NotFound("Split payment"))?;
+    let legs = db.split_payment.list_legs(split.id).await?;
+
+    let mut is_party = has_access(db, split.payer_account_id, &claim, AccountAccess::View).await?;
+    for leg in &legs {
+        if is_party {
+            break;
+        }
+        is_party = has_access(db, leg.recipient_account_id, &claim, AccountAccess::View).await?;
+    }
+    if !is_party {
+        return Err(ApiError::AuthError(AuthError::Unauthorized));
+    }
+
+    Ok(web::Json(SplitPaymentDetailsResponse::new(split, legs)))
+}
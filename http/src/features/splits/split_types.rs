@@ -0,0 +1,203 @@
+use chrono::{DateTime, Utc};
+use db::split_payments::{SplitInput, SplitLegKind, SplitRecipient, SplitRule};
+use entity::{split_payment_legs, split_payments};
+use num_traits::cast::ToPrimitive;
+use serde::{Deserialize, Serialize};
+
+use crate::validation::{FieldError, Validate, Validator};
+
+/// How a recipient's share is computed
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SplitRuleType {
+    Percentage,
+    Fixed,
+}
+
+/// One recipient of a split payment
+#[derive(Debug, Deserialize)]
+pub struct SplitRecipientRequest {
+    pub account_id: i32,
+    #[serde(rename = "type")]
+    pub rule: SplitRuleType,
+    /// Percent of what is left after the platform fee and the fixed shares, or an amount
+    pub value: f64,
+}
+
+/// Request body for a payment split across several recipients
+#[derive(Debug, Deserialize)]
+pub struct CreateSplitPaymentRequest {
+    pub account_id: i32,
+    pub amount: f64,
+    pub description: Option<String>,
+    pub recipients: Vec<SplitRecipientRequest>,
+}
+
+impl CreateSplitPaymentRequest {
+    pub fn input(&self) -> SplitInput {
+        SplitInput {
+            amount: self.amount,
+            description: self
+                .description
+                .as_deref()
+                .map(|description| description.trim().to_string()),
+            recipients: self
+                .recipients
+                .iter()
+                .map(|recipient| SplitRecipient {
+                    account_id: recipient.account_id,
+                    rule: match recipient.rule {
+                        SplitRuleType::Percentage => SplitRule::Percentage(recipient.value),
+                        SplitRuleType::Fixed => SplitRule::Fixed(recipient.value),
+                    },
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Validate for CreateSplitPaymentRequest {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::new();
+        v.positive_amount(self.amount, "amount");
+        if let Some(description) = &self.description {
+            v.not_blank(description, "description");
+            v.length(description.trim(), "description", 1, 200);
+        }
+        v.check(
+            (1..=10).contains(&self.recipients.len()),
+            "recipients",
+            "length",
+            "Must contain between 1 and 10 recipients",
+        );
+
+        let mut percentage_total = 0.0;
+        let mut has_percentages = false;
+        for (index, recipient) in self.recipients.iter().enumerate() {
+            let field = |name: &str| format!("recipients[{index}].{name}");
+            v.check(
+                recipient.account_id != self.account_id,
+                &field("account_id"),
+                "same_account",
+                "Cannot pay a share to the paying account",
+            );
+            v.check(
+                !self.recipients[..index]
+                    .iter()
+                    .any(|other| other.account_id == recipient.account_id),
+                &field("account_id"),
+                "duplicate",
+                "Each account may only receive one share",
+            );
+            match recipient.rule {
+                SplitRuleType::Fixed => v.positive_amount(recipient.value, &field("value")),
+                SplitRuleType::Percentage => {
+                    v.check(
+                        recipient.value.is_finite()
+                            && recipient.value > 0.0
+                            && recipient.value <= 100.0,
+                        &field("value"),
+                        "range",
+                        "Must be a percentage greater than 0 and at most 100",
+                    );
+                    has_percentages = true;
+                    percentage_total += recipient.value;
+                }
+            }
+        }
+        if has_percentages {
+            v.check(
+                (percentage_total - 100.0).abs() < 1e-9,
+                "recipients",
+                "percentage_total",
+                "Percentage shares must add up to 100",
+            );
+        }
+        v.finish()
+    }
+}
+
+/// Query parameters for listing split payments
+#[derive(Debug, Deserialize)]
+pub struct ListSplitPaymentsQuery {
+    pub account_id: Option<i32>,
+}
+
+impl Validate for ListSplitPaymentsQuery {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        Ok(())
+    }
+}
+
+/// Response for a split payment
+#[derive(Debug, Serialize)]
+pub struct SplitPaymentResponse {
+    pub id: i32,
+    pub payer_account_id: i32,
+    pub created_by: i32,
+    pub amount: f64,
+    pub platform_fee: f64,
+    pub description: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<split_payments::Model> for SplitPaymentResponse {
+    fn from(split: split_payments::Model) -> Self {
+        Self {
+            id: split.id,
+            payer_account_id: split.payer_account_id,
+            created_by: split.created_by,
+            amount: split.amount.to_f64().unwrap(),
+            platform_fee: split.platform_fee.to_f64().unwrap(),
+            description: split.description,
+            created_at: split.created_at.with_timezone(&Utc),
+        }
+    }
+}
+
+/// Response for one share of a split payment
+#[derive(Debug, Serialize)]
+pub struct SplitLegResponse {
+    pub recipient_account_id: i32,
+    pub kind: SplitLegKind,
+    /// The percentage or fixed amount the share was computed from
+    pub rule_value: f64,
+    pub amount: f64,
+    pub transaction_id: i32,
+}
+
+impl From<split_payment_legs::Model> for SplitLegResponse {
+    fn from(leg: split_payment_legs::Model) -> Self {
+        Self {
+            recipient_account_id: leg.recipient_account_id,
+            kind: leg.kind,
+            rule_value: leg.rule_value.to_f64().unwrap(),
+            amount: leg.amount.to_f64().unwrap(),
+            transaction_id: leg.transaction_id,
+        }
+    }
+}
+
+/// Response for a split payment with its shares
+#[derive(Debug, Serialize)]
+pub struct SplitPaymentDetailsResponse {
+    #[serde(flatten)]
+    pub split: SplitPaymentResponse,
+    pub legs: Vec<SplitLegResponse>,
+}
+
+impl SplitPaymentDetailsResponse {
+    pub fn new(split: split_payments::Model, legs: Vec<split_payment_legs::Model>) -> Self {
+        Self {
+            split: SplitPaymentResponse::from(split),
+            legs: legs.into_iter().map(SplitLegResponse::from).collect(),
+        }
+    }
+}
+
+/// Response for a page of split payments
+#[derive(Debug, Serialize)]
+pub struct ListSplitPaymentsResponse {
+    pub split_payments: Vec<SplitPaymentResponse>,
+    pub next_cursor: Option<String>,
+}
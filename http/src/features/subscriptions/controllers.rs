@@ -0,0 +1,429 @@
+use actix_web::{delete, get, post, put, web, Responder};
+use db::db_client::DbClient;
+use db::subscriptions::SubscriptionFilter;
+use entity::{subscription_plans, subscriptions};
+use num_traits::cast::ToPrimitive;
+
+use crate::app_state::AppState;
+use crate::features::accounts::access::{
+    authorize_account, has_access, scope_account_ids, AccountAccess,
+};
+use crate::features::subscriptions::subscription_types::{
+    CancelSubscriptionRequest, ChangePlanRequest, CreatePlanRequest, ListPlansQuery,
+    ListPlansResponse, ListSubscriptionsQuery, ListSubscriptionsResponse, PlanResponse,
+    SubscribeRequest, SubscriptionDetailsResponse, SubscriptionDirection, SubscriptionResponse,
+};
+use crate::middlewares::auth::JWTClaim;
+use crate::pagination::{next_cursor, PageQuery};
+use crate::util::{ApiError, AuthError};
+use crate::validation::{ValidJson, ValidQuery};
+
+type State = web::Data<AppState>;
+
+/// Create a plan that subscribers pay into one of the user's accounts
+/// Endpoint: POST /api/subscription/plan/create
+/// Request Body: {
+///     "merchant_account_id": integer,
+///     "name": string,
+///     "amount": float (charged every billing period),
+///     "interval": "day" | "week" | "month" | "year",
+///     "interval_count": integer (optional, default 1, intervals per billing period),
+///     "trial_days": integer (optional, default 0)
+/// }
+/// Response Body: {
+///     "id": integer,
+///     "merchant_account_id": integer,
+///     "name": string,
+///     "amount": float,
+///     "interval": "day" | "week" | "month" | "year",
+///     "interval_count": integer,
+///     "trial_days": integer,
+///     "active": boolean,
+///     "created_by": integer,
+///     "created_at": string (RFC 3339),
+///     "updated_at": string (RFC 3339)
+/// }
+/// Requires authentication.
+/// Requires the owner or spender role on the merchant account
+#[post("/plan/create")]
+async fn create_plan(
+    state: State,
+    claim: JWTClaim,
+    request: ValidJson<CreatePlanRequest>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+
+    let merchant_account = authorize_account(
+        db,
+        request.merchant_account_id,
+        &claim,
+        AccountAccess::Spend,
+    )
+    .await?;
+
+    let plan = db
+        .subscription
+        .create_plan(merchant_account.id, claim.id(), request.input())
+        .await?;
+
+    Ok(web::Json(PlanResponse::from(plan)))
+}
+
+/// List the plans of the user's accounts
+/// Endpoint: GET /api/subscription/plan/list
+/// Query Parameters (all optional):
+///     merchant_account_id: integer - only plans of this account
+///     limit: integer, cursor: string - keyset pagination
+/// Response Body: {
+///     "plans": [ plan as returned by POST /api/subscription/plan/create ],
+///     "next_cursor": string | null
+/// }
+/// Requires authentication
+#[get("/plan/list")]
+async fn list_plans(
+    state: State,
+    claim: JWTClaim,
+    query: ValidQuery<ListPlansQuery>,
+    page: ValidQuery<PageQuery>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+    let page_request = page.page_request()?;
+
+    let merchant_account_ids = match query.merchant_account_id {
+        Some(account_id) => {
+            let account = authorize_account(db, account_id, &claim, AccountAccess::View).await?;
+            vec![account.id]
+        }
+        None => scope_account_ids(db, &claim).await?,
+    };
+
+    let plans = db
+        .subscription
+        .list_plans(&merchant_account_ids, page_request)
+        .await?;
+    let next_cursor = next_cursor(&plans);
+
+    Ok(web::Json(ListPlansResponse {
+        plans: plans.items.into_iter().map(PlanResponse::from).collect(),
+        next_cursor,
+    }))
+}
+
+/// Get a plan, e.g. to show it to a prospective subscriber
+/// Endpoint: GET /api/subscription/plan/{plan_id}
+/// Path Parameters: plan_id (integer)
+/// Response Body: plan as returned by POST /api/subscription/plan/create
+/// Requires authentication
+#[get("/plan/{plan_id}")]
+async fn get_plan(
+    state: State,
+    _claim: JWTClaim,
+    path: web::Path<i32>,
+) -> Result<impl Responder, ApiError> {
+    let plan = state
+        .db()
+        .subscription
+        .find_plan(path.into_inner())
+        .await?
+        .ok_or(ApiError::NotFound("Plan"))?;
+
+    Ok(web::Json(PlanResponse::from(plan)))
+}
+
+/// Close a plan to new subscribers; existing subscriptions keep renewing
+/// Endpoint: DELETE /api/subscription/plan/{plan_id}
+/// Path Parameters: plan_id (integer)
+/// Response Body: plan as returned by POST /api/subscription/plan/create
+/// Requires authentication.
+/// Requires the owner or spender role on the merchant account
+#[delete("/plan/{plan_id}")]
+async fn deactivate_plan(
+    state: State,
+    claim: JWTClaim,
+    path: web::Path<i32>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+
+    let plan = db
+        .subscription
+        .find_plan(path.into_inner())
+        .await?
+        .ok_or(ApiError::NotFound("Plan"))?;
+    authorize_account(db, plan.merchant_account_id, &claim, AccountAccess::Spend).await?;
+
+    let plan = db.subscription.deactivate_plan(plan.id).await?;
+
+    Ok(web::Json(PlanResponse::from(plan)))
+}
+
+/// Subscribe to a plan, paying from one of the user's accounts
+/// Endpoint: POST /api/subscription/create
+/// Request Body: {
+///     "plan_id": integer,
+///     "account_id": integer
+/// }
+/// Response Body: {
+///     "id": integer,
+///     "plan_id": integer,
+///     "subscriber_user_id": integer,
+///     "payer_account_id": integer,
+///     "status": "trialing" | "active" | "past_due" | "canceled",
+///     "current_period_start": string (RFC 3339),
+///     "current_period_end": string (RFC 3339),
+///     "trial_end": string (RFC 3339) | null,
+///     "next_charge_at": string (RFC 3339) | null,
+///     "failed_attempts": integer,
+///     "credit": float,
+///     "cancel_at_period_end": boolean,
+///     "canceled_at": string (RFC 3339) | null,
+///     "created_at": string (RFC 3339),
+///     "updated_at": string (RFC 3339),
+///     "plan": plan as returned by POST /api/subscription/plan/create,
+///     "charges": [
+///         {
+///             "id": integer,
+///             "plan_id": integer,
+///             "kind": "initial" | "renewal" | "proration",
+///             "status": "succeeded" | "failed",
+///             "amount": float,
+///             "transaction_id": integer | null,
+///             "failure_reason": string | null,
+///             "created_at": string (RFC 3339)
+///         }
+///     ]
+/// }
+/// Requires authentication.
+/// Requires the owner or spender role on the paying account. Plans without a trial are
+/// charged right away, and nothing is created if the balance does not cover the amount.
+/// Returns 409 if the plan is closed, the user is already subscribed, or the account
+/// needs approval for this amount
+#[post("/create")]
+async fn subscribe(
+    state: State,
+    claim: JWTClaim,
+    request: ValidJson<SubscribeRequest>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+
+    let payer_account =
+        authorize_account(db, request.account_id, &claim, AccountAccess::Spend).await?;
+    let plan = db
+        .subscription
+        .find_plan(request.plan_id)
+        .await?
+        .ok_or(ApiError::NotFound("Plan"))?;
+    check_approval_threshold(db, payer_account.id, &plan).await?;
+
+    let subscription = db
+        .subscription
+        .subscribe(plan.id, claim.id(), payer_account.id)
+        .await?;
+
+    subscription_details(db, subscription).await.map(web::Json)
+}
+
+/// List subscriptions the user pays for or that pay into the user's accounts
+/// Endpoint: GET /api/subscription/list
+/// Query Parameters (all optional):
+///     direction: "subscribed" | "merchant" - both if omitted
+///     status: "trialing" | "active" | "past_due" | "canceled"
+///     limit: integer, cursor: string - keyset pagination
+/// Response Body: {
+///     "subscriptions": [ subscription as returned by POST /api/subscription/create,
+///                        without plan and charges ],
+///     "next_cursor": string | null
+/// }
+/// Requires authentication
+#[get("/list")]
+async fn list_subscriptions(
+    state: State,
+    claim: JWTClaim,
+    query: ValidQuery<ListSubscriptionsQuery>,
+    page: ValidQuery<PageQuery>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+    let page_request = page.page_request()?;
+
+    let subscribed = !matches!(query.direction, Some(SubscriptionDirection::Merchant));
+    let merchant_account_ids = match query.direction {
+        Some(SubscriptionDirection::Subscribed) => vec![],
+        _ => scope_account_ids(db, &claim).await?,
+    };
+
+    if merchant_account_ids.is_empty() && !subscribed {
+        return Ok(web::Json(ListSubscriptionsResponse {
+            subscriptions: vec![],
+            next_cursor: None,
+        }));
+    }
+
+    let filter = SubscriptionFilter {
+        subscriber_user_id: subscribed.then(|| claim.id()),
+        merchant_account_ids,
+        status: query.status,
+    };
+    let subscriptions = db
+        .subscription
+        .list_subscriptions(&filter, page_request)
+        .await?;
+    let next_cursor = next_cursor(&subscriptions);
+
+    Ok(web::Json(ListSubscriptionsResponse {
+        subscriptions: subscriptions
+            .items
+            .into_iter()
+            .map(SubscriptionResponse::from)
+            .collect(),
+        next_cursor,
+    }))
+}
+
+/// Get a subscription with its plan and charges
+/// Endpoint: GET /api/subscription/{subscription_id}
+/// Path Parameters: subscription_id (integer)
+/// Response Body: subscription as returned by POST /api/subscription/create
+/// Requires authentication. The user must be the subscriber or able to see the merchant
+/// account
+#[get("/{subscription_id}")]
+async fn get_subscription(
+    state: State,
+    claim: JWTClaim,
+    path: web::Path<i32>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+
+    let subscription = find_subscription(db, path.into_inner()).await?;
+    if subscription.subscriber_user_id != claim.id() {
+        let plan = db
+            .subscription
+            .find_plan(subscription.plan_id)
+            .await?
+            .ok_or(ApiError::NotFound("Plan"))?;
+        if !has_access(db, plan.merchant_account_id, &claim, AccountAccess::View).await? {
+            return Err(ApiError::AuthError(AuthError::Unauthorized));
+        }
+    }
+
+    subscription_details(db, subscription).await.map(web::Json)
+}
+
+/// Move a subscription to another plan of the same merchant
+/// Endpoint: PUT /api/subscription/{subscription_id}/plan
+/// Path Parameters: subscription_id (integer)
+/// Request Body: {
+///     "plan_id": integer
+/// }
+/// Response Body: subscription as returned by POST /api/subscription/create
+/// Requires authentication. Only the subscriber can change the plan.
+/// The unused part of the current period is credited; a difference owed is charged
+/// right away and anything left over is taken off the next renewal. Returns 409 unless
+/// the subscription is active or trialing, or if the new plan's amount needs approval
+/// on the paying account
+#[put("/{subscription_id}/plan")]
+async fn change_plan(
+    state: State,
+    claim: JWTClaim,
+    path: web::Path<i32>,
+    request: ValidJson<ChangePlanRequest>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+
+    let subscription = find_subscription(db, path.into_inner()).await?;
+    if subscription.subscriber_user_id != claim.id() {
+        return Err(ApiError::AuthError(AuthError::Unauthorized));
+    }
+    let plan = db
+        .subscription
+        .find_plan(request.plan_id)
+        .await?
+        .ok_or(ApiError::NotFound("Plan"))?;
+    check_approval_threshold(db, subscription.payer_account_id, &plan).await?;
+
+    let subscription = db
+        .subscription
+        .change_plan(subscription.id, request.plan_id)
+        .await?;
+
+    subscription_details(db, subscription).await.map(web::Json)
+}
+
+/// Cancel a subscription, now or at the end of the paid period
+/// Endpoint: POST /api/subscription/{subscription_id}/cancel
+/// Path Parameters: subscription_id (integer)
+/// Request Body: {
+///     "at_period_end": boolean (optional, default false)
+/// }
+/// Response Body: subscription as returned by POST /api/subscription/create
+/// Requires authentication. The subscriber, or a user with the owner or spender role on
+/// the merchant account, can cancel. Returns 409 if the subscription is already canceled
+#[post("/{subscription_id}/cancel")]
+async fn cancel_subscription(
+    state: State,
+    claim: JWTClaim,
+    path: web::Path<i32>,
+    request: ValidJson<CancelSubscriptionRequest>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+
+    let subscription = find_subscription(db, path.into_inner()).await?;
+    if subscription.subscriber_user_id != claim.id() {
+        let plan = db
+            .subscription
+            .find_plan(subscription.plan_id)
+            .await?
+            .ok_or(ApiError::NotFound("Plan"))?;
+        authorize_account(db, plan.merchant_account_id, &claim, AccountAccess::Spend).await?;
+    }
+
+    let subscription = db
+        .subscription
+        .cancel(subscription.id, request.at_period_end)
+        .await?;
+
+    subscription_details(db, subscription).await.map(web::Json)
+}
+
+async fn find_subscription(db: &DbClient, id: i32) -> Result<subscriptions::Model, ApiError> {
+    db.subscription
+        .find_subscription(id)
+        .await?
+        .ok_or(ApiError::NotFound("Subscription"))
+}
+
+async fn subscription_details(
+    db: &DbClient,
+    subscription: subscriptions::Model,
+) -> Result<SubscriptionDetailsResponse, ApiError> {
+    let plan = db
+        .subscription
+        .find_plan(subscription.plan_id)
+        .await?
+        .ok_or(ApiError::NotFound("Plan"))?;
+    let charges = db.subscription.list_charges(subscription.id).await?;
+    Ok(SubscriptionDetailsResponse::new(
+        subscription,
+        plan,
+        charges,
+    ))
+}
+
+/// Subscribing to a plan, or moving to one, agrees to its recurring charges, so an
+/// amount that would need approval on the paying account is refused then. Renewals and
+/// their retries charge the agreed amount without checking the threshold again, also
+/// if it was lowered since.
+async fn check_approval_threshold(
+    db: &DbClient,
+    payer_account_id: i32,
+    plan: &subscription_plans::Model,
+) -> Result<(), ApiError> {
+    if db
+        .approval
+        .requires_approval(payer_account_id, plan.amount.to_f64().unwrap())
+        .await?
+    {
+        return Err(ApiError::Conflict(
+            "Payments above the approval threshold cannot be made from this account".to_string(),
+        ));
+    }
+    Ok(())
+}
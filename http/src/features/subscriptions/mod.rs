@@ -0,0 +1,3 @@
+pub mod controllers;
+pub mod scheduler;
+pub mod subscription_types;
@@ -0,0 +1,44 @@
+use std::time::Duration;
+
+use actix_web::web;
+use tracing::{info, warn};
+
+use crate::app_state::AppState;
+use crate::constants::{SUBSCRIPTION_BATCH_SIZE, SUBSCRIPTION_POLL_INTERVAL_SECS};
+
+/// Renews due subscriptions in the background for as long as the server runs.
+/// Each subscription is renewed in its own database transaction under a row lock, so
+/// several server instances can poll at the same time without charging twice.
+pub async fn run(state: web::Data<AppState>) {
+    let mut interval =
+        actix_web::rt::time::interval(Duration::from_secs(SUBSCRIPTION_POLL_INTERVAL_SECS));
+    loop {
+        interval.tick().await;
+        renew_due(&state).await;
+    }
+}
+
+async fn renew_due(state: &AppState) {
+    let db = state.db();
+    let due = match db
+        .subscription
+        .due_subscriptions(SUBSCRIPTION_BATCH_SIZE)
+        .await
+    {
+        Ok(due) => due,
+        Err(err) => {
+            warn!("Could not look up due subscriptions: {}", err);
+            return;
+        }
+    };
+
+    for id in due {
+        match db.subscription.renew(id).await {
+            Ok(subscription) => info!(
+                "Renewed subscription {}: {:?}",
+                subscription.id, subscription.status
+            ),
+            Err(err) => warn!("Could not renew subscription {}: {}", id, err),
+        }
+    }
+}
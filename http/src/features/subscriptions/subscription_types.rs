@@ -0,0 +1,278 @@
+use chrono::{DateTime, Utc};
+use db::subscriptions::{
+    PlanInput, PlanInterval, SubscriptionChargeKind, SubscriptionChargeStatus, SubscriptionStatus,
+};
+use entity::{subscription_charges, subscription_plans, subscriptions};
+use num_traits::cast::ToPrimitive;
+use serde::{Deserialize, Serialize};
+
+use crate::validation::{FieldError, Validate, Validator};
+
+/// Request body for creating a subscription plan
+#[derive(Debug, Deserialize)]
+pub struct CreatePlanRequest {
+    pub merchant_account_id: i32,
+    pub name: String,
+    pub amount: f64,
+    pub interval: PlanInterval,
+    #[serde(default = "default_interval_count")]
+    pub interval_count: i32,
+    #[serde(default)]
+    pub trial_days: i32,
+}
+
+fn default_interval_count() -> i32 {
+    1
+}
+
+impl CreatePlanRequest {
+    pub fn input(&self) -> PlanInput {
+        PlanInput {
+            name: self.name.trim().to_string(),
+            amount: self.amount,
+            interval: self.interval,
+            interval_count: self.interval_count,
+            trial_days: self.trial_days,
+        }
+    }
+}
+
+impl Validate for CreatePlanRequest {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::new();
+        v.not_blank(&self.name, "name");
+        v.length(self.name.trim(), "name", 1, 100);
+        v.positive_amount(self.amount, "amount");
+        v.check(
+            (1..=365).contains(&self.interval_count),
+            "interval_count",
+            "range",
+            "Must be between 1 and 365",
+        );
+        v.check(
+            (0..=365).contains(&self.trial_days),
+            "trial_days",
+            "range",
+            "Must be between 0 and 365",
+        );
+        v.finish()
+    }
+}
+
+/// Query parameters for listing plans
+#[derive(Debug, Deserialize)]
+pub struct ListPlansQuery {
+    pub merchant_account_id: Option<i32>,
+}
+
+impl Validate for ListPlansQuery {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        Ok(())
+    }
+}
+
+/// Response for a subscription plan
+#[derive(Debug, Serialize)]
+pub struct PlanResponse {
+    pub id: i32,
+    pub merchant_account_id: i32,
+    pub name: String,
+    pub amount: f64,
+    pub interval: PlanInterval,
+    pub interval_count: i32,
+    pub trial_days: i32,
+    pub active: bool,
+    pub created_by: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<subscription_plans::Model> for PlanResponse {
+    fn from(plan: subscription_plans::Model) -> Self {
+        Self {
+            id: plan.id,
+            merchant_account_id: plan.merchant_account_id,
+            name: plan.name,
+            amount: plan.amount.to_f64().unwrap(),
+            interval: plan.interval,
+            interval_count: plan.interval_count,
+            trial_days: plan.trial_days,
+            active: plan.active,
+            created_by: plan.created_by,
+            created_at: plan.created_at.with_timezone(&Utc),
+            updated_at: plan.updated_at.with_timezone(&Utc),
+        }
+    }
+}
+
+/// Response for a page of plans
+#[derive(Debug, Serialize)]
+pub struct ListPlansResponse {
+    pub plans: Vec<PlanResponse>,
+    pub next_cursor: Option<String>,
+}
+
+/// Request body for subscribing to a plan
+#[derive(Debug, Deserialize)]
+pub struct SubscribeRequest {
+    pub plan_id: i32,
+    /// Account the subscription is paid from
+    pub account_id: i32,
+}
+
+impl Validate for SubscribeRequest {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        Ok(())
+    }
+}
+
+/// Request body for moving a subscription to another plan
+#[derive(Debug, Deserialize)]
+pub struct ChangePlanRequest {
+    pub plan_id: i32,
+}
+
+impl Validate for ChangePlanRequest {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        Ok(())
+    }
+}
+
+/// Request body for canceling a subscription
+#[derive(Debug, Deserialize)]
+pub struct CancelSubscriptionRequest {
+    /// Keep the subscription until the paid period ends instead of canceling now
+    #[serde(default)]
+    pub at_period_end: bool,
+}
+
+impl Validate for CancelSubscriptionRequest {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        Ok(())
+    }
+}
+
+/// Which side of a subscription to list
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubscriptionDirection {
+    /// Subscriptions the user pays for
+    Subscribed,
+    /// Subscriptions to plans of the user's accounts
+    Merchant,
+}
+
+/// Query parameters for listing subscriptions
+#[derive(Debug, Deserialize)]
+pub struct ListSubscriptionsQuery {
+    pub direction: Option<SubscriptionDirection>,
+    pub status: Option<SubscriptionStatus>,
+}
+
+impl Validate for ListSubscriptionsQuery {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        Ok(())
+    }
+}
+
+/// Response for a subscription
+#[derive(Debug, Serialize)]
+pub struct SubscriptionResponse {
+    pub id: i32,
+    pub plan_id: i32,
+    pub subscriber_user_id: i32,
+    pub payer_account_id: i32,
+    pub status: SubscriptionStatus,
+    pub current_period_start: DateTime<Utc>,
+    pub current_period_end: DateTime<Utc>,
+    pub trial_end: Option<DateTime<Utc>>,
+    pub next_charge_at: Option<DateTime<Utc>>,
+    /// Failed renewal charges since the last successful one
+    pub failed_attempts: i32,
+    /// Left over from plan changes and taken off the next renewal
+    pub credit: f64,
+    pub cancel_at_period_end: bool,
+    pub canceled_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<subscriptions::Model> for SubscriptionResponse {
+    fn from(subscription: subscriptions::Model) -> Self {
+        Self {
+            id: subscription.id,
+            plan_id: subscription.plan_id,
+            subscriber_user_id: subscription.subscriber_user_id,
+            payer_account_id: subscription.payer_account_id,
+            status: subscription.status,
+            current_period_start: subscription.current_period_start.with_timezone(&Utc),
+            current_period_end: subscription.current_period_end.with_timezone(&Utc),
+            trial_end: subscription.trial_end.map(|at| at.with_timezone(&Utc)),
+            next_charge_at: subscription.next_charge_at.map(|at| at.with_timezone(&Utc)),
+            failed_attempts: subscription.failed_attempts,
+            credit: subscription.credit.to_f64().unwrap(),
+            cancel_at_period_end: subscription.cancel_at_period_end,
+            canceled_at: subscription.canceled_at.map(|at| at.with_timezone(&Utc)),
+            created_at: subscription.created_at.with_timezone(&Utc),
+            updated_at: subscription.updated_at.with_timezone(&Utc),
+        }
+    }
+}
+
+/// Response for a charge of a subscription
+#[derive(Debug, Serialize)]
+pub struct ChargeResponse {
+    pub id: i32,
+    pub plan_id: i32,
+    pub kind: SubscriptionChargeKind,
+    pub status: SubscriptionChargeStatus,
+    pub amount: f64,
+    pub transaction_id: Option<i32>,
+    pub failure_reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<subscription_charges::Model> for ChargeResponse {
+    fn from(charge: subscription_charges::Model) -> Self {
+        Self {
+            id: charge.id,
+            plan_id: charge.plan_id,
+            kind: charge.kind,
+            status: charge.status,
+            amount: charge.amount.to_f64().unwrap(),
+            transaction_id: charge.transaction_id,
+            failure_reason: charge.failure_reason,
+            created_at: charge.created_at.with_timezone(&Utc),
+        }
+    }
+}
+
+/// Response for a subscription with its plan and charges
+#[derive(Debug, Serialize)]
+pub struct SubscriptionDetailsResponse {
+    #[serde(flatten)]
+    pub subscription: SubscriptionResponse,
+    pub plan: PlanResponse,
+    pub charges: Vec<ChargeResponse>,
+}
+
+impl SubscriptionDetailsResponse {
+    pub fn new(
+        subscription: subscriptions::Model,
+        plan: subscription_plans::Model,
+        charges: Vec<subscription_charges::Model>,
+    ) -> Self {
+        Self {
+            subscription: SubscriptionResponse::from(subscription),
+            plan: PlanResponse::from(plan),
+            charges: charges.into_iter().map(ChargeResponse::from).collect(),
+        }
+    }
+}
+
+/// Response for a page of subscriptions
+#[derive(Debug, Serialize)]
+pub struct ListSubscriptionsResponse {
+    pub subscriptions: Vec<SubscriptionResponse>,
+    pub next_cursor: Option<String>,
+}
@@ -1,11 +1,19 @@
 use crate::app_state::AppState;
+use crate::features::accounts::access::{
+    account_scope, authorize_account, has_access, AccountAccess,
+};
+use crate::features::approvals::approval_types::ApprovalResponse;
 use crate::features::transactions::transaction_types::{
-    CreateTransactionRequest, ListTransactionsResponse, TransactionResponse,
+    CreateTransactionRequest, ListTransactionsQuery, ListTransactionsResponse, TransactionResponse,
 };
+use crate::features::user::recipients::resolve_recipient;
 use crate::middlewares::auth::JWTClaim;
+use crate::pagination::{next_cursor, PageQuery};
 use crate::util::ApiError;
 use crate::util::AuthError;
-use actix_web::{get, post, web, Responder};
+use crate::validation::{FieldError, ValidJson, ValidQuery};
+use actix_web::{get, post, web, HttpResponse, Responder};
+use chrono::Utc;
 use num_traits::cast::ToPrimitive;
 
 type State = web::Data<AppState>;
@@ -14,7 +22,8 @@ type State = web::Data<AppState>;
 /// Endpoint: POST /api/transaction/create
 /// Request Body: {
 ///     "from_account_id": integer,
-///     "to_account_id": integer,
+///     "to_account_id": integer, or
+///     "to": string (recipient username or account alias),
 ///     "amount": float
 /// }
 /// Response Body: {
@@ -22,19 +31,22 @@ type State = web::Data<AppState>;
 ///     "from_account_id": integer,
 ///     "to_account_id": integer,
 ///     "amount": float,
-///     "created_at": string
+///     "status": string,
+///     "created_at": string (RFC 3339)
 /// }
 /// Requires authentication.
-/// Returns error if user doesn't own an account
+/// Requires the owner or spender role on the sending account.
+/// Transfers above the approval threshold of a shared or organization account are not
+/// executed; they return 202 Accepted with the pending approval as returned by
+/// GET /api/approval/list and wait for a second user
 #[post("/create")]
 async fn create_transaction(
     state: State,
     claim: JWTClaim,
-    request: web::Json<CreateTransactionRequest>,
+    request: ValidJson<CreateTransactionRequest>,
 ) -> Result<impl Responder, ApiError> {
     let db = state.db();
     let user_id = claim.id();
-    let amount = request.amount;
 
     let _user = db
         .user
@@ -42,57 +54,81 @@ async fn create_transaction(
         .await?
         .ok_or(ApiError::AuthError(AuthError::UserNotFound))?;
 
-    let from_account = db
-        .account
-        .find_account(request.from_account_id)
-        .await?
-        .ok_or(ApiError::AuthError(AuthError::AccountNotFound))?;
+    let from_account =
+        authorize_account(db, request.from_account_id, &claim, AccountAccess::Spend).await?;
+
+    let to_account_id = match (request.to_account_id, request.to.as_deref()) {
+        (Some(to_account_id), None) => to_account_id,
+        (None, Some(handle)) => {
+            resolve_recipient(db, handle)
+                .await?
+                .ok_or(ApiError::AuthError(AuthError::RecipientNotFound))?
+                .account_id
+        }
+        _ => {
+            return Err(ApiError::Validation(vec![FieldError::new(
+                "to",
+                "one_of",
+                "Specify the recipient with exactly one of to_account_id or to",
+            )]))
+        }
+    };
+
+    if to_account_id == request.from_account_id {
+        return Err(ApiError::Validation(vec![FieldError::new(
+            "to",
+            "same_account",
+            "Cannot send money to the account it is sent from",
+        )]));
+    }
 
     let to_account = db
         .account
-        .find_account(request.to_account_id)
+        .find_account(to_account_id)
         .await?
         .ok_or(ApiError::AuthError(AuthError::AccountNotFound))?;
 
-    if from_account.user_id != user_id {
-        return Err(ApiError::AuthError(AuthError::Unauthorized));
-    }
-
-    if from_account.balance.to_f64().unwrap() < request.amount {
-        return Err(ApiError::NotEnoughBalance);
+    if db
+        .approval
+        .requires_approval(from_account.id, request.amount)
+        .await?
+    {
+        let approval = db
+            .approval
+            .request(from_account.id, to_account.id, request.amount, user_id)
+            .await?;
+        return Ok(HttpResponse::Accepted().json(ApprovalResponse::from(approval)));
     }
 
-    let transaction_id = db
-        .transaction
-        .create_transaction(
-            request.from_account_id,
-            request.to_account_id,
-            request.amount,
-        )
-        .await?;
-
-    db.account.deduct_money(from_account.id, amount).await?;
-    db.account.add_money(to_account.id, amount).await?;
-
+    // Balance is checked against the available (unheld) balance while both accounts are locked
     let transaction = db
         .transaction
-        .find_transaction(transaction_id)
-        .await?
-        .ok_or(ApiError::AuthError(AuthError::TransactionNotFound))?;
+        .transfer(from_account.id, to_account.id, request.amount)
+        .await?;
 
     let response = TransactionResponse::new(
         transaction.id,
         transaction.from_account_id,
         transaction.to_account_id,
         transaction.amount.to_f64().unwrap(),
-        transaction.created_at,
+        transaction.status,
+        transaction.created_at.with_timezone(&Utc),
     );
 
-    Ok(web::Json(response))
+    Ok(HttpResponse::Ok().json(response))
 }
 
-/// List all transactions involving user's accounts
+/// List transactions involving user's accounts
 /// Endpoint: GET /api/transaction/user/tx
+/// Query Parameters (all optional):
+///     account_id: integer - only this account (the user must be a member)
+///     direction: "in" | "out" - money received or sent by the user's accounts
+///     since, until: RFC 3339 timestamps - created_at range, `until` exclusive
+///     min_amount, max_amount: float - inclusive amount range
+///     counterparty_account_id: integer - the account on the other side
+///     status: "pending" | "completed" | "failed" | "reversed"
+///     sort: "newest" (default) | "oldest" | "amount_desc" | "amount_asc"
+///     limit: integer, cursor: string - keyset pagination
 /// Response Body: {
 ///     "transactions": [
 ///         {
@@ -100,15 +136,23 @@ async fn create_transaction(
 ///             "from_account_id": integer,
 ///             "to_account_id": integer,
 ///             "amount": float,
-///             "created_at": string
+///             "status": string,
+///             "created_at": string (RFC 3339)
 ///         }
-///     ]
+///     ],
+///     "next_cursor": string | null
 /// }
 /// Requires authentication. Returns empty list if user has no accounts
 #[get("/user/tx")]
-async fn list_user_transactions(state: State, claim: JWTClaim) -> Result<impl Responder, ApiError> {
+async fn list_user_transactions(
+    state: State,
+    claim: JWTClaim,
+    query: ValidQuery<ListTransactionsQuery>,
+    page: ValidQuery<PageQuery>,
+) -> Result<impl Responder, ApiError> {
     let db = state.db();
     let user_id = claim.id();
+    let page_request = page.page_request()?;
 
     let _user = db
         .user
@@ -116,24 +160,35 @@ async fn list_user_transactions(state: State, claim: JWTClaim) -> Result<impl Re
         .await?
         .ok_or(ApiError::AuthError(AuthError::UserNotFound))?;
 
-    let user_accounts = db.account.list_user_accounts(user_id).await?;
-    let account_ids: Vec<i32> = user_accounts
-        .into_iter()
-        .map(|account| account.id)
-        .collect();
+    let account_ids: Vec<i32> = match query.account_id {
+        Some(account_id) => {
+            let account = authorize_account(db, account_id, &claim, AccountAccess::View).await?;
+            vec![account.id]
+        }
+        None => db
+            .account
+            .list_accounts(account_scope(db, &claim).await?)
+            .await?
+            .into_iter()
+            .map(|account| account.id)
+            .collect(),
+    };
 
     if account_ids.is_empty() {
         return Ok(web::Json(ListTransactionsResponse {
             transactions: vec![],
+            next_cursor: None,
         }));
     }
 
     let transactions = db
         .transaction
-        .list_transactions_for_accounts(&account_ids)
+        .list_transactions_for_accounts(&account_ids, &query.filter(), page_request)
         .await?;
+    let next_cursor = next_cursor(&transactions);
 
     let transaction_responses: Vec<TransactionResponse> = transactions
+        .items
         .into_iter()
         .map(|t| {
             TransactionResponse::new(
@@ -141,13 +196,15 @@ async fn list_user_transactions(state: State, claim: JWTClaim) -> Result<impl Re
                 t.from_account_id,
                 t.to_account_id,
                 t.amount.to_f64().unwrap(),
-                t.created_at,
+                t.status,
+                t.created_at.with_timezone(&Utc),
             )
         })
         .collect();
 
     Ok(web::Json(ListTransactionsResponse {
         transactions: transaction_responses,
+        next_cursor,
     }))
 }
 
@@ -159,9 +216,10 @@ async fn list_user_transactions(state: State, claim: JWTClaim) -> Result<impl Re
 ///     "from_account_id": integer,
 ///     "to_account_id": integer,
 ///     "amount": float,
-///     "created_at": string
+///     "status": string,
+///     "created_at": string (RFC 3339)
 /// }
-/// Requires authentication. Returns error if user doesn't own either account involved
+/// Requires authentication. Returns error if the user is not a member of either account involved
 #[get("/{transaction_id}")]
 async fn get_transaction(
     state: State,
@@ -184,15 +242,9 @@ async fn get_transaction(
         .await?
         .ok_or(ApiError::AuthError(AuthError::TransactionNotFound))?;
 
-    let from_account = db.account.find_account(transaction.from_account_id).await?;
-    let to_account = db.account.find_account(transaction.to_account_id).await?;
-
-    let is_authorized = from_account
-        .as_ref()
-        .map_or(false, |acc| acc.user_id == user_id)
-        || to_account
-            .as_ref()
-            .map_or(false, |acc| acc.user_id == user_id);
+    let is_authorized = has_access(db, transaction.from_account_id, &claim, AccountAccess::View)
+        .await?
+        || has_access(db, transaction.to_account_id, &claim, AccountAccess::View).await?;
 
     if !is_authorized {
         return Err(ApiError::AuthError(AuthError::Unauthorized));
@@ -203,7 +255,8 @@ async fn get_transaction(
         transaction.from_account_id,
         transaction.to_account_id,
         transaction.amount.to_f64().unwrap(),
-        transaction.created_at,
+        transaction.status,
+        transaction.created_at.with_timezone(&Utc),
     );
 
     Ok(web::Json(response))
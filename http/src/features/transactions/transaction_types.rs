@@ -1,13 +1,46 @@
-use chrono::NaiveDate;
+use chrono::{DateTime, Utc};
+use db::transactions::{
+    TransactionDirection, TransactionFilter, TransactionSort, TransactionStatus,
+};
 use serde::{Deserialize, Serialize};
 
+use crate::validation::{FieldError, Validate, Validator};
+
+/// Request body for creating a transaction.
+/// The recipient is given either as `to_account_id` or as `to`, a username or account alias.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateTransactionRequest {
     pub from_account_id: i32,
-    pub to_account_id: i32,
+    #[serde(default)]
+    pub to_account_id: Option<i32>,
+    #[serde(default)]
+    pub to: Option<String>,
     pub amount: f64,
 }
 
+impl Validate for CreateTransactionRequest {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::new();
+        v.positive_amount(self.amount, "amount");
+        v.check(
+            self.to_account_id.is_some() != self.to.is_some(),
+            "to",
+            "one_of",
+            "Specify the recipient with exactly one of to_account_id or to",
+        );
+        if let Some(to) = &self.to {
+            v.not_blank(to, "to");
+        }
+        v.check(
+            self.to_account_id != Some(self.from_account_id),
+            "to_account_id",
+            "same_account",
+            "Cannot send money to the account it is sent from",
+        );
+        v.finish()
+    }
+}
+
 /// Response for a single transaction
 #[derive(Debug, Serialize)]
 pub struct TransactionResponse {
@@ -15,7 +48,8 @@ pub struct TransactionResponse {
     pub from_account_id: i32,
     pub to_account_id: i32,
     pub amount: f64,
-    pub created_at: NaiveDate,
+    pub status: TransactionStatus,
+    pub created_at: DateTime<Utc>,
 }
 
 impl TransactionResponse {
@@ -24,20 +58,137 @@ impl TransactionResponse {
         from_account_id: i32,
         to_account_id: i32,
         amount: f64,
-        created_at: NaiveDate,
+        status: TransactionStatus,
+        created_at: DateTime<Utc>,
     ) -> Self {
         Self {
             id,
             from_account_id,
             to_account_id,
             amount,
+            status,
             created_at,
         }
     }
 }
 
+/// Direction of a transaction in `ListTransactionsQuery`
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DirectionParam {
+    In,
+    Out,
+}
+
+impl From<DirectionParam> for TransactionDirection {
+    fn from(direction: DirectionParam) -> Self {
+        match direction {
+            DirectionParam::In => TransactionDirection::In,
+            DirectionParam::Out => TransactionDirection::Out,
+        }
+    }
+}
+
+/// Order of the transactions in `ListTransactionsQuery`
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortParam {
+    #[default]
+    Newest,
+    Oldest,
+    AmountDesc,
+    AmountAsc,
+}
+
+impl From<SortParam> for TransactionSort {
+    fn from(sort: SortParam) -> Self {
+        match sort {
+            SortParam::Newest => TransactionSort::Newest,
+            SortParam::Oldest => TransactionSort::Oldest,
+            SortParam::AmountDesc => TransactionSort::AmountDesc,
+            SortParam::AmountAsc => TransactionSort::AmountAsc,
+        }
+    }
+}
+
+/// Status of a transaction in `ListTransactionsQuery`
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatusParam {
+    Pending,
+    Completed,
+    Failed,
+    Reversed,
+}
+
+impl From<StatusParam> for TransactionStatus {
+    fn from(status: StatusParam) -> Self {
+        match status {
+            StatusParam::Pending => TransactionStatus::Pending,
+            StatusParam::Completed => TransactionStatus::Completed,
+            StatusParam::Failed => TransactionStatus::Failed,
+            StatusParam::Reversed => TransactionStatus::Reversed,
+        }
+    }
+}
+
+/// Query parameters for filtering and sorting the user's transactions
+#[derive(Debug, Deserialize)]
+pub struct ListTransactionsQuery {
+    pub account_id: Option<i32>,
+    pub direction: Option<DirectionParam>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub min_amount: Option<f64>,
+    pub max_amount: Option<f64>,
+    pub counterparty_account_id: Option<i32>,
+    pub status: Option<StatusParam>,
+    #[serde(default)]
+    pub sort: SortParam,
+}
+
+impl Validate for ListTransactionsQuery {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::new();
+        if let Some(min_amount) = self.min_amount {
+            v.non_negative_amount(min_amount, "min_amount");
+        }
+        if let Some(max_amount) = self.max_amount {
+            v.non_negative_amount(max_amount, "max_amount");
+        }
+        if let (Some(min_amount), Some(max_amount)) = (self.min_amount, self.max_amount) {
+            v.check(
+                min_amount <= max_amount,
+                "max_amount",
+                "range",
+                "Must not be less than min_amount",
+            );
+        }
+        if let (Some(since), Some(until)) = (self.since, self.until) {
+            v.check(since < until, "until", "range", "Must be later than since");
+        }
+        v.finish()
+    }
+}
+
+impl ListTransactionsQuery {
+    pub fn filter(&self) -> TransactionFilter {
+        TransactionFilter {
+            direction: self.direction.map(TransactionDirection::from),
+            since: self.since.map(|since| since.fixed_offset()),
+            until: self.until.map(|until| until.fixed_offset()),
+            min_amount: self.min_amount,
+            max_amount: self.max_amount,
+            counterparty_account_id: self.counterparty_account_id,
+            status: self.status.map(TransactionStatus::from),
+            sort: self.sort.into(),
+        }
+    }
+}
+
 /// Response for listing multiple transactions
 #[derive(Debug, Serialize)]
 pub struct ListTransactionsResponse {
     pub transactions: Vec<TransactionResponse>,
+    pub next_cursor: Option<String>,
 }
@@ -1,15 +1,24 @@
-use actix_web::{post, web, Responder};
+use actix_web::{get, post, put, web, HttpRequest, HttpResponse, Responder};
 
 use crate::app_state::AppState;
-use crate::middlewares::auth::{JWTClaim, KEYS};
+use crate::constants::{ACCESS_TOKEN_TTL_SECS, REFRESH_TOKEN_TTL_DAYS};
+use crate::middlewares::audit;
+use crate::middlewares::auth::JWTClaim;
 use crate::util::AuthError;
+use crate::validation::ValidJson;
 use crate::ApiError;
 
-use jsonwebtoken::encode;
-use jsonwebtoken::Header;
+use chrono::Duration;
+use db::db_client::DbClient;
+use db::refresh_tokens::Rotation;
+use db::user::{PreferencesUpdate, UserRole};
 use pwhash::bcrypt;
+use tracing::warn;
 
+use super::recipients::resolve_recipient;
 use super::user_types::{
+    ChangePasswordRequest, EmailResponse, PreferencesResponse, RecipientResponse,
+    RefreshTokenRequest, SetEmailRequest, TokenResponse, UpdatePreferencesRequest,
     UserLoginRequest, UserLoginResponse, UserRegisterRequest, UserRegisterResponse,
 };
 
@@ -19,35 +28,63 @@ type State = web::Data<AppState>;
 /// Endpoint: POST/api/user/register
 /// Request Body: {
 ///    "username": "string",
-///    "password": "string"
+///    "password": "string",
+///    "display_name": "string" (optional),
+///    "email": "string" (optional)
 ///}
 /// Response Body: {
 ///     "user_id" : integer,
 ///     "username" : "string",
-///     "token" : "string"
+///     "token" : "string",
+///     "refresh_token" : "string",
+///     "expires_in" : integer
 /// }
-/// Returns an error if registration fails
+/// Returns an error if the username or email is taken or registration fails
 #[post("/register")]
 async fn register(
     state: State,
-    request: web::Json<UserRegisterRequest>,
+    request: ValidJson<UserRegisterRequest>,
+    http_request: HttpRequest,
 ) -> Result<impl Responder, ApiError> {
     let db_client = state.db();
     let username = request.username.clone();
+
+    // Usernames and account aliases share one namespace for recipient lookups, where
+    // case is ignored.
+    if db_client.user.handle_taken(&username).await?
+        || db_client
+            .account
+            .find_account_by_alias(&username.to_lowercase())
+            .await?
+            .is_some()
+    {
+        return Err(ApiError::UsernameTaken);
+    }
+
+    let email = request.email.as_deref().map(normalize_email);
+    if let Some(email) = &email {
+        if db_client.user.find_user_by_email(email).await?.is_some() {
+            return Err(ApiError::EmailTaken);
+        }
+    }
+
     let password = request.password.clone();
-    let password = bcrypt::hash(password).map_err(|e| AuthError::BcryptError(e))?;
+    let password = bcrypt::hash(password).map_err(AuthError::BcryptError)?;
     let id = db_client
         .user
-        .create_user(username.clone(), password)
+        .create_user(
+            username.clone(),
+            password,
+            request.display_name.clone(),
+            email,
+        )
         .await
-        .map_err(|e| ApiError::DBError(e))?;
+        .map_err(ApiError::DBError)?;
+    audit::record_actor(&http_request, id);
 
-    let claim = JWTClaim::new(id);
-    let key = KEYS.encoding_key();
-    let token =
-        encode(&Header::default(), &claim, key).map_err(|err| AuthError::TokenError(err))?;
+    let tokens = issue_tokens(db_client, id, UserRole::User, None).await?;
 
-    let response = UserRegisterResponse::new(id, username, token);
+    let response = UserRegisterResponse::new(id, username, tokens);
 
     Ok(web::Json(response))
 }
@@ -61,12 +98,16 @@ async fn register(
 /// Response Body: {
 ///     "user_id": integer,
 ///     "username": "string",
-///     "token": "string"
+///     "token": "string",
+///     "refresh_token": "string",
+///     "expires_in": integer - seconds until the token expires
 /// }
+/// Every login is recorded, and the user is emailed about it if they opted in
 #[post("/login")]
 async fn login(
     state: State,
-    request: web::Json<UserLoginRequest>,
+    request: ValidJson<UserLoginRequest>,
+    http_request: HttpRequest,
 ) -> Result<impl Responder, ApiError> {
     let db = state.db();
     let username = request.username.clone();
@@ -76,12 +117,22 @@ async fn login(
         let db_password = user.password;
         let user_id = user.id;
         if bcrypt::verify(password, db_password.as_str()) {
-            let claim = JWTClaim::new(user_id);
-            let key = KEYS.encoding_key();
-            let token = encode(&Header::default(), &claim, key)
-                .map_err(|err| AuthError::TokenError(err))?;
+            // Only a correct password names the user; failed attempts stay anonymous
+            audit::record_actor(&http_request, user_id);
+            let tokens = issue_tokens(db, user_id, user.role, None).await?;
+
+            let ip = http_request
+                .connection_info()
+                .realip_remote_addr()
+                .map(str::to_string);
+            let user_agent = http_request
+                .headers()
+                .get("User-Agent")
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string);
+            db.user.record_login(user_id, ip, user_agent).await?;
 
-            let response = UserLoginResponse::new(user_id, username, token);
+            let response = UserLoginResponse::new(user_id, username, tokens);
             Ok(web::Json(response))
         } else {
             Err(ApiError::AuthError(AuthError::Unauthorized))
@@ -90,3 +141,266 @@ async fn login(
         Err(ApiError::AuthError(AuthError::UserNotFound))
     }
 }
+
+/// Look up who a payment to a username or account alias would go to
+/// Endpoint: GET /api/user/recipient/{handle}
+/// Path Parameters: handle (username, optionally prefixed with "@", or account alias)
+/// Response Body: {
+///     "username": "string" (omitted for organization accounts),
+///     "display_name": "string"
+/// }
+/// Requires authentication. Never exposes the recipient's account ids. Organization
+/// accounts show the organization's name and no username.
+/// Returns an error if the handle does not resolve to a receiving account
+#[get("/recipient/{handle}")]
+async fn lookup_recipient(
+    state: State,
+    _claim: JWTClaim,
+    path: web::Path<String>,
+) -> Result<impl Responder, ApiError> {
+    let recipient = resolve_recipient(state.db(), &path.into_inner())
+        .await?
+        .ok_or(ApiError::AuthError(AuthError::RecipientNotFound))?;
+
+    let response = RecipientResponse::new(recipient.username, recipient.display_name);
+    Ok(web::Json(response))
+}
+
+/// Exchange a refresh token for a new access token and refresh token
+/// Endpoint: POST /api/user/refresh
+/// Request Body: {
+///     "refresh_token": "string"
+/// }
+/// Response Body: {
+///     "token": "string",
+///     "refresh_token": "string",
+///     "expires_in": integer
+/// }
+/// Each refresh token can be used once. Returns 401 if it is unknown, expired or revoked;
+/// using it a second time also revokes every refresh token issued since its login. The
+/// access token acts for the organization the refresh token was issued for, as long as
+/// the user is still a member of it
+#[post("/refresh")]
+async fn refresh(
+    state: State,
+    request: ValidJson<RefreshTokenRequest>,
+    http_request: HttpRequest,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+    let ttl = Duration::days(REFRESH_TOKEN_TTL_DAYS);
+
+    match db.refresh_token.rotate(&request.refresh_token, ttl).await? {
+        Rotation::Rotated {
+            user_id,
+            organization_id,
+            token,
+        } => {
+            audit::record_actor(&http_request, user_id);
+            let user = db
+                .user
+                .find_user(user_id)
+                .await?
+                .ok_or(ApiError::AuthError(AuthError::UserNotFound))?;
+            // A user removed from the organization since keeps only their personal accounts.
+            let organization_id = match organization_id {
+                Some(organization_id) => db
+                    .organization
+                    .find_membership(organization_id, user_id)
+                    .await?
+                    .map(|_| organization_id),
+                None => None,
+            };
+            let access_token = JWTClaim::new(user.id, user.role)
+                .with_organization(organization_id)
+                .encode()?;
+            Ok(web::Json(TokenResponse::new(
+                access_token,
+                token,
+                ACCESS_TOKEN_TTL_SECS,
+            )))
+        }
+        Rotation::Reused { user_id } => {
+            audit::record_actor(&http_request, user_id);
+            warn!(user_id, "Refresh token reused, revoked its token family");
+            Err(ApiError::AuthError(AuthError::InvalidRefreshToken))
+        }
+        Rotation::Invalid => Err(ApiError::AuthError(AuthError::InvalidRefreshToken)),
+    }
+}
+
+/// Log out by revoking a refresh token and every token rotated from the same login.
+/// Access tokens already issued stay valid until they expire
+/// Endpoint: POST /api/user/logout
+/// Request Body: {
+///     "refresh_token": "string"
+/// }
+/// Returns 204 No Content, or 401 if the refresh token is unknown
+#[post("/logout")]
+async fn logout(
+    state: State,
+    request: ValidJson<RefreshTokenRequest>,
+    http_request: HttpRequest,
+) -> Result<impl Responder, ApiError> {
+    let user_id = state
+        .db()
+        .refresh_token
+        .revoke(&request.refresh_token)
+        .await?
+        .ok_or(ApiError::AuthError(AuthError::InvalidRefreshToken))?;
+    audit::record_actor(&http_request, user_id);
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Change the password of the authenticated user
+/// Endpoint: PUT /api/user/password
+/// Request Body: {
+///     "current_password": "string",
+///     "new_password": "string"
+/// }
+/// Requires authentication. Returns 401 if the current password is wrong. Every refresh
+/// token of the user is revoked, and the user is emailed about the change if they opted in
+#[put("/password")]
+async fn change_password(
+    state: State,
+    claim: JWTClaim,
+    request: ValidJson<ChangePasswordRequest>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+    let user = db
+        .user
+        .find_user(claim.id())
+        .await?
+        .ok_or(ApiError::AuthError(AuthError::UserNotFound))?;
+    if !bcrypt::verify(&request.current_password, &user.password) {
+        return Err(ApiError::AuthError(AuthError::Unauthorized));
+    }
+
+    let password = bcrypt::hash(&request.new_password).map_err(AuthError::BcryptError)?;
+    db.user.change_password(user.id, password).await?;
+    db.refresh_token.revoke_all(user.id).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Set or clear the address notification emails are sent to
+/// Endpoint: PUT /api/user/email
+/// Request Body: {
+///     "email": "string" | null
+/// }
+/// Response Body: {
+///     "email": "string" | null
+/// }
+/// Requires authentication. Addresses are stored in lower case and can belong to one
+/// user only
+#[put("/email")]
+async fn set_email(
+    state: State,
+    claim: JWTClaim,
+    request: ValidJson<SetEmailRequest>,
+    http_request: HttpRequest,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+    let user = db
+        .user
+        .find_user(claim.id())
+        .await?
+        .ok_or(ApiError::AuthError(AuthError::UserNotFound))?;
+    audit::record_before(&http_request, &EmailResponse::new(user.email));
+
+    let email = request.email.as_deref().map(normalize_email);
+    if let Some(email) = &email {
+        let owner = db.user.find_user_by_email(email).await?;
+        if owner.is_some_and(|owner| owner.id != claim.id()) {
+            return Err(ApiError::EmailTaken);
+        }
+    }
+
+    let user = db.user.set_email(claim.id(), email).await?;
+    Ok(web::Json(EmailResponse::new(user.email)))
+}
+
+/// Get the emails the authenticated user receives
+/// Endpoint: GET /api/user/notifications
+/// Response Body: {
+///     "money_received": bool,
+///     "money_sent": bool,
+///     "large_transfers": bool,
+///     "large_transfer_threshold": float,
+///     "new_logins": bool,
+///     "password_changes": bool,
+///     "updated_at": string (RFC 3339)
+/// }
+/// Requires authentication. Every email is on until the user changes it
+#[get("/notifications")]
+async fn get_notification_preferences(
+    state: State,
+    claim: JWTClaim,
+) -> Result<impl Responder, ApiError> {
+    let preferences = state.db().user.find_preferences(claim.id()).await?;
+    Ok(web::Json(PreferencesResponse::from(preferences)))
+}
+
+/// Choose the emails the authenticated user receives
+/// Endpoint: PUT /api/user/notifications
+/// Request Body: {
+///     "money_received": bool (optional),
+///     "money_sent": bool (optional),
+///     "large_transfers": bool (optional),
+///     "large_transfer_threshold": float (optional),
+///     "new_logins": bool (optional),
+///     "password_changes": bool (optional)
+/// }
+/// Response Body: preferences as returned by GET /api/user/notifications
+/// Requires authentication. Transfers of at least the threshold are reported as large
+/// transfers instead of plain sent money
+#[put("/notifications")]
+async fn update_notification_preferences(
+    state: State,
+    claim: JWTClaim,
+    request: ValidJson<UpdatePreferencesRequest>,
+    http_request: HttpRequest,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+    let before = db.user.find_preferences(claim.id()).await?;
+    audit::record_before(&http_request, &PreferencesResponse::from(before));
+
+    let update = PreferencesUpdate {
+        money_received: request.money_received,
+        money_sent: request.money_sent,
+        large_transfers: request.large_transfers,
+        large_transfer_threshold: request.large_transfer_threshold,
+        new_logins: request.new_logins,
+        password_changes: request.password_changes,
+    };
+    let preferences = db.user.update_preferences(claim.id(), update).await?;
+    Ok(web::Json(PreferencesResponse::from(preferences)))
+}
+
+fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+/// A new access token and the first refresh token of a new login, both acting for
+/// `organization_id` if set
+pub async fn issue_tokens(
+    db: &DbClient,
+    user_id: i32,
+    role: UserRole,
+    organization_id: Option<i32>,
+) -> Result<TokenResponse, ApiError> {
+    let token = JWTClaim::new(user_id, role)
+        .with_organization(organization_id)
+        .encode()?;
+    let refresh_token = db
+        .refresh_token
+        .issue(
+            user_id,
+            organization_id,
+            Duration::days(REFRESH_TOKEN_TTL_DAYS),
+        )
+        .await?;
+    Ok(TokenResponse::new(
+        token,
+        refresh_token,
+        ACCESS_TOKEN_TTL_SECS,
+    ))
+}
@@ -1,2 +1,3 @@
 pub mod controllers;
+pub mod recipients;
 pub mod user_types;
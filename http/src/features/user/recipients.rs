@@ -0,0 +1,71 @@
+use db::db_client::DbClient;
+use entity::accounts;
+
+use crate::util::{ApiError, AuthError};
+
+/// A payee resolved from a username or an account alias. Organization accounts have
+/// no username, so that the employee who opened them stays private.
+pub struct Recipient {
+    pub username: Option<String>,
+    pub display_name: String,
+    pub account_id: i32,
+}
+
+/// Resolves a recipient handle to the account that should receive the payment.
+/// A username (optionally prefixed with `@`) resolves to that user's default
+/// receiving account; otherwise the handle is looked up as an account alias. Both
+/// lookups ignore case, and usernames and aliases never collide.
+/// Returns `None` if nothing matches or the user has no default account.
+pub async fn resolve_recipient(db: &DbClient, handle: &str) -> Result<Option<Recipient>, ApiError> {
+    let handle = handle.trim().trim_start_matches('@');
+
+    if let Some(user) = db.user.find_user_by_handle(handle).await? {
+        return Ok(user.default_account_id.map(|account_id| Recipient {
+            display_name: user.display_name.unwrap_or_else(|| user.username.clone()),
+            username: Some(user.username),
+            account_id,
+        }));
+    }
+
+    if let Some(account) = db
+        .account
+        .find_account_by_alias(&handle.to_lowercase())
+        .await?
+    {
+        let username = match account.organization_id {
+            Some(_) => None,
+            None => db
+                .user
+                .find_user(account.user_id)
+                .await?
+                .map(|user| user.username),
+        };
+        return Ok(Some(Recipient {
+            display_name: payee_display_name(db, &account).await?,
+            username,
+            account_id: account.id,
+        }));
+    }
+
+    Ok(None)
+}
+
+/// The name payers see for the holder of an account: the organization's name for
+/// organization accounts, otherwise the owner's display name or username
+pub async fn payee_display_name(
+    db: &DbClient,
+    account: &accounts::Model,
+) -> Result<String, ApiError> {
+    if let Some(organization_id) = account.organization_id {
+        if let Some(organization) = db.organization.find_organization(organization_id).await? {
+            return Ok(organization.name);
+        }
+    }
+
+    let user = db
+        .user
+        .find_user(account.user_id)
+        .await?
+        .ok_or(ApiError::AuthError(AuthError::UserNotFound))?;
+    Ok(user.display_name.unwrap_or(user.username))
+}
@@ -1,26 +1,42 @@
+use chrono::{DateTime, Utc};
+use entity::notification_preferences;
+use num_traits::ToPrimitive;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize)]
-pub struct AuthResponse {
-    token: String,
-}
-
-impl AuthResponse {
-    pub fn new(token: String) -> AuthResponse {
-        AuthResponse { token }
-    }
-}
+use crate::validation::{FieldError, Validate, Validator};
 
 /// Request body for user registration
 #[derive(Deserialize)]
 pub struct UserRegisterRequest {
     pub username: String,
     pub password: String,
+    #[serde(default)]
+    pub display_name: Option<String>,
+    #[serde(default)]
+    pub email: Option<String>,
 }
 
-impl UserRegisterRequest {
-    pub fn new(username: String, password: String) -> Self {
-        Self { username, password }
+impl Validate for UserRegisterRequest {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::new();
+        v.length(&self.username, "username", 3, 32);
+        v.check(
+            self.username
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "._-".contains(c)),
+            "username",
+            "format",
+            "May only contain letters, digits, '.', '_' and '-'",
+        );
+        v.length(&self.password, "password", 8, 128);
+        if let Some(display_name) = &self.display_name {
+            v.not_blank(display_name, "display_name");
+            v.length(display_name, "display_name", 1, 64);
+        }
+        if let Some(email) = &self.email {
+            v.email(email, "email");
+        }
+        v.finish()
     }
 }
 
@@ -31,9 +47,12 @@ pub struct UserLoginRequest {
     pub password: String,
 }
 
-impl UserLoginRequest {
-    pub fn new(username: String, password: String) -> Self {
-        Self { username, password }
+impl Validate for UserLoginRequest {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::new();
+        v.not_blank(&self.username, "username");
+        v.not_blank(&self.password, "password");
+        v.finish()
     }
 }
 
@@ -42,15 +61,16 @@ impl UserLoginRequest {
 pub struct UserRegisterResponse {
     user_id: i32,
     username: String,
-    token: String,
+    #[serde(flatten)]
+    tokens: TokenResponse,
 }
 
 impl UserRegisterResponse {
-    pub fn new(user_id: i32, username: String, token: String) -> Self {
+    pub fn new(user_id: i32, username: String, tokens: TokenResponse) -> Self {
         Self {
             username,
             user_id,
-            token,
+            tokens,
         }
     }
 }
@@ -60,15 +80,163 @@ impl UserRegisterResponse {
 pub struct UserLoginResponse {
     user_id: i32,
     username: String,
-    token: String,
+    #[serde(flatten)]
+    tokens: TokenResponse,
 }
 
 impl UserLoginResponse {
-    pub fn new(user_id: i32, username: String, token: String) -> Self {
+    pub fn new(user_id: i32, username: String, tokens: TokenResponse) -> Self {
         Self {
             username,
             user_id,
+            tokens,
+        }
+    }
+}
+
+/// A short-lived access token with the refresh token that replaces it
+#[derive(Serialize)]
+pub struct TokenResponse {
+    token: String,
+    refresh_token: String,
+    /// Seconds until the access token expires
+    expires_in: i64,
+}
+
+impl TokenResponse {
+    pub fn new(token: String, refresh_token: String, expires_in: i64) -> Self {
+        Self {
             token,
+            refresh_token,
+            expires_in,
+        }
+    }
+}
+
+/// Request body for refreshing the access token or logging out
+#[derive(Deserialize)]
+pub struct RefreshTokenRequest {
+    pub refresh_token: String,
+}
+
+impl Validate for RefreshTokenRequest {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::new();
+        v.not_blank(&self.refresh_token, "refresh_token");
+        v.finish()
+    }
+}
+
+/// Response for a recipient lookup, confirming who a payment would go to
+#[derive(Serialize)]
+pub struct RecipientResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    username: Option<String>,
+    display_name: String,
+}
+
+impl RecipientResponse {
+    pub fn new(username: Option<String>, display_name: String) -> Self {
+        Self {
+            username,
+            display_name,
+        }
+    }
+}
+
+/// Request body for changing the password
+#[derive(Deserialize)]
+pub struct ChangePasswordRequest {
+    pub current_password: String,
+    pub new_password: String,
+}
+
+impl Validate for ChangePasswordRequest {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::new();
+        v.not_blank(&self.current_password, "current_password");
+        v.length(&self.new_password, "new_password", 8, 128);
+        v.check(
+            self.new_password != self.current_password,
+            "new_password",
+            "unchanged",
+            "Must differ from the current password",
+        );
+        v.finish()
+    }
+}
+
+/// Request body for setting or clearing (`null`) the notification email address
+#[derive(Deserialize)]
+pub struct SetEmailRequest {
+    pub email: Option<String>,
+}
+
+impl Validate for SetEmailRequest {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::new();
+        if let Some(email) = &self.email {
+            v.email(email, "email");
+        }
+        v.finish()
+    }
+}
+
+/// Response for the notification email address
+#[derive(Serialize)]
+pub struct EmailResponse {
+    email: Option<String>,
+}
+
+impl EmailResponse {
+    pub fn new(email: Option<String>) -> Self {
+        Self { email }
+    }
+}
+
+/// Request body for changing notification preferences; omitted fields keep their value
+#[derive(Deserialize)]
+pub struct UpdatePreferencesRequest {
+    pub money_received: Option<bool>,
+    pub money_sent: Option<bool>,
+    pub large_transfers: Option<bool>,
+    pub large_transfer_threshold: Option<f64>,
+    pub new_logins: Option<bool>,
+    pub password_changes: Option<bool>,
+}
+
+impl Validate for UpdatePreferencesRequest {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::new();
+        if let Some(threshold) = self.large_transfer_threshold {
+            v.positive_amount(threshold, "large_transfer_threshold");
+        }
+        v.finish()
+    }
+}
+
+/// Response for the emails a user receives
+#[derive(Serialize)]
+pub struct PreferencesResponse {
+    money_received: bool,
+    money_sent: bool,
+    large_transfers: bool,
+    large_transfer_threshold: f64,
+    new_logins: bool,
+    password_changes: bool,
+    updated_at: DateTime<Utc>,
+}
+
+impl From<notification_preferences::Model> for PreferencesResponse {
+    fn from(preferences: notification_preferences::Model) -> Self {
+        Self {
+            money_received: preferences.money_received,
+            money_sent: preferences.money_sent,
+            large_transfers: preferences.large_transfers,
+            large_transfer_threshold: preferences.large_transfer_threshold.to_f64().unwrap(),
+            new_logins: preferences.new_logins,
+            password_changes: preferences.password_changes,
+            updated_at: preferences.updated_at.with_timezone(&Utc),
         }
     }
 }
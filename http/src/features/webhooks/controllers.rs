@@ -0,0 +1,271 @@
+use actix_web::{delete, get, post, web, HttpRequest, Responder};
+use db::db_client::DbClient;
+use entity::webhook_endpoints;
+use reqwest::Url;
+
+use crate::app_state::AppState;
+use crate::features::accounts::access::{
+    authorize_account, has_access, scope_account_ids, AccountAccess,
+};
+use crate::features::webhooks::target::{self, TargetError};
+use crate::features::webhooks::webhook_types::{
+    CreateEndpointRequest, DeliveryDetailsResponse, DeliveryResponse, EndpointResponse,
+    ListDeliveriesQuery, ListDeliveriesResponse, ListEndpointsQuery, ListEndpointsResponse,
+};
+use crate::middlewares::audit;
+use crate::middlewares::auth::JWTClaim;
+use crate::pagination::{next_cursor, PageQuery};
+use crate::util::{ApiError, AuthError};
+use crate::validation::{FieldError, ValidJson, ValidQuery};
+
+type State = web::Data<AppState>;
+
+/// Register a URL that receives events of an account and its pockets
+/// Endpoint: POST /api/webhook/endpoint/create
+/// Request Body: {
+///     "account_id": integer,
+///     "url": string,
+///     "event_types": [ "transaction.created" | "transaction.reversed" | "dispute.opened"
+///                      | "dispute.resolved" | "payment_intent.succeeded" | "invoice.paid" ]
+/// }
+/// Response Body: {
+///     "id": integer,
+///     "account_id": integer,
+///     "created_by": integer,
+///     "url": string,
+///     "event_types": [string],
+///     "active": boolean,
+///     "secret": string,
+///     "created_at": string (RFC 3339),
+///     "updated_at": string (RFC 3339)
+/// }
+/// Requires authentication.
+/// Requires the owner role on the account. The secret signs every delivery and is only
+/// returned here. URLs on loopback, private or link-local addresses, or on names
+/// resolving to them, are refused with 422
+#[post("/endpoint/create")]
+async fn create_endpoint(
+    state: State,
+    claim: JWTClaim,
+    request: ValidJson<CreateEndpointRequest>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+
+    let account = authorize_account(db, request.account_id, &claim, AccountAccess::Manage).await?;
+    check_resolved_host(request.url.trim()).await?;
+    let endpoint = db
+        .webhook
+        .create_endpoint(
+            account.id,
+            claim.id(),
+            request.url.trim().to_string(),
+            &request.event_types,
+        )
+        .await?;
+
+    Ok(web::Json(EndpointResponse::with_secret(endpoint)))
+}
+
+/// List the active webhook endpoints of the accounts the user manages
+/// Endpoint: GET /api/webhook/endpoint/list
+/// Query Parameters (optional):
+///     account_id: integer - only endpoints of this account
+/// Response Body: {
+///     "endpoints": [ endpoint as returned by POST /api/webhook/endpoint/create,
+///                    without the secret ]
+/// }
+/// Requires authentication
+#[get("/endpoint/list")]
+async fn list_endpoints(
+    state: State,
+    claim: JWTClaim,
+    query: ValidQuery<ListEndpointsQuery>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+
+    let account_ids = match query.account_id {
+        Some(account_id) => {
+            let account = authorize_account(db, account_id, &claim, AccountAccess::Manage).await?;
+            vec![account.id]
+        }
+        None => {
+            let mut managed = Vec::new();
+            for account_id in scope_account_ids(db, &claim).await? {
+                if has_access(db, account_id, &claim, AccountAccess::Manage).await? {
+                    managed.push(account_id);
+                }
+            }
+            managed
+        }
+    };
+
+    let endpoints = db.webhook.list_endpoints(&account_ids).await?;
+
+    Ok(web::Json(ListEndpointsResponse {
+        endpoints: endpoints.into_iter().map(EndpointResponse::from).collect(),
+    }))
+}
+
+/// Get a webhook endpoint
+/// Endpoint: GET /api/webhook/endpoint/{endpoint_id}
+/// Path Parameters: endpoint_id (integer)
+/// Response Body: endpoint as returned by POST /api/webhook/endpoint/create, without the
+///     secret
+/// Requires authentication.
+/// Requires the owner role on the endpoint's account
+#[get("/endpoint/{endpoint_id}")]
+async fn get_endpoint(
+    state: State,
+    claim: JWTClaim,
+    path: web::Path<i32>,
+) -> Result<impl Responder, ApiError> {
+    let endpoint = find_managed_endpoint(state.db(), path.into_inner(), &claim).await?;
+
+    Ok(web::Json(EndpointResponse::from(endpoint)))
+}
+
+/// Delete a webhook endpoint. Deliveries waiting for a retry are marked failed
+/// Endpoint: DELETE /api/webhook/endpoint/{endpoint_id}
+/// Path Parameters: endpoint_id (integer)
+/// Response Body: endpoint as returned by POST /api/webhook/endpoint/create, without the
+///     secret
+/// Requires authentication.
+/// Requires the owner role on the endpoint's account
+#[delete("/endpoint/{endpoint_id}")]
+async fn delete_endpoint(
+    state: State,
+    claim: JWTClaim,
+    path: web::Path<i32>,
+    http_request: HttpRequest,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+
+    let endpoint = find_managed_endpoint(db, path.into_inner(), &claim).await?;
+    audit::record_before(&http_request, &EndpointResponse::from(endpoint.clone()));
+    let endpoint = db.webhook.deactivate_endpoint(endpoint.id).await?;
+
+    Ok(web::Json(EndpointResponse::from(endpoint)))
+}
+
+/// List the deliveries of a webhook endpoint
+/// Endpoint: GET /api/webhook/endpoint/{endpoint_id}/deliveries
+/// Path Parameters: endpoint_id (integer)
+/// Query Parameters (all optional):
+///     status: "pending" | "succeeded" | "failed"
+///     limit: integer, cursor: string - keyset pagination
+/// Response Body: {
+///     "deliveries": [
+///         {
+///             "id": integer,
+///             "endpoint_id": integer,
+///             "event_type": string,
+///             "payload": object,
+///             "status": "pending" | "succeeded" | "failed",
+///             "attempt_count": integer,
+///             "next_attempt_at": string (RFC 3339) | null,
+///             "created_at": string (RFC 3339),
+///             "updated_at": string (RFC 3339)
+///         }
+///     ],
+///     "next_cursor": string | null
+/// }
+/// Requires authentication.
+/// Requires the owner role on the endpoint's account
+#[get("/endpoint/{endpoint_id}/deliveries")]
+async fn list_deliveries(
+    state: State,
+    claim: JWTClaim,
+    path: web::Path<i32>,
+    query: ValidQuery<ListDeliveriesQuery>,
+    page: ValidQuery<PageQuery>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+    let page_request = page.page_request()?;
+
+    let endpoint = find_managed_endpoint(db, path.into_inner(), &claim).await?;
+    let deliveries = db
+        .webhook
+        .list_deliveries(endpoint.id, query.status, page_request)
+        .await?;
+    let next_cursor = next_cursor(&deliveries);
+
+    Ok(web::Json(ListDeliveriesResponse {
+        deliveries: deliveries
+            .items
+            .into_iter()
+            .map(DeliveryResponse::from)
+            .collect(),
+        next_cursor,
+    }))
+}
+
+/// Get a webhook delivery with every attempt at sending it
+/// Endpoint: GET /api/webhook/delivery/{delivery_id}
+/// Path Parameters: delivery_id (integer)
+/// Response Body: delivery as listed by GET /api/webhook/endpoint/{endpoint_id}/deliveries,
+///     plus "attempts": [
+///         {
+///             "id": integer,
+///             "response_status": integer | null,
+///             "error": string | null,
+///             "duration_ms": integer,
+///             "attempted_at": string (RFC 3339)
+///         }
+///     ]
+/// Requires authentication.
+/// Requires the owner role on the endpoint's account
+#[get("/delivery/{delivery_id}")]
+async fn get_delivery(
+    state: State,
+    claim: JWTClaim,
+    path: web::Path<i32>,
+) -> Result<impl Responder, ApiError> {
+    let db = state.db();
+
+    let delivery = db
+        .webhook
+        .find_delivery(path.into_inner())
+        .await?
+        .ok_or(ApiError::NotFound("Webhook delivery"))?;
+    find_managed_endpoint(db, delivery.endpoint_id, &claim).await?;
+    let attempts = db.webhook.list_attempts(delivery.id).await?;
+
+    Ok(web::Json(DeliveryDetailsResponse::new(delivery, attempts)))
+}
+
+/// Loads an endpoint on an account the user manages
+async fn find_managed_endpoint(
+    db: &DbClient,
+    id: i32,
+    claim: &JWTClaim,
+) -> Result<webhook_endpoints::Model, ApiError> {
+    let endpoint = db
+        .webhook
+        .find_endpoint(id)
+        .await?
+        .ok_or(ApiError::NotFound("Webhook endpoint"))?;
+    if !has_access(db, endpoint.account_id, claim, AccountAccess::Manage).await? {
+        return Err(ApiError::AuthError(AuthError::Unauthorized));
+    }
+    Ok(endpoint)
+}
+
+/// Refuses an endpoint whose host name resolves to an address that is not public.
+/// Names that do not resolve yet are accepted; the dispatcher checks them again on
+/// every send.
+async fn check_resolved_host(url: &str) -> Result<(), ApiError> {
+    let Some(host) = Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(String::from))
+    else {
+        return Ok(());
+    };
+    match target::resolve_public(&host).await {
+        Err(TargetError::NotPublic(_)) => Err(ApiError::Validation(vec![FieldError::new(
+            "url",
+            "not_public",
+            "Must not point to a loopback, private or link-local address",
+        )])),
+        _ => Ok(()),
+    }
+}
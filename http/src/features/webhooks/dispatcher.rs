@@ -0,0 +1,296 @@
+use std::error::Error;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use actix_web::web;
+use chrono::Utc;
+use entity::{webhook_deliveries, webhook_endpoints};
+use hmac::{Hmac, Mac};
+use reqwest::redirect::Policy;
+use reqwest::Url;
+use serde_json::json;
+use sha2::Sha256;
+use tracing::{info, warn};
+
+use crate::app_state::AppState;
+use crate::constants::{WEBHOOK_BATCH_SIZE, WEBHOOK_POLL_INTERVAL_SECS, WEBHOOK_TIMEOUT_SECS};
+use crate::features::webhooks::target::{self, PublicResolver};
+
+/// Longest error message kept for an attempt
+const MAX_ERROR_LENGTH: usize = 500;
+
+/// Sends due webhook deliveries in the background for as long as the server runs.
+/// Deliveries are claimed with `SKIP LOCKED`, so several server instances can poll at
+/// the same time without sending the same delivery twice. Host names are resolved
+/// through `PublicResolver` on every send and redirects are not followed, so an
+/// endpoint cannot be turned towards the server's own network after it was registered.
+pub async fn run(state: web::Data<AppState>) {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(WEBHOOK_TIMEOUT_SECS))
+        .dns_resolver(Arc::new(PublicResolver))
+        .redirect(Policy::none())
+        .build()
+        .expect("Could not build the webhook HTTP client");
+    let mut interval =
+        actix_web::rt::time::interval(Duration::from_secs(WEBHOOK_POLL_INTERVAL_SECS));
+    loop {
+        interval.tick().await;
+        send_due(&state, &client).await;
+    }
+}
+
+async fn send_due(state: &AppState, client: &reqwest::Client) {
+    let db = state.db();
+    let due = match db.webhook.claim_due_deliveries(WEBHOOK_BATCH_SIZE).await {
+        Ok(due) => due,
+        Err(err) => {
+            warn!("Could not claim due webhook deliveries: {}", err);
+            return;
+        }
+    };
+
+    for (delivery, endpoint) in due {
+        let started = Instant::now();
+        let checked = Url::parse(&endpoint.url)
+            .map_err(|err| err.to_string())
+            .and_then(|url| target::check_host(&url).map_err(|err| err.to_string()));
+        let (response_status, error) = match checked {
+            Err(err) => (None, Some(err)),
+            Ok(()) => match send(client, &delivery, &endpoint).await {
+                Ok(status) => (Some(status), None),
+                Err(err) => (None, Some(describe(&err))),
+            },
+        };
+        let duration_ms = started.elapsed().as_millis().min(i32::MAX as u128) as i32;
+
+        match db
+            .webhook
+            .record_attempt(delivery.id, response_status, error, duration_ms)
+            .await
+        {
+            Ok(delivery) => info!(
+                "Webhook delivery {} to endpoint {}: {:?} after {} attempts",
+                delivery.id, endpoint.id, delivery.status, delivery.attempt_count
+            ),
+            Err(err) => warn!(
+                "Could not record webhook delivery attempt {}: {}",
+                delivery.id, err
+            ),
+        }
+    }
+}
+
+/// POSTs a delivery to its endpoint and returns the response status. The body is signed
+/// as `X-Webhook-Signature: t=<unix time>,v1=<hex HMAC-SHA256 of "<t>.<body>">`.
+async fn send(
+    client: &reqwest::Client,
+    delivery: &webhook_deliveries::Model,
+    endpoint: &webhook_endpoints::Model,
+) -> Result<i32, reqwest::Error> {
+    let body = json!({
+        "id": delivery.id,
+        "type": delivery.event_type,
+        "created_at": delivery.created_at,
+        "data": delivery.payload,
+    })
+    .to_string();
+    let timestamp = Utc::now().timestamp();
+
+    let response = client
+        .post(&endpoint.url)
+        .header("Content-Type", "application/json")
+        .header("X-Webhook-Id", delivery.id.to_string())
+        .header(
+            "X-Webhook-Event",
+            json!(delivery.event_type).as_str().unwrap_or_default(),
+        )
+        .header(
+            "X-Webhook-Signature",
+            format!(
+                "t={},v1={}",
+                timestamp,
+                sign(&endpoint.secret, timestamp, &body)
+            ),
+        )
+        .body(body)
+        .send()
+        .await?;
+    Ok(response.status().as_u16() as i32)
+}
+
+pub(crate) fn sign(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(format!("{timestamp}.{body}").as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// The error with its causes, cut to what an attempt can store
+fn describe(err: &reqwest::Error) -> String {
+    let mut error = err.to_string();
+    let mut source = err.source();
+    while let Some(cause) = source {
+        error.push_str(": ");
+        error.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    if error.len() > MAX_ERROR_LENGTH {
+        let mut end = MAX_ERROR_LENGTH;
+        while !error.is_char_boundary(end) {
+            end -= 1;
+        }
+        error.truncate(end);
+    }
+    error
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use actix_web::{App, HttpRequest, HttpResponse, HttpServer};
+    use db::webhooks::{WebhookDeliveryStatus, WebhookEventType};
+
+    use super::*;
+
+    const SECRET: &str = "whsec_test";
+
+    /// A request the test receiver got: the signature and event headers and the body
+    struct Received {
+        signature: String,
+        event: String,
+        body: String,
+    }
+
+    type Inbox = web::Data<Mutex<Vec<Received>>>;
+
+    async fn receive(inbox: Inbox, request: HttpRequest, body: String) -> HttpResponse {
+        let header = |name: &str| {
+            request
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or_default()
+                .to_string()
+        };
+        inbox.lock().unwrap().push(Received {
+            signature: header("X-Webhook-Signature"),
+            event: header("X-Webhook-Event"),
+            body,
+        });
+        if request.path() == "/fail" {
+            HttpResponse::InternalServerError().finish()
+        } else {
+            HttpResponse::NoContent().finish()
+        }
+    }
+
+    fn delivery() -> webhook_deliveries::Model {
+        let now = Utc::now().fixed_offset();
+        webhook_deliveries::Model {
+            id: 7,
+            endpoint_id: 3,
+            event_type: WebhookEventType::TransactionCreated,
+            payload: json!({ "id": 42, "amount": 12.5 }),
+            status: WebhookDeliveryStatus::Pending,
+            attempt_count: 0,
+            next_attempt_at: Some(now),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    fn endpoint(url: String) -> webhook_endpoints::Model {
+        let now = Utc::now().fixed_offset();
+        webhook_endpoints::Model {
+            id: 3,
+            account_id: 1,
+            created_by: 1,
+            url,
+            secret: SECRET.to_string(),
+            event_types: json!(["transaction.created"]),
+            active: true,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    #[test]
+    fn sign_is_hmac_sha256_of_timestamp_and_body() {
+        assert_eq!(
+            sign(SECRET, 1_700_000_000, r#"{"id":1}"#),
+            "2f441ba4b3b2d50d28a9ab9d9fd8880376ecd1eb5d0435401553f5d8d0a5dcf8"
+        );
+    }
+
+    #[test]
+    fn sign_covers_the_secret_timestamp_and_body() {
+        let signature = sign(SECRET, 1_700_000_000, "{}");
+        assert_ne!(sign("whsec_other", 1_700_000_000, "{}"), signature);
+        assert_ne!(sign(SECRET, 1_700_000_001, "{}"), signature);
+        assert_ne!(sign(SECRET, 1_700_000_000, "{ }"), signature);
+    }
+
+    #[actix_web::test]
+    async fn sends_signed_deliveries_to_the_endpoint() {
+        let inbox: Inbox = web::Data::new(Mutex::new(Vec::new()));
+        let app_inbox = inbox.clone();
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(app_inbox.clone())
+                .default_service(web::post().to(receive))
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let address = server.addrs()[0];
+        let server = server.run();
+        let handle = server.handle();
+        actix_web::rt::spawn(server);
+
+        let client = reqwest::Client::new();
+        let delivery = delivery();
+        let ok = endpoint(format!("http://{address}/hook"));
+        let failing = endpoint(format!("http://{address}/fail"));
+        assert_eq!(send(&client, &delivery, &ok).await.unwrap(), 204);
+        assert_eq!(send(&client, &delivery, &failing).await.unwrap(), 500);
+        handle.stop(true).await;
+
+        let inbox = inbox.lock().unwrap();
+        assert_eq!(inbox.len(), 2);
+        let received = &inbox[0];
+        assert_eq!(received.event, "transaction.created");
+        let body: serde_json::Value = serde_json::from_str(&received.body).unwrap();
+        assert_eq!(body["id"], 7);
+        assert_eq!(body["type"], "transaction.created");
+        assert_eq!(body["data"], delivery.payload);
+
+        let (timestamp, signature) = received
+            .signature
+            .strip_prefix("t=")
+            .and_then(|rest| rest.split_once(",v1="))
+            .unwrap();
+        let timestamp: i64 = timestamp.parse().unwrap();
+        assert!((Utc::now().timestamp() - timestamp).abs() < 60);
+        assert_eq!(signature, sign(SECRET, timestamp, &received.body));
+    }
+
+    #[actix_web::test]
+    async fn unreachable_endpoints_fail_with_a_description() {
+        // Nothing listens on a port that was just released.
+        let address = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let err = send(
+            &reqwest::Client::new(),
+            &delivery(),
+            &endpoint(format!("http://{address}/hook")),
+        )
+        .await
+        .unwrap_err();
+        let description = describe(&err);
+        assert!(!description.is_empty());
+        assert!(description.len() <= MAX_ERROR_LENGTH);
+    }
+}
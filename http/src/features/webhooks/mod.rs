@@ -0,0 +1,4 @@
+pub mod controllers;
+pub mod dispatcher;
+pub mod target;
+pub mod webhook_types;
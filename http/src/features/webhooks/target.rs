@@ -0,0 +1,159 @@
+use std::error::Error;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use common::error::thiserror;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::Url;
+
+/// Why a webhook URL may not be sent to
+#[derive(Debug, thiserror::Error)]
+pub enum TargetError {
+    #[error("The host {0} is not a public address")]
+    NotPublic(String),
+
+    #[error("The host {0} could not be resolved: {1}")]
+    Unresolved(String, std::io::Error),
+}
+
+/// Whether webhooks may be sent to an address. Loopback, private, link-local (which
+/// includes the cloud metadata address 169.254.169.254), shared, unspecified, broadcast
+/// and multicast addresses all point into the network the server runs in.
+pub fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_v4(ip),
+            None => is_public_v6(ip),
+        },
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    let shared = a == 100 && (64..128).contains(&b);
+    !(ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || shared
+        || a == 0)
+}
+
+fn is_public_v6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    let unique_local = first & 0xfe00 == 0xfc00;
+    let link_local = first & 0xffc0 == 0xfe80;
+    !(ip.is_loopback() || ip.is_unspecified() || ip.is_multicast() || unique_local || link_local)
+}
+
+/// Checks the host of a URL without a DNS lookup: IP literals must be public and
+/// `localhost` is refused. Other names are checked when they are resolved.
+pub fn check_host(url: &Url) -> Result<(), TargetError> {
+    let host = url.host_str().unwrap_or_default();
+    let refused = match host.trim_start_matches('[').trim_end_matches(']').parse() {
+        Ok(ip) => !is_public(ip),
+        Err(_) => is_localhost(host),
+    };
+    if refused {
+        return Err(TargetError::NotPublic(host.to_string()));
+    }
+    Ok(())
+}
+
+fn is_localhost(domain: &str) -> bool {
+    let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+    domain == "localhost" || domain.ends_with(".localhost")
+}
+
+/// Resolves a host name and fails unless every address it resolves to is public
+pub async fn resolve_public(host: &str) -> Result<Vec<SocketAddr>, TargetError> {
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, 0))
+        .await
+        .map_err(|err| TargetError::Unresolved(host.to_string(), err))?
+        .collect();
+    match addrs.iter().find(|addr| !is_public(addr.ip())) {
+        Some(addr) => Err(TargetError::NotPublic(format!("{host} ({})", addr.ip()))),
+        None => Ok(addrs),
+    }
+}
+
+/// DNS resolver for the webhook client that refuses names resolving to addresses that
+/// are not public. Checking every lookup the client makes keeps a name that passed the
+/// check at registration from being pointed at an internal address later.
+pub struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addrs = resolve_public(name.as_str())
+                .await
+                .map_err(|err| Box::new(err) as Box<dyn Error + Send + Sync>)?;
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(value: &str) -> IpAddr {
+        value.parse().unwrap()
+    }
+
+    fn url(value: &str) -> Url {
+        Url::parse(value).unwrap()
+    }
+
+    #[test]
+    fn internal_addresses_are_not_public() {
+        for value in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "172.31.255.255",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "255.255.255.255",
+            "::1",
+            "::",
+            "fc00::1",
+            "fd12:3456::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "::ffff:169.254.169.254",
+        ] {
+            assert!(!is_public(ip(value)), "{value} should not be public");
+        }
+    }
+
+    #[test]
+    fn internet_addresses_are_public() {
+        for value in ["1.1.1.1", "93.184.216.34", "172.32.0.1", "2606:4700::1111"] {
+            assert!(is_public(ip(value)), "{value} should be public");
+        }
+    }
+
+    #[test]
+    fn literal_and_localhost_hosts_are_checked_without_a_lookup() {
+        assert!(check_host(&url("http://127.0.0.1:8080/hook")).is_err());
+        assert!(check_host(&url("http://[::1]/hook")).is_err());
+        assert!(check_host(&url("http://169.254.169.254/latest/meta-data")).is_err());
+        assert!(check_host(&url("http://LOCALHOST./hook")).is_err());
+        assert!(check_host(&url("http://api.localhost/hook")).is_err());
+        assert!(check_host(&url("https://1.1.1.1/hook")).is_ok());
+        assert!(check_host(&url("https://example.com/hook")).is_ok());
+    }
+
+    #[actix_web::test]
+    async fn names_resolving_to_internal_addresses_are_refused() {
+        assert!(matches!(
+            resolve_public("localhost").await,
+            Err(TargetError::NotPublic(_))
+        ));
+    }
+}
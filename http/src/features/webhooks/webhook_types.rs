@@ -0,0 +1,205 @@
+use std::collections::HashSet;
+
+use chrono::{DateTime, Utc};
+use db::webhooks::{WebhookDeliveryStatus, WebhookEventType};
+use entity::{webhook_attempts, webhook_deliveries, webhook_endpoints};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::features::webhooks::target;
+use crate::validation::{FieldError, Validate, Validator};
+
+/// Request body for registering a webhook endpoint
+#[derive(Debug, Deserialize)]
+pub struct CreateEndpointRequest {
+    pub account_id: i32,
+    pub url: String,
+    pub event_types: Vec<WebhookEventType>,
+}
+
+impl Validate for CreateEndpointRequest {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::new();
+        v.length(self.url.trim(), "url", 1, 2048);
+        v.check(
+            Url::parse(self.url.trim())
+                .is_ok_and(|url| matches!(url.scheme(), "http" | "https") && url.has_host()),
+            "url",
+            "format",
+            "Must be an http or https URL",
+        );
+        v.check(
+            Url::parse(self.url.trim()).map_or(true, |url| target::check_host(&url).is_ok()),
+            "url",
+            "not_public",
+            "Must not point to a loopback, private or link-local address",
+        );
+        v.check(
+            !self.event_types.is_empty(),
+            "event_types",
+            "required",
+            "Must subscribe to at least one event type",
+        );
+        v.check(
+            self.event_types.iter().collect::<HashSet<_>>().len() == self.event_types.len(),
+            "event_types",
+            "duplicate",
+            "Must not list an event type twice",
+        );
+        v.finish()
+    }
+}
+
+/// Query parameters for listing webhook endpoints
+#[derive(Debug, Deserialize)]
+pub struct ListEndpointsQuery {
+    pub account_id: Option<i32>,
+}
+
+impl Validate for ListEndpointsQuery {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        Ok(())
+    }
+}
+
+/// Response for a webhook endpoint. The secret is only included when the endpoint
+/// is created.
+#[derive(Debug, Serialize)]
+pub struct EndpointResponse {
+    pub id: i32,
+    pub account_id: i32,
+    pub created_by: i32,
+    pub url: String,
+    pub event_types: Value,
+    pub active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<webhook_endpoints::Model> for EndpointResponse {
+    fn from(endpoint: webhook_endpoints::Model) -> Self {
+        Self {
+            id: endpoint.id,
+            account_id: endpoint.account_id,
+            created_by: endpoint.created_by,
+            url: endpoint.url,
+            event_types: endpoint.event_types,
+            active: endpoint.active,
+            secret: None,
+            created_at: endpoint.created_at.with_timezone(&Utc),
+            updated_at: endpoint.updated_at.with_timezone(&Utc),
+        }
+    }
+}
+
+impl EndpointResponse {
+    /// Response for a new endpoint, the only time its secret is shown
+    pub fn with_secret(endpoint: webhook_endpoints::Model) -> Self {
+        let secret = endpoint.secret.clone();
+        Self {
+            secret: Some(secret),
+            ..Self::from(endpoint)
+        }
+    }
+}
+
+/// Response for the webhook endpoints of the user's accounts
+#[derive(Debug, Serialize)]
+pub struct ListEndpointsResponse {
+    pub endpoints: Vec<EndpointResponse>,
+}
+
+/// Query parameters for listing the deliveries of an endpoint
+#[derive(Debug, Deserialize)]
+pub struct ListDeliveriesQuery {
+    pub status: Option<WebhookDeliveryStatus>,
+}
+
+impl Validate for ListDeliveriesQuery {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        Ok(())
+    }
+}
+
+/// Response for a webhook delivery
+#[derive(Debug, Serialize)]
+pub struct DeliveryResponse {
+    pub id: i32,
+    pub endpoint_id: i32,
+    pub event_type: WebhookEventType,
+    pub payload: Value,
+    pub status: WebhookDeliveryStatus,
+    pub attempt_count: i32,
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<webhook_deliveries::Model> for DeliveryResponse {
+    fn from(delivery: webhook_deliveries::Model) -> Self {
+        Self {
+            id: delivery.id,
+            endpoint_id: delivery.endpoint_id,
+            event_type: delivery.event_type,
+            payload: delivery.payload,
+            status: delivery.status,
+            attempt_count: delivery.attempt_count,
+            next_attempt_at: delivery.next_attempt_at.map(|at| at.with_timezone(&Utc)),
+            created_at: delivery.created_at.with_timezone(&Utc),
+            updated_at: delivery.updated_at.with_timezone(&Utc),
+        }
+    }
+}
+
+/// Response for one attempt at sending a delivery
+#[derive(Debug, Serialize)]
+pub struct AttemptResponse {
+    pub id: i32,
+    /// HTTP status returned by the endpoint, `null` if no response was received
+    pub response_status: Option<i32>,
+    pub error: Option<String>,
+    pub duration_ms: i32,
+    pub attempted_at: DateTime<Utc>,
+}
+
+impl From<webhook_attempts::Model> for AttemptResponse {
+    fn from(attempt: webhook_attempts::Model) -> Self {
+        Self {
+            id: attempt.id,
+            response_status: attempt.response_status,
+            error: attempt.error,
+            duration_ms: attempt.duration_ms,
+            attempted_at: attempt.attempted_at.with_timezone(&Utc),
+        }
+    }
+}
+
+/// Response for a delivery with its attempts
+#[derive(Debug, Serialize)]
+pub struct DeliveryDetailsResponse {
+    #[serde(flatten)]
+    pub delivery: DeliveryResponse,
+    pub attempts: Vec<AttemptResponse>,
+}
+
+impl DeliveryDetailsResponse {
+    pub fn new(
+        delivery: webhook_deliveries::Model,
+        attempts: Vec<webhook_attempts::Model>,
+    ) -> Self {
+        Self {
+            delivery: DeliveryResponse::from(delivery),
+            attempts: attempts.into_iter().map(AttemptResponse::from).collect(),
+        }
+    }
+}
+
+/// Response for a page of deliveries
+#[derive(Debug, Serialize)]
+pub struct ListDeliveriesResponse {
+    pub deliveries: Vec<DeliveryResponse>,
+    pub next_cursor: Option<String>,
+}
@@ -0,0 +1,23 @@
+use actix_web::http::header::{CacheControl, CacheDirective};
+use actix_web::{get, HttpResponse, Responder};
+
+use crate::middlewares::jwt_keys;
+
+/// The public keys access tokens are signed with, for other services to verify them
+/// Endpoint: GET /.well-known/jwks.json
+/// Response Body: {
+///     "keys": [
+///         { "kty": "RSA", "use": "sig", "alg": "RS256", "kid": string, "n": string, "e": string }
+///         | { "kty": "OKP", "crv": "Ed25519", "use": "sig", "alg": "EdDSA", "kid": string, "x": string }
+///     ]
+/// }
+/// The signing key comes first. HS256 secrets are never listed. May be cached for 5 minutes
+#[get("/jwks.json")]
+async fn jwks() -> impl Responder {
+    HttpResponse::Ok()
+        .insert_header(CacheControl(vec![
+            CacheDirective::Public,
+            CacheDirective::MaxAge(300),
+        ]))
+        .json(jwt_keys::keys().jwks())
+}
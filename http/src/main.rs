@@ -1,10 +1,17 @@
 mod app_state;
+mod cli;
 mod constants;
+mod email;
 mod features;
 mod middlewares;
+mod outbox;
+mod pagination;
 mod routes;
 mod types;
 mod util;
+mod validation;
+
+use std::process;
 
 use actix_web::middleware as actix_middlewares;
 use actix_web::{web, App, HttpServer};
@@ -23,13 +30,36 @@ use util::{ApiError, RateLimitError};
 /// - Request compression
 /// - CORS
 /// - Request tracing
-/// - API routes
+/// - API key authentication
+/// - Audit log of every mutating API call
+/// - API routes, the notification WebSocket and the JWKS of the signing keys
+/// - Background renewal of due subscriptions
+/// - Background delivery of webhooks, outbox events and notification emails
+/// - Live account events for the event streams
 /// Binds to: 0.0.0.0:8080
+///
+/// Run with a command, e.g. `http verify-audit`, to run that command instead
 #[actix_web::main]
 async fn main() -> Result<(), ApiError> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some((command, args)) = args.split_first() {
+        env_logger::init_from_env(Env::default().default_filter_or("warn"));
+        return cli::run(command, args).await;
+    }
+
     env_logger::init_from_env(Env::default().default_filter_or("info"));
 
+    // Loads the signing keys first, so that a bad key configuration stops the server
+    // with a message saying what to fix
+    if let Err(err) = middlewares::jwt_keys::init() {
+        eprintln!("Invalid JWT key configuration: {err}");
+        process::exit(1);
+    }
     let app_state = web::Data::new(AppState::new().await?);
+    actix_web::rt::spawn(features::subscriptions::scheduler::run(app_state.clone()));
+    actix_web::rt::spawn(features::webhooks::dispatcher::run(app_state.clone()));
+    actix_web::rt::spawn(outbox::relay::run(app_state.clone()));
+    actix_web::rt::spawn(features::accounts::events::listen(app_state.clone()));
 
     #[derive(Clone)]
     pub struct RateLimitKey;
@@ -43,9 +73,9 @@ async fn main() -> Result<(), ApiError> {
         ) -> Result<Self::Key, Self::KeyExtractionError> {
             let head = req.head();
             match head.headers().get("Authorization") {
-                Some(data) => return Ok(data.to_str().unwrap().to_string()),
-                None => return Ok("Demo String for Testing".to_string()),
-            };
+                Some(data) => Ok(data.to_str().unwrap().to_string()),
+                None => Ok("Demo String for Testing".to_string()),
+            }
         }
     }
 
@@ -64,12 +94,19 @@ async fn main() -> Result<(), ApiError> {
 
     HttpServer::new(move || {
         App::new()
+            .wrap(actix_middlewares::from_fn(middlewares::audit::audit))
+            .wrap(actix_middlewares::from_fn(
+                middlewares::api_key::authenticate,
+            ))
             .wrap(TracingLogger::default())
             .wrap(actix_middlewares::Compress::default())
             .wrap(Governor::new(&governor_conf))
             .wrap(middlewares::cors::cors())
             .app_data(app_state.clone())
+            .app_data(validation::path_config())
             .configure(routes::api)
+            .configure(routes::ws)
+            .configure(routes::well_known)
     })
     .bind(constants::BIND)?
     .run()
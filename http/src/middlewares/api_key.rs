@@ -0,0 +1,147 @@
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::Method;
+use actix_web::middleware::Next;
+use actix_web::{web, Error, HttpMessage};
+use db::api_keys::{self, ApiKeyScope, KEY_PREFIX};
+use db::user::UserRole;
+
+use crate::app_state::AppState;
+use crate::middlewares::auth::JWTClaim;
+use crate::util::{ApiError, AuthError};
+
+/// Authenticates requests made with `Authorization: Bearer sk_...`. A valid key whose
+/// scopes cover the route acts as its user: the claim is stored on the request, where
+/// the [`JWTClaim`] extractor picks it up, so handlers need not tell keys and tokens
+/// apart. Keys never act as support or admin, nor for an organization.
+///
+/// Returns `401` for an unknown, expired or revoked key and `403` if the key lacks the
+/// route's scope. Requests with a JWT pass through untouched.
+pub async fn authenticate(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let key = req
+        .headers()
+        .get("Authorization")
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| header.strip_prefix("Bearer "))
+        .filter(|token| token.starts_with(KEY_PREFIX))
+        .map(str::to_string);
+
+    if let Some(key) = key {
+        let state = req
+            .app_data::<web::Data<AppState>>()
+            .cloned()
+            .expect("AppState is registered as app data");
+        let api_key = state
+            .db()
+            .api_key
+            .authenticate(&key)
+            .await
+            .map_err(ApiError::from)?
+            .ok_or(ApiError::AuthError(AuthError::InvalidApiKey))?;
+
+        let scope = required_scope(req.method(), req.path()).ok_or(ApiError::Forbidden)?;
+        if !api_keys::scopes(&api_key).contains(&scope) {
+            return Err(ApiError::Forbidden.into());
+        }
+        req.extensions_mut()
+            .insert(JWTClaim::new(api_key.user_id, UserRole::User));
+    }
+
+    next.call(req).await
+}
+
+/// The scope a key needs for a request, or `None` for routes that keys cannot use:
+/// user settings and API keys themselves, switching organization (which would mint a
+/// token without the key's scopes), staff routes and the notification WebSocket
+fn required_scope(method: &Method, path: &str) -> Option<ApiKeyScope> {
+    let read = matches!(*method, Method::GET | Method::HEAD);
+    let area = path.strip_prefix("/api/")?.split('/').next()?;
+    let (read_scope, write_scope) = match area {
+        "org" if path == "/api/org/switch" => return None,
+        "account" | "org" => (ApiKeyScope::AccountsRead, ApiKeyScope::AccountsWrite),
+        "transaction" | "approval" | "dispute" => (
+            ApiKeyScope::TransactionsRead,
+            ApiKeyScope::TransactionsWrite,
+        ),
+        "payment" | "link" | "invoice" | "subscription" | "split" => {
+            (ApiKeyScope::PaymentsRead, ApiKeyScope::PaymentsWrite)
+        }
+        "webhook" => (ApiKeyScope::WebhooksRead, ApiKeyScope::WebhooksWrite),
+        _ => return None,
+    };
+    Some(if read { read_scope } else { write_scope })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_and_writes_need_the_scope_of_their_area() {
+        for (method, path, scope) in [
+            (Method::GET, "/api/account/3", ApiKeyScope::AccountsRead),
+            (
+                Method::HEAD,
+                "/api/org/2/members",
+                ApiKeyScope::AccountsRead,
+            ),
+            (
+                Method::POST,
+                "/api/account/create",
+                ApiKeyScope::AccountsWrite,
+            ),
+            (
+                Method::DELETE,
+                "/api/org/2/members/5",
+                ApiKeyScope::AccountsWrite,
+            ),
+            (
+                Method::GET,
+                "/api/transaction/41",
+                ApiKeyScope::TransactionsRead,
+            ),
+            (
+                Method::POST,
+                "/api/approval/7/approve",
+                ApiKeyScope::TransactionsWrite,
+            ),
+            (
+                Method::POST,
+                "/api/dispute/create",
+                ApiKeyScope::TransactionsWrite,
+            ),
+            (Method::GET, "/api/invoice/list", ApiKeyScope::PaymentsRead),
+            (Method::POST, "/api/link/create", ApiKeyScope::PaymentsWrite),
+            (Method::PUT, "/api/split/4", ApiKeyScope::PaymentsWrite),
+            (Method::GET, "/api/webhook/list", ApiKeyScope::WebhooksRead),
+            (
+                Method::POST,
+                "/api/webhook/create",
+                ApiKeyScope::WebhooksWrite,
+            ),
+        ] {
+            assert_eq!(
+                required_scope(&method, path),
+                Some(scope),
+                "{method} {path}"
+            );
+        }
+    }
+
+    #[test]
+    fn keys_cannot_reach_settings_staff_or_organization_switching() {
+        for (method, path) in [
+            (Method::PUT, "/api/user/email"),
+            (Method::POST, "/api/key/create"),
+            (Method::POST, "/api/org/switch"),
+            (Method::GET, "/api/admin/users"),
+            (Method::GET, "/api/healthcheck"),
+            (Method::GET, "/ws"),
+        ] {
+            assert_eq!(required_scope(&method, path), None, "{method} {path}");
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use actix_web::body::{self, BoxBody, MessageBody};
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::http::header::{self, HeaderName, HeaderValue};
+use actix_web::http::Method;
+use actix_web::middleware::Next;
+use actix_web::{error, web, Error, FromRequest, HttpMessage, HttpRequest};
+use db::audit::AuditEntry;
+use serde::Serialize;
+use serde_json::Value;
+use tracing::error;
+use tracing_actix_web::RequestId;
+
+use crate::app_state::AppState;
+use crate::constants::AUDIT_MAX_SNAPSHOT_SIZE;
+use crate::middlewares::auth::JWTClaim;
+
+/// Header carrying the id of the request, the same id its audit entry is recorded with
+const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Fields holding credentials, left out of snapshots, also with a prefix such as
+/// `new_password`
+const REDACTED_FIELDS: [&str; 3] = ["password", "secret", "token"];
+
+/// Details a handler adds to the audit entry of its request
+#[derive(Clone, Default)]
+struct AuditDetails {
+    actor_id: Option<i32>,
+    before: Option<Value>,
+}
+
+/// Records the state of what the request is about to change as the `before` snapshot
+/// of its audit entry
+pub fn record_before(req: &HttpRequest, before: &impl Serialize) {
+    let before = serde_json::to_value(before).ok().map(redact);
+    update_details(req, |details| details.before = before);
+}
+
+/// Records who performed a request that carries no token, such as a login
+pub fn record_actor(req: &HttpRequest, user_id: i32) {
+    update_details(req, |details| details.actor_id = Some(user_id));
+}
+
+fn update_details(req: &HttpRequest, update: impl FnOnce(&mut AuditDetails)) {
+    let mut extensions = req.extensions_mut();
+    match extensions.get_mut::<AuditDetails>() {
+        Some(details) => update(details),
+        None => {
+            let mut details = AuditDetails::default();
+            update(&mut details);
+            extensions.insert(details);
+        }
+    }
+}
+
+/// Appends every API call that may change state (any method but `GET`, `HEAD` and
+/// `OPTIONS`) to the audit log once it has been handled, whatever its outcome.
+///
+/// The entry's action is the method and route, e.g. `POST /api/account/{account_id}/alias`,
+/// and its target the path that was called. The actor is the user of the request's
+/// token. The `after` snapshot is the JSON body of a successful response, and handlers
+/// add a `before` snapshot with [`record_before`]. Every response carries its request
+/// id in `X-Request-Id`.
+pub async fn audit(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let request_id = req.extensions().get::<RequestId>().map(ToString::to_string);
+    let mutating = !matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS);
+    let entry = mutating.then(|| AuditEntry {
+        actor_id: JWTClaim::from_request(req.request(), &mut Payload::None)
+            .into_inner()
+            .ok()
+            .map(|claim| claim.id()),
+        action: req.method().to_string(),
+        target: req.path().to_string(),
+        ip: req
+            .connection_info()
+            .realip_remote_addr()
+            .map(str::to_string),
+        request_id: request_id.clone(),
+        ..Default::default()
+    });
+
+    let res = next.call(req).await?;
+    let mut res = match (entry, res.request().match_pattern()) {
+        (Some(entry), Some(route)) => record(res, entry, route).await?,
+        _ => res.map_into_boxed_body(),
+    };
+
+    if let Some(value) = request_id.and_then(|id| HeaderValue::from_str(&id).ok()) {
+        res.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    Ok(res)
+}
+
+async fn record<B: MessageBody + 'static>(
+    res: ServiceResponse<B>,
+    mut entry: AuditEntry,
+    route: String,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let is_json = res
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+    let status = res.status();
+    let (request, response) = res.into_parts();
+    let (response, body) = response.into_parts();
+    let body = body::to_bytes(body)
+        .await
+        .map_err(|err| error::ErrorInternalServerError(err.into()))?;
+
+    let details = request
+        .extensions()
+        .get::<AuditDetails>()
+        .cloned()
+        .unwrap_or_default();
+    entry.action = format!("{} {route}", entry.action);
+    entry.status = status.as_u16();
+    entry.actor_id = entry.actor_id.or(details.actor_id);
+    entry.before = details.before;
+    if status.is_success() && is_json && body.len() <= AUDIT_MAX_SNAPSHOT_SIZE {
+        entry.after = serde_json::from_slice(&body).ok().map(redact);
+    }
+
+    // The call has already taken effect, so a failure to record it is logged rather
+    // than turned into an error response
+    let state = request
+        .app_data::<web::Data<AppState>>()
+        .expect("AppState is registered as app data");
+    if let Err(err) = state.db().audit.append(entry).await {
+        error!(error = %err, "Could not record the audit entry");
+    }
+
+    Ok(ServiceResponse::new(
+        request,
+        response.set_body(BoxBody::new(body)),
+    ))
+}
+
+/// Replaces the values of credential fields, at any depth
+fn redact(value: Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(key, value)| {
+                    let lower = key.to_lowercase();
+                    let redacted = REDACTED_FIELDS
+                        .iter()
+                        .any(|field| lower == *field || lower.ends_with(&format!("_{field}")));
+                    if redacted {
+                        (key, Value::from("[redacted]"))
+                    } else {
+                        (key, redact(value))
+                    }
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.into_iter().map(redact).collect()),
+        value => value,
+    }
+}
@@ -1,60 +1,91 @@
+use actix_web::web;
 use actix_web::Error;
 use actix_web::FromRequest;
+use actix_web::HttpMessage;
+use chrono::Utc;
+use db::db_client::DbClient;
+use db::user::UserRole;
 use futures_util::future::err;
 use futures_util::future::ok;
+use futures_util::future::LocalBoxFuture;
 use futures_util::future::Ready;
-use jsonwebtoken::decode;
 use jsonwebtoken::Validation;
-use jsonwebtoken::{DecodingKey, EncodingKey};
-use once_cell::sync::Lazy;
+use rand::distributions::{Alphanumeric, DistString};
 use serde::{Deserialize, Serialize};
 
+use crate::app_state::AppState;
+use crate::constants::{ACCESS_TOKEN_TTL_SECS, JWT_AUDIENCE, JWT_ISSUER};
+use crate::middlewares::jwt_keys;
 use crate::util::ApiError;
 use crate::util::AuthError;
 
-pub struct JWTKeys {
-    encoding: EncodingKey,
-    decoding: DecodingKey,
-}
-
-impl JWTKeys {
-    pub fn new() -> Self {
-        const JWT_SECRET: &str = "dodotakehome";
-        let secret = JWT_SECRET.as_bytes();
-        Self {
-            encoding: EncodingKey::from_secret(secret),
-            decoding: DecodingKey::from_secret(secret),
-        }
-    }
-
-    pub fn encoding_key(&self) -> &EncodingKey {
-        &self.encoding
-    }
-
-    pub fn decoding_key(&self) -> &DecodingKey {
-        &self.decoding
-    }
-}
-
-pub static KEYS: Lazy<JWTKeys> = Lazy::new(|| JWTKeys::new());
-
 /// JWT claims structure for authentication
 /// Contains:
 /// - User ID
-/// - Expiration time
+/// - Role of the user when the token was issued
+/// - Active organization, if the user switched into one
+/// - Expiration time, `ACCESS_TOKEN_TTL_SECS` after issue
 /// - Issued at time
-#[derive(Debug, Serialize, Deserialize)]
+/// - Token id, issuer and audience
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct JWTClaim {
     user_id: i32,
+    role: UserRole,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    org_id: Option<i32>,
+    exp: i64,
+    iat: i64,
+    jti: String,
+    iss: String,
+    aud: String,
 }
+
 impl JWTClaim {
-    pub fn new(user_id: i32) -> Self {
-        JWTClaim { user_id }
+    pub fn new(user_id: i32, role: UserRole) -> Self {
+        let now = Utc::now().timestamp();
+        JWTClaim {
+            user_id,
+            role,
+            org_id: None,
+            exp: now + ACCESS_TOKEN_TTL_SECS,
+            iat: now,
+            jti: Alphanumeric.sample_string(&mut rand::thread_rng(), 16),
+            iss: JWT_ISSUER.to_string(),
+            aud: JWT_AUDIENCE.to_string(),
+        }
+    }
+
+    /// Claim acting on behalf of an organization; account endpoints then work
+    /// against the organization's accounts
+    pub fn with_organization(mut self, organization_id: Option<i32>) -> Self {
+        self.org_id = organization_id;
+        self
+    }
+
+    /// The signed access token
+    pub fn encode(&self) -> Result<String, ApiError> {
+        let token = jwt_keys::keys()
+            .encode(self)
+            .map_err(AuthError::TokenError)?;
+        Ok(token)
     }
 
     pub fn id(&self) -> i32 {
         self.user_id
     }
+
+    pub fn role(&self) -> UserRole {
+        self.role
+    }
+
+    pub fn organization_id(&self) -> Option<i32> {
+        self.org_id
+    }
+
+    /// When the token expires, in seconds since the Unix epoch
+    pub fn expires_at(&self) -> i64 {
+        self.exp
+    }
 }
 
 impl FromRequest for JWTClaim {
@@ -64,14 +95,20 @@ impl FromRequest for JWTClaim {
         req: &actix_web::HttpRequest,
         _payload: &mut actix_web::dev::Payload,
     ) -> Self::Future {
+        // Requests made with an API key were already authenticated by `api_key::authenticate`
+        if let Some(claim) = req.extensions().get::<JWTClaim>() {
+            return ok(claim.clone());
+        }
         if let Some(header) = req.headers().get("Authorization") {
             if let Ok(token) = header.to_str() {
-                let key = KEYS.decoding_key();
+                let token = token.strip_prefix("Bearer ").unwrap_or(token);
                 let mut validation = Validation::default();
-                validation.required_spec_claims.remove("exp");
-                validation.validate_exp = false;
-                let token_data = decode::<JWTClaim>(token, key, &validation)
-                    .map_err(|err| AuthError::TokenError(err));
+                validation.set_required_spec_claims(&["exp", "iss", "aud"]);
+                validation.set_issuer(&[JWT_ISSUER]);
+                validation.set_audience(&[JWT_AUDIENCE]);
+                let token_data = jwt_keys::keys()
+                    .decode::<JWTClaim>(token, validation)
+                    .map_err(AuthError::TokenError);
                 match token_data {
                     Ok(data) => ok(data.claims),
                     Err(error) => err(ApiError::AuthError(error).into()),
@@ -84,3 +121,143 @@ impl FromRequest for JWTClaim {
         }
     }
 }
+
+/// What support agents and admins may do beyond using their own accounts
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Permission {
+    /// Search users and look at their profiles
+    ViewUsers,
+    /// Look at any account and its balance adjustments
+    ViewAccounts,
+    /// List transactions between any accounts
+    ViewTransactions,
+    FreezeAccounts,
+    AdjustBalances,
+    /// See every dispute and review and resolve them
+    ResolveDisputes,
+    ViewAuditLog,
+}
+
+/// Whether a role grants a permission. Users have none; support agents can look
+/// around and freeze accounts; admins can do everything.
+pub fn has_permission(role: UserRole, permission: Permission) -> bool {
+    match role {
+        UserRole::User => false,
+        UserRole::Support => matches!(
+            permission,
+            Permission::ViewUsers
+                | Permission::ViewAccounts
+                | Permission::ViewTransactions
+                | Permission::FreezeAccounts
+        ),
+        UserRole::Admin => true,
+    }
+}
+
+/// Claim of an authenticated support agent or admin. The role in the token must still
+/// be the user's role in the database, so a demotion takes effect immediately, while a
+/// promotion needs a new token. Handlers then check the permission they need with
+/// [`StaffClaim::require`].
+pub struct StaffClaim(JWTClaim);
+
+impl StaffClaim {
+    /// The claim as a staff claim, or `None` for users without a staff role and tokens
+    /// whose role is no longer the user's
+    pub async fn of(db: &DbClient, claim: JWTClaim) -> Result<Option<Self>, ApiError> {
+        if claim.role() == UserRole::User {
+            return Ok(None);
+        }
+        let user = db
+            .user
+            .find_user(claim.id())
+            .await?
+            .ok_or(ApiError::AuthError(AuthError::UserNotFound))?;
+        if user.role != claim.role() {
+            return Ok(None);
+        }
+        Ok(Some(StaffClaim(claim)))
+    }
+
+    pub fn id(&self) -> i32 {
+        self.0.id()
+    }
+
+    /// Whether the role grants `permission`
+    pub fn can(&self, permission: Permission) -> bool {
+        has_permission(self.0.role(), permission)
+    }
+
+    /// Fails with `403 Forbidden` unless the role grants `permission`
+    pub fn require(&self, permission: Permission) -> Result<(), ApiError> {
+        if self.can(permission) {
+            Ok(())
+        } else {
+            Err(ApiError::Forbidden)
+        }
+    }
+}
+
+impl FromRequest for StaffClaim {
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+    fn from_request(
+        req: &actix_web::HttpRequest,
+        payload: &mut actix_web::dev::Payload,
+    ) -> Self::Future {
+        let claim = JWTClaim::from_request(req, payload).into_inner();
+        let state = req.app_data::<web::Data<AppState>>().cloned();
+        Box::pin(async move {
+            let claim = claim?;
+            let state = state.expect("AppState is registered as app data");
+            match StaffClaim::of(state.db(), claim).await? {
+                Some(staff) => Ok(staff),
+                None => Err(ApiError::Forbidden.into()),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PERMISSIONS: [Permission; 7] = [
+        Permission::ViewUsers,
+        Permission::ViewAccounts,
+        Permission::ViewTransactions,
+        Permission::FreezeAccounts,
+        Permission::AdjustBalances,
+        Permission::ResolveDisputes,
+        Permission::ViewAuditLog,
+    ];
+
+    fn granted(role: UserRole) -> Vec<Permission> {
+        PERMISSIONS
+            .into_iter()
+            .filter(|&permission| has_permission(role, permission))
+            .collect()
+    }
+
+    #[test]
+    fn users_have_no_staff_permissions() {
+        assert_eq!(granted(UserRole::User), []);
+    }
+
+    #[test]
+    fn support_can_look_around_and_freeze_accounts() {
+        assert_eq!(
+            granted(UserRole::Support),
+            [
+                Permission::ViewUsers,
+                Permission::ViewAccounts,
+                Permission::ViewTransactions,
+                Permission::FreezeAccounts,
+            ]
+        );
+    }
+
+    #[test]
+    fn admins_have_every_permission() {
+        assert_eq!(granted(UserRole::Admin), PERMISSIONS);
+    }
+}
@@ -0,0 +1,381 @@
+use std::env;
+use std::fs;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use common::error::thiserror;
+use jsonwebtoken::errors::{Error as JwtError, ErrorKind};
+use jsonwebtoken::{
+    decode, decode_header, encode, Algorithm, DecodingKey, EncodingKey, Header, TokenData,
+    Validation,
+};
+use once_cell::sync::OnceCell;
+use ring::rsa::PublicKeyComponents;
+use ring::signature::{Ed25519KeyPair, KeyPair, RsaKeyPair};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+/// HMAC secrets shorter than this are rejected
+const MIN_SECRET_LENGTH: usize = 32;
+
+/// A signing key configuration the server cannot start with
+#[derive(thiserror::Error, Debug)]
+#[error("{0}")]
+pub struct KeyConfigError(String);
+
+/// A key tokens are signed or verified with, named by the `kid` in their header
+struct JWTKey {
+    kid: String,
+    algorithm: Algorithm,
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+    /// The public half as a JWK; `None` for HMAC secrets, which are never published
+    jwk: Option<Value>,
+}
+
+/// The keys of the server. New tokens are signed with the signing key, while tokens
+/// signed with any of the keys are accepted, so that keys can be rotated without
+/// logging everyone out.
+pub struct JWTKeys {
+    signing_kid: String,
+    keys: Vec<JWTKey>,
+}
+
+impl JWTKeys {
+    /// Loads the keys in `JWT_KEYS`, a comma-separated list of `kid:algorithm:path`
+    /// entries. The algorithm is `HS256`, whose file holds the secret, or `RS256` or
+    /// `EdDSA`, whose file holds the private key as PEM. `JWT_SIGNING_KEY` names the key
+    /// new tokens are signed with, by default the first one.
+    ///
+    /// Without `JWT_KEYS`, `JWT_SECRET` is used as the only key, an HS256 secret with the
+    /// kid `default`. Fails if neither is set or a key cannot be loaded.
+    pub fn from_env() -> Result<Self, KeyConfigError> {
+        let keys = match env::var("JWT_KEYS") {
+            Ok(entries) => entries
+                .split(',')
+                .map(str::trim)
+                .filter(|entry| !entry.is_empty())
+                .map(load_key)
+                .collect::<Result<Vec<_>, _>>()?,
+            Err(_) => {
+                let secret = env::var("JWT_SECRET").map_err(|_| {
+                    KeyConfigError("JWT_KEYS or JWT_SECRET must be set".to_string())
+                })?;
+                vec![hmac_key("default", secret.as_bytes())?]
+            }
+        };
+        Self::new(keys, env::var("JWT_SIGNING_KEY").ok())
+    }
+
+    /// The keys, signing new tokens with `signing_kid`, or the first key without one
+    fn new(keys: Vec<JWTKey>, signing_kid: Option<String>) -> Result<Self, KeyConfigError> {
+        let Some(first) = keys.first() else {
+            return Err(KeyConfigError(
+                "JWT_KEYS must list at least one key".to_string(),
+            ));
+        };
+        for (i, key) in keys.iter().enumerate() {
+            if keys[..i].iter().any(|other| other.kid == key.kid) {
+                return Err(KeyConfigError(format!(
+                    "JWT_KEYS lists the key {:?} twice",
+                    key.kid
+                )));
+            }
+        }
+
+        let signing_kid = signing_kid.unwrap_or_else(|| first.kid.clone());
+        if !keys.iter().any(|key| key.kid == signing_kid) {
+            return Err(KeyConfigError(format!(
+                "JWT_SIGNING_KEY {signing_kid:?} is not one of the keys in JWT_KEYS"
+            )));
+        }
+        Ok(Self { signing_kid, keys })
+    }
+
+    /// Signs the claims with the signing key, naming it in the header
+    pub fn encode<T: Serialize>(&self, claims: &T) -> Result<String, JwtError> {
+        let key = self
+            .find(&self.signing_kid)
+            .expect("The signing key is one of the keys");
+        let mut header = Header::new(key.algorithm);
+        header.kid = Some(key.kid.clone());
+        encode(&header, claims, &key.encoding)
+    }
+
+    /// Verifies a token with the key named in its header, which must be one of ours.
+    /// Only that key's algorithm is accepted.
+    pub fn decode<T: DeserializeOwned>(
+        &self,
+        token: &str,
+        mut validation: Validation,
+    ) -> Result<TokenData<T>, JwtError> {
+        let header = decode_header(token)?;
+        let key = header
+            .kid
+            .as_deref()
+            .and_then(|kid| self.find(kid))
+            .ok_or_else(|| JwtError::from(ErrorKind::InvalidToken))?;
+        validation.algorithms = vec![key.algorithm];
+        decode(token, &key.decoding, &validation)
+    }
+
+    /// The public keys as a JWK set, signing key first
+    pub fn jwks(&self) -> Value {
+        let mut keys: Vec<&JWTKey> = self.keys.iter().collect();
+        keys.sort_by_key(|key| key.kid != self.signing_kid);
+        json!({ "keys": keys.iter().filter_map(|key| key.jwk.clone()).collect::<Vec<_>>() })
+    }
+
+    fn find(&self, kid: &str) -> Option<&JWTKey> {
+        self.keys.iter().find(|key| key.kid == kid)
+    }
+}
+
+static KEYS: OnceCell<JWTKeys> = OnceCell::new();
+
+/// Loads the keys from the environment. Called once at startup, before any token is
+/// signed or verified.
+pub fn init() -> Result<(), KeyConfigError> {
+    let keys = JWTKeys::from_env()?;
+    // Keys loaded by an earlier call stay in use
+    let _ = KEYS.set(keys);
+    Ok(())
+}
+
+/// The keys loaded by `init`
+pub fn keys() -> &'static JWTKeys {
+    KEYS.get().expect("The JWT keys are loaded at startup")
+}
+
+fn load_key(entry: &str) -> Result<JWTKey, KeyConfigError> {
+    let mut parts = entry.splitn(3, ':');
+    let (Some(kid), Some(algorithm), Some(path)) = (parts.next(), parts.next(), parts.next())
+    else {
+        return Err(KeyConfigError(format!(
+            "JWT_KEYS entry {entry:?} must look like kid:algorithm:path"
+        )));
+    };
+    let contents = fs::read(path)
+        .map_err(|err| KeyConfigError(format!("Could not read the key {kid:?}: {err}")))?;
+    match algorithm {
+        "HS256" => hmac_key(kid, contents.trim_ascii_end()),
+        "RS256" => rsa_key(kid, &contents),
+        "EdDSA" => ed25519_key(kid, &contents),
+        other => Err(KeyConfigError(format!(
+            "Unsupported algorithm {other:?} for the key {kid:?}"
+        ))),
+    }
+}
+
+fn hmac_key(kid: &str, secret: &[u8]) -> Result<JWTKey, KeyConfigError> {
+    if secret.len() < MIN_SECRET_LENGTH {
+        return Err(KeyConfigError(format!(
+            "The HS256 secret {kid:?} must be at least {MIN_SECRET_LENGTH} bytes long"
+        )));
+    }
+    Ok(JWTKey {
+        kid: kid.to_string(),
+        algorithm: Algorithm::HS256,
+        encoding: EncodingKey::from_secret(secret),
+        decoding: DecodingKey::from_secret(secret),
+        jwk: None,
+    })
+}
+
+/// An RSA private key, in PKCS#8 (`PRIVATE KEY`) or PKCS#1 (`RSA PRIVATE KEY`) PEM
+fn rsa_key(kid: &str, pem: &[u8]) -> Result<JWTKey, KeyConfigError> {
+    let invalid = |err: &dyn std::fmt::Display| {
+        KeyConfigError(format!(
+            "The RS256 key {kid:?} is not a valid RSA private key: {err}"
+        ))
+    };
+    let parsed = pem::parse(pem).map_err(|err| invalid(&err))?;
+    let key_pair = match parsed.tag() {
+        "PRIVATE KEY" => RsaKeyPair::from_pkcs8(parsed.contents()),
+        "RSA PRIVATE KEY" => RsaKeyPair::from_der(parsed.contents()),
+        other => return Err(invalid(&format!("unexpected PEM block {other:?}"))),
+    }
+    .map_err(|err| invalid(&err))?;
+    let public: PublicKeyComponents<Vec<u8>> = key_pair.public().into();
+
+    Ok(JWTKey {
+        kid: kid.to_string(),
+        algorithm: Algorithm::RS256,
+        encoding: EncodingKey::from_rsa_pem(pem).map_err(|err| invalid(&err))?,
+        decoding: DecodingKey::from_rsa_raw_components(&public.n, &public.e),
+        jwk: Some(json!({
+            "kty": "RSA",
+            "use": "sig",
+            "alg": "RS256",
+            "kid": kid,
+            "n": URL_SAFE_NO_PAD.encode(&public.n),
+            "e": URL_SAFE_NO_PAD.encode(&public.e),
+        })),
+    })
+}
+
+/// An Ed25519 private key in PKCS#8 PEM, as written by `openssl genpkey -algorithm ed25519`
+fn ed25519_key(kid: &str, pem: &[u8]) -> Result<JWTKey, KeyConfigError> {
+    let invalid = |err: &dyn std::fmt::Display| {
+        KeyConfigError(format!(
+            "The EdDSA key {kid:?} is not a valid Ed25519 private key: {err}"
+        ))
+    };
+    let parsed = pem::parse(pem).map_err(|err| invalid(&err))?;
+    let key_pair = Ed25519KeyPair::from_pkcs8_maybe_unchecked(parsed.contents())
+        .map_err(|err| invalid(&err))?;
+    let x = URL_SAFE_NO_PAD.encode(key_pair.public_key().as_ref());
+
+    Ok(JWTKey {
+        kid: kid.to_string(),
+        algorithm: Algorithm::EdDSA,
+        encoding: EncodingKey::from_ed_pem(pem).map_err(|err| invalid(&err))?,
+        decoding: DecodingKey::from_ed_components(&x).map_err(|err| invalid(&err))?,
+        jwk: Some(json!({
+            "kty": "OKP",
+            "crv": "Ed25519",
+            "use": "sig",
+            "alg": "EdDSA",
+            "kid": kid,
+            "x": x,
+        })),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use ring::rand::SystemRandom;
+
+    use super::*;
+
+    const OLD_SECRET: &[u8] = b"the old secret, retired but still accepted";
+    const NEW_SECRET: &[u8] = b"the new secret that signs every new token";
+
+    fn keys(entries: &[(&str, &[u8])], signing_kid: &str) -> JWTKeys {
+        let keys = entries
+            .iter()
+            .map(|(kid, secret)| hmac_key(kid, secret).unwrap())
+            .collect();
+        JWTKeys::new(keys, Some(signing_kid.to_string())).unwrap()
+    }
+
+    fn ed25519(kid: &str) -> JWTKey {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        let pem = pem::encode(&pem::Pem::new("PRIVATE KEY", pkcs8.as_ref()));
+        ed25519_key(kid, pem.as_bytes()).unwrap()
+    }
+
+    fn claims() -> Value {
+        json!({ "sub": "2", "exp": Utc::now().timestamp() + 60 })
+    }
+
+    fn kid(token: &str) -> Option<String> {
+        decode_header(token).unwrap().kid
+    }
+
+    fn verify(keys: &JWTKeys, token: &str) -> Result<Value, JwtError> {
+        keys.decode::<Value>(token, Validation::default())
+            .map(|data| data.claims)
+    }
+
+    #[test]
+    fn signs_with_the_signing_key() {
+        let keys = keys(&[("old", OLD_SECRET), ("new", NEW_SECRET)], "new");
+        let token = keys.encode(&claims()).unwrap();
+
+        assert_eq!(kid(&token).as_deref(), Some("new"));
+        assert_eq!(verify(&keys, &token).unwrap()["sub"], "2");
+    }
+
+    #[test]
+    fn accepts_tokens_signed_with_a_retired_key() {
+        let before = keys(&[("old", OLD_SECRET)], "old");
+        let token = before.encode(&claims()).unwrap();
+
+        let rotated = keys(&[("new", NEW_SECRET), ("old", OLD_SECRET)], "new");
+        assert_eq!(verify(&rotated, &token).unwrap()["sub"], "2");
+    }
+
+    #[test]
+    fn rejects_tokens_of_removed_or_unnamed_keys() {
+        let before = keys(&[("old", OLD_SECRET)], "old");
+        let token = before.encode(&claims()).unwrap();
+        let after = keys(&[("new", NEW_SECRET)], "new");
+        assert!(verify(&after, &token).is_err());
+
+        let unnamed = encode(
+            &Header::default(),
+            &claims(),
+            &EncodingKey::from_secret(NEW_SECRET),
+        )
+        .unwrap();
+        assert!(verify(&after, &unnamed).is_err());
+    }
+
+    #[test]
+    fn rejects_a_token_claiming_a_key_it_was_not_signed_with() {
+        let keys = keys(&[("new", NEW_SECRET)], "new");
+        let mut header = Header::new(Algorithm::HS256);
+        header.kid = Some("new".to_string());
+        let forged = encode(&header, &claims(), &EncodingKey::from_secret(OLD_SECRET)).unwrap();
+        assert!(verify(&keys, &forged).is_err());
+    }
+
+    #[test]
+    fn only_accepts_the_algorithm_of_the_named_key() {
+        let keys = JWTKeys::new(
+            vec![ed25519("ed"), hmac_key("hs", NEW_SECRET).unwrap()],
+            None,
+        )
+        .unwrap();
+        let mut header = Header::new(Algorithm::HS256);
+        header.kid = Some("ed".to_string());
+        let confused = encode(&header, &claims(), &EncodingKey::from_secret(NEW_SECRET)).unwrap();
+        assert!(verify(&keys, &confused).is_err());
+
+        let token = keys.encode(&claims()).unwrap();
+        assert_eq!(kid(&token).as_deref(), Some("ed"));
+        assert!(verify(&keys, &token).is_ok());
+    }
+
+    #[test]
+    fn publishes_only_public_keys_signing_key_first() {
+        let keys = JWTKeys::new(
+            vec![
+                hmac_key("hs", NEW_SECRET).unwrap(),
+                ed25519("ed-old"),
+                ed25519("ed-new"),
+            ],
+            Some("ed-new".to_string()),
+        )
+        .unwrap();
+        let kids: Vec<Value> = keys.jwks()["keys"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|jwk| jwk["kid"].clone())
+            .collect();
+        assert_eq!(kids, ["ed-new", "ed-old"]);
+    }
+
+    #[test]
+    fn rejects_invalid_key_configurations() {
+        assert!(hmac_key("short", b"too short").is_err());
+        assert!(JWTKeys::new(Vec::new(), None).is_err());
+        assert!(JWTKeys::new(
+            vec![
+                hmac_key("twice", OLD_SECRET).unwrap(),
+                hmac_key("twice", NEW_SECRET).unwrap(),
+            ],
+            None,
+        )
+        .is_err());
+        assert!(JWTKeys::new(
+            vec![hmac_key("new", NEW_SECRET).unwrap()],
+            Some("missing".to_string()),
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,8 @@
+//! Publishing of the domain events that the database layer writes to the outbox
+//!
+//! The relay reads the outbox in order and hands every event to each configured sink.
+//! Every sink keeps its own offset in the database, so a sink that is down holds back
+//! only itself, and an event is published again if the relay stops before recording it.
+
+pub mod relay;
+pub mod sinks;
@@ -0,0 +1,84 @@
+use std::time::Duration;
+
+use actix_web::web;
+use tracing::{info, warn};
+
+use crate::app_state::AppState;
+use crate::constants::{OUTBOX_BATCH_SIZE, OUTBOX_LEASE_SECS, OUTBOX_POLL_INTERVAL_SECS};
+use crate::email;
+use crate::outbox::sinks::{self, Sink};
+
+/// Publishes outbox events to the sinks configured in the environment for as long as
+/// the server runs. Each sink is leased to one server instance at a time and its offset
+/// only moves past an event once the sink has taken it, so every event reaches every
+/// sink at least once.
+pub async fn run(state: web::Data<AppState>) {
+    let mut sinks = sinks::from_env();
+    sinks.extend(email::notifier::from_env(state.clone()));
+    if sinks.is_empty() {
+        info!("No outbox sinks configured, outbox events are not published");
+        return;
+    }
+
+    let mut interval =
+        actix_web::rt::time::interval(Duration::from_secs(OUTBOX_POLL_INTERVAL_SECS));
+    loop {
+        interval.tick().await;
+        for sink in &sinks {
+            relay(&state, sink.as_ref()).await;
+        }
+    }
+}
+
+/// Publishes the pending events of one sink until it is caught up or fails
+async fn relay(state: &AppState, sink: &dyn Sink) {
+    let db = state.db();
+    let lease = chrono::Duration::seconds(OUTBOX_LEASE_SECS);
+    let mut offset = match db
+        .outbox
+        .claim_sink(sink.name(), lease, sink.replays_history())
+        .await
+    {
+        Ok(Some(offset)) => offset,
+        Ok(None) => return,
+        Err(err) => {
+            warn!("Could not claim outbox sink {}: {}", sink.name(), err);
+            return;
+        }
+    };
+
+    'batches: loop {
+        let events = match db.outbox.events_after(&offset, OUTBOX_BATCH_SIZE).await {
+            Ok(events) => events,
+            Err(err) => {
+                warn!("Could not read the outbox for {}: {}", sink.name(), err);
+                break;
+            }
+        };
+        if events.is_empty() {
+            break;
+        }
+
+        for event in &events {
+            if let Err(err) = sink.publish(event).await {
+                warn!(
+                    "Outbox sink {} failed on event {}: {}",
+                    sink.name(),
+                    event.id,
+                    err
+                );
+                break 'batches;
+            }
+            if let Err(err) = db.outbox.advance(sink.name(), event, lease).await {
+                warn!("Could not advance outbox sink {}: {}", sink.name(), err);
+                break 'batches;
+            }
+            offset.last_transaction_id = event.transaction_id;
+            offset.last_event_id = event.id;
+        }
+    }
+
+    if let Err(err) = db.outbox.release_sink(sink.name()).await {
+        warn!("Could not release outbox sink {}: {}", sink.name(), err);
+    }
+}
@@ -0,0 +1,195 @@
+use std::env;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use common::error::thiserror;
+use db::util::DBError;
+use entity::outbox;
+use serde_json::json;
+
+use crate::constants::WEBHOOK_TIMEOUT_SECS;
+use crate::email::transport::EmailError;
+use crate::features::webhooks::dispatcher::sign;
+
+/// Why a sink could not take an event
+#[derive(thiserror::Error, Debug)]
+pub enum SinkError {
+    #[error("Could not write the event: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not send the event: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("The webhook responded with status {0}")]
+    Status(u16),
+    #[error("Could not send the email: {0}")]
+    Email(#[from] EmailError),
+    #[error("Could not load the event's details: {0}")]
+    Database(#[from] DBError),
+}
+
+/// A destination for outbox events. `publish` must only return once the event is
+/// stored or accepted, since the relay moves past it right after.
+#[async_trait]
+pub trait Sink: Send + Sync {
+    /// Identifies the sink's offset; renaming a sink replays the outbox into it
+    fn name(&self) -> &str;
+
+    /// Whether the sink receives the events committed before it was first started
+    fn replays_history(&self) -> bool {
+        true
+    }
+
+    async fn publish(&self, event: &outbox::Model) -> Result<(), SinkError>;
+}
+
+/// Builds the sinks listed in `OUTBOX_SINKS`, a comma-separated list of `stdout`,
+/// `file` and `webhook`. The file sink appends to `OUTBOX_LOG_FILE` (default
+/// `outbox.log`); the webhook sink POSTs to `OUTBOX_WEBHOOK_URL`, signing with
+/// `OUTBOX_WEBHOOK_SECRET` like endpoint deliveries.
+pub fn from_env() -> Vec<Box<dyn Sink>> {
+    let Ok(names) = env::var("OUTBOX_SINKS") else {
+        return Vec::new();
+    };
+    names
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| -> Box<dyn Sink> {
+            match name {
+                "stdout" => Box::new(StdoutSink),
+                "file" => Box::new(FileSink::open(
+                    &env::var("OUTBOX_LOG_FILE").unwrap_or_else(|_| "outbox.log".to_string()),
+                )),
+                "webhook" => Box::new(WebhookSink::new(
+                    env::var("OUTBOX_WEBHOOK_URL")
+                        .expect("OUTBOX_WEBHOOK_URL must be set for the webhook sink"),
+                    env::var("OUTBOX_WEBHOOK_SECRET")
+                        .expect("OUTBOX_WEBHOOK_SECRET must be set for the webhook sink"),
+                )),
+                other => panic!("Unknown outbox sink {other:?} in OUTBOX_SINKS"),
+            }
+        })
+        .collect()
+}
+
+/// One JSON line describing an event, as every sink publishes it
+fn envelope(event: &outbox::Model) -> String {
+    json!({
+        "id": event.id,
+        "type": event.event_type,
+        "aggregate_id": event.aggregate_id,
+        "created_at": event.created_at,
+        "data": event.payload,
+    })
+    .to_string()
+}
+
+/// Prints every event to standard output
+pub struct StdoutSink;
+
+#[async_trait]
+impl Sink for StdoutSink {
+    fn name(&self) -> &str {
+        "stdout"
+    }
+
+    async fn publish(&self, event: &outbox::Model) -> Result<(), SinkError> {
+        let mut stdout = std::io::stdout().lock();
+        writeln!(stdout, "{}", envelope(event))?;
+        stdout.flush()?;
+        Ok(())
+    }
+}
+
+/// Appends every event as a line to a file
+pub struct FileSink {
+    file: Mutex<File>,
+}
+
+impl FileSink {
+    pub fn open(path: &str) -> Self {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .unwrap_or_else(|err| panic!("Could not open the outbox log file {path}: {err}"));
+        Self {
+            file: Mutex::new(file),
+        }
+    }
+}
+
+#[async_trait]
+impl Sink for FileSink {
+    fn name(&self) -> &str {
+        "file"
+    }
+
+    async fn publish(&self, event: &outbox::Model) -> Result<(), SinkError> {
+        let line = envelope(event) + "\n";
+        let mut file = self.file.lock().unwrap();
+        file.write_all(line.as_bytes())?;
+        file.sync_data()?;
+        Ok(())
+    }
+}
+
+/// POSTs every event to one URL with the same signature headers as webhook deliveries
+pub struct WebhookSink {
+    client: reqwest::Client,
+    url: String,
+    secret: String,
+}
+
+impl WebhookSink {
+    pub fn new(url: String, secret: String) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(WEBHOOK_TIMEOUT_SECS))
+            .build()
+            .expect("Could not build the outbox HTTP client");
+        Self {
+            client,
+            url,
+            secret,
+        }
+    }
+}
+
+#[async_trait]
+impl Sink for WebhookSink {
+    fn name(&self) -> &str {
+        "webhook"
+    }
+
+    async fn publish(&self, event: &outbox::Model) -> Result<(), SinkError> {
+        let body = envelope(event);
+        let timestamp = Utc::now().timestamp();
+        let response = self
+            .client
+            .post(&self.url)
+            .header("Content-Type", "application/json")
+            .header("X-Webhook-Id", event.id.to_string())
+            .header(
+                "X-Webhook-Event",
+                json!(event.event_type).as_str().unwrap_or_default(),
+            )
+            .header(
+                "X-Webhook-Signature",
+                format!(
+                    "t={},v1={}",
+                    timestamp,
+                    sign(&self.secret, timestamp, &body)
+                ),
+            )
+            .body(body)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(SinkError::Status(response.status().as_u16()));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,83 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::DateTime;
+use db::pagination::{Keyset, Page, PageRequest};
+use serde::Deserialize;
+
+use crate::constants::{DEFAULT_PAGE_LIMIT, MAX_PAGE_LIMIT};
+use crate::util::ApiError;
+use crate::validation::{FieldError, Validate, Validator};
+
+/// Keyset pagination query parameters shared by every list endpoint
+/// - `limit`: page size, defaults to `DEFAULT_PAGE_LIMIT` and may not exceed `MAX_PAGE_LIMIT`
+/// - `cursor`: the `next_cursor` returned with the previous page
+#[derive(Debug, Deserialize)]
+pub struct PageQuery {
+    pub limit: Option<u64>,
+    pub cursor: Option<String>,
+}
+
+impl PageQuery {
+    pub fn page_request(&self) -> Result<PageRequest, ApiError> {
+        let limit = self.limit.unwrap_or(DEFAULT_PAGE_LIMIT);
+        let after = match &self.cursor {
+            Some(cursor) => Some(decode_cursor(cursor).ok_or_else(|| {
+                ApiError::Validation(vec![FieldError::new(
+                    "cursor",
+                    "invalid",
+                    "The pagination cursor is invalid",
+                )])
+            })?),
+            None => None,
+        };
+        Ok(PageRequest { limit, after })
+    }
+}
+
+impl Validate for PageQuery {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::new();
+        if let Some(limit) = self.limit {
+            v.check(
+                (1..=MAX_PAGE_LIMIT).contains(&limit),
+                "limit",
+                "out_of_range",
+                &format!("Must be between 1 and {MAX_PAGE_LIMIT}"),
+            );
+        }
+        v.finish()
+    }
+}
+
+/// Opaque cursor pointing after the last item of `page`, or `None` on the last page
+pub fn next_cursor<T>(page: &Page<T>) -> Option<String> {
+    page.next.as_ref().map(encode_cursor)
+}
+
+/// `created_at.id`, followed by `:sort` and `:value` for listings with a choice of sort
+fn encode_cursor(keyset: &Keyset) -> String {
+    let mut raw = format!("{}.{}", keyset.created_at.timestamp_micros(), keyset.id);
+    if let Some(sort) = &keyset.sort {
+        raw.push_str(&format!(":{}", sort.key));
+        if let Some(value) = sort.value {
+            raw.push_str(&format!(":{value}"));
+        }
+    }
+    URL_SAFE_NO_PAD.encode(raw)
+}
+
+fn decode_cursor(cursor: &str) -> Option<Keyset> {
+    let raw = String::from_utf8(URL_SAFE_NO_PAD.decode(cursor).ok()?).ok()?;
+    let mut parts = raw.split(':');
+    let (micros, id) = parts.next()?.split_once('.')?;
+    let created_at = DateTime::from_timestamp_micros(micros.parse().ok()?)?;
+    let mut keyset = Keyset::new(created_at.fixed_offset(), id.parse().ok()?);
+    if let Some(key) = parts.next() {
+        let value = parts.next().map(str::parse).transpose().ok()?;
+        keyset = keyset.with_sort(key, value);
+    }
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(keyset)
+}
@@ -11,7 +11,20 @@ pub fn api(cfg: &mut web::ServiceConfig) {
             .service(
                 web::scope("/user")
                     .service(features::user::controllers::register)
-                    .service(features::user::controllers::login),
+                    .service(features::user::controllers::login)
+                    .service(features::user::controllers::refresh)
+                    .service(features::user::controllers::logout)
+                    .service(features::user::controllers::lookup_recipient)
+                    .service(features::user::controllers::change_password)
+                    .service(features::user::controllers::set_email)
+                    .service(features::user::controllers::get_notification_preferences)
+                    .service(features::user::controllers::update_notification_preferences),
+            )
+            .service(
+                web::scope("/key")
+                    .service(features::api_keys::controllers::create_key)
+                    .service(features::api_keys::controllers::list_keys)
+                    .service(features::api_keys::controllers::revoke_key),
             )
             .service(
                 web::scope("/transaction")
@@ -23,8 +36,118 @@ pub fn api(cfg: &mut web::ServiceConfig) {
                 web::scope("/account")
                     .service(features::accounts::controllers::get_account)
                     .service(features::accounts::controllers::get_balance)
+                    .service(features::accounts::controllers::account_events)
                     .service(features::accounts::controllers::create_account)
-                    .service(features::accounts::controllers::list_accounts),
+                    .service(features::accounts::controllers::list_accounts)
+                    .service(features::accounts::controllers::set_default_account)
+                    .service(features::accounts::controllers::set_account_alias)
+                    .service(features::accounts::controllers::set_approval_threshold)
+                    .service(features::accounts::controllers::list_members)
+                    .service(features::accounts::controllers::add_member)
+                    .service(features::accounts::controllers::remove_member)
+                    .service(features::accounts::controllers::create_pocket)
+                    .service(features::accounts::controllers::move_money),
+            )
+            .service(
+                web::scope("/org")
+                    .service(features::organizations::controllers::create_organization)
+                    .service(features::organizations::controllers::list_organizations)
+                    .service(features::organizations::controllers::switch_organization)
+                    .service(features::organizations::controllers::list_organization_members)
+                    .service(features::organizations::controllers::add_organization_member)
+                    .service(features::organizations::controllers::update_organization_member)
+                    .service(features::organizations::controllers::remove_organization_member),
+            )
+            .service(
+                web::scope("/approval")
+                    .service(features::approvals::controllers::list_approvals)
+                    .service(features::approvals::controllers::get_approval)
+                    .service(features::approvals::controllers::approve_transfer)
+                    .service(features::approvals::controllers::reject_transfer)
+                    .service(features::approvals::controllers::cancel_transfer),
+            )
+            .service(
+                web::scope("/payment")
+                    .service(features::payments::controllers::create_payment_intent)
+                    .service(features::payments::controllers::list_payment_intents)
+                    .service(features::payments::controllers::get_payment_intent)
+                    .service(features::payments::controllers::confirm_payment_intent)
+                    .service(features::payments::controllers::cancel_payment_intent),
+            )
+            .service(
+                web::scope("/link")
+                    .service(features::payment_links::controllers::create_payment_link)
+                    .service(features::payment_links::controllers::list_payment_links)
+                    .service(features::payment_links::controllers::get_payment_link)
+                    .service(features::payment_links::controllers::pay_payment_link)
+                    .service(features::payment_links::controllers::deactivate_payment_link),
+            )
+            .service(
+                web::scope("/invoice")
+                    .service(features::invoices::controllers::create_invoice)
+                    .service(features::invoices::controllers::list_invoices)
+                    .service(features::invoices::controllers::get_invoice)
+                    .service(features::invoices::controllers::update_invoice)
+                    .service(features::invoices::controllers::void_invoice)
+                    .service(features::invoices::controllers::pay_invoice),
+            )
+            .service(
+                web::scope("/subscription")
+                    .service(features::subscriptions::controllers::create_plan)
+                    .service(features::subscriptions::controllers::list_plans)
+                    .service(features::subscriptions::controllers::get_plan)
+                    .service(features::subscriptions::controllers::deactivate_plan)
+                    .service(features::subscriptions::controllers::subscribe)
+                    .service(features::subscriptions::controllers::list_subscriptions)
+                    .service(features::subscriptions::controllers::get_subscription)
+                    .service(features::subscriptions::controllers::change_plan)
+                    .service(features::subscriptions::controllers::cancel_subscription),
+            )
+            .service(
+                web::scope("/split")
+                    .service(features::splits::controllers::create_split_payment)
+                    .service(features::splits::controllers::list_split_payments)
+                    .service(features::splits::controllers::get_split_payment),
+            )
+            .service(
+                web::scope("/webhook")
+                    .service(features::webhooks::controllers::create_endpoint)
+                    .service(features::webhooks::controllers::list_endpoints)
+                    .service(features::webhooks::controllers::list_deliveries)
+                    .service(features::webhooks::controllers::get_endpoint)
+                    .service(features::webhooks::controllers::delete_endpoint)
+                    .service(features::webhooks::controllers::get_delivery),
+            )
+            .service(
+                web::scope("/dispute")
+                    .service(features::disputes::controllers::open_dispute)
+                    .service(features::disputes::controllers::get_dispute)
+                    .service(features::disputes::controllers::add_dispute_note),
+            )
+            .service(
+                web::scope("/admin")
+                    .service(features::admin::controllers::list_disputes)
+                    .service(features::admin::controllers::review_dispute)
+                    .service(features::admin::controllers::resolve_dispute)
+                    .service(features::admin::controllers::list_audit_log)
+                    .service(features::admin::controllers::search_users)
+                    .service(features::admin::controllers::get_user)
+                    .service(features::admin::controllers::get_account)
+                    .service(features::admin::controllers::freeze_account)
+                    .service(features::admin::controllers::unfreeze_account)
+                    .service(features::admin::controllers::create_adjustment)
+                    .service(features::admin::controllers::list_adjustments)
+                    .service(features::admin::controllers::list_transactions),
             ),
     );
 }
+
+/// Well-known documents, such as the keys for verifying tokens
+pub fn well_known(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::scope("/.well-known").service(features::well_known::controllers::jwks));
+}
+
+/// Realtime connections, served next to the REST API
+pub fn ws(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::scope("/ws").service(features::notifications::controllers::connect));
+}
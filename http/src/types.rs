@@ -1,11 +1,16 @@
 use serde::Serialize;
 
-/// Standard error response structure for all API endpoints
+use crate::validation::FieldError;
+
+/// Standard error response structure for all API endpoints.
+/// `errors` lists the offending fields of a rejected request and is omitted otherwise.
 #[derive(Serialize)]
 pub struct ErrorResponse {
     pub cause: String,
     pub description: String,
     pub status_code: u16,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
 }
 
 impl ErrorResponse {
@@ -14,6 +19,12 @@ impl ErrorResponse {
             cause,
             description,
             status_code,
+            errors: Vec::new(),
         }
     }
+
+    pub fn with_errors(mut self, errors: Vec<FieldError>) -> Self {
+        self.errors = errors;
+        self
+    }
 }
@@ -4,16 +4,22 @@ use db::util::DBError;
 use std::io::Error;
 
 use crate::types::ErrorResponse;
+use crate::validation::FieldError;
 
 /// Main error types for API operations
 /// Handles:
 /// - Database errors (500)
 /// - IO errors (500)
 /// - Authentication errors (401)
+/// - Missing permissions (403)
+/// - Insufficient balance (400)
+/// - Missing resources (404)
+/// - Taken usernames, aliases and email addresses, and requests conflicting with the current state (409)
+/// - Invalid request fields (422)
 #[derive(thiserror::Error, Debug)]
 pub enum ApiError {
     #[error("An Database Error has occurred. Please try again later")]
-    DBError(#[from] DBError),
+    DBError(DBError),
 
     #[error("An IO Error has occurred. Please try again later")]
     IoError(#[from] Error),
@@ -21,30 +27,94 @@ pub enum ApiError {
     #[error("An Authentication error has occurred please try again later")]
     AuthError(#[from] AuthError),
 
+    #[error("You do not have permission to do this")]
+    Forbidden,
+
     #[error("The account has not enough balance to proceed the transaction")]
     NotEnoughBalance,
+
+    #[error("The request is invalid")]
+    Validation(Vec<FieldError>),
+
+    #[error("{0} not found")]
+    NotFound(&'static str),
+
+    #[error("{0}")]
+    Conflict(String),
+
+    #[error("This username is already taken")]
+    UsernameTaken,
+
+    #[error("This alias is already taken")]
+    AliasTaken,
+
+    #[error("This email address is already in use")]
+    EmailTaken,
+}
+
+/// Domain errors raised inside database transactions map to their API counterparts
+impl From<DBError> for ApiError {
+    fn from(err: DBError) -> Self {
+        match err {
+            DBError::AccountNotFound => ApiError::AuthError(AuthError::AccountNotFound),
+            DBError::NotEnoughBalance => ApiError::NotEnoughBalance,
+            DBError::NotFound(what) => ApiError::NotFound(what),
+            DBError::Conflict(message) => ApiError::Conflict(message),
+            DBError::InvalidAmount => ApiError::Validation(vec![FieldError::new(
+                "amount",
+                "range",
+                DBError::InvalidAmount.to_string(),
+            )]),
+            DBError::InvalidCursor => ApiError::Validation(vec![FieldError::new(
+                "cursor",
+                "invalid",
+                DBError::InvalidCursor.to_string(),
+            )]),
+            err => ApiError::DBError(err),
+        }
+    }
 }
 
 impl ResponseError for ApiError {
     fn status_code(&self) -> StatusCode {
         match *self {
             Self::NotEnoughBalance => StatusCode::BAD_REQUEST,
+            Self::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::NotFound(_) => StatusCode::NOT_FOUND,
+            Self::Conflict(_) => StatusCode::CONFLICT,
+            Self::UsernameTaken => StatusCode::CONFLICT,
+            Self::AliasTaken => StatusCode::CONFLICT,
+            Self::EmailTaken => StatusCode::CONFLICT,
             Self::DBError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Self::IoError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Self::AuthError(_) => StatusCode::UNAUTHORIZED,
+            Self::Forbidden => StatusCode::FORBIDDEN,
         }
     }
     fn error_response(&self) -> HttpResponse {
-        match *self {
-            _ => {
-                let error_response = ErrorResponse::new(
-                    "Database".to_string(),
-                    self.to_string(),
-                    self.status_code().as_u16(),
-                );
-                return HttpResponse::build(self.status_code()).json(error_response);
-            }
+        HttpResponse::build(self.status_code()).json(self.body())
+    }
+}
+
+impl ApiError {
+    /// The error body sent to clients, also used for errors on notification connections
+    pub fn body(&self) -> ErrorResponse {
+        let cause = match self {
+            Self::DBError(_) => "Database",
+            Self::IoError(_) => "IO",
+            Self::AuthError(_) => "Authentication",
+            Self::Validation(_) => "Validation",
+            _ => "Request",
+        };
+        let mut error_response = ErrorResponse::new(
+            cause.to_string(),
+            self.to_string(),
+            self.status_code().as_u16(),
+        );
+        if let Self::Validation(errors) = self {
+            error_response = error_response.with_errors(errors.clone());
         }
+        error_response
     }
 }
 
@@ -86,6 +156,12 @@ pub enum AuthError {
     #[error("You have no token! Please Login!!")]
     TokenNotFound,
 
+    #[error("The API key is invalid, expired or revoked")]
+    InvalidApiKey,
+
+    #[error("The refresh token is invalid, expired or revoked")]
+    InvalidRefreshToken,
+
     #[error("Bcrypt Hashing Error")]
     BcryptError(#[from] pwhash::error::Error),
 
@@ -100,4 +176,10 @@ pub enum AuthError {
 
     #[error("Transaction Not found")]
     TransactionNotFound,
+
+    #[error("No receiving account found for this recipient")]
+    RecipientNotFound,
+
+    #[error("Dispute Not found")]
+    DisputeNotFound,
 }
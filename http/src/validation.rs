@@ -0,0 +1,247 @@
+use std::ops::Deref;
+
+use actix_web::{dev::Payload, web, FromRequest, HttpRequest};
+use futures_util::future::LocalBoxFuture;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::constants::{MAX_AMOUNT, MAX_AMOUNT_DECIMAL_PLACES};
+use crate::util::ApiError;
+
+/// A single invalid request field, returned in the `errors` list of a 422 response
+#[derive(Clone, Debug, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// Checks the fields of a deserialized request before it reaches the database.
+/// Implemented next to each request type in the `*_types.rs` modules.
+pub trait Validate {
+    fn validate(&self) -> Result<(), Vec<FieldError>>;
+}
+
+/// Collects field errors so that a request reports every problem at once
+#[derive(Default)]
+pub struct Validator {
+    errors: Vec<FieldError>,
+}
+
+impl Validator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records an error for `field` unless `valid` holds
+    pub fn check(&mut self, valid: bool, field: &str, code: &'static str, message: &str) {
+        if !valid {
+            self.errors.push(FieldError::new(field, code, message));
+        }
+    }
+
+    /// Rejects empty or whitespace-only strings
+    pub fn not_blank(&mut self, value: &str, field: &str) {
+        self.check(
+            !value.trim().is_empty(),
+            field,
+            "required",
+            "This field must not be empty",
+        );
+    }
+
+    /// Checks the length of a string in characters
+    pub fn length(&mut self, value: &str, field: &str, min: usize, max: usize) {
+        let len = value.chars().count();
+        self.check(
+            (min..=max).contains(&len),
+            field,
+            "length",
+            &format!("Must be between {min} and {max} characters long"),
+        );
+    }
+
+    /// Rejects zero, negative and non-finite amounts, and those `amount_limits` rejects
+    pub fn positive_amount(&mut self, value: f64, field: &str) {
+        self.check(
+            value.is_finite() && value > 0.0,
+            field,
+            "positive",
+            "Must be a number greater than zero",
+        );
+        self.amount_limits(value, field);
+    }
+
+    /// Rejects negative and non-finite amounts, and those `amount_limits` rejects
+    pub fn non_negative_amount(&mut self, value: f64, field: &str) {
+        self.check(
+            value.is_finite() && value >= 0.0,
+            field,
+            "non_negative",
+            "Must be a number greater than or equal to zero",
+        );
+        self.amount_limits(value, field);
+    }
+
+    /// Rejects amounts larger than `MAX_AMOUNT` either way or with fractions of a cent.
+    /// Non-finite values are left to the sign checks above.
+    pub fn amount_limits(&mut self, value: f64, field: &str) {
+        if !value.is_finite() {
+            return;
+        }
+        self.check(
+            value.abs() <= MAX_AMOUNT,
+            field,
+            "max",
+            &format!("Must not exceed {MAX_AMOUNT}"),
+        );
+        self.check(
+            decimal_places(value) <= MAX_AMOUNT_DECIMAL_PLACES,
+            field,
+            "decimal_places",
+            &format!("Must have at most {MAX_AMOUNT_DECIMAL_PLACES} decimal places"),
+        );
+    }
+
+    /// Checks that a string is a single email address
+    pub fn email(&mut self, value: &str, field: &str) {
+        self.check(
+            value.len() <= 254 && value.parse::<lettre::Address>().is_ok(),
+            field,
+            "format",
+            "Must be a valid email address",
+        );
+    }
+
+    pub fn finish(self) -> Result<(), Vec<FieldError>> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(self.errors)
+        }
+    }
+}
+
+/// JSON body extractor that reports deserialization failures as field errors
+/// and runs `Validate` before the handler sees the request
+pub struct ValidJson<T>(pub T);
+
+impl<T> Deref for ValidJson<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> FromRequest for ValidJson<T>
+where
+    T: DeserializeOwned + Validate + 'static,
+{
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let body = web::Bytes::from_request(req, payload);
+        Box::pin(async move {
+            let body = body.await?;
+            let deserializer = &mut serde_json::Deserializer::from_slice(&body);
+            let value: T = serde_path_to_error::deserialize(deserializer)
+                .map_err(|err| ApiError::Validation(vec![json_field_error(err)]))?;
+            value.validate().map_err(ApiError::Validation)?;
+            Ok(ValidJson(value))
+        })
+    }
+}
+
+/// Query string extractor with the same error reporting and validation as `ValidJson`
+pub struct ValidQuery<T>(pub T);
+
+impl<T> Deref for ValidQuery<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> FromRequest for ValidQuery<T>
+where
+    T: DeserializeOwned + Validate + 'static,
+{
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let query = req.query_string().to_string();
+        Box::pin(async move {
+            let deserializer =
+                serde_urlencoded::Deserializer::new(form_urlencoded::parse(query.as_bytes()));
+            let value: T = serde_path_to_error::deserialize(deserializer).map_err(|err| {
+                let field = error_field(&err.path().to_string(), &err.inner().to_string());
+                ApiError::Validation(vec![FieldError::new(
+                    field,
+                    "invalid",
+                    err.inner().to_string(),
+                )])
+            })?;
+            value.validate().map_err(ApiError::Validation)?;
+            Ok(ValidQuery(value))
+        })
+    }
+}
+
+/// Structured errors for malformed path segments, e.g. a non-numeric id
+pub fn path_config() -> web::PathConfig {
+    web::PathConfig::default().error_handler(|err, _req| {
+        ApiError::Validation(vec![FieldError::new("path", "invalid", err.to_string())]).into()
+    })
+}
+
+/// Decimal places of the shortest representation of `value`, which is how it was written
+/// in the request
+fn decimal_places(value: f64) -> usize {
+    value
+        .to_string()
+        .split_once('.')
+        .map_or(0, |(_, fraction)| fraction.len())
+}
+
+fn json_field_error(err: serde_path_to_error::Error<serde_json::Error>) -> FieldError {
+    let message = err.inner().to_string();
+    if err.inner().is_syntax() || err.inner().is_eof() {
+        return FieldError::new("body", "invalid_json", message);
+    }
+    let field = error_field(&err.path().to_string(), &message);
+    let code = if message.starts_with("missing field") {
+        "required"
+    } else {
+        "invalid"
+    };
+    FieldError::new(field, code, message)
+}
+
+/// serde reports missing fields against the parent path, so take the name from the message
+fn error_field(path: &str, message: &str) -> String {
+    if let Some(name) = message
+        .strip_prefix("missing field `")
+        .and_then(|rest| rest.split('`').next())
+    {
+        return name.to_string();
+    }
+    if path == "." {
+        "body".to_string()
+    } else {
+        path.to_string()
+    }
+}